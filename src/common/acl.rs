@@ -0,0 +1,165 @@
+use std::fmt;
+
+use num_enum::{FromPrimitive, IntoPrimitive};
+
+/// Sentinel returned in `*_authorized_operations` fields when the client did
+/// not ask for them.
+pub const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+#[derive(FromPrimitive, IntoPrimitive, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i8)]
+pub enum AclOperation {
+    #[default]
+    Unknown = 0,
+    Any = 1,
+    All = 2,
+    Read = 3,
+    Write = 4,
+    Create = 5,
+    Delete = 6,
+    Alter = 7,
+    Describe = 8,
+    ClusterAction = 9,
+    DescribeConfigs = 10,
+    AlterConfigs = 11,
+    IdempotentWrite = 12,
+    CreateTokens = 13,
+    DescribeTokens = 14,
+}
+
+impl AclOperation {
+    /// Operations that are implicitly granted when `self` is allowed,
+    /// e.g. READ implies DESCRIBE.
+    pub fn implied(&self) -> &'static [AclOperation] {
+        use AclOperation::*;
+        match self {
+            Read | Write | Delete | Alter => &[Describe],
+            AlterConfigs => &[DescribeConfigs],
+            _ => &[],
+        }
+    }
+}
+
+#[derive(FromPrimitive, IntoPrimitive, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i8)]
+pub enum ResourceType {
+    #[default]
+    Unknown = 0,
+    Any = 1,
+    Topic = 2,
+    Group = 3,
+    Cluster = 4,
+    TransactionalId = 5,
+    DelegationToken = 6,
+    User = 7,
+}
+
+impl ResourceType {
+    /// The operations that make sense for this resource type, i.e. the bits
+    /// that can ever be set in its authorized operations bitfield.
+    pub fn supported_operations(&self) -> &'static [AclOperation] {
+        use AclOperation::*;
+        match self {
+            ResourceType::Topic => &[
+                Read, Write, Create, Delete, Alter, Describe, DescribeConfigs, AlterConfigs,
+            ],
+            ResourceType::Group => &[Read, Delete, Describe],
+            ResourceType::Cluster => &[
+                Create, Alter, Describe, ClusterAction, DescribeConfigs, AlterConfigs,
+                IdempotentWrite, CreateTokens, DescribeTokens,
+            ],
+            ResourceType::TransactionalId => &[Write, Describe],
+            ResourceType::DelegationToken => &[Describe],
+            ResourceType::User => &[CreateTokens, DescribeTokens],
+            _ => &[],
+        }
+    }
+}
+
+#[derive(FromPrimitive, IntoPrimitive, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i8)]
+pub enum PatternType {
+    #[default]
+    Unknown = 0,
+    Any = 1,
+    Match = 2,
+    Literal = 3,
+    Prefixed = 4,
+}
+
+#[derive(FromPrimitive, IntoPrimitive, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i8)]
+pub enum AclPermissionType {
+    #[default]
+    Unknown = 0,
+    Any = 1,
+    Deny = 2,
+    Allow = 3,
+}
+
+/// `<type>:<name>`, e.g. `User:alice`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KafPrincipal {
+    pub principal_type: String,
+    pub name: String,
+}
+
+impl KafPrincipal {
+    pub fn user(name: &str) -> Self {
+        KafPrincipal {
+            principal_type: "User".to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    // we don't do SASL/SSL yet so every connection is anonymous
+    pub fn anonymous() -> Self {
+        Self::user("ANONYMOUS")
+    }
+}
+
+impl fmt::Display for KafPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.principal_type, self.name)
+    }
+}
+
+pub const WILDCARD_RESOURCE: &str = "*";
+pub const WILDCARD_PRINCIPAL: &str = "User:*";
+pub const WILDCARD_HOST: &str = "*";
+/// The name of the one `Cluster` resource
+pub const CLUSTER_RESOURCE: &str = "kafka-cluster";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourcePattern {
+    pub resource_type: ResourceType,
+    pub name: String,
+    pub pattern_type: PatternType,
+}
+
+impl ResourcePattern {
+    pub fn matches(&self, resource_type: ResourceType, name: &str) -> bool {
+        if self.resource_type != resource_type && self.resource_type != ResourceType::Any {
+            return false;
+        }
+        match self.pattern_type {
+            PatternType::Literal => self.name == name || self.name == WILDCARD_RESOURCE,
+            PatternType::Prefixed => name.starts_with(&self.name),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccessControlEntry {
+    pub principal: String, // e.g. "User:alice" or "User:*"
+    pub host: String,
+    pub operation: AclOperation,
+    pub permission_type: AclPermissionType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AclBinding {
+    pub pattern: ResourcePattern,
+    pub entry: AccessControlEntry,
+}
@@ -85,3 +85,36 @@ pub enum KafApiKey {
     AlterShareGroupOffsets = 91,
     DeleteShareGroupOffsets = 92,
 }
+
+impl KafApiKey {
+    /// The version from which the api uses the flexible (KIP-482) encoding:
+    /// compact strings/arrays, tag buffers, and the v2 request / v1 response headers
+    pub fn first_flexible_version(&self) -> Option<i16> {
+        use KafApiKey::*;
+        match self {
+            Undefined | SaslHandshake | OffsetDelete => None,
+            Produce => Some(9),
+            Fetch => Some(12),
+            ListOffsets => Some(6),
+            Metadata => Some(9),
+            OffsetCommit => Some(8),
+            OffsetFetch => Some(6),
+            FindCoordinator => Some(3),
+            JoinGroup => Some(6),
+            Heartbeat | LeaveGroup | SyncGroup | DeleteTopics | OffsetForLeaderEpoch | DescribeConfigs => Some(4),
+            DescribeGroups | CreateTopics => Some(5),
+            ListGroups | ApiVersions | AddPartitionsToTxn | AddOffsetsToTxn | EndTxn | TxnOffsetCommit => Some(3),
+            DeleteRecords | InitProducerId | DescribeAcls | CreateAcls | DeleteAcls | AlterConfigs
+            | AlterReplicaLogDirs | DescribeLogDirs | SaslAuthenticate | CreatePartitions | CreateDelegationToken
+            | RenewDelegationToken | ExpireDelegationToken | DescribeDelegationToken | DeleteGroups
+            | ElectLeaders => Some(2),
+            WriteTxnMarkers | IncrementalAlterConfigs | DescribeClientQuotas | AlterClientQuotas => Some(1),
+            // everything newer was flexible from the start
+            _ => Some(0),
+        }
+    }
+
+    pub fn is_flexible(&self, version: i16) -> bool {
+        self.first_flexible_version().is_some_and(|first| version >= first)
+    }
+}
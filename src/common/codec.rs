@@ -0,0 +1,197 @@
+use bytes::Bytes;
+
+use crate::{
+    common::{EncodeToBytes, EncodingError},
+    utils::parse_primitive_types::{
+        encode_unsigned_varint, read_compact_tag_buffer, read_exact, read_i16_be, read_i32_be, read_unsigned_varint,
+    },
+};
+
+/*
+* Helpers for fields whose wire format depends on the api version: from an
+* api's first flexible version on, strings, bytes and arrays switch to their
+* COMPACT_ form (UNSIGNED_VARINT of N + 1) and structs end with a tag buffer.
+*/
+
+fn read_length(input: &[u8], offset: &mut usize, flexible: bool) -> Result<i32, EncodingError> {
+    if flexible {
+        Ok(read_unsigned_varint(input, offset)? as i32 - 1)
+    } else {
+        read_i32_be(input, offset)
+    }
+}
+
+fn write_length(buf: &mut Vec<u8>, len: i32, flexible: bool) {
+    if flexible {
+        buf.extend(encode_unsigned_varint((len + 1) as u32));
+    } else {
+        buf.extend(len.encode_to_bytes());
+    }
+}
+
+/// STRING / COMPACT_STRING
+pub fn read_string(input: &[u8], offset: &mut usize, flexible: bool) -> Result<String, EncodingError> {
+    read_nullable_string(input, offset, flexible)?.ok_or(EncodingError::InvalidLength(-1))
+}
+
+/// NULLABLE_STRING / COMPACT_NULLABLE_STRING
+pub fn read_nullable_string(input: &[u8], offset: &mut usize, flexible: bool) -> Result<Option<String>, EncodingError> {
+    let len = if flexible {
+        read_unsigned_varint(input, offset)? as i64 - 1
+    } else {
+        read_i16_be(input, offset)? as i64
+    };
+    if len == -1 {
+        return Ok(None);
+    }
+    if len < 0 {
+        return Err(EncodingError::InvalidLength(len));
+    }
+    let bytes = read_exact(input, offset, len as usize)?;
+    Ok(Some(std::str::from_utf8(bytes)?.to_string()))
+}
+
+/// NULLABLE_BYTES / COMPACT_NULLABLE_BYTES
+pub fn read_nullable_bytes(input: &[u8], offset: &mut usize, flexible: bool) -> Result<Option<Bytes>, EncodingError> {
+    let len = read_length(input, offset, flexible)?;
+    if len == -1 {
+        return Ok(None);
+    }
+    if len < 0 {
+        return Err(EncodingError::InvalidLength(len as i64));
+    }
+    Ok(Some(Bytes::copy_from_slice(read_exact(input, offset, len as usize)?)))
+}
+
+/// BYTES / COMPACT_BYTES
+pub fn read_bytes(input: &[u8], offset: &mut usize, flexible: bool) -> Result<Bytes, EncodingError> {
+    read_nullable_bytes(input, offset, flexible)?.ok_or(EncodingError::InvalidLength(-1))
+}
+
+/// ARRAY / COMPACT_ARRAY, a null array reads as empty
+pub fn read_array<T>(
+    input: &[u8],
+    offset: &mut usize,
+    flexible: bool,
+    read_item: impl FnMut(&[u8], &mut usize) -> Result<T, EncodingError>,
+) -> Result<Vec<T>, EncodingError> {
+    Ok(read_nullable_array(input, offset, flexible, read_item)?.unwrap_or_default())
+}
+
+/// Nullable ARRAY / COMPACT_ARRAY
+pub fn read_nullable_array<T>(
+    input: &[u8],
+    offset: &mut usize,
+    flexible: bool,
+    mut read_item: impl FnMut(&[u8], &mut usize) -> Result<T, EncodingError>,
+) -> Result<Option<Vec<T>>, EncodingError> {
+    let len = read_length(input, offset, flexible)?;
+    if len == -1 {
+        return Ok(None);
+    }
+    if len < -1 {
+        return Err(EncodingError::InvalidLength(len as i64));
+    }
+    // don't trust the length for the allocation, every item takes at least a byte
+    let mut items = Vec::with_capacity((len as usize).min(input.len().saturating_sub(*offset)));
+    for _ in 0..len {
+        items.push(read_item(input, offset)?);
+    }
+    Ok(Some(items))
+}
+
+/// Skip the tag buffer ending a flexible struct, no-op before the first flexible version
+pub fn skip_tagged_fields(input: &[u8], offset: &mut usize, flexible: bool) -> Result<(), EncodingError> {
+    if flexible {
+        read_compact_tag_buffer(input, offset)?;
+    }
+    Ok(())
+}
+
+pub fn write_string(buf: &mut Vec<u8>, value: &str, flexible: bool) {
+    write_nullable_string(buf, Some(value), flexible)
+}
+
+pub fn write_nullable_string(buf: &mut Vec<u8>, value: Option<&str>, flexible: bool) {
+    match (value, flexible) {
+        (None, true) => buf.push(0),
+        (None, false) => buf.extend((-1i16).encode_to_bytes()),
+        (Some(s), true) => {
+            buf.extend(encode_unsigned_varint(s.len() as u32 + 1));
+            buf.extend(s.as_bytes());
+        }
+        (Some(s), false) => {
+            buf.extend((s.len() as i16).encode_to_bytes());
+            buf.extend(s.as_bytes());
+        }
+    }
+}
+
+pub fn write_nullable_bytes(buf: &mut Vec<u8>, value: Option<&[u8]>, flexible: bool) {
+    match value {
+        None => write_length(buf, -1, flexible),
+        Some(bytes) => {
+            write_length(buf, bytes.len() as i32, flexible);
+            buf.extend(bytes);
+        }
+    }
+}
+
+pub fn write_bytes(buf: &mut Vec<u8>, value: &[u8], flexible: bool) {
+    write_nullable_bytes(buf, Some(value), flexible)
+}
+
+pub fn write_array<T>(buf: &mut Vec<u8>, items: &[T], flexible: bool, write_item: impl FnMut(&mut Vec<u8>, &T)) {
+    write_nullable_array(buf, Some(items), flexible, write_item)
+}
+
+pub fn write_nullable_array<T>(buf: &mut Vec<u8>, items: Option<&[T]>, flexible: bool, mut write_item: impl FnMut(&mut Vec<u8>, &T)) {
+    let Some(items) = items else {
+        return write_length(buf, -1, flexible);
+    };
+    write_length(buf, items.len() as i32, flexible);
+    for item in items {
+        write_item(buf, item);
+    }
+}
+
+/// An empty tag buffer, we never send tagged fields
+pub fn write_tagged_fields(buf: &mut Vec<u8>, flexible: bool) {
+    if flexible {
+        buf.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_arrays_and_bytes_round_trip_in_both_forms() {
+        for flexible in [false, true] {
+            let mut buf = vec![];
+            write_string(&mut buf, "topic", flexible);
+            write_nullable_string(&mut buf, None, flexible);
+            write_nullable_bytes(&mut buf, Some(b"abc"), flexible);
+            write_nullable_bytes(&mut buf, None, flexible);
+            write_array(&mut buf, &[1i32, 2, 3], flexible, |buf, i| buf.extend(i.encode_to_bytes()));
+            write_nullable_array::<i32>(&mut buf, None, flexible, |buf, i| buf.extend(i.encode_to_bytes()));
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            assert_eq!(read_string(&buf, &mut offset, flexible).unwrap(), "topic");
+            assert_eq!(read_nullable_string(&buf, &mut offset, flexible).unwrap(), None);
+            assert_eq!(read_nullable_bytes(&buf, &mut offset, flexible).unwrap().unwrap(), &b"abc"[..]);
+            assert_eq!(read_nullable_bytes(&buf, &mut offset, flexible).unwrap(), None);
+            let items = read_array(&buf, &mut offset, flexible, read_i32_be).unwrap();
+            assert_eq!(items, vec![1, 2, 3]);
+            assert_eq!(read_nullable_array(&buf, &mut offset, flexible, read_i32_be).unwrap(), None);
+            skip_tagged_fields(&buf, &mut offset, flexible).unwrap();
+            assert_eq!(offset, buf.len());
+        }
+        // "topic" as a COMPACT_STRING
+        let mut buf = vec![];
+        write_string(&mut buf, "topic", true);
+        assert_eq!(buf[0], 6);
+    }
+}
@@ -1,14 +1,270 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}};
 
 use lazy_static::lazy_static;
 
-use crate::common::{api::{api_key::KafApiKey, api_version_entry::ApiVersionEntry}, types::CompactArray};
+use crate::{
+    common::{
+        api::{api_key::KafApiKey, api_version_entry::ApiVersionEntry},
+        config_def::{ConfigDef, ConfigKey, ConfigType, Validator},
+        types::CompactArray,
+    },
+    coordinator::assignor::ConsumerGroupAssignor,
+    log::config::{CLEANUP_POLICIES, COMPRESSION_TYPES, TIMESTAMP_TYPES},
+};
 
 
 lazy_static! {
     pub static ref SUPPORTED_API: HashMap<KafApiKey, ApiVersionEntry> = HashMap::from([
-        // (KafApiKey::Fetch, ApiVersionEntry::new(KafApiKey::Fetch, 0, 17)),
+        (KafApiKey::Produce, ApiVersionEntry::new(KafApiKey::Produce, 3, 11)),
+        (KafApiKey::Fetch, ApiVersionEntry::new(KafApiKey::Fetch, 0, 17)),
+        (KafApiKey::ListOffsets, ApiVersionEntry::new(KafApiKey::ListOffsets, 1, 10)),
+        (KafApiKey::Metadata, ApiVersionEntry::new(KafApiKey::Metadata, 0, 12)),
+        (KafApiKey::OffsetCommit, ApiVersionEntry::new(KafApiKey::OffsetCommit, 2, 8)),
+        (KafApiKey::OffsetFetch, ApiVersionEntry::new(KafApiKey::OffsetFetch, 1, 8)),
+        (KafApiKey::FindCoordinator, ApiVersionEntry::new(KafApiKey::FindCoordinator, 0, 4)),
+        (KafApiKey::JoinGroup, ApiVersionEntry::new(KafApiKey::JoinGroup, 0, 9)),
+        (KafApiKey::Heartbeat, ApiVersionEntry::new(KafApiKey::Heartbeat, 0, 4)),
+        (KafApiKey::LeaveGroup, ApiVersionEntry::new(KafApiKey::LeaveGroup, 0, 5)),
+        (KafApiKey::SyncGroup, ApiVersionEntry::new(KafApiKey::SyncGroup, 0, 5)),
+        (KafApiKey::DescribeGroups, ApiVersionEntry::new(KafApiKey::DescribeGroups, 0, 5)),
+        (KafApiKey::ListGroups, ApiVersionEntry::new(KafApiKey::ListGroups, 0, 5)),
+        (KafApiKey::DeleteGroups, ApiVersionEntry::new(KafApiKey::DeleteGroups, 0, 2)),
+        (KafApiKey::OffsetDelete, ApiVersionEntry::new(KafApiKey::OffsetDelete, 0, 0)),
+        (KafApiKey::ConsumerGroupHeartbeat, ApiVersionEntry::new(KafApiKey::ConsumerGroupHeartbeat, 0, 1)),
+        (KafApiKey::ConsumerGroupDescribe, ApiVersionEntry::new(KafApiKey::ConsumerGroupDescribe, 0, 0)),
+        (KafApiKey::CreateTopics, ApiVersionEntry::new(KafApiKey::CreateTopics, 2, 7)),
+        (KafApiKey::DeleteTopics, ApiVersionEntry::new(KafApiKey::DeleteTopics, 1, 6)),
+        (KafApiKey::DeleteRecords, ApiVersionEntry::new(KafApiKey::DeleteRecords, 0, 2)),
+        (KafApiKey::DescribeConfigs, ApiVersionEntry::new(KafApiKey::DescribeConfigs, 1, 4)),
+        (KafApiKey::AlterConfigs, ApiVersionEntry::new(KafApiKey::AlterConfigs, 0, 2)),
+        (KafApiKey::CreatePartitions, ApiVersionEntry::new(KafApiKey::CreatePartitions, 0, 3)),
+        (KafApiKey::IncrementalAlterConfigs, ApiVersionEntry::new(KafApiKey::IncrementalAlterConfigs, 0, 1)),
         (KafApiKey::ApiVersions, ApiVersionEntry::new(KafApiKey::ApiVersions, 0, 4)),
         (KafApiKey::DescribeTopicPartitions, ApiVersionEntry::new(KafApiKey::DescribeTopicPartitions, 0, 0)),
     ]);
 }
+
+pub const DEFAULT_LOG_DIR: &str = "/tmp/kraft-combined-logs";
+pub const DEFAULT_PORT: i32 = 9092;
+
+lazy_static! {
+    /// The broker configs, set in `server.properties`. The topic defaults
+    /// (`log.retention.ms`, ...) can also be changed at runtime, see
+    /// `is_dynamic_broker_config`.
+    pub static ref BROKER_CONFIGS: ConfigDef = ConfigDef::new(vec![
+        ConfigKey::new("node.id", ConfigType::Int, Some("1"), Validator::AtLeast(0), "The id of this broker."),
+        ConfigKey::new("log.dirs", ConfigType::List, Some(DEFAULT_LOG_DIR), Validator::None,
+            "The directories partition logs are kept in."),
+        ConfigKey::new("listeners", ConfigType::List, None, Validator::None, "The addresses the broker listens on."),
+        ConfigKey::new("advertised.listeners", ConfigType::List, None, Validator::None,
+            "The addresses clients are told to connect to, `listeners` if unset."),
+        ConfigKey::new("broker.rack", ConfigType::String, None, Validator::None, "The rack of this broker."),
+        ConfigKey::new("num.partitions", ConfigType::Int, Some("1"), Validator::AtLeast(1),
+            "Number of partitions of topics created without one."),
+        ConfigKey::new("default.replication.factor", ConfigType::Int, Some("1"), Validator::AtLeast(1),
+            "Replication factor of topics created without one."),
+        ConfigKey::new("auto.create.topics.enable", ConfigType::Boolean, Some("true"), Validator::None,
+            "Create topics that Metadata requests ask for and don't exist."),
+        ConfigKey::new("delete.topic.enable", ConfigType::Boolean, Some("true"), Validator::None,
+            "Allow DeleteTopics."),
+        ConfigKey::new("super.users", ConfigType::String, None, Validator::None,
+            "Principals allowed everything whatever the ACLs, separated by ';', e.g. User:admin."),
+        ConfigKey::new("allow.everyone.if.no.acl.found", ConfigType::Boolean, Some("true"), Validator::None,
+            "Allow everyone to access resources no ACL matches."),
+        ConfigKey::new("log.retention.check.interval.ms", ConfigType::Long, Some("300000"), Validator::AtLeast(1),
+            "How often retention is enforced."),
+        ConfigKey::new("log.cleaner.enable", ConfigType::Boolean, Some("true"), Validator::None,
+            "Run the log cleaner, which compacts topics."),
+        ConfigKey::new("log.cleaner.backoff.ms", ConfigType::Long, Some("15000"), Validator::AtLeast(0),
+            "How long the log cleaner sleeps between runs."),
+        ConfigKey::new("log.segment.delete.delay.ms", ConfigType::Long, Some("60000"), Validator::AtLeast(0),
+            "How long the files of a deleted log stay around."),
+        ConfigKey::new("log.flush.offset.checkpoint.interval.ms", ConfigType::Int, Some("60000"), Validator::AtLeast(0),
+            "How often recovery points are checkpointed."),
+        ConfigKey::new("log.flush.start.offset.checkpoint.interval.ms", ConfigType::Int, Some("60000"), Validator::AtLeast(0),
+            "How often log start offsets are checkpointed."),
+        ConfigKey::new("max.incremental.fetch.session.cache.slots", ConfigType::Int, Some("1000"), Validator::AtLeast(0),
+            "Maximum number of incremental fetch sessions."),
+        ConfigKey::new("group.min.session.timeout.ms", ConfigType::Int, Some("6000"), Validator::AtLeast(0),
+            "The smallest session.timeout.ms consumers may use."),
+        ConfigKey::new("group.max.session.timeout.ms", ConfigType::Int, Some("1800000"), Validator::AtLeast(0),
+            "The largest session.timeout.ms consumers may use."),
+        ConfigKey::new("group.initial.rebalance.delay.ms", ConfigType::Int, Some("3000"), Validator::AtLeast(0),
+            "How long the first rebalance of an empty group waits for more members to join."),
+        ConfigKey::new("group.max.size", ConfigType::Int, Some("2147483647"), Validator::AtLeast(1),
+            "Maximum number of members of a group."),
+        ConfigKey::new("group.consumer.heartbeat.interval.ms", ConfigType::Int, Some("5000"), Validator::AtLeast(1),
+            "How often members of consumer groups heartbeat."),
+        ConfigKey::new("group.consumer.session.timeout.ms", ConfigType::Int, Some("45000"), Validator::AtLeast(1),
+            "How long members of consumer groups may go without heartbeating."),
+        ConfigKey::new("group.consumer.max.size", ConfigType::Int, Some("2147483647"), Validator::AtLeast(1),
+            "Maximum number of members of a consumer group."),
+        ConfigKey::new("group.consumer.assignors", ConfigType::List, Some("uniform,range"),
+            Validator::ListOf(ConsumerGroupAssignor::NAMES),
+            "Assignors consumer groups may use, the first one by default."),
+        ConfigKey::new("offsets.topic.num.partitions", ConfigType::Int, Some("50"), Validator::AtLeast(1),
+            "Number of partitions of __consumer_offsets."),
+        ConfigKey::new("offsets.topic.replication.factor", ConfigType::Short, Some("1"), Validator::AtLeast(1),
+            "Replication factor of __consumer_offsets."),
+        ConfigKey::new("offsets.topic.segment.bytes", ConfigType::Int, Some("104857600"), Validator::AtLeast(1),
+            "segment.bytes of __consumer_offsets."),
+        ConfigKey::new("offsets.retention.minutes", ConfigType::Int, Some("10080"), Validator::AtLeast(1),
+            "How long committed offsets are kept after their group becomes empty."),
+        ConfigKey::new("offsets.retention.check.interval.ms", ConfigType::Long, Some("600000"), Validator::AtLeast(1),
+            "How often expired offsets are removed."),
+        ConfigKey::new("offset.metadata.max.bytes", ConfigType::Int, Some("4096"), Validator::AtLeast(0),
+            "Longest metadata an offset commit may carry."),
+        ConfigKey::new("log.segment.bytes", ConfigType::Int, Some("1073741824"), Validator::AtLeast(14),
+            "Default segment.bytes."),
+        ConfigKey::new("log.roll.ms", ConfigType::Long, None, Validator::AtLeast(1),
+            "Default segment.ms, `log.roll.hours` if unset."),
+        ConfigKey::new("log.roll.hours", ConfigType::Int, Some("168"), Validator::AtLeast(1),
+            "Default segment.ms, in hours."),
+        ConfigKey::new("log.index.interval.bytes", ConfigType::Int, Some("4096"), Validator::AtLeast(0),
+            "Default index.interval.bytes."),
+        ConfigKey::new("log.index.size.max.bytes", ConfigType::Int, Some("10485760"), Validator::AtLeast(4),
+            "Default segment.index.bytes."),
+        ConfigKey::new("log.retention.ms", ConfigType::Long, None, Validator::AtLeast(-1),
+            "Default retention.ms, `log.retention.minutes` if unset."),
+        ConfigKey::new("log.retention.minutes", ConfigType::Int, None, Validator::AtLeast(-1),
+            "Default retention.ms in minutes, `log.retention.hours` if unset."),
+        ConfigKey::new("log.retention.hours", ConfigType::Int, Some("168"), Validator::AtLeast(-1),
+            "Default retention.ms, in hours."),
+        ConfigKey::new("log.retention.bytes", ConfigType::Long, Some("-1"), Validator::AtLeast(-1),
+            "Default retention.bytes."),
+        ConfigKey::new("log.cleanup.policy", ConfigType::List, Some("delete"), Validator::ListOf(CLEANUP_POLICIES),
+            "Default cleanup.policy."),
+        ConfigKey::new("log.cleaner.delete.retention.ms", ConfigType::Long, Some("86400000"), Validator::AtLeast(0),
+            "Default delete.retention.ms."),
+        ConfigKey::new("log.cleaner.min.cleanable.ratio", ConfigType::Double, Some("0.5"), Validator::Between(0.0, 1.0),
+            "Default min.cleanable.dirty.ratio."),
+        ConfigKey::new("compression.type", ConfigType::String, Some("producer"), Validator::OneOf(COMPRESSION_TYPES),
+            "Default compression.type."),
+        ConfigKey::new("log.message.timestamp.type", ConfigType::String, Some("CreateTime"), Validator::OneOf(TIMESTAMP_TYPES),
+            "Default message.timestamp.type."),
+        ConfigKey::new("log.flush.interval.messages", ConfigType::Long, Some("9223372036854775807"), Validator::AtLeast(1),
+            "Default flush.messages."),
+        ConfigKey::new("log.flush.interval.ms", ConfigType::Long, None, Validator::AtLeast(0),
+            "Default flush.ms, `log.flush.scheduler.interval.ms` if unset."),
+        ConfigKey::new("log.flush.scheduler.interval.ms", ConfigType::Long, Some("9223372036854775807"), Validator::AtLeast(0),
+            "How often logs are checked for a due flush."),
+        ConfigKey::new("message.max.bytes", ConfigType::Int, Some("1048588"), Validator::AtLeast(0),
+            "Default max.message.bytes."),
+    ]);
+}
+
+/// Broker settings, read from the `server.properties` file given on the command line
+#[derive(Debug, Clone)]
+pub struct BrokerConfig {
+    pub node_id: i32,
+    pub log_dirs: Vec<PathBuf>,
+    // everything from the file, for settings that are looked up by name (e.g. topic defaults)
+    pub props: HashMap<String, String>,
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        Self::from_props(HashMap::new())
+    }
+}
+
+impl BrokerConfig {
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Ok(Self::from_props(parse_properties(&fs::read_to_string(path)?)))
+    }
+
+    pub fn from_props(props: HashMap<String, String>) -> Self {
+        let node_id = props
+            .get("node.id")
+            .or_else(|| props.get("broker.id"))
+            .and_then(|id| id.parse().ok())
+            .unwrap_or(1);
+
+        let log_dirs = props
+            .get("log.dirs")
+            .or_else(|| props.get("log.dir"))
+            .map(|dirs| dirs.split(',').map(|d| PathBuf::from(d.trim())).collect())
+            .unwrap_or_else(|| vec![PathBuf::from(DEFAULT_LOG_DIR)]);
+
+        BrokerConfig {
+            node_id,
+            log_dirs,
+            props,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.props.get(key).map(String::as_str)
+    }
+
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get(key).and_then(|v| v.trim().parse().ok())
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(|v| v.trim().parse().ok())
+    }
+
+    /// Host and port clients are told to connect to: the first of
+    /// `advertised.listeners`, else of `listeners`, e.g. `PLAINTEXT://localhost:9092`
+    pub fn advertised_listener(&self) -> (String, i32) {
+        let listener = self
+            .get("advertised.listeners")
+            .or_else(|| self.get("listeners"))
+            .and_then(|listeners| listeners.split(',').next())
+            .map(|listener| {
+                let listener = listener.trim();
+                listener.split_once("://").map_or(listener, |(_, address)| address)
+            });
+        let (host, port) = listener.and_then(|address| address.rsplit_once(':')).unwrap_or(("", ""));
+        let host = if host.is_empty() { "localhost" } else { host };
+        (host.to_string(), port.parse().unwrap_or(DEFAULT_PORT))
+    }
+
+    pub fn rack(&self) -> Option<String> {
+        self.get("broker.rack").map(str::to_string)
+    }
+
+    /// This config with the dynamic broker configs on top: the cluster wide
+    /// defaults, then the ones set for this broker
+    pub fn with_dynamic_configs(&self, cluster_defaults: &HashMap<String, String>, broker: &HashMap<String, String>) -> Self {
+        let mut props = self.props.clone();
+        props.extend(cluster_defaults.iter().chain(broker).map(|(k, v)| (k.clone(), v.clone())));
+        Self::from_props(props)
+    }
+}
+
+/// Java `.properties` style: `key=value` lines, `#` and `!` start comments
+pub fn parse_properties(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=').or_else(|| line.split_once(':'))?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advertised_listener_falls_back_to_listeners_then_localhost() {
+        let config = |props: &[(&str, &str)]| {
+            BrokerConfig::from_props(props.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+        };
+        assert_eq!(config(&[]).advertised_listener(), ("localhost".to_string(), DEFAULT_PORT));
+        assert_eq!(
+            config(&[("listeners", "PLAINTEXT://:9093,CONTROLLER://:9094")]).advertised_listener(),
+            ("localhost".to_string(), 9093)
+        );
+        assert_eq!(
+            config(&[("listeners", "PLAINTEXT://:9093"), ("advertised.listeners", "PLAINTEXT://kafka-1:19092")])
+                .advertised_listener(),
+            ("kafka-1".to_string(), 19092)
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+/// Where the value of a config comes from, `config_source` in DescribeConfigs
+/// and CreateTopics. Lower values take precedence.
+pub mod config_source {
+    pub const DYNAMIC_TOPIC_CONFIG: i8 = 1;
+    pub const DYNAMIC_BROKER_CONFIG: i8 = 2;
+    pub const DYNAMIC_DEFAULT_BROKER_CONFIG: i8 = 3;
+    pub const STATIC_BROKER_CONFIG: i8 = 4;
+    pub const DEFAULT_CONFIG: i8 = 5;
+}
+
+/// Type of a config's value, `config_type` in DescribeConfigs v3+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigType {
+    Boolean,
+    String,
+    Int,
+    Short,
+    Long,
+    Double,
+    /// comma separated
+    List,
+    /// a string never shown by DescribeConfigs
+    Password,
+}
+
+impl ConfigType {
+    pub fn id(&self) -> i8 {
+        match self {
+            ConfigType::Boolean => 1,
+            ConfigType::String => 2,
+            ConfigType::Int => 3,
+            ConfigType::Short => 4,
+            ConfigType::Long => 5,
+            ConfigType::Double => 6,
+            ConfigType::List => 7,
+            ConfigType::Password => 9,
+        }
+    }
+
+    fn parses(&self, value: &str) -> bool {
+        let value = value.trim();
+        match self {
+            ConfigType::Boolean => value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false"),
+            ConfigType::Int => value.parse::<i32>().is_ok(),
+            ConfigType::Short => value.parse::<i16>().is_ok(),
+            ConfigType::Long => value.parse::<i64>().is_ok(),
+            ConfigType::Double => value.parse::<f64>().is_ok(),
+            ConfigType::String | ConfigType::List | ConfigType::Password => true,
+        }
+    }
+}
+
+/// What a value must look like once it parses as the config's type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Validator {
+    None,
+    AtLeast(i64),
+    Between(f64, f64),
+    OneOf(&'static [&'static str]),
+    /// a non-empty list of these
+    ListOf(&'static [&'static str]),
+}
+
+/// The definition of one config
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigKey {
+    pub name: &'static str,
+    pub config_type: ConfigType,
+    /// None when the config is unset unless given
+    pub default: Option<&'static str>,
+    pub validator: Validator,
+    pub documentation: &'static str,
+}
+
+impl ConfigKey {
+    pub const fn new(
+        name: &'static str,
+        config_type: ConfigType,
+        default: Option<&'static str>,
+        validator: Validator,
+        documentation: &'static str,
+    ) -> Self {
+        ConfigKey {
+            name,
+            config_type,
+            default,
+            validator,
+            documentation,
+        }
+    }
+
+    pub fn is_sensitive(&self) -> bool {
+        self.config_type == ConfigType::Password
+    }
+
+    /// Err with the reason if `value` isn't valid for this config
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let invalid = || Err(format!("Invalid value {} for configuration {}", value, self.name));
+        if !self.config_type.parses(value) {
+            return invalid();
+        }
+        let value = value.trim();
+        let valid = match self.validator {
+            Validator::None => true,
+            Validator::AtLeast(min) => value.parse::<i64>().is_ok_and(|v| v >= min),
+            Validator::Between(min, max) => value.parse::<f64>().is_ok_and(|v| (min..=max).contains(&v)),
+            Validator::OneOf(valid) => valid.contains(&value),
+            Validator::ListOf(valid) => {
+                let items = list_items(value);
+                !items.is_empty() && items.iter().all(|item| valid.contains(item))
+            }
+        };
+        if !valid {
+            return invalid();
+        }
+        Ok(())
+    }
+}
+
+/// The items of a list config, `a, b` is `["a", "b"]`
+pub fn list_items(value: &str) -> Vec<&str> {
+    value.split(',').map(str::trim).filter(|item| !item.is_empty()).collect()
+}
+
+/// The configs of one kind of resource, by name
+#[derive(Debug, Clone, Default)]
+pub struct ConfigDef {
+    keys: BTreeMap<&'static str, ConfigKey>,
+}
+
+impl ConfigDef {
+    pub fn new(keys: Vec<ConfigKey>) -> Self {
+        ConfigDef {
+            keys: keys.into_iter().map(|key| (key.name, key)).collect(),
+        }
+    }
+
+    pub fn key(&self, name: &str) -> Option<&ConfigKey> {
+        self.keys.get(name)
+    }
+
+    /// In name order
+    pub fn keys(&self) -> impl Iterator<Item = &ConfigKey> {
+        self.keys.values()
+    }
+
+    /// Err with the reason if `name` isn't a config we know or `value` isn't valid for it
+    pub fn validate(&self, name: &str, value: &str) -> Result<(), String> {
+        match self.key(name) {
+            Some(key) => key.validate(value),
+            None => Err(format!("Unknown config name {}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_checked_against_their_type_and_validator() {
+        let def = ConfigDef::new(vec![
+            ConfigKey::new("segment.bytes", ConfigType::Int, Some("1024"), Validator::AtLeast(14), ""),
+            ConfigKey::new("ratio", ConfigType::Double, None, Validator::Between(0.0, 1.0), ""),
+            ConfigKey::new("policy", ConfigType::List, None, Validator::ListOf(&["delete", "compact"]), ""),
+            ConfigKey::new("enabled", ConfigType::Boolean, None, Validator::None, ""),
+            ConfigKey::new("secret", ConfigType::Password, None, Validator::None, ""),
+        ]);
+        assert!(def.validate("segment.bytes", "14").is_ok());
+        assert!(def.validate("segment.bytes", "13").is_err());
+        // too big for an int
+        assert!(def.validate("segment.bytes", "4294967296").is_err());
+        assert!(def.validate("ratio", "0.5").is_ok() && def.validate("ratio", "1.5").is_err());
+        assert!(def.validate("policy", "compact, delete").is_ok());
+        assert!(def.validate("policy", "compact,archive").is_err() && def.validate("policy", "").is_err());
+        assert!(def.validate("enabled", "TRUE").is_ok() && def.validate("enabled", "yes").is_err());
+        assert!(def.validate("unknown", "1").unwrap_err().contains("Unknown config"));
+        assert!(def.key("secret").unwrap().is_sensitive() && !def.key("ratio").unwrap().is_sensitive());
+        assert_eq!(def.keys().map(|key| key.name).collect::<Vec<_>>(), ["enabled", "policy", "ratio", "secret", "segment.bytes"]);
+    }
+}
@@ -1,11 +1,16 @@
 use crate::{StrError};
 
+pub mod acl;
 pub mod api;
+pub mod codec;
 pub mod config;
+pub mod config_def;
 pub mod error;
 pub mod response;
 pub mod request;
+pub mod topic_partition;
 pub mod types;
+pub mod uuid;
 
 #[derive(thiserror::Error, Debug)]
 pub enum EncodingError {
@@ -19,6 +24,8 @@ pub enum EncodingError {
     VarIntOverflow,
     #[error("invalid length: {0}")]
     InvalidLength(i64),
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(i64),
 }
 
 
@@ -30,3 +37,13 @@ pub trait DecodeFromBytes {
 pub trait EncodeToBytes {
     fn encode_to_bytes(&self) -> Vec<u8>;
 }
+
+/// For request and response bodies whose layout depends on the api version
+pub trait DecodeVersioned {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError>
+        where Self: Sized;
+}
+
+pub trait EncodeVersioned {
+    fn encode_versioned(&self, version: i16) -> Vec<u8>;
+}
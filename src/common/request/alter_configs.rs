@@ -0,0 +1,104 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i8_be, read_u8_be},
+};
+
+/*
+* AlterConfigs Request (Version: 0-2) => [resources] validate_only _tagged_fields (v2+)
+* resources => resource_type resource_name [configs] _tagged_fields (v2+)
+*   configs => name value _tagged_fields (v2+)
+*     the configs replace every config set on the resource
+*/
+#[derive(Debug, Clone)]
+pub struct AlterConfigsBody {
+    pub resources: Vec<AlterConfigsResource>,
+    pub validate_only: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AlterConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub configs: Vec<AlterableConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlterableConfig {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl DecodeVersioned for AlterConfigsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::AlterConfigs.is_flexible(version);
+        let resources = read_array(input, offset, flexible, |input, offset| {
+            let resource = AlterConfigsResource {
+                resource_type: read_i8_be(input, offset)?,
+                resource_name: read_string(input, offset, flexible)?,
+                configs: read_array(input, offset, flexible, |input, offset| {
+                    let config = AlterableConfig {
+                        name: read_string(input, offset, flexible)?,
+                        value: read_nullable_string(input, offset, flexible)?,
+                    };
+                    skip_tagged_fields(input, offset, flexible)?;
+                    Ok(config)
+                })?,
+            };
+            skip_tagged_fields(input, offset, flexible)?;
+            Ok(resource)
+        })?;
+        let body = AlterConfigsBody {
+            resources,
+            validate_only: read_u8_be(input, offset)? != 0,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_the_configs_of_each_resource() {
+        for version in [0, 2] {
+            let flexible = version >= 2;
+            let mut buf = vec![];
+            write_array(&mut buf, &["orders"], flexible, |buf, name| {
+                buf.extend(2i8.encode_to_bytes());
+                write_string(buf, name, flexible);
+                write_array(buf, &[("retention.ms", Some("1000")), ("segment.ms", None)], flexible, |buf, (name, value)| {
+                    write_string(buf, name, flexible);
+                    write_nullable_string(buf, *value, flexible);
+                    write_tagged_fields(buf, flexible);
+                });
+                write_tagged_fields(buf, flexible);
+            });
+            buf.extend(true.encode_to_bytes());
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = AlterConfigsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            let resource = &body.resources[0];
+            assert_eq!((resource.resource_type, resource.resource_name.as_str()), (2, "orders"));
+            assert_eq!(
+                resource.configs,
+                vec![
+                    AlterableConfig { name: "retention.ms".to_string(), value: Some("1000".to_string()) },
+                    AlterableConfig { name: "segment.ms".to_string(), value: None },
+                ]
+            );
+            assert!(body.validate_only);
+        }
+    }
+}
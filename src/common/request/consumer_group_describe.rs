@@ -0,0 +1,28 @@
+use crate::{
+    common::{
+        codec::{read_array, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_u8_be,
+};
+
+/*
+* ConsumerGroupDescribe Request (Version: 0) => [group_ids] include_authorized_operations _tagged_fields
+*   group_ids => string
+*/
+#[derive(Debug, Clone)]
+pub struct ConsumerGroupDescribeBody {
+    pub group_ids: Vec<String>,
+    pub include_authorized_operations: bool,
+}
+
+impl DecodeVersioned for ConsumerGroupDescribeBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, _version: i16) -> Result<Self, EncodingError> {
+        let body = ConsumerGroupDescribeBody {
+            group_ids: read_array(input, offset, true, |input, offset| read_string(input, offset, true))?,
+            include_authorized_operations: read_u8_be(input, offset)? != 0,
+        };
+        skip_tagged_fields(input, offset, true)?;
+        Ok(body)
+    }
+}
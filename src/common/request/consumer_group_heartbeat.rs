@@ -0,0 +1,124 @@
+use crate::{
+    common::{
+        codec::{read_array, read_nullable_array, read_nullable_string, read_string, skip_tagged_fields},
+        uuid::KafUuid,
+        DecodeFromBytes, DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_i32_be,
+};
+
+/// member_epoch of a member joining the group
+pub const JOIN_GROUP_MEMBER_EPOCH: i32 = 0;
+/// member_epoch of a member leaving the group
+pub const LEAVE_GROUP_MEMBER_EPOCH: i32 = -1;
+/// member_epoch of a static member leaving for a while, keeping its partitions
+pub const LEAVE_GROUP_STATIC_MEMBER_EPOCH: i32 = -2;
+
+/*
+* ConsumerGroupHeartbeat Request (Version: 0-1) => group_id member_id member_epoch instance_id rack_id rebalance_timeout_ms
+*                                                  [subscribed_topic_names] subscribed_topic_regex (v1+) server_assignor
+*                                                  [topic_partitions] _tagged_fields
+*   instance_id, rack_id, server_assignor => null if unchanged since the last heartbeat
+*   rebalance_timeout_ms => -1 if unchanged
+*   subscribed_topic_names, subscribed_topic_regex, topic_partitions => nullable, null if unchanged
+*   topic_partitions => topic_id [partitions] _tagged_fields, the partitions the member owns
+*
+* Since v1 members pick their member_id, before it the coordinator does.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerGroupHeartbeatBody {
+    pub group_id: String,
+    pub member_id: String,
+    pub member_epoch: i32,
+    pub instance_id: Option<String>,
+    pub rack_id: Option<String>,
+    pub rebalance_timeout_ms: i32,
+    pub subscribed_topic_names: Option<Vec<String>>,
+    pub subscribed_topic_regex: Option<String>,
+    pub server_assignor: Option<String>,
+    pub topic_partitions: Option<Vec<TopicPartitions>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicPartitions {
+    pub topic_id: KafUuid,
+    pub partitions: Vec<i32>,
+}
+
+impl TopicPartitions {
+    pub fn read(input: &[u8], offset: &mut usize) -> Result<Self, EncodingError> {
+        let topic_partitions = TopicPartitions {
+            topic_id: KafUuid::read_from_u8(input, offset)?,
+            partitions: read_array(input, offset, true, read_i32_be)?,
+        };
+        skip_tagged_fields(input, offset, true)?;
+        Ok(topic_partitions)
+    }
+}
+
+impl DecodeVersioned for ConsumerGroupHeartbeatBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let body = ConsumerGroupHeartbeatBody {
+            group_id: read_string(input, offset, true)?,
+            member_id: read_string(input, offset, true)?,
+            member_epoch: read_i32_be(input, offset)?,
+            instance_id: read_nullable_string(input, offset, true)?,
+            rack_id: read_nullable_string(input, offset, true)?,
+            rebalance_timeout_ms: read_i32_be(input, offset)?,
+            subscribed_topic_names: read_nullable_array(input, offset, true, |input, offset| read_string(input, offset, true))?,
+            subscribed_topic_regex: if version >= 1 { read_nullable_string(input, offset, true)? } else { None },
+            server_assignor: read_nullable_string(input, offset, true)?,
+            topic_partitions: read_nullable_array(input, offset, true, TopicPartitions::read)?,
+        };
+        skip_tagged_fields(input, offset, true)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_array, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_a_join_and_a_heartbeat_with_owned_partitions() {
+        for version in [0, 1] {
+            let mut buf = vec![];
+            write_string(&mut buf, "orders-app", true);
+            write_string(&mut buf, "", true);
+            buf.extend(7i32.encode_to_bytes());
+            write_nullable_string(&mut buf, None, true);
+            write_nullable_string(&mut buf, Some("rack-a"), true);
+            buf.extend((-1i32).encode_to_bytes());
+            write_nullable_array(&mut buf, Some(&["orders"][..]), true, |buf, name| write_string(buf, name, true));
+            if version >= 1 {
+                write_nullable_string(&mut buf, Some("pay.*"), true);
+            }
+            write_nullable_string(&mut buf, Some("range"), true);
+            write_nullable_array(&mut buf, Some(&[KafUuid([1; 16])][..]), true, |buf, topic_id| {
+                buf.extend(topic_id.encode_to_bytes());
+                write_array(buf, &[0, 2], true, |buf, partition| buf.extend(partition.encode_to_bytes()));
+                write_tagged_fields(buf, true);
+            });
+            write_tagged_fields(&mut buf, true);
+
+            let mut offset = 0;
+            let body = ConsumerGroupHeartbeatBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!((body.member_epoch, body.rack_id.as_deref()), (7, Some("rack-a")));
+            assert_eq!(body.subscribed_topic_names, Some(vec!["orders".to_string()]));
+            assert_eq!(body.subscribed_topic_regex.as_deref(), (version >= 1).then_some("pay.*"));
+            assert_eq!(body.server_assignor.as_deref(), Some("range"));
+            assert_eq!(
+                body.topic_partitions,
+                Some(vec![TopicPartitions {
+                    topic_id: KafUuid([1; 16]),
+                    partitions: vec![0, 2]
+                }])
+            );
+        }
+    }
+}
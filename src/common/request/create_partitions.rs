@@ -0,0 +1,93 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_array, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i32_be, read_u8_be},
+};
+
+/*
+* CreatePartitions Request (Version: 0-3) => [topics] timeout_ms validate_only _tagged_fields (v2+)
+* topics => name count [assignments] _tagged_fields (v2+)
+*   count => INT32, the total number of partitions the topic should have
+*   assignments => [broker_ids] _tagged_fields (v2+), nullable
+*/
+#[derive(Debug, Clone)]
+pub struct CreatePartitionsBody {
+    pub topics: Vec<CreatePartitionsTopic>,
+    pub timeout_ms: i32,
+    pub validate_only: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatePartitionsTopic {
+    pub name: String,
+    pub count: i32,
+    /// the replicas of each new partition, None to let the broker place them
+    pub assignments: Option<Vec<Vec<i32>>>,
+}
+
+impl DecodeVersioned for CreatePartitionsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::CreatePartitions.is_flexible(version);
+        let topics = read_array(input, offset, flexible, |input, offset| {
+            let topic = CreatePartitionsTopic {
+                name: read_string(input, offset, flexible)?,
+                count: read_i32_be(input, offset)?,
+                assignments: read_nullable_array(input, offset, flexible, |input, offset| {
+                    let broker_ids = read_array(input, offset, flexible, read_i32_be)?;
+                    skip_tagged_fields(input, offset, flexible)?;
+                    Ok(broker_ids)
+                })?,
+            };
+            skip_tagged_fields(input, offset, flexible)?;
+            Ok(topic)
+        })?;
+        let body = CreatePartitionsBody {
+            topics,
+            timeout_ms: read_i32_be(input, offset)?,
+            validate_only: read_u8_be(input, offset)? != 0,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_array, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_explicit_and_broker_chosen_assignments() {
+        for version in [1, 3] {
+            let flexible = version >= 2;
+            let topics: [(&str, Option<Vec<Vec<i32>>>); 2] = [("orders", Some(vec![vec![1], vec![2]])), ("payments", None)];
+            let mut buf = vec![];
+            write_array(&mut buf, &topics, flexible, |buf, (name, assignments)| {
+                write_string(buf, name, flexible);
+                buf.extend(4i32.encode_to_bytes());
+                write_nullable_array(buf, assignments.as_deref(), flexible, |buf, ids| {
+                    write_array(buf, ids, flexible, |buf, id| buf.extend(id.encode_to_bytes()));
+                    write_tagged_fields(buf, flexible);
+                });
+                write_tagged_fields(buf, flexible);
+            });
+            buf.extend(30_000i32.encode_to_bytes());
+            buf.push(1);
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = CreatePartitionsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!(body.topics[0].assignments, Some(vec![vec![1], vec![2]]));
+            assert_eq!((body.topics[1].name.as_str(), body.topics[1].count), ("payments", 4));
+            assert!(body.topics[1].assignments.is_none());
+            assert!(body.validate_only);
+        }
+    }
+}
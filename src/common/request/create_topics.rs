@@ -0,0 +1,136 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i16_be, read_i32_be, read_u8_be},
+};
+
+/*
+* CreateTopics Request (Version: 2-7) => [topics] timeout_ms validate_only _tagged_fields (v5+)
+* topics => name num_partitions replication_factor [assignments] [configs] _tagged_fields (v5+)
+*   num_partitions => INT32, -1 for the broker's num.partitions or with assignments
+*   replication_factor => INT16, -1 for default.replication.factor or with assignments
+*   assignments => partition_index [broker_ids] _tagged_fields (v5+)
+*   configs => name value _tagged_fields (v5+)
+*/
+#[derive(Debug, Clone)]
+pub struct CreateTopicsBody {
+    pub topics: Vec<CreatableTopic>,
+    pub timeout_ms: i32,
+    /// check the topics could be created without creating them
+    pub validate_only: bool,
+}
+
+impl DecodeVersioned for CreateTopicsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::CreateTopics.is_flexible(version);
+        let body = CreateTopicsBody {
+            topics: read_array(input, offset, flexible, |input, offset| {
+                CreatableTopic::read_versioned(input, offset, version)
+            })?,
+            timeout_ms: read_i32_be(input, offset)?,
+            validate_only: read_u8_be(input, offset)? != 0,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatableTopic {
+    pub name: String,
+    pub num_partitions: i32,
+    pub replication_factor: i16,
+    pub assignments: Vec<CreatableReplicaAssignment>,
+    pub configs: Vec<CreatableTopicConfig>,
+}
+
+impl DecodeVersioned for CreatableTopic {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::CreateTopics.is_flexible(version);
+        let topic = CreatableTopic {
+            name: read_string(input, offset, flexible)?,
+            num_partitions: read_i32_be(input, offset)?,
+            replication_factor: read_i16_be(input, offset)?,
+            assignments: read_array(input, offset, flexible, |input, offset| {
+                let assignment = CreatableReplicaAssignment {
+                    partition_index: read_i32_be(input, offset)?,
+                    broker_ids: read_array(input, offset, flexible, read_i32_be)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(assignment)
+            })?,
+            configs: read_array(input, offset, flexible, |input, offset| {
+                let config = CreatableTopicConfig {
+                    name: read_string(input, offset, flexible)?,
+                    value: read_nullable_string(input, offset, flexible)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(config)
+            })?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(topic)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatableReplicaAssignment {
+    pub partition_index: i32,
+    /// the first is the partition's leader
+    pub broker_ids: Vec<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatableTopicConfig {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_assignments_and_configs() {
+        for version in [2, 7] {
+            let flexible = KafApiKey::CreateTopics.is_flexible(version);
+            let mut buf = vec![];
+            write_array(&mut buf, &["orders"], flexible, |buf, name| {
+                write_string(buf, name, flexible);
+                buf.extend((-1i32).encode_to_bytes());
+                buf.extend((-1i16).encode_to_bytes());
+                write_array(buf, &[0, 1], flexible, |buf, partition: &i32| {
+                    buf.extend(partition.encode_to_bytes());
+                    write_array(buf, &[1], flexible, |buf, id: &i32| buf.extend(id.encode_to_bytes()));
+                    write_tagged_fields(buf, flexible);
+                });
+                write_array(buf, &["compact"], flexible, |buf, value| {
+                    write_string(buf, "cleanup.policy", flexible);
+                    write_nullable_string(buf, Some(value), flexible);
+                    write_tagged_fields(buf, flexible);
+                });
+                write_tagged_fields(buf, flexible);
+            });
+            buf.extend(30_000i32.encode_to_bytes());
+            buf.push(1);
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = CreateTopicsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert!(body.validate_only);
+            let topic = &body.topics[0];
+            assert_eq!((topic.name.as_str(), topic.num_partitions, topic.replication_factor), ("orders", -1, -1));
+            assert_eq!(topic.assignments[1].partition_index, 1);
+            assert_eq!(topic.assignments[1].broker_ids, vec![1]);
+            assert_eq!(topic.configs[0].value.as_deref(), Some("compact"));
+        }
+    }
+}
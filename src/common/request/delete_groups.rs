@@ -0,0 +1,25 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{read_array, read_string, skip_tagged_fields},
+    DecodeVersioned, EncodingError,
+};
+
+/*
+* DeleteGroups Request (Version: 0-2) => [groups_names] _tagged_fields (v2+)
+*   groups_names => string
+*/
+#[derive(Debug, Clone)]
+pub struct DeleteGroupsBody {
+    pub groups_names: Vec<String>,
+}
+
+impl DecodeVersioned for DeleteGroupsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::DeleteGroups.is_flexible(version);
+        let body = DeleteGroupsBody {
+            groups_names: read_array(input, offset, flexible, |input, offset| read_string(input, offset, flexible))?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i32_be, read_i64_be},
+};
+
+/// DeleteRecords offset for "up to the high watermark"
+pub const HIGH_WATERMARK: i64 = -1;
+
+/*
+* DeleteRecords Request (Version: 0-2) => [topics] timeout_ms _tagged_fields (v2+)
+* topics => name [partitions] _tagged_fields (v2+)
+*   partitions => partition_index offset _tagged_fields (v2+)
+*     offset => INT64, records before it are deleted, HIGH_WATERMARK for all committed ones
+*/
+#[derive(Debug, Clone)]
+pub struct DeleteRecordsBody {
+    pub topics: Vec<DeleteRecordsTopic>,
+    pub timeout_ms: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteRecordsTopic {
+    pub name: String,
+    pub partitions: Vec<DeleteRecordsPartition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteRecordsPartition {
+    pub partition_index: i32,
+    pub offset: i64,
+}
+
+impl DecodeVersioned for DeleteRecordsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::DeleteRecords.is_flexible(version);
+        let topics = read_array(input, offset, flexible, |input, offset| {
+            let topic = DeleteRecordsTopic {
+                name: read_string(input, offset, flexible)?,
+                partitions: read_array(input, offset, flexible, |input, offset| {
+                    let partition = DeleteRecordsPartition {
+                        partition_index: read_i32_be(input, offset)?,
+                        offset: read_i64_be(input, offset)?,
+                    };
+                    skip_tagged_fields(input, offset, flexible)?;
+                    Ok(partition)
+                })?,
+            };
+            skip_tagged_fields(input, offset, flexible)?;
+            Ok(topic)
+        })?;
+        let body = DeleteRecordsBody {
+            topics,
+            timeout_ms: read_i32_be(input, offset)?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_offsets_per_partition() {
+        for version in [0, 2] {
+            let flexible = version >= 2;
+            let mut buf = vec![];
+            write_array(&mut buf, &["orders"], flexible, |buf, name| {
+                write_string(buf, name, flexible);
+                write_array(buf, &[(0i32, 42i64), (1, HIGH_WATERMARK)], flexible, |buf, (partition, offset)| {
+                    buf.extend(partition.encode_to_bytes());
+                    buf.extend(offset.encode_to_bytes());
+                    write_tagged_fields(buf, flexible);
+                });
+                write_tagged_fields(buf, flexible);
+            });
+            buf.extend(30_000i32.encode_to_bytes());
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = DeleteRecordsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!(body.topics[0].name, "orders");
+            assert_eq!(
+                body.topics[0].partitions,
+                vec![
+                    DeleteRecordsPartition { partition_index: 0, offset: 42 },
+                    DeleteRecordsPartition { partition_index: 1, offset: HIGH_WATERMARK },
+                ]
+            );
+            assert_eq!(body.timeout_ms, 30_000);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_string, read_string, skip_tagged_fields},
+        uuid::KafUuid,
+        DecodeFromBytes, DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_i32_be,
+};
+
+/*
+* DeleteTopics Request (Version: 1-6) => [topic_names] (v1-5) [topics] (v6+) timeout_ms _tagged_fields (v4+)
+* topic_names => STRING
+* topics => name topic_id _tagged_fields
+*   name => NULLABLE_STRING, null when deleting by id
+*   topic_id => UUID, zero when deleting by name
+*/
+#[derive(Debug, Clone)]
+pub struct DeleteTopicsBody {
+    pub topics: Vec<DeleteTopicState>,
+    pub timeout_ms: i32,
+}
+
+impl DecodeVersioned for DeleteTopicsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::DeleteTopics.is_flexible(version);
+        let topics = if version >= 6 {
+            read_array(input, offset, flexible, |input, offset| {
+                let topic = DeleteTopicState {
+                    name: read_nullable_string(input, offset, flexible)?,
+                    topic_id: KafUuid::read_from_u8(input, offset)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(topic)
+            })?
+        } else {
+            read_array(input, offset, flexible, |input, offset| {
+                Ok(DeleteTopicState {
+                    name: Some(read_string(input, offset, flexible)?),
+                    topic_id: KafUuid::ZERO,
+                })
+            })?
+        };
+        let body = DeleteTopicsBody {
+            topics,
+            timeout_ms: read_i32_be(input, offset)?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteTopicState {
+    pub name: Option<String>,
+    pub topic_id: KafUuid,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_names_before_v6_and_ids_after() {
+        let mut buf = vec![];
+        write_array(&mut buf, &["orders", "payments"], false, |buf, name| write_string(buf, name, false));
+        buf.extend(5_000i32.encode_to_bytes());
+        let mut offset = 0;
+        let body = DeleteTopicsBody::read_versioned(&buf, &mut offset, 3).unwrap();
+        assert_eq!(offset, buf.len());
+        assert_eq!(body.topics[1].name.as_deref(), Some("payments"));
+        assert_eq!(body.timeout_ms, 5_000);
+
+        let topic_id = KafUuid([6; 16]);
+        let mut buf = vec![];
+        write_array(&mut buf, &[topic_id], true, |buf, id| {
+            write_nullable_string(buf, None, true);
+            buf.extend(id.encode_to_bytes());
+            write_tagged_fields(buf, true);
+        });
+        buf.extend(5_000i32.encode_to_bytes());
+        write_tagged_fields(&mut buf, true);
+        let mut offset = 0;
+        let body = DeleteTopicsBody::read_versioned(&buf, &mut offset, 6).unwrap();
+        assert_eq!(offset, buf.len());
+        assert_eq!((body.topics[0].name.clone(), body.topics[0].topic_id), (None, topic_id));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_array, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i8_be, read_u8_be},
+};
+
+/*
+* DescribeConfigs Request (Version: 1-4) => [resources] include_synonyms include_documentation (v3+) _tagged_fields (v4+)
+* resources => resource_type resource_name [configuration_keys] _tagged_fields (v4+)
+*   resource_type => INT8, 2 for a topic, 4 for a broker
+*   configuration_keys => nullable, null for every config
+*/
+#[derive(Debug, Clone)]
+pub struct DescribeConfigsBody {
+    pub resources: Vec<DescribeConfigsResource>,
+    pub include_synonyms: bool,
+    pub include_documentation: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescribeConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub configuration_keys: Option<Vec<String>>,
+}
+
+impl DecodeVersioned for DescribeConfigsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::DescribeConfigs.is_flexible(version);
+        let resources = read_array(input, offset, flexible, |input, offset| {
+            let resource = DescribeConfigsResource {
+                resource_type: read_i8_be(input, offset)?,
+                resource_name: read_string(input, offset, flexible)?,
+                configuration_keys: read_nullable_array(input, offset, flexible, |input, offset| {
+                    read_string(input, offset, flexible)
+                })?,
+            };
+            skip_tagged_fields(input, offset, flexible)?;
+            Ok(resource)
+        })?;
+        let body = DescribeConfigsBody {
+            resources,
+            include_synonyms: read_u8_be(input, offset)? != 0,
+            include_documentation: if version >= 3 { read_u8_be(input, offset)? != 0 } else { false },
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_array, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_resources_and_the_documentation_flag_from_v3() {
+        for version in [1, 4] {
+            let flexible = version >= 4;
+            let mut buf = vec![];
+            let resources = [(2i8, "orders", Some(vec!["retention.ms".to_string()])), (4, "1", None)];
+            write_array(&mut buf, &resources, flexible, |buf, (resource_type, name, keys)| {
+                buf.extend(resource_type.encode_to_bytes());
+                write_string(buf, name, flexible);
+                write_nullable_array(buf, keys.as_deref(), flexible, |buf, key| write_string(buf, key, flexible));
+                write_tagged_fields(buf, flexible);
+            });
+            buf.extend(true.encode_to_bytes());
+            if version >= 3 {
+                buf.extend(true.encode_to_bytes());
+            }
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = DescribeConfigsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!(
+                body.resources,
+                vec![
+                    DescribeConfigsResource {
+                        resource_type: 2,
+                        resource_name: "orders".to_string(),
+                        configuration_keys: Some(vec!["retention.ms".to_string()]),
+                    },
+                    DescribeConfigsResource {
+                        resource_type: 4,
+                        resource_name: "1".to_string(),
+                        configuration_keys: None,
+                    },
+                ]
+            );
+            assert!(body.include_synonyms);
+            assert_eq!(body.include_documentation, version >= 3);
+        }
+    }
+}
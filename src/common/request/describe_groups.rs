@@ -0,0 +1,30 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_u8_be,
+};
+
+/*
+* DescribeGroups Request (Version: 0-5) => [groups] include_authorized_operations (v3+) _tagged_fields (v5+)
+*   groups => string
+*/
+#[derive(Debug, Clone)]
+pub struct DescribeGroupsBody {
+    pub groups: Vec<String>,
+    pub include_authorized_operations: bool,
+}
+
+impl DecodeVersioned for DescribeGroupsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::DescribeGroups.is_flexible(version);
+        let body = DescribeGroupsBody {
+            groups: read_array(input, offset, flexible, |input, offset| read_string(input, offset, flexible))?,
+            include_authorized_operations: version >= 3 && read_u8_be(input, offset)? != 0,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
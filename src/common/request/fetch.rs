@@ -0,0 +1,216 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_string, skip_tagged_fields},
+        uuid::KafUuid,
+        DecodeFromBytes, DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i32_be, read_i64_be, read_i8_be},
+};
+
+/*
+* Fetch Request (Version: 0-17) => replica_id (v0-14) max_wait_ms min_bytes max_bytes (v3+) isolation_level (v4+)
+*                                  session_id (v7+) session_epoch (v7+) [topics] [forgotten_topics_data] (v7+)
+*                                  rack_id (v11+) _tagged_fields (v12+)
+* topics => topic (v0-12) topic_id (v13+) [partitions] _tagged_fields (v12+)
+*   partitions => partition current_leader_epoch (v9+) fetch_offset last_fetched_epoch (v12+)
+*                 log_start_offset (v5+) partition_max_bytes _tagged_fields (v12+)
+* forgotten_topics_data => topic (v7-12) topic_id (v13+) [partitions] _tagged_fields (v12+)
+*   partitions => INT32
+*/
+#[derive(Debug, Clone)]
+pub struct FetchBody {
+    pub replica_id: i32,
+    pub max_wait_ms: i32,
+    pub min_bytes: i32,
+    pub max_bytes: i32,
+    /// 0 for READ_UNCOMMITTED, 1 for READ_COMMITTED
+    pub isolation_level: i8,
+    pub session_id: i32,
+    pub session_epoch: i32,
+    pub topics: Vec<FetchTopic>,
+    pub forgotten_topics_data: Vec<ForgottenTopic>,
+    pub rack_id: String,
+}
+
+/// Topics are named up to v12 and identified by topic id from v13
+fn read_topic(input: &[u8], offset: &mut usize, version: i16) -> Result<(String, KafUuid), EncodingError> {
+    if version >= 13 {
+        Ok((String::new(), KafUuid::read_from_u8(input, offset)?))
+    } else {
+        Ok((read_string(input, offset, KafApiKey::Fetch.is_flexible(version))?, KafUuid::ZERO))
+    }
+}
+
+impl DecodeVersioned for FetchBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::Fetch.is_flexible(version);
+        let body = FetchBody {
+            // from v15 brokers send it as a tagged field, consumers are -1
+            replica_id: if version <= 14 { read_i32_be(input, offset)? } else { -1 },
+            max_wait_ms: read_i32_be(input, offset)?,
+            min_bytes: read_i32_be(input, offset)?,
+            max_bytes: if version >= 3 { read_i32_be(input, offset)? } else { i32::MAX },
+            isolation_level: if version >= 4 { read_i8_be(input, offset)? } else { 0 },
+            session_id: if version >= 7 { read_i32_be(input, offset)? } else { 0 },
+            session_epoch: if version >= 7 { read_i32_be(input, offset)? } else { -1 },
+            topics: read_array(input, offset, flexible, |input, offset| {
+                let (topic, topic_id) = read_topic(input, offset, version)?;
+                let topic = FetchTopic {
+                    topic,
+                    topic_id,
+                    partitions: read_array(input, offset, flexible, |input, offset| {
+                        let partition = FetchPartition {
+                            partition: read_i32_be(input, offset)?,
+                            current_leader_epoch: if version >= 9 { read_i32_be(input, offset)? } else { -1 },
+                            fetch_offset: read_i64_be(input, offset)?,
+                            last_fetched_epoch: if version >= 12 { read_i32_be(input, offset)? } else { -1 },
+                            log_start_offset: if version >= 5 { read_i64_be(input, offset)? } else { -1 },
+                            partition_max_bytes: read_i32_be(input, offset)?,
+                        };
+                        skip_tagged_fields(input, offset, flexible)?;
+                        Ok(partition)
+                    })?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(topic)
+            })?,
+            forgotten_topics_data: if version >= 7 {
+                read_array(input, offset, flexible, |input, offset| {
+                    let (topic, topic_id) = read_topic(input, offset, version)?;
+                    let forgotten = ForgottenTopic {
+                        topic,
+                        topic_id,
+                        partitions: read_array(input, offset, flexible, read_i32_be)?,
+                    };
+                    skip_tagged_fields(input, offset, flexible)?;
+                    Ok(forgotten)
+                })?
+            } else {
+                vec![]
+            },
+            rack_id: if version >= 11 { read_string(input, offset, flexible)? } else { String::new() },
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchTopic {
+    /// empty from v13
+    pub topic: String,
+    /// zero before v13
+    pub topic_id: KafUuid,
+    pub partitions: Vec<FetchPartition>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchPartition {
+    pub partition: i32,
+    pub current_leader_epoch: i32,
+    pub fetch_offset: i64,
+    pub last_fetched_epoch: i32,
+    pub log_start_offset: i64,
+    pub partition_max_bytes: i32,
+}
+
+/// Partitions to drop from an incremental fetch session
+#[derive(Debug, Clone)]
+pub struct ForgottenTopic {
+    pub topic: String,
+    pub topic_id: KafUuid,
+    pub partitions: Vec<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    fn encode(version: i16, topic_id: KafUuid) -> Vec<u8> {
+        let flexible = KafApiKey::Fetch.is_flexible(version);
+        let mut buf = vec![];
+        if version <= 14 {
+            buf.extend((-1i32).encode_to_bytes());
+        }
+        buf.extend(500i32.encode_to_bytes());
+        buf.extend(1i32.encode_to_bytes());
+        if version >= 3 {
+            buf.extend(1_000_000i32.encode_to_bytes());
+        }
+        if version >= 4 {
+            buf.push(1);
+        }
+        if version >= 7 {
+            buf.extend(0i32.encode_to_bytes());
+            buf.extend((-1i32).encode_to_bytes());
+        }
+        write_array(&mut buf, &[()], flexible, |buf, _| {
+            if version >= 13 {
+                buf.extend(topic_id.encode_to_bytes());
+            } else {
+                write_string(buf, "orders", flexible);
+            }
+            write_array(buf, &[4i32], flexible, |buf, partition| {
+                buf.extend(partition.encode_to_bytes());
+                if version >= 9 {
+                    buf.extend(3i32.encode_to_bytes());
+                }
+                buf.extend(42i64.encode_to_bytes());
+                if version >= 12 {
+                    buf.extend((-1i32).encode_to_bytes());
+                }
+                if version >= 5 {
+                    buf.extend(0i64.encode_to_bytes());
+                }
+                buf.extend(1024i32.encode_to_bytes());
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        if version >= 7 {
+            write_array(&mut buf, &[()], flexible, |buf, _| {
+                if version >= 13 {
+                    buf.extend(topic_id.encode_to_bytes());
+                } else {
+                    write_string(buf, "gone", flexible);
+                }
+                write_array(buf, &[0i32, 1], flexible, |buf, p| buf.extend(p.encode_to_bytes()));
+                write_tagged_fields(buf, flexible);
+            });
+        }
+        if version >= 11 {
+            write_string(&mut buf, "rack-a", flexible);
+        }
+        write_tagged_fields(&mut buf, flexible);
+        buf
+    }
+
+    #[test]
+    fn decodes_named_and_topic_id_versions() {
+        let topic_id = KafUuid([7; 16]);
+        for version in [0, 3, 4, 7, 11, 12, 13, 15, 17] {
+            let buf = encode(version, topic_id);
+            let mut offset = 0;
+            let body = FetchBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len(), "v{}", version);
+            let max_bytes = if version >= 3 { 1_000_000 } else { i32::MAX };
+            let isolation_level = if version >= 4 { 1 } else { 0 };
+            assert_eq!((body.max_wait_ms, body.min_bytes, body.max_bytes, body.isolation_level), (500, 1, max_bytes, isolation_level));
+            let topic = &body.topics[0];
+            if version >= 13 {
+                assert_eq!((topic.topic.as_str(), topic.topic_id), ("", topic_id));
+            } else {
+                assert_eq!((topic.topic.as_str(), topic.topic_id), ("orders", KafUuid::ZERO));
+            }
+            let partition = &topic.partitions[0];
+            assert_eq!((partition.partition, partition.fetch_offset, partition.partition_max_bytes), (4, 42, 1024));
+            let forgotten = body.forgotten_topics_data.first().map(|f| f.partitions.clone());
+            assert_eq!(forgotten, (version >= 7).then(|| vec![0, 1]));
+        }
+    }
+}
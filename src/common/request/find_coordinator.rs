@@ -0,0 +1,76 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_i8_be,
+};
+
+/// `key_type` values
+pub mod coordinator_type {
+    pub const GROUP: i8 = 0;
+    pub const TRANSACTION: i8 = 1;
+}
+
+/*
+* FindCoordinator Request (Version: 0-4) => key (v0-3) key_type (v1+) [coordinator_keys] (v4+) _tagged_fields (v3+)
+*   key => STRING, a group id or transactional id
+*/
+#[derive(Debug, Clone)]
+pub struct FindCoordinatorBody {
+    pub key_type: i8,
+    /// the one key of v0-3, or the batch of v4+
+    pub coordinator_keys: Vec<String>,
+}
+
+impl DecodeVersioned for FindCoordinatorBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::FindCoordinator.is_flexible(version);
+        let key = if version < 4 { Some(read_string(input, offset, flexible)?) } else { None };
+        let key_type = if version >= 1 { read_i8_be(input, offset)? } else { coordinator_type::GROUP };
+        let coordinator_keys = match key {
+            Some(key) => vec![key],
+            None => read_array(input, offset, flexible, |input, offset| read_string(input, offset, flexible))?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(FindCoordinatorBody {
+            key_type,
+            coordinator_keys,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_one_key_before_v4_and_a_batch_after() {
+        for version in [0, 3, 4] {
+            let flexible = version >= 3;
+            let mut buf = vec![];
+            if version < 4 {
+                write_string(&mut buf, "orders-app", flexible);
+            }
+            if version >= 1 {
+                buf.extend(coordinator_type::GROUP.encode_to_bytes());
+            }
+            if version >= 4 {
+                write_array(&mut buf, &["orders-app", "billing"], flexible, |buf, key| write_string(buf, key, flexible));
+            }
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = FindCoordinatorBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!(body.key_type, coordinator_type::GROUP);
+            let expected: &[&str] = if version < 4 { &["orders-app"] } else { &["orders-app", "billing"] };
+            assert_eq!(body.coordinator_keys, expected);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_i32_be,
+};
+
+/*
+* Heartbeat Request (Version: 0-4) => group_id generation_id member_id group_instance_id (v3+) _tagged_fields (v4+)
+*/
+#[derive(Debug, Clone)]
+pub struct HeartbeatBody {
+    pub group_id: String,
+    pub generation_id: i32,
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+}
+
+impl DecodeVersioned for HeartbeatBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::Heartbeat.is_flexible(version);
+        let body = HeartbeatBody {
+            group_id: read_string(input, offset, flexible)?,
+            generation_id: read_i32_be(input, offset)?,
+            member_id: read_string(input, offset, flexible)?,
+            group_instance_id: if version >= 3 { read_nullable_string(input, offset, flexible)? } else { None },
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
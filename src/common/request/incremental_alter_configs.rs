@@ -0,0 +1,125 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i8_be, read_u8_be},
+};
+
+/// `config_operation` values
+pub mod config_operation {
+    pub const SET: i8 = 0;
+    pub const DELETE: i8 = 1;
+    /// add items to a list config
+    pub const APPEND: i8 = 2;
+    /// remove items from a list config
+    pub const SUBTRACT: i8 = 3;
+}
+
+/*
+* IncrementalAlterConfigs Request (Version: 0-1) => [resources] validate_only _tagged_fields (v1+)
+* resources => resource_type resource_name [configs] _tagged_fields (v1+)
+*   configs => name config_operation value _tagged_fields (v1+)
+*/
+#[derive(Debug, Clone)]
+pub struct IncrementalAlterConfigsBody {
+    pub resources: Vec<IncrementalAlterConfigsResource>,
+    pub validate_only: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct IncrementalAlterConfigsResource {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub configs: Vec<AlterableConfigOp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlterableConfigOp {
+    pub name: String,
+    pub config_operation: i8,
+    pub value: Option<String>,
+}
+
+impl DecodeVersioned for IncrementalAlterConfigsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::IncrementalAlterConfigs.is_flexible(version);
+        let resources = read_array(input, offset, flexible, |input, offset| {
+            let resource = IncrementalAlterConfigsResource {
+                resource_type: read_i8_be(input, offset)?,
+                resource_name: read_string(input, offset, flexible)?,
+                configs: read_array(input, offset, flexible, |input, offset| {
+                    let config = AlterableConfigOp {
+                        name: read_string(input, offset, flexible)?,
+                        config_operation: read_i8_be(input, offset)?,
+                        value: read_nullable_string(input, offset, flexible)?,
+                    };
+                    skip_tagged_fields(input, offset, flexible)?;
+                    Ok(config)
+                })?,
+            };
+            skip_tagged_fields(input, offset, flexible)?;
+            Ok(resource)
+        })?;
+        let body = IncrementalAlterConfigsBody {
+            resources,
+            validate_only: read_u8_be(input, offset)? != 0,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_an_operation_per_config() {
+        for version in [0, 1] {
+            let flexible = version >= 1;
+            let mut buf = vec![];
+            write_array(&mut buf, &["1"], flexible, |buf, name| {
+                buf.extend(4i8.encode_to_bytes());
+                write_string(buf, name, flexible);
+                let ops = [("log.cleanup.policy", config_operation::APPEND, Some("compact")), ("log.retention.ms", config_operation::DELETE, None)];
+                write_array(buf, &ops, flexible, |buf, (name, op, value)| {
+                    write_string(buf, name, flexible);
+                    buf.extend(op.encode_to_bytes());
+                    write_nullable_string(buf, *value, flexible);
+                    write_tagged_fields(buf, flexible);
+                });
+                write_tagged_fields(buf, flexible);
+            });
+            buf.extend(false.encode_to_bytes());
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = IncrementalAlterConfigsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            let resource = &body.resources[0];
+            assert_eq!((resource.resource_type, resource.resource_name.as_str()), (4, "1"));
+            assert_eq!(
+                resource.configs,
+                vec![
+                    AlterableConfigOp {
+                        name: "log.cleanup.policy".to_string(),
+                        config_operation: config_operation::APPEND,
+                        value: Some("compact".to_string()),
+                    },
+                    AlterableConfigOp {
+                        name: "log.retention.ms".to_string(),
+                        config_operation: config_operation::DELETE,
+                        value: None,
+                    },
+                ]
+            );
+            assert!(!body.validate_only);
+        }
+    }
+}
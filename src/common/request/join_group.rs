@@ -0,0 +1,119 @@
+use bytes::Bytes;
+
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_bytes, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_i32_be,
+};
+
+/// member_id of a member joining for the first time
+pub const UNKNOWN_MEMBER_ID: &str = "";
+
+/*
+* JoinGroup Request (Version: 0-9) => group_id session_timeout_ms rebalance_timeout_ms (v1+) member_id
+*                                     group_instance_id (v5+) protocol_type [protocols] reason (v8+) _tagged_fields (v6+)
+* protocols => name metadata _tagged_fields (v6+)
+*   protocols are in the member's order of preference
+*/
+#[derive(Debug, Clone)]
+pub struct JoinGroupBody {
+    pub group_id: String,
+    pub session_timeout_ms: i32,
+    /// session_timeout_ms before v1
+    pub rebalance_timeout_ms: i32,
+    pub member_id: String,
+    /// set by static members
+    pub group_instance_id: Option<String>,
+    pub protocol_type: String,
+    pub protocols: Vec<JoinGroupRequestProtocol>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinGroupRequestProtocol {
+    pub name: String,
+    pub metadata: Bytes,
+}
+
+impl DecodeVersioned for JoinGroupBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::JoinGroup.is_flexible(version);
+        let group_id = read_string(input, offset, flexible)?;
+        let session_timeout_ms = read_i32_be(input, offset)?;
+        let body = JoinGroupBody {
+            group_id,
+            session_timeout_ms,
+            rebalance_timeout_ms: if version >= 1 { read_i32_be(input, offset)? } else { session_timeout_ms },
+            member_id: read_string(input, offset, flexible)?,
+            group_instance_id: if version >= 5 { read_nullable_string(input, offset, flexible)? } else { None },
+            protocol_type: read_string(input, offset, flexible)?,
+            protocols: read_array(input, offset, flexible, |input, offset| {
+                let protocol = JoinGroupRequestProtocol {
+                    name: read_string(input, offset, flexible)?,
+                    metadata: read_bytes(input, offset, flexible)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(protocol)
+            })?,
+            reason: if version >= 8 { read_nullable_string(input, offset, flexible)? } else { None },
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_bytes, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_the_fields_each_version_has() {
+        for version in [0, 5, 9] {
+            let flexible = version >= 6;
+            let mut buf = vec![];
+            write_string(&mut buf, "orders-app", flexible);
+            buf.extend(10_000i32.encode_to_bytes());
+            if version >= 1 {
+                buf.extend(60_000i32.encode_to_bytes());
+            }
+            write_string(&mut buf, "member-1", flexible);
+            if version >= 5 {
+                write_nullable_string(&mut buf, Some("instance-1"), flexible);
+            }
+            write_string(&mut buf, "consumer", flexible);
+            write_array(&mut buf, &[("range", b"r"), ("roundrobin", b"o")], flexible, |buf, (name, metadata)| {
+                write_string(buf, name, flexible);
+                write_bytes(buf, *metadata, flexible);
+                write_tagged_fields(buf, flexible);
+            });
+            if version >= 8 {
+                write_nullable_string(&mut buf, Some("rejoining"), flexible);
+            }
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = JoinGroupBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!((body.group_id.as_str(), body.member_id.as_str()), ("orders-app", "member-1"));
+            assert_eq!(body.session_timeout_ms, 10_000);
+            assert_eq!(body.rebalance_timeout_ms, if version >= 1 { 60_000 } else { 10_000 });
+            assert_eq!(body.group_instance_id.as_deref(), (version >= 5).then_some("instance-1"));
+            assert_eq!(body.protocol_type, "consumer");
+            assert_eq!(
+                body.protocols,
+                vec![
+                    JoinGroupRequestProtocol { name: "range".to_string(), metadata: Bytes::from_static(b"r") },
+                    JoinGroupRequestProtocol { name: "roundrobin".to_string(), metadata: Bytes::from_static(b"o") },
+                ]
+            );
+            assert_eq!(body.reason.as_deref(), (version >= 8).then_some("rejoining"));
+        }
+    }
+}
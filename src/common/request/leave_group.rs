@@ -0,0 +1,88 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{read_array, read_nullable_string, read_string, skip_tagged_fields},
+    DecodeVersioned, EncodingError,
+};
+
+/*
+* LeaveGroup Request (Version: 0-5) => group_id member_id (v0-2) [members] (v3+) _tagged_fields (v4+)
+* members => member_id group_instance_id reason (v5+) _tagged_fields (v4+)
+*/
+#[derive(Debug, Clone)]
+pub struct LeaveGroupBody {
+    pub group_id: String,
+    /// the one member of v0-2, or the batch of v3+
+    pub members: Vec<MemberIdentity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberIdentity {
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl DecodeVersioned for LeaveGroupBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::LeaveGroup.is_flexible(version);
+        let group_id = read_string(input, offset, flexible)?;
+        let members = if version < 3 {
+            vec![MemberIdentity {
+                member_id: read_string(input, offset, flexible)?,
+                group_instance_id: None,
+                reason: None,
+            }]
+        } else {
+            read_array(input, offset, flexible, |input, offset| {
+                let member = MemberIdentity {
+                    member_id: read_string(input, offset, flexible)?,
+                    group_instance_id: read_nullable_string(input, offset, flexible)?,
+                    reason: if version >= 5 { read_nullable_string(input, offset, flexible)? } else { None },
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(member)
+            })?
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(LeaveGroupBody { group_id, members })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::codec::{write_array, write_nullable_string, write_string, write_tagged_fields};
+
+    #[test]
+    fn decodes_one_member_before_v3_and_a_batch_after() {
+        for version in [0, 3, 5] {
+            let flexible = version >= 4;
+            let mut buf = vec![];
+            write_string(&mut buf, "orders-app", flexible);
+            if version < 3 {
+                write_string(&mut buf, "member-1", flexible);
+            } else {
+                write_array(&mut buf, &[("member-1", None), ("", Some("instance-2"))], flexible, |buf, (member_id, instance_id)| {
+                    write_string(buf, member_id, flexible);
+                    write_nullable_string(buf, *instance_id, flexible);
+                    if version >= 5 {
+                        write_nullable_string(buf, Some("shutting down"), flexible);
+                    }
+                    write_tagged_fields(buf, flexible);
+                });
+            }
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = LeaveGroupBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!(body.group_id, "orders-app");
+            assert_eq!(body.members[0].member_id, "member-1");
+            assert_eq!(body.members.len(), if version < 3 { 1 } else { 2 });
+            if version >= 3 {
+                assert_eq!(body.members[1].group_instance_id.as_deref(), Some("instance-2"));
+                assert_eq!(body.members[1].reason.as_deref(), (version >= 5).then_some("shutting down"));
+            }
+        }
+    }
+}
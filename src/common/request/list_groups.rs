@@ -0,0 +1,56 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{read_array, read_string, skip_tagged_fields},
+    DecodeVersioned, EncodingError,
+};
+
+/*
+* ListGroups Request (Version: 0-5) => states_filter (v4+) types_filter (v5+) _tagged_fields (v3+)
+*   states_filter => [string], empty for every state
+*   types_filter => [string], empty for every type
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ListGroupsBody {
+    pub states_filter: Vec<String>,
+    pub types_filter: Vec<String>,
+}
+
+impl DecodeVersioned for ListGroupsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::ListGroups.is_flexible(version);
+        let read_filter = |input: &[u8], offset: &mut usize| read_array(input, offset, flexible, |input, offset| read_string(input, offset, flexible));
+        let body = ListGroupsBody {
+            states_filter: if version >= 4 { read_filter(input, offset)? } else { vec![] },
+            types_filter: if version >= 5 { read_filter(input, offset)? } else { vec![] },
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::codec::{write_array, write_string, write_tagged_fields};
+
+    #[test]
+    fn decodes_the_filters_of_the_versions_that_have_them() {
+        for version in [0, 3, 4, 5] {
+            let flexible = version >= 3;
+            let mut buf = vec![];
+            if version >= 4 {
+                write_array(&mut buf, &["Stable", "Empty"], flexible, |buf, state| write_string(buf, state, flexible));
+            }
+            if version >= 5 {
+                write_array(&mut buf, &["classic"], flexible, |buf, group_type| write_string(buf, group_type, flexible));
+            }
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = ListGroupsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!(body.states_filter.len(), if version >= 4 { 2 } else { 0 });
+            assert_eq!(body.types_filter.len(), if version >= 5 { 1 } else { 0 });
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i32_be, read_i64_be, read_i8_be},
+};
+
+/// The offset after the last fetchable record: the high watermark, or the
+/// last stable offset for read_committed
+pub const LATEST_TIMESTAMP: i64 = -1;
+/// The log start offset
+pub const EARLIEST_TIMESTAMP: i64 = -2;
+/// The first record with the largest timestamp (v7+)
+pub const MAX_TIMESTAMP: i64 = -3;
+/// The start of the log on local disk, the same as EARLIEST without tiered storage (v8+)
+pub const EARLIEST_LOCAL_TIMESTAMP: i64 = -4;
+/// The last offset in tiered storage (v9+)
+pub const LATEST_TIERED_TIMESTAMP: i64 = -5;
+
+/*
+* ListOffsets Request (Version: 1-10) => replica_id isolation_level (v2+) [topics] timeout_ms (v10+) _tagged_fields (v6+)
+* replica_id => INT32
+* isolation_level => INT8
+* topics => name [partitions] _tagged_fields (v6+)
+*   name => STRING
+*   partitions => partition_index current_leader_epoch (v4+) timestamp _tagged_fields (v6+)
+*     partition_index => INT32
+*     current_leader_epoch => INT32
+*     timestamp => INT64
+*/
+#[derive(Debug, Clone)]
+pub struct ListOffsetsBody {
+    /// -1 for consumers
+    pub replica_id: i32,
+    /// 0 read_uncommitted, 1 read_committed
+    pub isolation_level: i8,
+    pub topics: Vec<ListOffsetsTopic>,
+    pub timeout_ms: i32,
+}
+
+impl DecodeVersioned for ListOffsetsBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::ListOffsets.is_flexible(version);
+        let body = ListOffsetsBody {
+            replica_id: read_i32_be(input, offset)?,
+            isolation_level: if version >= 2 { read_i8_be(input, offset)? } else { 0 },
+            topics: read_array(input, offset, flexible, |input, offset| {
+                ListOffsetsTopic::read_versioned(input, offset, version)
+            })?,
+            timeout_ms: if version >= 10 { read_i32_be(input, offset)? } else { 0 },
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListOffsetsTopic {
+    pub name: String,
+    pub partitions: Vec<ListOffsetsPartition>,
+}
+
+impl DecodeVersioned for ListOffsetsTopic {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::ListOffsets.is_flexible(version);
+        let topic = ListOffsetsTopic {
+            name: read_string(input, offset, flexible)?,
+            partitions: read_array(input, offset, flexible, |input, offset| {
+                let partition = ListOffsetsPartition {
+                    partition_index: read_i32_be(input, offset)?,
+                    current_leader_epoch: if version >= 4 { read_i32_be(input, offset)? } else { -1 },
+                    timestamp: read_i64_be(input, offset)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(partition)
+            })?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(topic)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListOffsetsPartition {
+    pub partition_index: i32,
+    /// -1 if the client doesn't know it
+    pub current_leader_epoch: i32,
+    /// a timestamp in ms, or one of the special values above
+    pub timestamp: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    fn encode(version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::ListOffsets.is_flexible(version);
+        let mut buf = (-1i32).encode_to_bytes();
+        if version >= 2 {
+            buf.push(1);
+        }
+        write_array(&mut buf, &["orders"], flexible, |buf, name| {
+            write_string(buf, name, flexible);
+            write_array(buf, &[MAX_TIMESTAMP], flexible, |buf, timestamp| {
+                buf.extend(3i32.encode_to_bytes());
+                if version >= 4 {
+                    buf.extend(5i32.encode_to_bytes());
+                }
+                buf.extend(timestamp.encode_to_bytes());
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        if version >= 10 {
+            buf.extend(1_000i32.encode_to_bytes());
+        }
+        write_tagged_fields(&mut buf, flexible);
+        buf
+    }
+
+    #[test]
+    fn decodes_every_version() {
+        for version in 1..=10 {
+            let buf = encode(version);
+            let mut offset = 0;
+            let body = ListOffsetsBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len(), "v{}", version);
+            assert_eq!(body.isolation_level, if version >= 2 { 1 } else { 0 });
+            let partition = &body.topics[0].partitions[0];
+            assert_eq!((partition.partition_index, partition.timestamp), (3, MAX_TIMESTAMP));
+            assert_eq!(partition.current_leader_epoch, if version >= 4 { 5 } else { -1 });
+        }
+    }
+}
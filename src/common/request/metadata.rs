@@ -0,0 +1,112 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_nullable_array, read_nullable_string, read_string, skip_tagged_fields},
+        uuid::KafUuid,
+        DecodeFromBytes, DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_u8_be,
+};
+
+/*
+* Metadata Request (Version: 0-12) => [topics] allow_auto_topic_creation (v4+)
+*                                     include_cluster_authorized_operations (v8-10)
+*                                     include_topic_authorized_operations (v8+) _tagged_fields (v9+)
+* topics => topic_id (v10+) name _tagged_fields (v9+)
+*/
+#[derive(Debug, Clone)]
+pub struct MetadataBody {
+    /// None for every topic: a null array, or an empty one in v0
+    pub topics: Option<Vec<MetadataRequestTopic>>,
+    pub allow_auto_topic_creation: bool,
+    pub include_cluster_authorized_operations: bool,
+    pub include_topic_authorized_operations: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataRequestTopic {
+    /// zero before v10, or when looking the topic up by name
+    pub topic_id: KafUuid,
+    /// nullable from v10, when looking the topic up by id
+    pub name: Option<String>,
+}
+
+fn read_bool(input: &[u8], offset: &mut usize) -> Result<bool, EncodingError> {
+    Ok(read_u8_be(input, offset)? != 0)
+}
+
+impl DecodeVersioned for MetadataBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::Metadata.is_flexible(version);
+        let topics = read_nullable_array(input, offset, flexible, |input, offset| {
+            let topic = if version >= 10 {
+                MetadataRequestTopic {
+                    topic_id: KafUuid::read_from_u8(input, offset)?,
+                    name: read_nullable_string(input, offset, flexible)?,
+                }
+            } else {
+                MetadataRequestTopic {
+                    topic_id: KafUuid::ZERO,
+                    name: Some(read_string(input, offset, flexible)?),
+                }
+            };
+            skip_tagged_fields(input, offset, flexible)?;
+            Ok(topic)
+        })?;
+        let body = MetadataBody {
+            topics: topics.filter(|topics| version > 0 || !topics.is_empty()),
+            // before v4 topics were always created if the broker allowed it
+            allow_auto_topic_creation: if version >= 4 { read_bool(input, offset)? } else { true },
+            include_cluster_authorized_operations: if (8..=10).contains(&version) { read_bool(input, offset)? } else { false },
+            include_topic_authorized_operations: if version >= 8 { read_bool(input, offset)? } else { false },
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_array, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_named_null_and_topic_id_requests() {
+        // v1 with a null array is every topic
+        let mut offset = 0;
+        let body = MetadataBody::read_versioned(&(-1i32).encode_to_bytes(), &mut offset, 1).unwrap();
+        assert!(body.topics.is_none() && body.allow_auto_topic_creation);
+
+        // v0 with an empty one too
+        let mut offset = 0;
+        assert!(MetadataBody::read_versioned(&0i32.encode_to_bytes(), &mut offset, 0).unwrap().topics.is_none());
+
+        let mut buf = vec![];
+        write_array(&mut buf, &["orders"], false, |buf, name| write_string(buf, name, false));
+        // allow_auto_topic_creation, include_cluster_authorized_operations, include_topic_authorized_operations
+        buf.extend([0, 0, 1]);
+        let mut offset = 0;
+        let body = MetadataBody::read_versioned(&buf, &mut offset, 8).unwrap();
+        assert_eq!(offset, buf.len());
+        assert_eq!(body.topics.unwrap()[0].name.as_deref(), Some("orders"));
+        assert!(!body.allow_auto_topic_creation && body.include_topic_authorized_operations);
+
+        let topic_id = KafUuid([4; 16]);
+        let mut buf = vec![];
+        write_nullable_array(&mut buf, Some(&[topic_id]), true, |buf, id| {
+            buf.extend(id.encode_to_bytes());
+            write_nullable_string(buf, None, true);
+            write_tagged_fields(buf, true);
+        });
+        buf.extend([1, 0]);
+        write_tagged_fields(&mut buf, true);
+        let mut offset = 0;
+        let body = MetadataBody::read_versioned(&buf, &mut offset, 12).unwrap();
+        assert_eq!(offset, buf.len());
+        let topics = body.topics.unwrap();
+        assert_eq!((topics[0].topic_id, topics[0].name.clone()), (topic_id, None));
+    }
+}
@@ -1,9 +1,34 @@
+#[allow(clippy::module_inception)]
 pub mod request;
+pub mod alter_configs;
+pub mod consumer_group_describe;
+pub mod consumer_group_heartbeat;
+pub mod create_partitions;
+pub mod create_topics;
+pub mod delete_groups;
+pub mod delete_records;
+pub mod delete_topics;
+pub mod describe_configs;
+pub mod describe_groups;
 pub mod describe_topic_partitions;
+pub mod fetch;
+pub mod find_coordinator;
+pub mod heartbeat;
+pub mod incremental_alter_configs;
+pub mod join_group;
+pub mod leave_group;
+pub mod list_groups;
+pub mod list_offsets;
+pub mod metadata;
+pub mod offset_commit;
+pub mod offset_delete;
+pub mod offset_fetch;
+pub mod produce;
+pub mod sync_group;
 
 use crate::{
-    common::{api::api_key::KafApiKey, request::{describe_topic_partitions::DescribeTopicPartitionsBody, request::KafRequestBody}, DecodeFromBytes, EncodingError},
-    utils::parse_primitive_types::*,
+    common::{api::api_key::KafApiKey, request::{alter_configs::AlterConfigsBody, consumer_group_describe::ConsumerGroupDescribeBody, consumer_group_heartbeat::ConsumerGroupHeartbeatBody, create_partitions::CreatePartitionsBody, create_topics::CreateTopicsBody, delete_groups::DeleteGroupsBody, delete_records::DeleteRecordsBody, delete_topics::DeleteTopicsBody, describe_configs::DescribeConfigsBody, describe_groups::DescribeGroupsBody, describe_topic_partitions::DescribeTopicPartitionsBody, fetch::FetchBody, find_coordinator::FindCoordinatorBody, heartbeat::HeartbeatBody, incremental_alter_configs::IncrementalAlterConfigsBody, join_group::JoinGroupBody, leave_group::LeaveGroupBody, list_groups::ListGroupsBody, list_offsets::ListOffsetsBody, metadata::MetadataBody, offset_commit::OffsetCommitBody, offset_delete::OffsetDeleteBody, offset_fetch::OffsetFetchBody, produce::ProduceBody, sync_group::SyncGroupBody, request::KafRequestBody}, DecodeFromBytes, DecodeVersioned, EncodingError},
+    utils::{is_api_version_compatible, parse_primitive_types::*},
 };
 
 #[derive(Debug, Clone)]
@@ -15,19 +40,28 @@ pub struct KafRequestHeader {
     pub tags: Option<Vec<TaggedField>>, // COMPACT_ARRAY (nullable)
 }
 
-impl DecodeFromBytes for KafRequestHeader {
-    // Parse a request header from `input`, returning the header and the number of bytes consumed.
-    fn read_from_u8(
+impl KafRequestHeader {
+    /// Header v1 (no tag buffer) for versions before the api went flexible, v2 after
+    pub fn read_for_api(input: &[u8], offset: &mut usize) -> Result<KafRequestHeader, EncodingError> {
+        Self::read(input, offset, |api_key, version| api_key.is_flexible(version))
+    }
+
+    fn read(
         input: &[u8],
-        mut offset: &mut usize,
+        offset: &mut usize,
+        has_tags: impl Fn(&KafApiKey, i16) -> bool,
     ) -> Result<KafRequestHeader, EncodingError> {
-        let request_api_key = read_i16_be(input, &mut offset)?.into();
-        let request_api_version = read_i16_be(input, &mut offset)?;
-        let correlation_id = read_i32_be(input, &mut offset)?;
-        let client_id = read_nullable_string(input, &mut offset)?;
+        let request_api_key: KafApiKey = read_i16_be(input, offset)?.into();
+        let request_api_version = read_i16_be(input, offset)?;
+        let correlation_id = read_i32_be(input, offset)?;
+        let client_id = read_nullable_string(input, offset)?;
 
         // TAG_BUFFER: COMPACT_ARRAY of TaggedField
-        let tags = read_compact_tag_buffer(input, &mut offset)?;
+        let tags = if has_tags(&request_api_key, request_api_version) {
+            read_compact_tag_buffer(input, offset)?
+        } else {
+            None
+        };
 
         Ok(KafRequestHeader {
             request_api_key,
@@ -39,6 +73,16 @@ impl DecodeFromBytes for KafRequestHeader {
     }
 }
 
+impl DecodeFromBytes for KafRequestHeader {
+    // Parse a v2 request header from `input`, advancing `offset` past it.
+    fn read_from_u8(
+        input: &[u8],
+        offset: &mut usize,
+    ) -> Result<KafRequestHeader, EncodingError> {
+        Self::read(input, offset, |_, _| true)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct KafRequest {
     pub header: KafRequestHeader,
@@ -46,14 +90,45 @@ pub struct KafRequest {
 }
 
 impl DecodeFromBytes for KafRequest {
-    fn read_from_u8(input: &[u8], mut offset: &mut usize) -> Result<KafRequest, EncodingError> {
+    fn read_from_u8(input: &[u8], offset: &mut usize) -> Result<KafRequest, EncodingError> {
         use KafRequestBody::*;
-        let header = KafRequestHeader::read_from_u8(input, &mut offset).unwrap();
+        let header = KafRequestHeader::read_for_api(input, offset)?;
 
+        // bodies of versions we don't support are left for the handler to reject
+        let version = header.request_api_version;
+        if !is_api_version_compatible(header.request_api_key.clone(), version) {
+            return Ok(KafRequest { header, body: Empty });
+        }
         let body = match header.request_api_key {
             KafApiKey::DescribeTopicPartitions => DescribeTopicPartitions(
                 DescribeTopicPartitionsBody::read_from_u8(input, offset)?
             ),
+            KafApiKey::Produce => Produce(ProduceBody::read_versioned(input, offset, version)?),
+            KafApiKey::Fetch => Fetch(FetchBody::read_versioned(input, offset, version)?),
+            KafApiKey::CreateTopics => CreateTopics(CreateTopicsBody::read_versioned(input, offset, version)?),
+            KafApiKey::DeleteTopics => DeleteTopics(DeleteTopicsBody::read_versioned(input, offset, version)?),
+            KafApiKey::CreatePartitions => CreatePartitions(CreatePartitionsBody::read_versioned(input, offset, version)?),
+            KafApiKey::DeleteRecords => DeleteRecords(DeleteRecordsBody::read_versioned(input, offset, version)?),
+            KafApiKey::DescribeConfigs => DescribeConfigs(DescribeConfigsBody::read_versioned(input, offset, version)?),
+            KafApiKey::AlterConfigs => AlterConfigs(AlterConfigsBody::read_versioned(input, offset, version)?),
+            KafApiKey::IncrementalAlterConfigs => {
+                IncrementalAlterConfigs(IncrementalAlterConfigsBody::read_versioned(input, offset, version)?)
+            }
+            KafApiKey::OffsetCommit => OffsetCommit(OffsetCommitBody::read_versioned(input, offset, version)?),
+            KafApiKey::OffsetFetch => OffsetFetch(OffsetFetchBody::read_versioned(input, offset, version)?),
+            KafApiKey::FindCoordinator => FindCoordinator(FindCoordinatorBody::read_versioned(input, offset, version)?),
+            KafApiKey::JoinGroup => JoinGroup(JoinGroupBody::read_versioned(input, offset, version)?),
+            KafApiKey::SyncGroup => SyncGroup(SyncGroupBody::read_versioned(input, offset, version)?),
+            KafApiKey::Heartbeat => Heartbeat(HeartbeatBody::read_versioned(input, offset, version)?),
+            KafApiKey::LeaveGroup => LeaveGroup(LeaveGroupBody::read_versioned(input, offset, version)?),
+            KafApiKey::DescribeGroups => DescribeGroups(DescribeGroupsBody::read_versioned(input, offset, version)?),
+            KafApiKey::ListGroups => ListGroups(ListGroupsBody::read_versioned(input, offset, version)?),
+            KafApiKey::DeleteGroups => DeleteGroups(DeleteGroupsBody::read_versioned(input, offset, version)?),
+            KafApiKey::OffsetDelete => OffsetDelete(OffsetDeleteBody::read_versioned(input, offset, version)?),
+            KafApiKey::ConsumerGroupHeartbeat => ConsumerGroupHeartbeat(ConsumerGroupHeartbeatBody::read_versioned(input, offset, version)?),
+            KafApiKey::ConsumerGroupDescribe => ConsumerGroupDescribe(ConsumerGroupDescribeBody::read_versioned(input, offset, version)?),
+            KafApiKey::ListOffsets => ListOffsets(ListOffsetsBody::read_versioned(input, offset, version)?),
+            KafApiKey::Metadata => Metadata(MetadataBody::read_versioned(input, offset, version)?),
             _ => Empty,
         };
 
@@ -0,0 +1,142 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i32_be, read_i64_be},
+};
+
+/// generation_id_or_member_epoch of commits from outside any generation, by
+/// admin tools or consumers that assign partitions themselves
+pub const NO_GENERATION_ID: i32 = -1;
+
+/*
+* OffsetCommit Request (Version: 0-9) => group_id generation_id_or_member_epoch (v1+) member_id (v1+)
+*                                        group_instance_id (v7+) retention_time_ms (v2-4) [topics] _tagged_fields (v8+)
+* topics => name [partitions] _tagged_fields (v8+)
+*   partitions => partition_index committed_offset committed_leader_epoch (v6+) commit_timestamp (v1)
+*                 committed_metadata _tagged_fields (v8+)
+*/
+#[derive(Debug, Clone)]
+pub struct OffsetCommitBody {
+    pub group_id: String,
+    /// NO_GENERATION_ID before v1
+    pub generation_id_or_member_epoch: i32,
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    /// -1 unless set by v2-4, the broker's offsets.retention.minutes applies
+    pub retention_time_ms: i64,
+    pub topics: Vec<OffsetCommitRequestTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetCommitRequestTopic {
+    pub name: String,
+    pub partitions: Vec<OffsetCommitRequestPartition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetCommitRequestPartition {
+    pub partition_index: i32,
+    pub committed_offset: i64,
+    /// -1 before v6
+    pub committed_leader_epoch: i32,
+    pub committed_metadata: Option<String>,
+}
+
+impl DecodeVersioned for OffsetCommitBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::OffsetCommit.is_flexible(version);
+        let group_id = read_string(input, offset, flexible)?;
+        let (generation_id_or_member_epoch, member_id) = if version >= 1 {
+            (read_i32_be(input, offset)?, read_string(input, offset, flexible)?)
+        } else {
+            (NO_GENERATION_ID, String::new())
+        };
+        let group_instance_id = if version >= 7 { read_nullable_string(input, offset, flexible)? } else { None };
+        let retention_time_ms = if (2..=4).contains(&version) { read_i64_be(input, offset)? } else { -1 };
+        let topics = read_array(input, offset, flexible, |input, offset| {
+            let name = read_string(input, offset, flexible)?;
+            let partitions = read_array(input, offset, flexible, |input, offset| {
+                let partition_index = read_i32_be(input, offset)?;
+                let committed_offset = read_i64_be(input, offset)?;
+                let committed_leader_epoch = if version >= 6 { read_i32_be(input, offset)? } else { -1 };
+                if version == 1 {
+                    // commit_timestamp, which the broker sets itself
+                    read_i64_be(input, offset)?;
+                }
+                let partition = OffsetCommitRequestPartition {
+                    partition_index,
+                    committed_offset,
+                    committed_leader_epoch,
+                    committed_metadata: read_nullable_string(input, offset, flexible)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(partition)
+            })?;
+            skip_tagged_fields(input, offset, flexible)?;
+            Ok(OffsetCommitRequestTopic { name, partitions })
+        })?;
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(OffsetCommitBody {
+            group_id,
+            generation_id_or_member_epoch,
+            member_id,
+            group_instance_id,
+            retention_time_ms,
+            topics,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_the_fields_each_version_has() {
+        for version in [2, 6, 8] {
+            let flexible = version >= 8;
+            let mut buf = vec![];
+            write_string(&mut buf, "orders-app", flexible);
+            buf.extend(3i32.encode_to_bytes());
+            write_string(&mut buf, "member-1", flexible);
+            if version >= 7 {
+                write_nullable_string(&mut buf, Some("instance-1"), flexible);
+            }
+            if version <= 4 {
+                buf.extend(60_000i64.encode_to_bytes());
+            }
+            write_array(&mut buf, &["orders"], flexible, |buf, name| {
+                write_string(buf, name, flexible);
+                write_array(buf, &[0, 1], flexible, |buf, partition: &i32| {
+                    buf.extend(partition.encode_to_bytes());
+                    buf.extend(42i64.encode_to_bytes());
+                    if version >= 6 {
+                        buf.extend(5i32.encode_to_bytes());
+                    }
+                    write_nullable_string(buf, (*partition == 0).then_some("meta"), flexible);
+                    write_tagged_fields(buf, flexible);
+                });
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = OffsetCommitBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!((body.generation_id_or_member_epoch, body.member_id.as_str()), (3, "member-1"));
+            assert_eq!(body.group_instance_id.is_some(), version >= 7);
+            assert_eq!(body.retention_time_ms, if version <= 4 { 60_000 } else { -1 });
+            let partitions = &body.topics[0].partitions;
+            assert_eq!((partitions[0].committed_offset, partitions[0].committed_metadata.as_deref()), (42, Some("meta")));
+            assert_eq!(partitions[1].committed_leader_epoch, if version >= 6 { 5 } else { -1 });
+            assert_eq!(partitions[1].committed_metadata, None);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{
+    common::{
+        codec::{read_array, read_string},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_i32_be,
+};
+
+/*
+* OffsetDelete Request (Version: 0) => group_id [topics]
+* topics => name [partitions]
+*   partitions => partition_index
+*/
+#[derive(Debug, Clone)]
+pub struct OffsetDeleteBody {
+    pub group_id: String,
+    pub topics: Vec<OffsetDeleteRequestTopic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetDeleteRequestTopic {
+    pub name: String,
+    pub partitions: Vec<i32>,
+}
+
+impl DecodeVersioned for OffsetDeleteBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, _version: i16) -> Result<Self, EncodingError> {
+        Ok(OffsetDeleteBody {
+            group_id: read_string(input, offset, false)?,
+            topics: read_array(input, offset, false, |input, offset| {
+                Ok(OffsetDeleteRequestTopic {
+                    name: read_string(input, offset, false)?,
+                    partitions: read_array(input, offset, false, read_i32_be)?,
+                })
+            })?,
+        })
+    }
+}
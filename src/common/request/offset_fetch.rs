@@ -0,0 +1,132 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_array, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i32_be, read_u8_be},
+};
+
+/*
+* OffsetFetch Request (Version: 0-7) => group_id [topics] require_stable (v7+) _tagged_fields (v6+)
+* topics => name [partition_indexes] _tagged_fields (v6+)
+*
+* OffsetFetch Request (Version: 8-9) => [groups] require_stable _tagged_fields
+* groups => group_id member_id (v9+) member_epoch (v9+) [topics] _tagged_fields
+*   topics => name [partition_indexes] _tagged_fields
+*
+* topics is nullable from v2, null for every partition the group committed
+*/
+#[derive(Debug, Clone)]
+pub struct OffsetFetchBody {
+    /// the one group of v0-7, or the batch of v8+
+    pub groups: Vec<OffsetFetchRequestGroup>,
+    pub require_stable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetFetchRequestGroup {
+    pub group_id: String,
+    pub member_id: Option<String>,
+    /// -1 before v9
+    pub member_epoch: i32,
+    pub topics: Option<Vec<OffsetFetchRequestTopic>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetFetchRequestTopic {
+    pub name: String,
+    pub partition_indexes: Vec<i32>,
+}
+
+fn read_topics(input: &[u8], offset: &mut usize, version: i16, flexible: bool) -> Result<Option<Vec<OffsetFetchRequestTopic>>, EncodingError> {
+    let read_topic = |input: &[u8], offset: &mut usize| {
+        let topic = OffsetFetchRequestTopic {
+            name: read_string(input, offset, flexible)?,
+            partition_indexes: read_array(input, offset, flexible, read_i32_be)?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(topic)
+    };
+    if version >= 2 {
+        read_nullable_array(input, offset, flexible, read_topic)
+    } else {
+        read_array(input, offset, flexible, read_topic).map(Some)
+    }
+}
+
+impl DecodeVersioned for OffsetFetchBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::OffsetFetch.is_flexible(version);
+        let groups = if version >= 8 {
+            read_array(input, offset, flexible, |input, offset| {
+                let group_id = read_string(input, offset, flexible)?;
+                let (member_id, member_epoch) = if version >= 9 {
+                    (read_nullable_string(input, offset, flexible)?, read_i32_be(input, offset)?)
+                } else {
+                    (None, -1)
+                };
+                let group = OffsetFetchRequestGroup {
+                    group_id,
+                    member_id,
+                    member_epoch,
+                    topics: read_topics(input, offset, version, flexible)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(group)
+            })?
+        } else {
+            vec![OffsetFetchRequestGroup {
+                group_id: read_string(input, offset, flexible)?,
+                member_id: None,
+                member_epoch: -1,
+                topics: read_topics(input, offset, version, flexible)?,
+            }]
+        };
+        let require_stable = version >= 7 && read_u8_be(input, offset)? != 0;
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(OffsetFetchBody { groups, require_stable })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_array, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    fn write_topics(buf: &mut Vec<u8>, topics: Option<&[&str]>, flexible: bool) {
+        write_nullable_array(buf, topics, flexible, |buf, name| {
+            write_string(buf, name, flexible);
+            write_array(buf, &[0, 2], flexible, |buf, partition: &i32| buf.extend(partition.encode_to_bytes()));
+            write_tagged_fields(buf, flexible);
+        });
+    }
+
+    #[test]
+    fn decodes_one_group_before_v8_and_a_batch_after() {
+        let mut buf = vec![];
+        write_string(&mut buf, "orders-app", false);
+        write_topics(&mut buf, None, false);
+        let body = OffsetFetchBody::read_versioned(&buf, &mut 0, 2).unwrap();
+        assert_eq!((body.groups[0].group_id.as_str(), body.groups[0].topics.as_ref()), ("orders-app", None));
+
+        let mut buf = vec![];
+        write_array(&mut buf, &["orders-app", "billing"], true, |buf, group_id| {
+            write_string(buf, group_id, true);
+            write_topics(buf, (*group_id == "billing").then_some(&["invoices"]), true);
+            write_tagged_fields(buf, true);
+        });
+        buf.push(1);
+        write_tagged_fields(&mut buf, true);
+        let mut offset = 0;
+        let body = OffsetFetchBody::read_versioned(&buf, &mut offset, 8).unwrap();
+        assert_eq!(offset, buf.len());
+        assert!(body.require_stable);
+        assert_eq!(body.groups[0].topics, None);
+        let topics = body.groups[1].topics.as_ref().unwrap();
+        assert_eq!((topics[0].name.as_str(), &topics[0].partition_indexes[..]), ("invoices", &[0, 2][..]));
+    }
+}
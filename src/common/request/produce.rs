@@ -0,0 +1,118 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_nullable_bytes, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    records::MemoryRecords,
+    utils::parse_primitive_types::{read_i16_be, read_i32_be},
+};
+
+/*
+* Produce Request (Version: 3-11) => transactional_id acks timeout_ms [topic_data] _tagged_fields (v9+)
+* transactional_id => NULLABLE_STRING
+* acks => INT16
+* timeout_ms => INT32
+* topic_data => name [partition_data] _tagged_fields (v9+)
+*   name => STRING
+*   partition_data => index records _tagged_fields (v9+)
+*     index => INT32
+*     records => RECORDS
+*/
+#[derive(Debug, Clone)]
+pub struct ProduceBody {
+    pub transactional_id: Option<String>,
+    pub acks: i16,
+    pub timeout_ms: i32,
+    pub topic_data: Vec<TopicProduceData>,
+}
+
+impl DecodeVersioned for ProduceBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::Produce.is_flexible(version);
+        let body = ProduceBody {
+            transactional_id: if version >= 3 { read_nullable_string(input, offset, flexible)? } else { None },
+            acks: read_i16_be(input, offset)?,
+            timeout_ms: read_i32_be(input, offset)?,
+            topic_data: read_array(input, offset, flexible, |input, offset| {
+                TopicProduceData::read_versioned(input, offset, version)
+            })?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TopicProduceData {
+    pub name: String,
+    pub partition_data: Vec<PartitionProduceData>,
+}
+
+impl DecodeVersioned for TopicProduceData {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::Produce.is_flexible(version);
+        let topic = TopicProduceData {
+            name: read_string(input, offset, flexible)?,
+            partition_data: read_array(input, offset, flexible, |input, offset| {
+                let partition = PartitionProduceData {
+                    index: read_i32_be(input, offset)?,
+                    records: read_nullable_bytes(input, offset, flexible)?.map(MemoryRecords::new),
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(partition)
+            })?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(topic)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PartitionProduceData {
+    pub index: i32,
+    pub records: Option<MemoryRecords>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_nullable_bytes, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    fn encode(version: i16, records: &[u8]) -> Vec<u8> {
+        let flexible = KafApiKey::Produce.is_flexible(version);
+        let mut buf = vec![];
+        write_nullable_string(&mut buf, None, flexible);
+        buf.extend((-1i16).encode_to_bytes());
+        buf.extend(30_000i32.encode_to_bytes());
+        write_array(&mut buf, &["orders"], flexible, |buf, name| {
+            write_string(buf, name, flexible);
+            write_array(buf, &[2i32], flexible, |buf, index| {
+                buf.extend(index.encode_to_bytes());
+                write_nullable_bytes(buf, Some(records), flexible);
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut buf, flexible);
+        buf
+    }
+
+    #[test]
+    fn decodes_classic_and_flexible_versions() {
+        for version in [3, 8, 9, 11] {
+            let buf = encode(version, b"batch");
+            let mut offset = 0;
+            let body = ProduceBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len(), "v{}", version);
+            assert_eq!((body.transactional_id, body.acks, body.timeout_ms), (None, -1, 30_000));
+            assert_eq!(body.topic_data[0].name, "orders");
+            let partition = &body.topic_data[0].partition_data[0];
+            assert_eq!(partition.index, 2);
+            assert_eq!(partition.records.as_ref().unwrap().as_bytes(), &b"batch"[..]);
+        }
+    }
+}
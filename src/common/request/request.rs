@@ -1,9 +1,33 @@
 use enum_as_inner::EnumAsInner;
 
-use crate::common::{request::describe_topic_partitions::DescribeTopicPartitionsBody, DecodeFromBytes, EncodingError};
+use crate::common::{request::{alter_configs::AlterConfigsBody, consumer_group_describe::ConsumerGroupDescribeBody, consumer_group_heartbeat::ConsumerGroupHeartbeatBody, create_partitions::CreatePartitionsBody, create_topics::CreateTopicsBody, delete_groups::DeleteGroupsBody, delete_records::DeleteRecordsBody, delete_topics::DeleteTopicsBody, describe_configs::DescribeConfigsBody, describe_groups::DescribeGroupsBody, describe_topic_partitions::DescribeTopicPartitionsBody, fetch::FetchBody, find_coordinator::FindCoordinatorBody, heartbeat::HeartbeatBody, incremental_alter_configs::IncrementalAlterConfigsBody, join_group::JoinGroupBody, leave_group::LeaveGroupBody, list_groups::ListGroupsBody, list_offsets::ListOffsetsBody, metadata::MetadataBody, offset_commit::OffsetCommitBody, offset_delete::OffsetDeleteBody, offset_fetch::OffsetFetchBody, produce::ProduceBody, sync_group::SyncGroupBody}, DecodeFromBytes, EncodingError};
 
 #[derive(Debug, EnumAsInner, Clone)]
 pub enum KafRequestBody {
     Empty,
     DescribeTopicPartitions(DescribeTopicPartitionsBody),
+    Produce(ProduceBody),
+    Fetch(FetchBody),
+    ListOffsets(ListOffsetsBody),
+    Metadata(MetadataBody),
+    CreateTopics(CreateTopicsBody),
+    DeleteTopics(DeleteTopicsBody),
+    CreatePartitions(CreatePartitionsBody),
+    DeleteRecords(DeleteRecordsBody),
+    DescribeConfigs(DescribeConfigsBody),
+    AlterConfigs(AlterConfigsBody),
+    IncrementalAlterConfigs(IncrementalAlterConfigsBody),
+    OffsetCommit(OffsetCommitBody),
+    OffsetFetch(OffsetFetchBody),
+    FindCoordinator(FindCoordinatorBody),
+    JoinGroup(JoinGroupBody),
+    SyncGroup(SyncGroupBody),
+    Heartbeat(HeartbeatBody),
+    LeaveGroup(LeaveGroupBody),
+    DescribeGroups(DescribeGroupsBody),
+    ListGroups(ListGroupsBody),
+    DeleteGroups(DeleteGroupsBody),
+    OffsetDelete(OffsetDeleteBody),
+    ConsumerGroupHeartbeat(ConsumerGroupHeartbeatBody),
+    ConsumerGroupDescribe(ConsumerGroupDescribeBody),
 }
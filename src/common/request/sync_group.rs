@@ -0,0 +1,97 @@
+use bytes::Bytes;
+
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{read_array, read_bytes, read_nullable_string, read_string, skip_tagged_fields},
+        DecodeVersioned, EncodingError,
+    },
+    utils::parse_primitive_types::read_i32_be,
+};
+
+/*
+* SyncGroup Request (Version: 0-5) => group_id generation_id member_id group_instance_id (v3+)
+*                                     protocol_type (v5+) protocol_name (v5+) [assignments] _tagged_fields (v4+)
+* assignments => member_id assignment _tagged_fields (v4+)
+*   only the leader sends assignments
+*/
+#[derive(Debug, Clone)]
+pub struct SyncGroupBody {
+    pub group_id: String,
+    pub generation_id: i32,
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub assignments: Vec<SyncGroupRequestAssignment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncGroupRequestAssignment {
+    pub member_id: String,
+    pub assignment: Bytes,
+}
+
+impl DecodeVersioned for SyncGroupBody {
+    fn read_versioned(input: &[u8], offset: &mut usize, version: i16) -> Result<Self, EncodingError> {
+        let flexible = KafApiKey::SyncGroup.is_flexible(version);
+        let body = SyncGroupBody {
+            group_id: read_string(input, offset, flexible)?,
+            generation_id: read_i32_be(input, offset)?,
+            member_id: read_string(input, offset, flexible)?,
+            group_instance_id: if version >= 3 { read_nullable_string(input, offset, flexible)? } else { None },
+            protocol_type: if version >= 5 { read_nullable_string(input, offset, flexible)? } else { None },
+            protocol_name: if version >= 5 { read_nullable_string(input, offset, flexible)? } else { None },
+            assignments: read_array(input, offset, flexible, |input, offset| {
+                let assignment = SyncGroupRequestAssignment {
+                    member_id: read_string(input, offset, flexible)?,
+                    assignment: read_bytes(input, offset, flexible)?,
+                };
+                skip_tagged_fields(input, offset, flexible)?;
+                Ok(assignment)
+            })?,
+        };
+        skip_tagged_fields(input, offset, flexible)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{
+        codec::{write_array, write_bytes, write_nullable_string, write_string, write_tagged_fields},
+        EncodeToBytes,
+    };
+
+    #[test]
+    fn decodes_the_leaders_assignments() {
+        for version in [0, 5] {
+            let flexible = version >= 4;
+            let mut buf = vec![];
+            write_string(&mut buf, "orders-app", flexible);
+            buf.extend(3i32.encode_to_bytes());
+            write_string(&mut buf, "member-1", flexible);
+            if version >= 3 {
+                write_nullable_string(&mut buf, None, flexible);
+            }
+            if version >= 5 {
+                write_nullable_string(&mut buf, Some("consumer"), flexible);
+                write_nullable_string(&mut buf, Some("range"), flexible);
+            }
+            write_array(&mut buf, &[("member-1", b"a1"), ("member-2", b"a2")], flexible, |buf, (member_id, assignment)| {
+                write_string(buf, member_id, flexible);
+                write_bytes(buf, *assignment, flexible);
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(&mut buf, flexible);
+
+            let mut offset = 0;
+            let body = SyncGroupBody::read_versioned(&buf, &mut offset, version).unwrap();
+            assert_eq!(offset, buf.len());
+            assert_eq!((body.generation_id, body.member_id.as_str()), (3, "member-1"));
+            assert_eq!(body.protocol_name.as_deref(), (version >= 5).then_some("range"));
+            assert_eq!(body.assignments[1].assignment, Bytes::from_static(b"a2"));
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* AlterConfigs Response (Version: 0-2) => throttle_time_ms [responses] _tagged_fields (v2+)
+* responses => error_code error_message resource_type resource_name _tagged_fields (v2+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct AlterConfigsResponse {
+    pub throttle_time_ms: i32,
+    pub responses: Vec<AlterConfigsResourceResponse>,
+}
+
+impl EncodeVersioned for AlterConfigsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        encode_responses(self.throttle_time_ms, &self.responses, KafApiKey::AlterConfigs.is_flexible(version))
+    }
+}
+
+/// Shared with IncrementalAlterConfigs, whose response is the same
+pub fn encode_responses(throttle_time_ms: i32, responses: &[AlterConfigsResourceResponse], flexible: bool) -> Vec<u8> {
+    let mut res: Vec<u8> = vec![];
+
+    res.extend(throttle_time_ms.encode_to_bytes());
+    write_array(&mut res, responses, flexible, |buf, response| {
+        buf.extend(response.error_code.encode_to_bytes());
+        write_nullable_string(buf, response.error_message.as_deref(), flexible);
+        buf.extend(response.resource_type.encode_to_bytes());
+        write_string(buf, &response.resource_name, flexible);
+        write_tagged_fields(buf, flexible);
+    });
+    write_tagged_fields(&mut res, flexible);
+
+    res
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlterConfigsResourceResponse {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub resource_type: i8,
+    pub resource_name: String,
+}
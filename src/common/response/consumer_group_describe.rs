@@ -0,0 +1,112 @@
+use crate::common::{
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    uuid::KafUuid,
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* ConsumerGroupDescribe Response (Version: 0) => throttle_time_ms [groups] _tagged_fields
+* groups => error_code error_message group_id group_state group_epoch assignment_epoch assignor_name [members]
+*           authorized_operations _tagged_fields
+*   members => member_id instance_id rack_id member_epoch client_id client_host [subscribed_topic_names]
+*              subscribed_topic_regex assignment target_assignment _tagged_fields
+*     assignment, target_assignment => [topic_partitions] _tagged_fields
+*       topic_partitions => topic_id topic_name [partitions] _tagged_fields
+*/
+#[derive(Debug, Default, Clone)]
+pub struct ConsumerGroupDescribeResponse {
+    pub throttle_time_ms: i32,
+    pub groups: Vec<DescribedConsumerGroup>,
+}
+
+fn write_assignment(buf: &mut Vec<u8>, assignment: &[AssignedTopicPartitions]) {
+    write_array(buf, assignment, true, |buf, topic| {
+        buf.extend(topic.topic_id.encode_to_bytes());
+        write_string(buf, &topic.topic_name, true);
+        write_array(buf, &topic.partitions, true, |buf, partition| buf.extend(partition.encode_to_bytes()));
+        write_tagged_fields(buf, true);
+    });
+    write_tagged_fields(buf, true);
+}
+
+impl EncodeVersioned for ConsumerGroupDescribeResponse {
+    fn encode_versioned(&self, _version: i16) -> Vec<u8> {
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.groups, true, |buf, group| {
+            buf.extend(group.error_code.encode_to_bytes());
+            write_nullable_string(buf, group.error_message.as_deref(), true);
+            write_string(buf, &group.group_id, true);
+            write_string(buf, &group.group_state, true);
+            buf.extend(group.group_epoch.encode_to_bytes());
+            buf.extend(group.assignment_epoch.encode_to_bytes());
+            write_string(buf, &group.assignor_name, true);
+            write_array(buf, &group.members, true, |buf, member| {
+                write_string(buf, &member.member_id, true);
+                write_nullable_string(buf, member.instance_id.as_deref(), true);
+                write_nullable_string(buf, member.rack_id.as_deref(), true);
+                buf.extend(member.member_epoch.encode_to_bytes());
+                write_string(buf, &member.client_id, true);
+                write_string(buf, &member.client_host, true);
+                write_array(buf, &member.subscribed_topic_names, true, |buf, name| write_string(buf, name, true));
+                write_nullable_string(buf, member.subscribed_topic_regex.as_deref(), true);
+                write_assignment(buf, &member.assignment);
+                write_assignment(buf, &member.target_assignment);
+                write_tagged_fields(buf, true);
+            });
+            buf.extend(group.authorized_operations.encode_to_bytes());
+            write_tagged_fields(buf, true);
+        });
+        write_tagged_fields(&mut res, true);
+
+        res
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DescribedConsumerGroup {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub group_id: String,
+    pub group_state: String,
+    pub group_epoch: i32,
+    pub assignment_epoch: i32,
+    pub assignor_name: String,
+    pub members: Vec<DescribedConsumerGroupMember>,
+    pub authorized_operations: i32,
+}
+
+impl DescribedConsumerGroup {
+    pub fn error(group_id: &str, error_code: i16, error_message: Option<String>) -> Self {
+        DescribedConsumerGroup {
+            error_code,
+            error_message,
+            group_id: group_id.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescribedConsumerGroupMember {
+    pub member_id: String,
+    pub instance_id: Option<String>,
+    pub rack_id: Option<String>,
+    pub member_epoch: i32,
+    pub client_id: String,
+    pub client_host: String,
+    pub subscribed_topic_names: Vec<String>,
+    pub subscribed_topic_regex: Option<String>,
+    /// what the member owns
+    pub assignment: Vec<AssignedTopicPartitions>,
+    /// what it will own once it reconciled
+    pub target_assignment: Vec<AssignedTopicPartitions>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssignedTopicPartitions {
+    pub topic_id: KafUuid,
+    pub topic_name: String,
+    pub partitions: Vec<i32>,
+}
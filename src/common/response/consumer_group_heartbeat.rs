@@ -0,0 +1,93 @@
+use crate::common::{
+    codec::{write_array, write_nullable_string, write_tagged_fields},
+    request::consumer_group_heartbeat::TopicPartitions,
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* ConsumerGroupHeartbeat Response (Version: 0-1) => throttle_time_ms error_code error_message member_id member_epoch
+*                                                   heartbeat_interval_ms assignment _tagged_fields
+*   member_id => nullable, the member's id, picked by the coordinator before v1
+*   assignment => nullable, null if unchanged since the last heartbeat
+*     assignment => [topic_partitions] _tagged_fields
+*     topic_partitions => topic_id [partitions] _tagged_fields
+*/
+#[derive(Debug, Default, Clone)]
+pub struct ConsumerGroupHeartbeatResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub member_id: Option<String>,
+    pub member_epoch: i32,
+    pub heartbeat_interval_ms: i32,
+    pub assignment: Option<Vec<TopicPartitions>>,
+}
+
+impl ConsumerGroupHeartbeatResponse {
+    pub fn error(error_code: i16, error_message: Option<String>) -> Self {
+        ConsumerGroupHeartbeatResponse {
+            error_code,
+            error_message,
+            ..Default::default()
+        }
+    }
+}
+
+impl EncodeVersioned for ConsumerGroupHeartbeatResponse {
+    fn encode_versioned(&self, _version: i16) -> Vec<u8> {
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        res.extend(self.error_code.encode_to_bytes());
+        write_nullable_string(&mut res, self.error_message.as_deref(), true);
+        write_nullable_string(&mut res, self.member_id.as_deref(), true);
+        res.extend(self.member_epoch.encode_to_bytes());
+        res.extend(self.heartbeat_interval_ms.encode_to_bytes());
+        // a nullable struct is prefixed with -1 if null, 1 otherwise
+        match &self.assignment {
+            None => res.extend((-1i8).to_be_bytes()),
+            Some(topic_partitions) => {
+                res.extend(1i8.to_be_bytes());
+                write_array(&mut res, topic_partitions, true, |buf, topic| {
+                    buf.extend(topic.topic_id.encode_to_bytes());
+                    write_array(buf, &topic.partitions, true, |buf, partition| buf.extend(partition.encode_to_bytes()));
+                    write_tagged_fields(buf, true);
+                });
+                write_tagged_fields(&mut res, true);
+            }
+        }
+        write_tagged_fields(&mut res, true);
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::uuid::KafUuid;
+
+    #[test]
+    fn the_assignment_is_a_nullable_struct() {
+        let mut response = ConsumerGroupHeartbeatResponse {
+            member_id: Some("m".to_string()),
+            member_epoch: 3,
+            heartbeat_interval_ms: 5_000,
+            ..Default::default()
+        };
+        // throttle, error, null message, member, epoch, interval, null assignment, tags
+        let unchanged = 4 + 2 + 1 + 2 + 4 + 4 + 1 + 1;
+        let encoded = response.encode_versioned(0);
+        assert_eq!(encoded.len(), unchanged);
+        assert_eq!(encoded[unchanged - 2], 0xff);
+
+        response.assignment = Some(vec![TopicPartitions {
+            topic_id: KafUuid([1; 16]),
+            partitions: vec![0, 1],
+        }]);
+        let encoded = response.encode_versioned(1);
+        assert_eq!(encoded[unchanged - 2], 1);
+        // topics, topic id, partitions, tags of the topic, tags of the assignment
+        assert_eq!(encoded.len(), unchanged + 1 + 16 + 1 + 8 + 1 + 1);
+    }
+}
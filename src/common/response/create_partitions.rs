@@ -0,0 +1,40 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* CreatePartitions Response (Version: 0-3) => throttle_time_ms [results] _tagged_fields (v2+)
+* results => name error_code error_message _tagged_fields (v2+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct CreatePartitionsResponse {
+    pub throttle_time_ms: i32,
+    pub results: Vec<CreatePartitionsTopicResult>,
+}
+
+impl EncodeVersioned for CreatePartitionsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::CreatePartitions.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.results, flexible, |buf, result| {
+            write_string(buf, &result.name, flexible);
+            buf.extend(result.error_code.encode_to_bytes());
+            write_nullable_string(buf, result.error_message.as_deref(), flexible);
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatePartitionsTopicResult {
+    pub name: String,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+}
@@ -0,0 +1,86 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_array, write_nullable_string, write_string, write_tagged_fields},
+    uuid::KafUuid,
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* CreateTopics Response (Version: 2-7) => throttle_time_ms [topics] _tagged_fields (v5+)
+* topics => name topic_id (v7+) error_code error_message num_partitions (v5+) replication_factor (v5+)
+*           [configs] (v5+) _tagged_fields (v5+)
+*   configs => name value read_only config_source is_sensitive _tagged_fields (v5+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct CreateTopicsResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<CreatableTopicResult>,
+}
+
+impl EncodeVersioned for CreateTopicsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::CreateTopics.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.topics, flexible, |buf, topic| {
+            write_string(buf, &topic.name, flexible);
+            if version >= 7 {
+                buf.extend(topic.topic_id.encode_to_bytes());
+            }
+            buf.extend(topic.error_code.encode_to_bytes());
+            write_nullable_string(buf, topic.error_message.as_deref(), flexible);
+            if version >= 5 {
+                buf.extend(topic.num_partitions.encode_to_bytes());
+                buf.extend(topic.replication_factor.encode_to_bytes());
+                write_nullable_array(buf, topic.configs.as_deref(), flexible, |buf, config| {
+                    write_string(buf, &config.name, flexible);
+                    write_nullable_string(buf, config.value.as_deref(), flexible);
+                    buf.extend(config.read_only.encode_to_bytes());
+                    buf.extend(config.config_source.encode_to_bytes());
+                    buf.extend(config.is_sensitive.encode_to_bytes());
+                    write_tagged_fields(buf, flexible);
+                });
+            }
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatableTopicResult {
+    pub name: String,
+    pub topic_id: KafUuid,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub num_partitions: i32,
+    pub replication_factor: i16,
+    /// None on errors
+    pub configs: Option<Vec<CreatableTopicConfigs>>,
+}
+
+impl CreatableTopicResult {
+    pub fn error(name: &str, error_code: i16, error_message: String) -> Self {
+        CreatableTopicResult {
+            name: name.to_string(),
+            topic_id: KafUuid::ZERO,
+            error_code,
+            error_message: Some(error_message),
+            num_partitions: -1,
+            replication_factor: -1,
+            configs: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatableTopicConfigs {
+    pub name: String,
+    pub value: Option<String>,
+    pub read_only: bool,
+    pub config_source: i8,
+    pub is_sensitive: bool,
+}
@@ -0,0 +1,38 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* DeleteGroups Response (Version: 0-2) => throttle_time_ms [results] _tagged_fields (v2+)
+* results => group_id error_code _tagged_fields (v2+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct DeleteGroupsResponse {
+    pub throttle_time_ms: i32,
+    pub results: Vec<DeletableGroupResult>,
+}
+
+impl EncodeVersioned for DeleteGroupsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::DeleteGroups.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.results, flexible, |buf, result| {
+            write_string(buf, &result.group_id, flexible);
+            buf.extend(result.error_code.encode_to_bytes());
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletableGroupResult {
+    pub group_id: String,
+    pub error_code: i16,
+}
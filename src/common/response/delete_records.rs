@@ -0,0 +1,52 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* DeleteRecords Response (Version: 0-2) => throttle_time_ms [topics] _tagged_fields (v2+)
+* topics => name [partitions] _tagged_fields (v2+)
+*   partitions => partition_index low_watermark error_code _tagged_fields (v2+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct DeleteRecordsResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<DeleteRecordsTopicResult>,
+}
+
+impl EncodeVersioned for DeleteRecordsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::DeleteRecords.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.topics, flexible, |buf, topic| {
+            write_string(buf, &topic.name, flexible);
+            write_array(buf, &topic.partitions, flexible, |buf, partition| {
+                buf.extend(partition.partition_index.encode_to_bytes());
+                buf.extend(partition.low_watermark.encode_to_bytes());
+                buf.extend(partition.error_code.encode_to_bytes());
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteRecordsTopicResult {
+    pub name: String,
+    pub partitions: Vec<DeleteRecordsPartitionResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteRecordsPartitionResult {
+    pub partition_index: i32,
+    /// the log start offset after the deletion, -1 on error
+    pub low_watermark: i64,
+    pub error_code: i16,
+}
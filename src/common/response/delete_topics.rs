@@ -0,0 +1,51 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    uuid::KafUuid,
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* DeleteTopics Response (Version: 1-6) => throttle_time_ms [responses] _tagged_fields (v4+)
+* responses => name topic_id (v6+) error_code error_message (v5+) _tagged_fields (v4+)
+*   name => STRING, NULLABLE_STRING in v6+
+*/
+#[derive(Debug, Default, Clone)]
+pub struct DeleteTopicsResponse {
+    pub throttle_time_ms: i32,
+    pub responses: Vec<DeletableTopicResult>,
+}
+
+impl EncodeVersioned for DeleteTopicsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::DeleteTopics.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.responses, flexible, |buf, topic| {
+            if version >= 6 {
+                write_nullable_string(buf, topic.name.as_deref(), flexible);
+                buf.extend(topic.topic_id.encode_to_bytes());
+            } else {
+                write_string(buf, topic.name.as_deref().unwrap_or_default(), flexible);
+            }
+            buf.extend(topic.error_code.encode_to_bytes());
+            if version >= 5 {
+                write_nullable_string(buf, topic.error_message.as_deref(), flexible);
+            }
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletableTopicResult {
+    /// null for a topic id we don't know
+    pub name: Option<String>,
+    pub topic_id: KafUuid,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+}
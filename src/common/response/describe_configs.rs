@@ -0,0 +1,85 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* DescribeConfigs Response (Version: 1-4) => throttle_time_ms [results] _tagged_fields (v4+)
+* results => error_code error_message resource_type resource_name [configs] _tagged_fields (v4+)
+*   configs => name value read_only config_source is_sensitive [synonyms] config_type (v3+)
+*              documentation (v3+) _tagged_fields (v4+)
+*     synonyms => name value source _tagged_fields (v4+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct DescribeConfigsResponse {
+    pub throttle_time_ms: i32,
+    pub results: Vec<DescribeConfigsResult>,
+}
+
+impl EncodeVersioned for DescribeConfigsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::DescribeConfigs.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.results, flexible, |buf, result| {
+            buf.extend(result.error_code.encode_to_bytes());
+            write_nullable_string(buf, result.error_message.as_deref(), flexible);
+            buf.extend(result.resource_type.encode_to_bytes());
+            write_string(buf, &result.resource_name, flexible);
+            write_array(buf, &result.configs, flexible, |buf, config| {
+                write_string(buf, &config.name, flexible);
+                write_nullable_string(buf, config.value.as_deref(), flexible);
+                buf.extend(config.read_only.encode_to_bytes());
+                buf.extend(config.config_source.encode_to_bytes());
+                buf.extend(config.is_sensitive.encode_to_bytes());
+                write_array(buf, &config.synonyms, flexible, |buf, synonym| {
+                    write_string(buf, &synonym.name, flexible);
+                    write_nullable_string(buf, synonym.value.as_deref(), flexible);
+                    buf.extend(synonym.source.encode_to_bytes());
+                    write_tagged_fields(buf, flexible);
+                });
+                if version >= 3 {
+                    buf.extend(config.config_type.encode_to_bytes());
+                    write_nullable_string(buf, config.documentation.as_deref(), flexible);
+                }
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DescribeConfigsResult {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub configs: Vec<DescribeConfigsResourceResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescribeConfigsResourceResult {
+    pub name: String,
+    /// None for sensitive configs
+    pub value: Option<String>,
+    pub read_only: bool,
+    pub config_source: i8,
+    pub is_sensitive: bool,
+    /// empty unless asked for with include_synonyms
+    pub synonyms: Vec<DescribeConfigsSynonym>,
+    pub config_type: i8,
+    pub documentation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescribeConfigsSynonym {
+    pub name: String,
+    pub value: Option<String>,
+    pub source: i8,
+}
@@ -0,0 +1,121 @@
+use bytes::Bytes;
+
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_bytes, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* DescribeGroups Response (Version: 0-5) => throttle_time_ms (v1+) [groups] _tagged_fields (v5+)
+* groups => error_code group_id group_state protocol_type protocol_data [members] authorized_operations (v3+) _tagged_fields (v5+)
+*   protocol_data => the chosen protocol, empty unless the group is Stable
+*   members => member_id group_instance_id (v4+) client_id client_host member_metadata member_assignment _tagged_fields (v5+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct DescribeGroupsResponse {
+    pub throttle_time_ms: i32,
+    pub groups: Vec<DescribedGroup>,
+}
+
+impl EncodeVersioned for DescribeGroupsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::DescribeGroups.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 1 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        write_array(&mut res, &self.groups, flexible, |buf, group| {
+            buf.extend(group.error_code.encode_to_bytes());
+            write_string(buf, &group.group_id, flexible);
+            write_string(buf, &group.group_state, flexible);
+            write_string(buf, &group.protocol_type, flexible);
+            write_string(buf, &group.protocol_data, flexible);
+            write_array(buf, &group.members, flexible, |buf, member| {
+                write_string(buf, &member.member_id, flexible);
+                if version >= 4 {
+                    write_nullable_string(buf, member.group_instance_id.as_deref(), flexible);
+                }
+                write_string(buf, &member.client_id, flexible);
+                write_string(buf, &member.client_host, flexible);
+                write_bytes(buf, &member.member_metadata, flexible);
+                write_bytes(buf, &member.member_assignment, flexible);
+                write_tagged_fields(buf, flexible);
+            });
+            if version >= 3 {
+                buf.extend(group.authorized_operations.encode_to_bytes());
+            }
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DescribedGroup {
+    pub error_code: i16,
+    pub group_id: String,
+    pub group_state: String,
+    pub protocol_type: String,
+    pub protocol_data: String,
+    pub members: Vec<DescribedGroupMember>,
+    pub authorized_operations: i32,
+}
+
+impl DescribedGroup {
+    pub fn error(group_id: &str, error_code: i16) -> Self {
+        DescribedGroup {
+            error_code,
+            group_id: group_id.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescribedGroupMember {
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    /// the member's metadata for the chosen protocol, empty unless the group is Stable
+    pub member_metadata: Bytes,
+    pub member_assignment: Bytes,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_ids_from_v4_and_tag_buffers_from_v5() {
+        let response = DescribeGroupsResponse {
+            throttle_time_ms: 0,
+            groups: vec![DescribedGroup {
+                group_id: "g".to_string(),
+                group_state: "Stable".to_string(),
+                members: vec![DescribedGroupMember {
+                    member_id: "m".to_string(),
+                    group_instance_id: Some("i".to_string()),
+                    client_id: "c".to_string(),
+                    client_host: "h".to_string(),
+                    member_metadata: Bytes::from_static(b"md"),
+                    member_assignment: Bytes::new(),
+                }],
+                ..Default::default()
+            }],
+        };
+        // groups, error, group, state, type, data, members, member, client, host, metadata, assignment
+        let v0 = 4 + 2 + 3 + 8 + 2 + 2 + 4 + 3 + 3 + 3 + 6 + 4;
+        assert_eq!(response.encode_versioned(0).len(), v0);
+        // throttle_time_ms, then authorized_operations
+        assert_eq!(response.encode_versioned(3).len(), v0 + 4 + 4);
+        assert_eq!(response.encode_versioned(4).len(), v0 + 4 + 4 + 3);
+        // compact lengths save 1 byte per string and 3 per array or bytes, tag buffers cost 1
+        let strings = 8;
+        assert_eq!(response.encode_versioned(5).len(), v0 + 4 + 4 + 3 - strings - 3 * 4 + 3);
+    }
+}
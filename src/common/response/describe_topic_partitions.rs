@@ -9,7 +9,7 @@ pub struct DescribeTopicPartitionsResponse {
 }
 
 impl DescribeTopicPartitionsResponse {
-    pub fn bad_request() -> Self {
+    pub fn bad_request(topic_authorized_operations: i32) -> Self {
         DescribeTopicPartitionsResponse {
             throttle_time: 0,
             topics: CompactArray(Some(vec![TopicsEntry {
@@ -17,7 +17,7 @@ impl DescribeTopicPartitionsResponse {
                 topic_id: [0; 16],
                 is_internal: false,
                 partitions: CompactArray(Some(vec![])),
-                topic_authorized_operations: 0x00000df8i32,
+                topic_authorized_operations,
                 ..Default::default()
             }])),
             next_cursor: 0xFF,
@@ -55,19 +55,19 @@ pub struct TopicsEntry {
     pub topic_id: [u8; 16],
     pub is_internal: bool,
     pub partitions: CompactArray<PartitionsEntry>,
-    pub topic_authorized_operations: i32, // NOT IMPLEMENTED
+    pub topic_authorized_operations: i32, // bitfield of AclOperation, see Authorizer
     pub _tagged_fields: u8, // NOT IMPLEMENTED
 }
 
 impl TopicsEntry {
-    pub fn unknown_topic(topic_name: String) -> Self {
+    pub fn unknown_topic(topic_name: String, topic_authorized_operations: i32) -> Self {
         TopicsEntry {
             error_code: UNKNOWN_TOPIC_OR_PARTITION,
             name: CompactString(topic_name),
             topic_id: [0; 16],
             is_internal: false,
             partitions: CompactArray(Some(vec![])),
-            topic_authorized_operations: 0x00000df8i32,
+            topic_authorized_operations,
             ..Default::default()
         }
     }
@@ -90,7 +90,7 @@ impl EncodeToBytes for TopicsEntry {
 }
 
 #[derive(Debug, Default, Clone)]
-pub(super) struct PartitionsEntry {
+pub struct PartitionsEntry {
     pub error_code: i16,
     pub partition_index: i32,
     pub leader_id: i32,
@@ -0,0 +1,149 @@
+use crate::{
+    common::{
+        api::api_key::KafApiKey,
+        codec::{write_array, write_nullable_bytes, write_string, write_tagged_fields},
+        uuid::KafUuid,
+        EncodeToBytes, EncodeVersioned,
+    },
+    records::MemoryRecords,
+};
+
+/*
+* Fetch Response (Version: 0-17) => throttle_time_ms (v1+) error_code (v7+) session_id (v7+) [responses] _tagged_fields (v12+)
+* responses => topic (v0-12) topic_id (v13+) [partitions] _tagged_fields (v12+)
+*   partitions => partition_index error_code high_watermark last_stable_offset (v4+) log_start_offset (v5+)
+*                 [aborted_transactions] (v4+) preferred_read_replica (v11+) records _tagged_fields (v12+)
+*     aborted_transactions => producer_id first_offset _tagged_fields (v12+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct FetchResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub session_id: i32,
+    pub responses: Vec<FetchableTopicResponse>,
+}
+
+impl FetchResponse {
+    /// A top level error, e.g. for a bad fetch session
+    pub fn error(error_code: i16) -> Self {
+        FetchResponse {
+            error_code,
+            ..Default::default()
+        }
+    }
+}
+
+impl EncodeVersioned for FetchResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Fetch.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 1 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        if version >= 7 {
+            res.extend(self.error_code.encode_to_bytes());
+            res.extend(self.session_id.encode_to_bytes());
+        }
+        write_array(&mut res, &self.responses, flexible, |buf, topic| {
+            if version >= 13 {
+                buf.extend(topic.topic_id.encode_to_bytes());
+            } else {
+                write_string(buf, &topic.topic, flexible);
+            }
+            write_array(buf, &topic.partitions, flexible, |buf, partition| {
+                buf.extend(partition.encode_versioned(version))
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FetchableTopicResponse {
+    pub topic: String,
+    pub topic_id: KafUuid,
+    pub partitions: Vec<PartitionData>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartitionData {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub high_watermark: i64,
+    pub last_stable_offset: i64,
+    pub log_start_offset: i64,
+    /// None unless the fetch is READ_COMMITTED
+    pub aborted_transactions: Option<Vec<AbortedTransaction>>,
+    pub preferred_read_replica: i32,
+    pub records: Option<MemoryRecords>,
+}
+
+impl PartitionData {
+    pub fn error(partition_index: i32, error_code: i16) -> Self {
+        PartitionData {
+            partition_index,
+            error_code,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for PartitionData {
+    fn default() -> Self {
+        PartitionData {
+            partition_index: 0,
+            error_code: 0,
+            high_watermark: -1,
+            last_stable_offset: -1,
+            log_start_offset: -1,
+            aborted_transactions: None,
+            preferred_read_replica: -1,
+            records: None,
+        }
+    }
+}
+
+impl EncodeVersioned for PartitionData {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Fetch.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.partition_index.encode_to_bytes());
+        res.extend(self.error_code.encode_to_bytes());
+        res.extend(self.high_watermark.encode_to_bytes());
+        if version >= 4 {
+            res.extend(self.last_stable_offset.encode_to_bytes());
+        }
+        if version >= 5 {
+            res.extend(self.log_start_offset.encode_to_bytes());
+        }
+        match &self.aborted_transactions {
+            _ if version < 4 => {}
+            // a null array
+            None if flexible => res.push(0),
+            None => res.extend((-1i32).encode_to_bytes()),
+            Some(aborted) => write_array(&mut res, aborted, flexible, |buf, txn| {
+                buf.extend(txn.producer_id.encode_to_bytes());
+                buf.extend(txn.first_offset.encode_to_bytes());
+                write_tagged_fields(buf, flexible);
+            }),
+        }
+        if version >= 11 {
+            res.extend(self.preferred_read_replica.encode_to_bytes());
+        }
+        write_nullable_bytes(&mut res, self.records.as_ref().map(|r| &r.as_bytes()[..]), flexible);
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AbortedTransaction {
+    pub producer_id: i64,
+    pub first_offset: i64,
+}
@@ -0,0 +1,73 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* FindCoordinator Response (Version: 0-3) => throttle_time_ms (v1+) error_code error_message (v1+) node_id host port _tagged_fields (v3+)
+* FindCoordinator Response (Version: 4) => throttle_time_ms [coordinators] _tagged_fields
+* coordinators => key node_id host port error_code error_message _tagged_fields
+*/
+#[derive(Debug, Default, Clone)]
+pub struct FindCoordinatorResponse {
+    pub throttle_time_ms: i32,
+    /// one per key, before v4 the only one is written at the top level
+    pub coordinators: Vec<Coordinator>,
+}
+
+impl EncodeVersioned for FindCoordinatorResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::FindCoordinator.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 1 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        if version >= 4 {
+            write_array(&mut res, &self.coordinators, flexible, |buf, coordinator| {
+                write_string(buf, &coordinator.key, flexible);
+                buf.extend(coordinator.node_id.encode_to_bytes());
+                write_string(buf, &coordinator.host, flexible);
+                buf.extend(coordinator.port.encode_to_bytes());
+                buf.extend(coordinator.error_code.encode_to_bytes());
+                write_nullable_string(buf, coordinator.error_message.as_deref(), flexible);
+                write_tagged_fields(buf, flexible);
+            });
+        } else if let Some(coordinator) = self.coordinators.first() {
+            res.extend(coordinator.error_code.encode_to_bytes());
+            if version >= 1 {
+                write_nullable_string(&mut res, coordinator.error_message.as_deref(), flexible);
+            }
+            res.extend(coordinator.node_id.encode_to_bytes());
+            write_string(&mut res, &coordinator.host, flexible);
+            res.extend(coordinator.port.encode_to_bytes());
+        }
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coordinator {
+    pub key: String,
+    pub node_id: i32,
+    pub host: String,
+    pub port: i32,
+    pub error_code: i16,
+    pub error_message: Option<String>,
+}
+
+impl Coordinator {
+    pub fn error(key: &str, error_code: i16, error_message: Option<String>) -> Self {
+        Coordinator {
+            key: key.to_string(),
+            node_id: -1,
+            host: String::new(),
+            port: -1,
+            error_code,
+            error_message,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::common::{api::api_key::KafApiKey, codec::write_tagged_fields, EncodeToBytes, EncodeVersioned};
+
+/*
+* Heartbeat Response (Version: 0-4) => throttle_time_ms (v1+) error_code _tagged_fields (v4+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct HeartbeatResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+}
+
+impl EncodeVersioned for HeartbeatResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Heartbeat.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 1 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        res.extend(self.error_code.encode_to_bytes());
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
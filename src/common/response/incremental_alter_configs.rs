@@ -0,0 +1,25 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    response::alter_configs::{encode_responses, AlterConfigsResourceResponse},
+    EncodeVersioned,
+};
+
+/*
+* IncrementalAlterConfigs Response (Version: 0-1) => throttle_time_ms [responses] _tagged_fields (v1+)
+* responses => error_code error_message resource_type resource_name _tagged_fields (v1+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct IncrementalAlterConfigsResponse {
+    pub throttle_time_ms: i32,
+    pub responses: Vec<AlterConfigsResourceResponse>,
+}
+
+impl EncodeVersioned for IncrementalAlterConfigsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        encode_responses(
+            self.throttle_time_ms,
+            &self.responses,
+            KafApiKey::IncrementalAlterConfigs.is_flexible(version),
+        )
+    }
+}
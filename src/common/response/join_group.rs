@@ -0,0 +1,70 @@
+use bytes::Bytes;
+
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_bytes, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* JoinGroup Response (Version: 0-9) => throttle_time_ms (v2+) error_code generation_id protocol_type (v7+)
+*                                      protocol_name leader skip_assignment (v9+) member_id [members] _tagged_fields (v6+)
+*   protocol_name => nullable from v7
+* members => member_id group_instance_id (v5+) metadata _tagged_fields (v6+)
+*   empty for everyone but the leader
+*/
+#[derive(Debug, Default, Clone)]
+pub struct JoinGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub generation_id: i32,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub leader: String,
+    pub skip_assignment: bool,
+    pub member_id: String,
+    pub members: Vec<JoinGroupResponseMember>,
+}
+
+impl EncodeVersioned for JoinGroupResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::JoinGroup.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 2 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        res.extend(self.error_code.encode_to_bytes());
+        res.extend(self.generation_id.encode_to_bytes());
+        if version >= 7 {
+            write_nullable_string(&mut res, self.protocol_type.as_deref(), flexible);
+            write_nullable_string(&mut res, self.protocol_name.as_deref(), flexible);
+        } else {
+            write_string(&mut res, self.protocol_name.as_deref().unwrap_or_default(), flexible);
+        }
+        write_string(&mut res, &self.leader, flexible);
+        if version >= 9 {
+            res.extend(self.skip_assignment.encode_to_bytes());
+        }
+        write_string(&mut res, &self.member_id, flexible);
+        write_array(&mut res, &self.members, flexible, |buf, member| {
+            write_string(buf, &member.member_id, flexible);
+            if version >= 5 {
+                write_nullable_string(buf, member.group_instance_id.as_deref(), flexible);
+            }
+            write_bytes(buf, &member.metadata, flexible);
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinGroupResponseMember {
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    /// the member's metadata for the chosen protocol
+    pub metadata: Bytes,
+}
@@ -0,0 +1,46 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* LeaveGroup Response (Version: 0-5) => throttle_time_ms (v1+) error_code [members] (v3+) _tagged_fields (v4+)
+* members => member_id group_instance_id error_code _tagged_fields (v4+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct LeaveGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub members: Vec<MemberResponse>,
+}
+
+impl EncodeVersioned for LeaveGroupResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::LeaveGroup.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 1 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        res.extend(self.error_code.encode_to_bytes());
+        if version >= 3 {
+            write_array(&mut res, &self.members, flexible, |buf, member| {
+                write_string(buf, &member.member_id, flexible);
+                write_nullable_string(buf, member.group_instance_id.as_deref(), flexible);
+                buf.extend(member.error_code.encode_to_bytes());
+                write_tagged_fields(buf, flexible);
+            });
+        }
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberResponse {
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub error_code: i16,
+}
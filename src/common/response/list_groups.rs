@@ -0,0 +1,51 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* ListGroups Response (Version: 0-5) => throttle_time_ms (v1+) error_code [groups] _tagged_fields (v3+)
+* groups => group_id protocol_type group_state (v4+) group_type (v5+) _tagged_fields (v3+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct ListGroupsResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub groups: Vec<ListedGroup>,
+}
+
+impl EncodeVersioned for ListGroupsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::ListGroups.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 1 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        res.extend(self.error_code.encode_to_bytes());
+        write_array(&mut res, &self.groups, flexible, |buf, group| {
+            write_string(buf, &group.group_id, flexible);
+            write_string(buf, &group.protocol_type, flexible);
+            if version >= 4 {
+                write_string(buf, &group.group_state, flexible);
+            }
+            if version >= 5 {
+                write_string(buf, &group.group_type, flexible);
+            }
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedGroup {
+    pub group_id: String,
+    pub protocol_type: String,
+    pub group_state: String,
+    /// "classic" or "consumer"
+    pub group_type: String,
+}
@@ -0,0 +1,73 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* ListOffsets Response (Version: 1-10) => throttle_time_ms (v2+) [topics] _tagged_fields (v6+)
+* topics => name [partitions] _tagged_fields (v6+)
+*   partitions => partition_index error_code timestamp offset leader_epoch (v4+) _tagged_fields (v6+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct ListOffsetsResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<ListOffsetsTopicResponse>,
+}
+
+impl EncodeVersioned for ListOffsetsResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::ListOffsets.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 2 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        write_array(&mut res, &self.topics, flexible, |buf, topic| {
+            write_string(buf, &topic.name, flexible);
+            write_array(buf, &topic.partitions, flexible, |buf, partition| {
+                buf.extend(partition.partition_index.encode_to_bytes());
+                buf.extend(partition.error_code.encode_to_bytes());
+                buf.extend(partition.timestamp.encode_to_bytes());
+                buf.extend(partition.offset.encode_to_bytes());
+                if version >= 4 {
+                    buf.extend(partition.leader_epoch.encode_to_bytes());
+                }
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ListOffsetsTopicResponse {
+    pub name: String,
+    pub partitions: Vec<ListOffsetsPartitionResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListOffsetsPartitionResponse {
+    pub partition_index: i32,
+    pub error_code: i16,
+    /// -1 for the special lookups and when nothing was found
+    pub timestamp: i64,
+    /// -1 when nothing was found
+    pub offset: i64,
+    pub leader_epoch: i32,
+}
+
+impl ListOffsetsPartitionResponse {
+    pub fn error(partition_index: i32, error_code: i16) -> Self {
+        ListOffsetsPartitionResponse {
+            partition_index,
+            error_code,
+            timestamp: -1,
+            offset: -1,
+            leader_epoch: -1,
+        }
+    }
+}
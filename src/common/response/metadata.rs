@@ -0,0 +1,190 @@
+use crate::common::{
+    acl::AUTHORIZED_OPERATIONS_OMITTED,
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    uuid::KafUuid,
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* Metadata Response (Version: 0-12) => throttle_time_ms (v3+) [brokers] cluster_id (v2+) controller_id (v1+) [topics]
+*                                      cluster_authorized_operations (v8-10) _tagged_fields (v9+)
+* brokers => node_id host port rack (v1+) _tagged_fields (v9+)
+* topics => error_code name topic_id (v10+) is_internal (v1+) [partitions] topic_authorized_operations (v8+) _tagged_fields (v9+)
+*   partitions => error_code partition_index leader_id leader_epoch (v7+) [replica_nodes] [isr_nodes]
+*                 [offline_replicas] (v5+) _tagged_fields (v9+)
+*/
+#[derive(Debug, Clone)]
+pub struct MetadataResponse {
+    pub throttle_time_ms: i32,
+    pub brokers: Vec<MetadataResponseBroker>,
+    pub cluster_id: Option<String>,
+    pub controller_id: i32,
+    pub topics: Vec<MetadataResponseTopic>,
+    pub cluster_authorized_operations: i32,
+}
+
+impl Default for MetadataResponse {
+    fn default() -> Self {
+        MetadataResponse {
+            throttle_time_ms: 0,
+            brokers: vec![],
+            cluster_id: None,
+            controller_id: -1,
+            topics: vec![],
+            cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+        }
+    }
+}
+
+impl EncodeVersioned for MetadataResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Metadata.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 3 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        write_array(&mut res, &self.brokers, flexible, |buf, broker| {
+            buf.extend(broker.node_id.encode_to_bytes());
+            write_string(buf, &broker.host, flexible);
+            buf.extend(broker.port.encode_to_bytes());
+            if version >= 1 {
+                write_nullable_string(buf, broker.rack.as_deref(), flexible);
+            }
+            write_tagged_fields(buf, flexible);
+        });
+        if version >= 2 {
+            write_nullable_string(&mut res, self.cluster_id.as_deref(), flexible);
+        }
+        if version >= 1 {
+            res.extend(self.controller_id.encode_to_bytes());
+        }
+        write_array(&mut res, &self.topics, flexible, |buf, topic| {
+            buf.extend(topic.encode_versioned(version))
+        });
+        if (8..=10).contains(&version) {
+            res.extend(self.cluster_authorized_operations.encode_to_bytes());
+        }
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataResponseBroker {
+    pub node_id: i32,
+    pub host: String,
+    pub port: i32,
+    pub rack: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataResponseTopic {
+    pub error_code: i16,
+    /// null only in v12+, for an unknown topic id
+    pub name: Option<String>,
+    pub topic_id: KafUuid,
+    pub is_internal: bool,
+    pub partitions: Vec<MetadataResponsePartition>,
+    pub topic_authorized_operations: i32,
+}
+
+impl MetadataResponseTopic {
+    pub fn error(error_code: i16, name: Option<String>, topic_id: KafUuid) -> Self {
+        MetadataResponseTopic {
+            error_code,
+            name,
+            topic_id,
+            is_internal: false,
+            partitions: vec![],
+            topic_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+        }
+    }
+}
+
+impl EncodeVersioned for MetadataResponseTopic {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Metadata.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.error_code.encode_to_bytes());
+        if version >= 12 {
+            write_nullable_string(&mut res, self.name.as_deref(), flexible);
+        } else {
+            write_string(&mut res, self.name.as_deref().unwrap_or_default(), flexible);
+        }
+        if version >= 10 {
+            res.extend(self.topic_id.encode_to_bytes());
+        }
+        if version >= 1 {
+            res.extend(self.is_internal.encode_to_bytes());
+        }
+        write_array(&mut res, &self.partitions, flexible, |buf, partition| {
+            let write_ids = |buf: &mut Vec<u8>, ids: &Vec<i32>| {
+                write_array(buf, ids, flexible, |buf, id| buf.extend(id.encode_to_bytes()))
+            };
+            buf.extend(partition.error_code.encode_to_bytes());
+            buf.extend(partition.partition_index.encode_to_bytes());
+            buf.extend(partition.leader_id.encode_to_bytes());
+            if version >= 7 {
+                buf.extend(partition.leader_epoch.encode_to_bytes());
+            }
+            write_ids(buf, &partition.replica_nodes);
+            write_ids(buf, &partition.isr_nodes);
+            if version >= 5 {
+                write_ids(buf, &partition.offline_replicas);
+            }
+            write_tagged_fields(buf, flexible);
+        });
+        if version >= 8 {
+            res.extend(self.topic_authorized_operations.encode_to_bytes());
+        }
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataResponsePartition {
+    pub error_code: i16,
+    pub partition_index: i32,
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    pub replica_nodes: Vec<i32>,
+    pub isr_nodes: Vec<i32>,
+    pub offline_replicas: Vec<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_the_fields_of_each_version() {
+        let response = MetadataResponse {
+            brokers: vec![MetadataResponseBroker { node_id: 1, host: "h".to_string(), port: 9092, rack: None }],
+            cluster_id: Some("c".to_string()),
+            controller_id: 1,
+            topics: vec![MetadataResponseTopic::error(3, None, KafUuid([7; 16]))],
+            ..Default::default()
+        };
+        let v0 = [
+            vec![0, 0, 0, 1, 0, 0, 0, 1, 0, 1, b'h', 0, 0, 0x23, 0x84],
+            vec![0, 0, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0],
+        ]
+        .concat();
+        assert_eq!(response.encode_versioned(0), v0);
+
+        let v12 = [
+            vec![0, 0, 0, 0, 2, 0, 0, 0, 1, 2, b'h', 0, 0, 0x23, 0x84, 0, 0],
+            vec![2, b'c', 0, 0, 0, 1, 2, 0, 3, 0],
+            vec![7; 16],
+            vec![0, 1, 0x80, 0, 0, 0, 0, 0],
+        ]
+        .concat();
+        assert_eq!(response.encode_versioned(12), v12);
+    }
+}
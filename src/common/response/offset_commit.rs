@@ -0,0 +1,51 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* OffsetCommit Response (Version: 0-9) => throttle_time_ms (v3+) [topics] _tagged_fields (v8+)
+* topics => name [partitions] _tagged_fields (v8+)
+*   partitions => partition_index error_code _tagged_fields (v8+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct OffsetCommitResponse {
+    pub throttle_time_ms: i32,
+    pub topics: Vec<OffsetCommitResponseTopic>,
+}
+
+impl EncodeVersioned for OffsetCommitResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::OffsetCommit.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 3 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        write_array(&mut res, &self.topics, flexible, |buf, topic| {
+            write_string(buf, &topic.name, flexible);
+            write_array(buf, &topic.partitions, flexible, |buf, partition| {
+                buf.extend(partition.partition_index.encode_to_bytes());
+                buf.extend(partition.error_code.encode_to_bytes());
+                write_tagged_fields(buf, flexible);
+            });
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetCommitResponseTopic {
+    pub name: String,
+    pub partitions: Vec<OffsetCommitResponsePartition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetCommitResponsePartition {
+    pub partition_index: i32,
+    pub error_code: i16,
+}
@@ -0,0 +1,43 @@
+use crate::common::{codec::{write_array, write_string}, EncodeToBytes, EncodeVersioned};
+
+/*
+* OffsetDelete Response (Version: 0) => error_code throttle_time_ms [topics]
+* topics => name [partitions]
+*   partitions => partition_index error_code
+*/
+#[derive(Debug, Default, Clone)]
+pub struct OffsetDeleteResponse {
+    pub error_code: i16,
+    pub throttle_time_ms: i32,
+    pub topics: Vec<OffsetDeleteResponseTopic>,
+}
+
+impl EncodeVersioned for OffsetDeleteResponse {
+    fn encode_versioned(&self, _version: i16) -> Vec<u8> {
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.error_code.encode_to_bytes());
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_array(&mut res, &self.topics, false, |buf, topic| {
+            write_string(buf, &topic.name, false);
+            write_array(buf, &topic.partitions, false, |buf, partition| {
+                buf.extend(partition.partition_index.encode_to_bytes());
+                buf.extend(partition.error_code.encode_to_bytes());
+            });
+        });
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetDeleteResponseTopic {
+    pub name: String,
+    pub partitions: Vec<OffsetDeleteResponsePartition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetDeleteResponsePartition {
+    pub partition_index: i32,
+    pub error_code: i16,
+}
@@ -0,0 +1,97 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    error::error_code,
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* OffsetFetch Response (Version: 0-7) => throttle_time_ms (v3+) [topics] error_code (v2+) _tagged_fields (v6+)
+* topics => name [partitions] _tagged_fields (v6+)
+*   partitions => partition_index committed_offset committed_leader_epoch (v5+) metadata error_code _tagged_fields (v6+)
+*
+* OffsetFetch Response (Version: 8-9) => throttle_time_ms [groups] _tagged_fields
+* groups => group_id [topics] error_code _tagged_fields
+*   topics => name [partitions] _tagged_fields
+*     partitions => partition_index committed_offset committed_leader_epoch metadata error_code _tagged_fields
+*/
+#[derive(Debug, Default, Clone)]
+pub struct OffsetFetchResponse {
+    pub throttle_time_ms: i32,
+    /// one per group, before v8 the only one is written at the top level
+    pub groups: Vec<OffsetFetchResponseGroup>,
+}
+
+fn write_topics(buf: &mut Vec<u8>, group: &OffsetFetchResponseGroup, version: i16, flexible: bool) {
+    write_array(buf, &group.topics, flexible, |buf, topic| {
+        write_string(buf, &topic.name, flexible);
+        write_array(buf, &topic.partitions, flexible, |buf, partition| {
+            buf.extend(partition.partition_index.encode_to_bytes());
+            buf.extend(partition.committed_offset.encode_to_bytes());
+            if version >= 5 {
+                buf.extend(partition.committed_leader_epoch.encode_to_bytes());
+            }
+            write_nullable_string(buf, partition.metadata.as_deref(), flexible);
+            // before v2 there's no group error, each partition carries it
+            let error_code = match group.error_code {
+                error_code::NONE => partition.error_code,
+                group_error if version < 2 => group_error,
+                _ => partition.error_code,
+            };
+            buf.extend(error_code.encode_to_bytes());
+            write_tagged_fields(buf, flexible);
+        });
+        write_tagged_fields(buf, flexible);
+    });
+}
+
+impl EncodeVersioned for OffsetFetchResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::OffsetFetch.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 3 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        if version >= 8 {
+            write_array(&mut res, &self.groups, flexible, |buf, group| {
+                write_string(buf, &group.group_id, flexible);
+                write_topics(buf, group, version, flexible);
+                buf.extend(group.error_code.encode_to_bytes());
+                write_tagged_fields(buf, flexible);
+            });
+        } else {
+            let group = self.groups.first().cloned().unwrap_or_default();
+            write_topics(&mut res, &group, version, flexible);
+            if version >= 2 {
+                res.extend(group.error_code.encode_to_bytes());
+            }
+        }
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OffsetFetchResponseGroup {
+    pub group_id: String,
+    pub topics: Vec<OffsetFetchResponseTopic>,
+    pub error_code: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetFetchResponseTopic {
+    pub name: String,
+    pub partitions: Vec<OffsetFetchResponsePartition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetFetchResponsePartition {
+    pub partition_index: i32,
+    /// -1 if the group committed nothing for the partition
+    pub committed_offset: i64,
+    pub committed_leader_epoch: i32,
+    pub metadata: Option<String>,
+    pub error_code: i16,
+}
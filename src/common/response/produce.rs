@@ -0,0 +1,156 @@
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_array, write_nullable_string, write_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* Produce Response (Version: 3-11) => [responses] throttle_time_ms _tagged_fields (v9+)
+* responses => name [partition_responses] _tagged_fields (v9+)
+*   name => STRING
+*   partition_responses => index error_code base_offset log_append_time_ms log_start_offset (v5+)
+*                          [record_errors] (v8+) error_message (v8+) _tagged_fields (v9+)
+*     record_errors => batch_index batch_index_error_message _tagged_fields (v9+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct ProduceResponse {
+    pub responses: Vec<TopicProduceResponse>,
+    pub throttle_time_ms: i32,
+}
+
+impl EncodeVersioned for ProduceResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Produce.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        write_array(&mut res, &self.responses, flexible, |buf, topic| {
+            buf.extend(topic.encode_versioned(version))
+        });
+        res.extend(self.throttle_time_ms.encode_to_bytes());
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TopicProduceResponse {
+    pub name: String,
+    pub partition_responses: Vec<PartitionProduceResponse>,
+}
+
+impl EncodeVersioned for TopicProduceResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Produce.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        write_string(&mut res, &self.name, flexible);
+        write_array(&mut res, &self.partition_responses, flexible, |buf, partition| {
+            buf.extend(partition.encode_versioned(version))
+        });
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionProduceResponse {
+    pub index: i32,
+    pub error_code: i16,
+    pub base_offset: i64,
+    /// -1 unless the topic uses LogAppendTime
+    pub log_append_time_ms: i64,
+    pub log_start_offset: i64,
+    pub record_errors: Vec<BatchIndexAndErrorMessage>,
+    pub error_message: Option<String>,
+}
+
+impl PartitionProduceResponse {
+    pub fn error(index: i32, error_code: i16, error_message: Option<String>) -> Self {
+        PartitionProduceResponse {
+            index,
+            error_code,
+            error_message,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for PartitionProduceResponse {
+    fn default() -> Self {
+        PartitionProduceResponse {
+            index: 0,
+            error_code: 0,
+            base_offset: -1,
+            log_append_time_ms: -1,
+            log_start_offset: -1,
+            record_errors: vec![],
+            error_message: None,
+        }
+    }
+}
+
+impl EncodeVersioned for PartitionProduceResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::Produce.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        res.extend(self.index.encode_to_bytes());
+        res.extend(self.error_code.encode_to_bytes());
+        res.extend(self.base_offset.encode_to_bytes());
+        if version >= 2 {
+            res.extend(self.log_append_time_ms.encode_to_bytes());
+        }
+        if version >= 5 {
+            res.extend(self.log_start_offset.encode_to_bytes());
+        }
+        if version >= 8 {
+            write_array(&mut res, &self.record_errors, flexible, |buf, e| {
+                buf.extend(e.batch_index.encode_to_bytes());
+                write_nullable_string(buf, e.batch_index_error_message.as_deref(), flexible);
+                write_tagged_fields(buf, flexible);
+            });
+            write_nullable_string(&mut res, self.error_message.as_deref(), flexible);
+        }
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
+
+/// The record in the batch that made the append fail
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BatchIndexAndErrorMessage {
+    pub batch_index: i32,
+    pub batch_index_error_message: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_come_and_go_with_the_version() {
+        let response = ProduceResponse {
+            responses: vec![TopicProduceResponse {
+                name: "t".to_string(),
+                partition_responses: vec![PartitionProduceResponse {
+                    base_offset: 42,
+                    ..Default::default()
+                }],
+            }],
+            throttle_time_ms: 0,
+        };
+        // array len + name + array len + index, error, base offset, append time + throttle
+        let v3 = 4 + 3 + 4 + 4 + 2 + 8 + 8 + 4;
+        assert_eq!(response.encode_versioned(3).len(), v3);
+        // + log start offset
+        assert_eq!(response.encode_versioned(5).len(), v3 + 8);
+        // + record errors and error message
+        assert_eq!(response.encode_versioned(8).len(), v3 + 8 + 4 + 2);
+        // compact lengths (3 bytes less for the arrays, 1 for the name, 3 for the
+        // record errors, 1 for the message) plus 4 tag buffers
+        assert_eq!(response.encode_versioned(9).len(), v3 + 8 + 4 + 2 - 3 - 3 - 1 - 3 - 1 + 3);
+    }
+}
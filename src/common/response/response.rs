@@ -1,4 +1,4 @@
-use crate::common::{request::{self, KafRequestHeader}, response::response_body::KafResponseBody, EncodeToBytes};
+use crate::common::{api::api_key::KafApiKey, request::{self, KafRequestHeader}, response::response_body::KafResponseBody, EncodeToBytes, EncodeVersioned};
 
 #[derive(Debug)]
 pub enum KafResponseHeader {
@@ -29,15 +29,14 @@ impl KafResponseHeader {
         })
     }
 
+    /// v1 for flexible versions, v0 otherwise. ApiVersions always answers with
+    /// v0 so clients that sent a version we don't know can still parse it.
     pub fn from_request_header(request_header: KafRequestHeader) -> KafResponseHeader {
-        match request_header.request_api_version {
-            0 => KafResponseHeader::V0(V0Header {
-                correlation_id: request_header.correlation_id
-            }),
-            _ => KafResponseHeader::V1(V1Header {
-                correlation_id: request_header.correlation_id,
-                ..Default::default()
-            }),
+        let api_key = &request_header.request_api_key;
+        if *api_key != KafApiKey::ApiVersions && api_key.is_flexible(request_header.request_api_version) {
+            Self::v1(request_header)
+        } else {
+            Self::v0(request_header)
         }
     }
 }
@@ -68,6 +67,8 @@ impl EncodeToBytes for KafResponseHeader {
 pub struct KafResponse {
     pub header: KafResponseHeader,
     pub body: KafResponseBody,
+    // the request's version, versioned bodies are encoded with it
+    pub api_version: i16,
 }
 
 impl KafResponse {
@@ -75,6 +76,16 @@ impl KafResponse {
         KafResponse {
             header,
             body,
+            api_version: 0,
+        }
+    }
+
+    /// Response to `request_header`, in its api version with the matching header
+    pub fn for_request(request_header: KafRequestHeader, body: KafResponseBody) -> KafResponse {
+        KafResponse {
+            api_version: request_header.request_api_version,
+            header: KafResponseHeader::from_request_header(request_header),
+            body,
         }
     }
 }
@@ -85,7 +96,7 @@ impl EncodeToBytes for KafResponse {
         let mut res: Vec<u8> = vec![]; 
 
         let header_bytes = self.header.encode_to_bytes();
-        let body_bytes = self.body.encode_to_bytes();
+        let body_bytes = self.body.encode_versioned(self.api_version);
 
         // + 4 bytes for total_length
         let total_length: i32 = (header_bytes.len() + body_bytes.len()).try_into().unwrap_or(-1i32);
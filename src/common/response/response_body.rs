@@ -1,11 +1,37 @@
-use crate::common::{api::{api_key, api_version_entry::ApiVersionEntry}, error::error_code, response::describe_topic_partitions::DescribeTopicPartitionsResponse, types::CompactArray, EncodeToBytes};
+use enum_as_inner::EnumAsInner;
+
+use crate::common::{api::{api_key, api_version_entry::ApiVersionEntry}, error::error_code, response::{alter_configs::AlterConfigsResponse, consumer_group_describe::ConsumerGroupDescribeResponse, consumer_group_heartbeat::ConsumerGroupHeartbeatResponse, create_partitions::CreatePartitionsResponse, create_topics::CreateTopicsResponse, delete_groups::DeleteGroupsResponse, delete_records::DeleteRecordsResponse, delete_topics::DeleteTopicsResponse, describe_configs::DescribeConfigsResponse, describe_groups::DescribeGroupsResponse, describe_topic_partitions::DescribeTopicPartitionsResponse, fetch::FetchResponse, find_coordinator::FindCoordinatorResponse, heartbeat::HeartbeatResponse, incremental_alter_configs::IncrementalAlterConfigsResponse, join_group::JoinGroupResponse, leave_group::LeaveGroupResponse, list_groups::ListGroupsResponse, list_offsets::ListOffsetsResponse, metadata::MetadataResponse, offset_commit::OffsetCommitResponse, offset_delete::OffsetDeleteResponse, offset_fetch::OffsetFetchResponse, produce::ProduceResponse, sync_group::SyncGroupResponse}, types::CompactArray, EncodeToBytes, EncodeVersioned};
 
 // TODO: probably best as a builder but for later
-#[derive(Debug)]
+#[derive(Debug, EnumAsInner)]
 pub enum KafResponseBody {
     Unsupported(UnsupportedResponse),
     ApiVersions(ApiVersionsResponse),
-    DescribeTopicPartitions(DescribeTopicPartitionsResponse)
+    DescribeTopicPartitions(DescribeTopicPartitionsResponse),
+    Produce(ProduceResponse),
+    Fetch(FetchResponse),
+    ListOffsets(ListOffsetsResponse),
+    Metadata(MetadataResponse),
+    CreateTopics(CreateTopicsResponse),
+    DeleteTopics(DeleteTopicsResponse),
+    CreatePartitions(CreatePartitionsResponse),
+    DeleteRecords(DeleteRecordsResponse),
+    DescribeConfigs(DescribeConfigsResponse),
+    AlterConfigs(AlterConfigsResponse),
+    IncrementalAlterConfigs(IncrementalAlterConfigsResponse),
+    OffsetCommit(OffsetCommitResponse),
+    OffsetFetch(OffsetFetchResponse),
+    FindCoordinator(FindCoordinatorResponse),
+    JoinGroup(JoinGroupResponse),
+    SyncGroup(SyncGroupResponse),
+    Heartbeat(HeartbeatResponse),
+    LeaveGroup(LeaveGroupResponse),
+    DescribeGroups(DescribeGroupsResponse),
+    ListGroups(ListGroupsResponse),
+    DeleteGroups(DeleteGroupsResponse),
+    OffsetDelete(OffsetDeleteResponse),
+    ConsumerGroupHeartbeat(ConsumerGroupHeartbeatResponse),
+    ConsumerGroupDescribe(ConsumerGroupDescribeResponse),
 }
 
 impl Default for KafResponseBody {
@@ -16,12 +42,36 @@ impl Default for KafResponseBody {
 
 // TODO: I should make a macro for this impl once it grows
 //
-impl EncodeToBytes for KafResponseBody {
-    fn encode_to_bytes(&self) -> Vec<u8> {
+impl EncodeVersioned for KafResponseBody {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
         use self::KafResponseBody::*;
         match self {
             ApiVersions(res) => res.encode_to_bytes(),
             DescribeTopicPartitions(res) => res.encode_to_bytes(),
+            Produce(res) => res.encode_versioned(version),
+            Fetch(res) => res.encode_versioned(version),
+            ListOffsets(res) => res.encode_versioned(version),
+            Metadata(res) => res.encode_versioned(version),
+            CreateTopics(res) => res.encode_versioned(version),
+            DeleteTopics(res) => res.encode_versioned(version),
+            CreatePartitions(res) => res.encode_versioned(version),
+            DeleteRecords(res) => res.encode_versioned(version),
+            DescribeConfigs(res) => res.encode_versioned(version),
+            AlterConfigs(res) => res.encode_versioned(version),
+            IncrementalAlterConfigs(res) => res.encode_versioned(version),
+            OffsetCommit(res) => res.encode_versioned(version),
+            OffsetFetch(res) => res.encode_versioned(version),
+            FindCoordinator(res) => res.encode_versioned(version),
+            JoinGroup(res) => res.encode_versioned(version),
+            SyncGroup(res) => res.encode_versioned(version),
+            Heartbeat(res) => res.encode_versioned(version),
+            LeaveGroup(res) => res.encode_versioned(version),
+            DescribeGroups(res) => res.encode_versioned(version),
+            ListGroups(res) => res.encode_versioned(version),
+            DeleteGroups(res) => res.encode_versioned(version),
+            OffsetDelete(res) => res.encode_versioned(version),
+            ConsumerGroupHeartbeat(res) => res.encode_versioned(version),
+            ConsumerGroupDescribe(res) => res.encode_versioned(version),
             _ => UnsupportedResponse::with_error_code(-1).encode_to_bytes(),
         }
     }
@@ -0,0 +1,40 @@
+use bytes::Bytes;
+
+use crate::common::{
+    api::api_key::KafApiKey,
+    codec::{write_bytes, write_nullable_string, write_tagged_fields},
+    EncodeToBytes, EncodeVersioned,
+};
+
+/*
+* SyncGroup Response (Version: 0-5) => throttle_time_ms (v1+) error_code protocol_type (v5+) protocol_name (v5+)
+*                                      assignment _tagged_fields (v4+)
+*/
+#[derive(Debug, Default, Clone)]
+pub struct SyncGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub assignment: Bytes,
+}
+
+impl EncodeVersioned for SyncGroupResponse {
+    fn encode_versioned(&self, version: i16) -> Vec<u8> {
+        let flexible = KafApiKey::SyncGroup.is_flexible(version);
+        let mut res: Vec<u8> = vec![];
+
+        if version >= 1 {
+            res.extend(self.throttle_time_ms.encode_to_bytes());
+        }
+        res.extend(self.error_code.encode_to_bytes());
+        if version >= 5 {
+            write_nullable_string(&mut res, self.protocol_type.as_deref(), flexible);
+            write_nullable_string(&mut res, self.protocol_name.as_deref(), flexible);
+        }
+        write_bytes(&mut res, &self.assignment, flexible);
+        write_tagged_fields(&mut res, flexible);
+
+        res
+    }
+}
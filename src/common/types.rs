@@ -13,6 +13,12 @@ use crate::{common::{DecodeFromBytes, EncodeToBytes, EncodingError}, utils::pars
 //  and enums might be helpful
 //  )
 
+impl EncodeToBytes for i8 {
+    fn encode_to_bytes(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+}
+
 impl EncodeToBytes for i16 {
     fn encode_to_bytes(&self) -> Vec<u8> {
         self.to_be_bytes().to_vec()
@@ -25,6 +31,12 @@ impl EncodeToBytes for i32 {
     }
 }
 
+impl EncodeToBytes for i64 {
+    fn encode_to_bytes(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+}
+
 impl EncodeToBytes for bool {
     fn encode_to_bytes(&self) -> Vec<u8> {
         match self {
@@ -0,0 +1,75 @@
+use std::{fmt, str::FromStr};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::{
+    common::{DecodeFromBytes, EncodeToBytes, EncodingError},
+    utils::parse_primitive_types::read_exact,
+};
+
+/// Kafka's UUID type, used for topic ids. Written as 16 raw bytes on the
+/// wire and as 22 characters of url-safe base64 everywhere else.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KafUuid(pub [u8; 16]);
+
+impl KafUuid {
+    /// "no topic id", what requests carry when they name topics instead
+    pub const ZERO: KafUuid = KafUuid([0; 16]);
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    /// A fresh random id. Like Kafka's, never zero and never one whose base64
+    /// starts with `-`, which would look like an option on command lines.
+    pub fn random() -> Self {
+        loop {
+            let id = KafUuid(rand::random());
+            if !id.is_zero() && !id.to_string().starts_with('-') {
+                return id;
+            }
+        }
+    }
+}
+
+impl fmt::Display for KafUuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&URL_SAFE_NO_PAD.encode(self.0))
+    }
+}
+
+impl FromStr for KafUuid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = URL_SAFE_NO_PAD.decode(s).map_err(|e| format!("invalid uuid {:?}: {}", s, e))?;
+        let bytes: [u8; 16] = bytes.try_into().map_err(|_| format!("invalid uuid {:?}: not 16 bytes", s))?;
+        Ok(KafUuid(bytes))
+    }
+}
+
+impl DecodeFromBytes for KafUuid {
+    fn read_from_u8(input: &[u8], offset: &mut usize) -> Result<Self, EncodingError> {
+        Ok(KafUuid(read_exact(input, offset, 16)?.try_into().unwrap()))
+    }
+}
+
+impl EncodeToBytes for KafUuid {
+    fn encode_to_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trip() {
+        let id = KafUuid([0xfb; 16]);
+        assert_eq!(id.to_string(), "-_v7-_v7-_v7-_v7-_v7-w");
+        assert_eq!(id.to_string().parse::<KafUuid>().unwrap(), id);
+        assert_eq!(KafUuid::ZERO.to_string(), "AAAAAAAAAAAAAAAAAAAAAA");
+        assert!("AAAA".parse::<KafUuid>().is_err());
+    }
+}
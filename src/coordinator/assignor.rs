@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::common::uuid::KafUuid;
+
+/// Partitions by topic id
+pub type Assignment = BTreeMap<KafUuid, BTreeSet<i32>>;
+
+/// A topic as the assignors see it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicMetadata {
+    pub topic_id: KafUuid,
+    pub name: String,
+    pub num_partitions: i32,
+}
+
+/// What an assignor knows of a member
+#[derive(Debug, Clone)]
+pub struct MemberSubscription<'a> {
+    /// names of the topics it's subscribed to, regexes resolved
+    pub topics: BTreeSet<String>,
+    /// its current target, which the uniform assignor sticks to
+    pub current: Option<&'a Assignment>,
+}
+
+/// The assignors consumer groups compute their target assignment with, on
+/// the broker. Members pick one with `server_assignor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsumerGroupAssignor {
+    /// as even as possible, moving as few partitions as it can
+    Uniform,
+    /// each topic's partitions in ranges, so members get the same
+    /// partitions of co-partitioned topics
+    Range,
+}
+
+impl ConsumerGroupAssignor {
+    pub const NAMES: &'static [&'static str] = &["uniform", "range"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConsumerGroupAssignor::Uniform => "uniform",
+            ConsumerGroupAssignor::Range => "range",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "uniform" => Some(ConsumerGroupAssignor::Uniform),
+            "range" => Some(ConsumerGroupAssignor::Range),
+            _ => None,
+        }
+    }
+
+    /// The target assignment of every member, of the partitions of `topics`
+    /// they're subscribed to
+    pub fn assign(
+        &self,
+        members: &BTreeMap<String, MemberSubscription>,
+        topics: &BTreeMap<String, TopicMetadata>,
+    ) -> BTreeMap<String, Assignment> {
+        let assigned = match self {
+            ConsumerGroupAssignor::Uniform => assign_uniform(members, topics),
+            ConsumerGroupAssignor::Range => assign_range(members, topics),
+        };
+        assigned
+            .into_iter()
+            .map(|(member_id, partitions)| {
+                let mut assignment = Assignment::new();
+                for (topic, partition) in partitions {
+                    assignment.entry(topics[topic].topic_id).or_default().insert(partition);
+                }
+                (member_id.to_string(), assignment)
+            })
+            .collect()
+    }
+}
+
+type Assigned<'a> = BTreeMap<&'a str, BTreeSet<(&'a str, i32)>>;
+
+fn assign_range<'a>(members: &'a BTreeMap<String, MemberSubscription>, topics: &'a BTreeMap<String, TopicMetadata>) -> Assigned<'a> {
+    let mut assigned: Assigned = members.keys().map(|member_id| (member_id.as_str(), BTreeSet::new())).collect();
+    for (name, topic) in topics {
+        let subscribers: Vec<&str> =
+            members.iter().filter(|(_, member)| member.topics.contains(name)).map(|(member_id, _)| member_id.as_str()).collect();
+        if subscribers.is_empty() {
+            continue;
+        }
+        let per_member = topic.num_partitions / subscribers.len() as i32;
+        let extra = topic.num_partitions % subscribers.len() as i32;
+        let mut start = 0;
+        for (i, member_id) in subscribers.into_iter().enumerate() {
+            let count = per_member + if (i as i32) < extra { 1 } else { 0 };
+            assigned.get_mut(member_id).unwrap().extend((start..start + count).map(|partition| (name.as_str(), partition)));
+            start += count;
+        }
+    }
+    assigned
+}
+
+fn assign_uniform<'a>(members: &'a BTreeMap<String, MemberSubscription>, topics: &'a BTreeMap<String, TopicMetadata>) -> Assigned<'a> {
+    let mut assigned: Assigned = members.keys().map(|member_id| (member_id.as_str(), BTreeSet::new())).collect();
+    let topics_by_id: HashMap<KafUuid, &TopicMetadata> = topics.values().map(|topic| (topic.topic_id, topic)).collect();
+    let subscribed = |member_id: &str, topic: &str| members[member_id].topics.contains(topic);
+
+    // members keep what they had, as long as it still exists and they're still subscribed
+    let mut taken = HashSet::new();
+    for (member_id, member) in members {
+        for (topic_id, partitions) in member.current.into_iter().flatten() {
+            let Some(topic) = topics_by_id.get(topic_id) else {
+                continue;
+            };
+            if !subscribed(member_id, &topic.name) {
+                continue;
+            }
+            for &partition in partitions.iter().filter(|partition| **partition < topic.num_partitions) {
+                if taken.insert((topic.name.as_str(), partition)) {
+                    assigned.get_mut(member_id.as_str()).unwrap().insert((topic.name.as_str(), partition));
+                }
+            }
+        }
+    }
+
+    // the rest go to whoever subscribed has the fewest
+    for (name, topic) in topics {
+        for partition in (0..topic.num_partitions).filter(|partition| !taken.contains(&(name.as_str(), *partition))) {
+            let least_loaded = assigned
+                .iter()
+                .filter(|(member_id, _)| subscribed(member_id, name))
+                .min_by_key(|(member_id, partitions)| (partitions.len(), **member_id))
+                .map(|(member_id, _)| *member_id);
+            if let Some(member_id) = least_loaded {
+                assigned.get_mut(member_id).unwrap().insert((name.as_str(), partition));
+            }
+        }
+    }
+
+    // then even out what was kept, one partition at a time from the most
+    // loaded members. Each move makes the assignment strictly more even.
+    loop {
+        let mut by_load: Vec<(&str, usize)> = assigned.iter().map(|(member_id, partitions)| (*member_id, partitions.len())).collect();
+        by_load.sort_by_key(|(member_id, load)| (std::cmp::Reverse(*load), *member_id));
+        let next_move = by_load.iter().find_map(|(donor, load)| {
+            assigned[donor].iter().rev().find_map(|(topic, partition)| {
+                by_load
+                    .iter()
+                    .rev()
+                    .find(|(receiver, receiver_load)| receiver_load + 1 < *load && subscribed(receiver, topic))
+                    .map(|(receiver, _)| (*donor, *receiver, (*topic, *partition)))
+            })
+        });
+        let Some((donor, receiver, topic_partition)) = next_move else {
+            break;
+        };
+        assigned.get_mut(donor).unwrap().remove(&topic_partition);
+        assigned.get_mut(receiver).unwrap().insert(topic_partition);
+    }
+    assigned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topics(counts: &[(&str, i32)]) -> BTreeMap<String, TopicMetadata> {
+        counts
+            .iter()
+            .enumerate()
+            .map(|(i, (name, num_partitions))| {
+                let topic = TopicMetadata {
+                    topic_id: KafUuid([i as u8 + 1; 16]),
+                    name: name.to_string(),
+                    num_partitions: *num_partitions,
+                };
+                (name.to_string(), topic)
+            })
+            .collect()
+    }
+
+    fn subscribed<'a>(members: &[(&str, &[&str], Option<&'a Assignment>)]) -> BTreeMap<String, MemberSubscription<'a>> {
+        members
+            .iter()
+            .map(|(member_id, topics, current)| {
+                let subscription = MemberSubscription {
+                    topics: topics.iter().map(|topic| topic.to_string()).collect(),
+                    current: *current,
+                };
+                (member_id.to_string(), subscription)
+            })
+            .collect()
+    }
+
+    fn partitions(assignment: &Assignment, topic_id: KafUuid) -> Vec<i32> {
+        assignment.get(&topic_id).map(|partitions| partitions.iter().copied().collect()).unwrap_or_default()
+    }
+
+    #[test]
+    fn range_gives_each_member_the_same_ranges_of_every_topic() {
+        let topics = topics(&[("orders", 5), ("payments", 5)]);
+        let members = subscribed(&[("a", &["orders", "payments"], None), ("b", &["orders", "payments"], None)]);
+        let assigned = ConsumerGroupAssignor::Range.assign(&members, &topics);
+        let (orders, payments) = (topics["orders"].topic_id, topics["payments"].topic_id);
+        assert_eq!(partitions(&assigned["a"], orders), vec![0, 1, 2]);
+        assert_eq!(partitions(&assigned["a"], payments), vec![0, 1, 2]);
+        assert_eq!(partitions(&assigned["b"], orders), vec![3, 4]);
+    }
+
+    #[test]
+    fn uniform_evens_out_and_moves_as_few_partitions_as_it_can() {
+        let topics = topics(&[("orders", 6)]);
+        let orders = topics["orders"].topic_id;
+        let members = subscribed(&[("a", &["orders"], None)]);
+        let first = ConsumerGroupAssignor::Uniform.assign(&members, &topics);
+        assert_eq!(partitions(&first["a"], orders), vec![0, 1, 2, 3, 4, 5]);
+
+        // a second and third member each take two of a's partitions
+        let members = subscribed(&[("a", &["orders"], Some(&first["a"])), ("b", &["orders"], None), ("c", &["orders"], None)]);
+        let second = ConsumerGroupAssignor::Uniform.assign(&members, &topics);
+        assert_eq!(partitions(&second["a"], orders), vec![0, 1]);
+        assert_eq!(second.values().map(|assignment| partitions(assignment, orders).len()).collect::<Vec<_>>(), vec![2, 2, 2]);
+
+        // when b leaves, a and c keep theirs and split b's
+        let members = subscribed(&[("a", &["orders"], Some(&second["a"])), ("c", &["orders"], Some(&second["c"]))]);
+        let third = ConsumerGroupAssignor::Uniform.assign(&members, &topics);
+        assert!(partitions(&third["a"], orders).starts_with(&[0, 1]));
+        assert!(partitions(&second["c"], orders).iter().all(|p| partitions(&third["c"], orders).contains(p)));
+        assert_eq!(partitions(&third["a"], orders).len() + partitions(&third["c"], orders).len(), 6);
+        assert_eq!(partitions(&third["a"], orders).len(), 3);
+    }
+
+    #[test]
+    fn uniform_only_assigns_members_the_topics_they_subscribed_to() {
+        let topics = topics(&[("orders", 4), ("payments", 2)]);
+        let members = subscribed(&[("a", &["orders", "payments"], None), ("b", &["orders"], None)]);
+        let assigned = ConsumerGroupAssignor::Uniform.assign(&members, &topics);
+        assert!(partitions(&assigned["b"], topics["payments"].topic_id).is_empty());
+        let total = |member: &str| assigned[member].values().map(BTreeSet::len).sum::<usize>();
+        assert_eq!((total("a"), total("b")), (3, 3));
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use regex::Regex;
+
+use crate::{
+    common::uuid::KafUuid,
+    coordinator::assignor::{Assignment, ConsumerGroupAssignor, MemberSubscription, TopicMetadata},
+    metadata::is_internal_topic,
+};
+
+/// The group type ListGroups gives groups of the consumer protocol (KIP-848)
+pub const CONSUMER_GROUP_TYPE: &str = "consumer";
+
+/// Where a consumer group is in converging on its target assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsumerGroupState {
+    Empty,
+    /// the target assignment is behind the group epoch
+    Assigning,
+    /// some members haven't reached the target assignment yet
+    Reconciling,
+    Stable,
+    /// removed, any request for it is retried elsewhere
+    Dead,
+}
+
+impl ConsumerGroupState {
+    /// As ConsumerGroupDescribe and ListGroups name it
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConsumerGroupState::Empty => "Empty",
+            ConsumerGroupState::Assigning => "Assigning",
+            ConsumerGroupState::Reconciling => "Reconciling",
+            ConsumerGroupState::Stable => "Stable",
+            ConsumerGroupState::Dead => "Dead",
+        }
+    }
+}
+
+/// Where a member is in reaching its target assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberState {
+    /// owns its target assignment, at the group's assignment epoch
+    Stable,
+    /// has to give up partitions before it gets the next epoch
+    UnrevokedPartitions,
+    /// at the assignment epoch, waiting for others to give up some of its target
+    UnreleasedPartitions,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsumerGroupMember {
+    pub member_id: String,
+    /// group.instance.id of static members
+    pub instance_id: Option<String>,
+    pub rack_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub member_epoch: i32,
+    /// the epoch before the last bump, still accepted from a member that
+    /// didn't get the heartbeat response with the new one
+    pub previous_member_epoch: i32,
+    pub state: MemberState,
+    pub rebalance_timeout_ms: i32,
+    pub server_assignor: Option<String>,
+    pub subscribed_topic_names: Vec<String>,
+    pub subscribed_topic_regex: Option<String>,
+    /// the partitions the member owns
+    pub assigned_partitions: Assignment,
+    /// partitions it still owns but was told to give up
+    pub partitions_pending_revocation: Assignment,
+    pub last_heartbeat_ms: i64,
+    /// when the member is removed if it still hasn't given up
+    /// partitions_pending_revocation
+    pub revocation_deadline_ms: Option<i64>,
+}
+
+impl ConsumerGroupMember {
+    pub fn new(member_id: &str, now_ms: i64) -> Self {
+        ConsumerGroupMember {
+            member_id: member_id.to_string(),
+            instance_id: None,
+            rack_id: None,
+            client_id: String::new(),
+            client_host: String::new(),
+            member_epoch: 0,
+            previous_member_epoch: 0,
+            state: MemberState::Stable,
+            rebalance_timeout_ms: 0,
+            server_assignor: None,
+            subscribed_topic_names: vec![],
+            subscribed_topic_regex: None,
+            assigned_partitions: Assignment::new(),
+            partitions_pending_revocation: Assignment::new(),
+            last_heartbeat_ms: now_ms,
+            revocation_deadline_ms: None,
+        }
+    }
+}
+
+/// A group using the consumer rebalance protocol of KIP-848. The group
+/// epoch is bumped whenever its members or what they subscribe to change,
+/// the coordinator then computes a new target assignment, which each member
+/// reconciles with on its own heartbeats.
+#[derive(Debug, Clone)]
+pub struct ConsumerGroup {
+    pub group_id: String,
+    pub state: ConsumerGroupState,
+    /// when the group got into its state
+    pub state_timestamp_ms: Option<i64>,
+    pub group_epoch: i32,
+    /// the group epoch target_assignment was computed at
+    pub assignment_epoch: i32,
+    pub members: BTreeMap<String, ConsumerGroupMember>,
+    pub target_assignment: BTreeMap<String, Assignment>,
+    /// group.instance.id of static members to their current member id
+    pub static_members: HashMap<String, String>,
+    /// the subscribed topics as they were when the group epoch was last bumped
+    pub subscription_metadata: BTreeMap<String, TopicMetadata>,
+    /// the assignor target_assignment was computed with
+    pub assignor_name: String,
+}
+
+impl ConsumerGroup {
+    pub fn new(group_id: &str, now_ms: i64) -> Self {
+        ConsumerGroup {
+            group_id: group_id.to_string(),
+            state: ConsumerGroupState::Empty,
+            state_timestamp_ms: Some(now_ms),
+            group_epoch: 0,
+            assignment_epoch: 0,
+            members: BTreeMap::new(),
+            target_assignment: BTreeMap::new(),
+            static_members: HashMap::new(),
+            subscription_metadata: BTreeMap::new(),
+            assignor_name: String::new(),
+        }
+    }
+
+    pub fn is(&self, state: ConsumerGroupState) -> bool {
+        self.state == state
+    }
+
+    pub fn add_member(&mut self, member: ConsumerGroupMember) {
+        if let Some(instance_id) = &member.instance_id {
+            self.static_members.insert(instance_id.clone(), member.member_id.clone());
+        }
+        self.members.insert(member.member_id.clone(), member);
+    }
+
+    /// Remove the member, its partitions are free for the others once the
+    /// target assignment is recomputed at the bumped epoch
+    pub fn remove_member(&mut self, member_id: &str) -> Option<ConsumerGroupMember> {
+        let member = self.members.remove(member_id)?;
+        if let Some(instance_id) = &member.instance_id {
+            if self.static_members.get(instance_id).map(String::as_str) == Some(member_id) {
+                self.static_members.remove(instance_id);
+            }
+        }
+        self.target_assignment.remove(member_id);
+        self.group_epoch += 1;
+        Some(member)
+    }
+
+    /// A static member that left temporarily is back with a new member id,
+    /// it takes over its partitions and its target
+    pub fn replace_static_member(&mut self, old_member_id: &str, new_member_id: &str) {
+        let Some(mut member) = self.members.remove(old_member_id) else {
+            return;
+        };
+        member.member_id = new_member_id.to_string();
+        member.member_epoch = 0;
+        if let Some(instance_id) = &member.instance_id {
+            self.static_members.insert(instance_id.clone(), new_member_id.to_string());
+        }
+        if let Some(target) = self.target_assignment.remove(old_member_id) {
+            self.target_assignment.insert(new_member_id.to_string(), target);
+        }
+        self.members.insert(new_member_id.to_string(), member);
+    }
+
+    /// The topics each member is subscribed to: those it named and those of
+    /// `topics` matching its regex, internal topics aside
+    pub fn subscriptions(&self, topics: &BTreeMap<String, TopicMetadata>) -> BTreeMap<String, BTreeSet<String>> {
+        let mut regexes: HashMap<&str, Option<Regex>> = HashMap::new();
+        self.members
+            .values()
+            .map(|member| {
+                let mut subscribed: BTreeSet<String> = member.subscribed_topic_names.iter().cloned().collect();
+                if let Some(pattern) = &member.subscribed_topic_regex {
+                    // validated when the member sent it
+                    if let Some(regex) = regexes.entry(pattern).or_insert_with(|| topic_regex(pattern).ok()) {
+                        subscribed.extend(topics.keys().filter(|name| !is_internal_topic(name) && regex.is_match(name)).cloned());
+                    }
+                }
+                (member.member_id.clone(), subscribed)
+            })
+            .collect()
+    }
+
+    /// The topics any member is subscribed to
+    pub fn subscribed_topics(&self, topics: &BTreeMap<String, TopicMetadata>) -> BTreeSet<String> {
+        self.subscriptions(topics).into_values().flatten().collect()
+    }
+
+    /// Update the subscribed topics, returns whether any was created,
+    /// deleted or got partitions since
+    pub fn update_subscription_metadata(&mut self, topics: &BTreeMap<String, TopicMetadata>) -> bool {
+        let metadata: BTreeMap<String, TopicMetadata> = self
+            .subscribed_topics(topics)
+            .into_iter()
+            .filter_map(|name| topics.get(&name).map(|topic| (name, topic.clone())))
+            .collect();
+        let changed = metadata != self.subscription_metadata;
+        self.subscription_metadata = metadata;
+        changed
+    }
+
+    /// Compute the target assignment of the current group epoch
+    pub fn compute_target_assignment(&mut self, assignor: ConsumerGroupAssignor, topics: &BTreeMap<String, TopicMetadata>) {
+        let members: BTreeMap<String, MemberSubscription> = self
+            .subscriptions(topics)
+            .into_iter()
+            .map(|(member_id, topics)| {
+                let current = self.target_assignment.get(&member_id);
+                (member_id, MemberSubscription { topics, current })
+            })
+            .collect();
+        let target = assignor.assign(&members, &self.subscription_metadata);
+        self.target_assignment = target;
+        self.assignment_epoch = self.group_epoch;
+        self.assignor_name = assignor.name().to_string();
+    }
+
+    /// Move the member a step toward its target assignment, like Kafka's
+    /// CurrentAssignmentBuilder. It first revokes what it has to give up,
+    /// keeping its epoch until its heartbeat stops listing those in `owned`.
+    /// Then it gets the assignment epoch and the partitions of its target
+    /// that nobody else still owns. Returns whether its assignment changed.
+    pub fn reconcile(&mut self, member_id: &str, owned: Option<&Assignment>, now_ms: i64) -> bool {
+        let target = self.target_assignment.get(member_id).cloned().unwrap_or_default();
+        let owned_by_others = self.partitions_owned_by_others(member_id);
+        let assignment_epoch = self.assignment_epoch;
+        let Some(member) = self.members.get_mut(member_id) else {
+            return false;
+        };
+
+        if member.state == MemberState::UnrevokedPartitions {
+            let revoked = owned.is_some_and(|owned| !intersects(owned, &member.partitions_pending_revocation));
+            if !revoked {
+                return false;
+            }
+            member.partitions_pending_revocation.clear();
+            member.revocation_deadline_ms = None;
+        } else if member.state == MemberState::Stable && member.member_epoch == assignment_epoch {
+            return false;
+        }
+
+        let to_revoke = difference(&member.assigned_partitions, &target);
+        if !to_revoke.is_empty() {
+            member.assigned_partitions = difference(&member.assigned_partitions, &to_revoke);
+            member.partitions_pending_revocation = to_revoke;
+            member.state = MemberState::UnrevokedPartitions;
+            member.revocation_deadline_ms = Some(now_ms + member.rebalance_timeout_ms as i64);
+            return true;
+        }
+
+        let assigned_before = member.assigned_partitions.clone();
+        for (topic_id, partitions) in &target {
+            for &partition in partitions.iter().filter(|partition| !owned_by_others.contains(&(*topic_id, **partition))) {
+                member.assigned_partitions.entry(*topic_id).or_default().insert(partition);
+            }
+        }
+        if member.member_epoch != assignment_epoch {
+            member.previous_member_epoch = member.member_epoch;
+            member.member_epoch = assignment_epoch;
+        }
+        member.state = if member.assigned_partitions == target { MemberState::Stable } else { MemberState::UnreleasedPartitions };
+        member.assigned_partitions != assigned_before
+    }
+
+    /// Partitions members other than `member_id` own or still have to revoke
+    fn partitions_owned_by_others(&self, member_id: &str) -> HashSet<(KafUuid, i32)> {
+        self.members
+            .values()
+            .filter(|member| member.member_id != member_id)
+            .flat_map(|member| member.assigned_partitions.iter().chain(&member.partitions_pending_revocation))
+            .flat_map(|(topic_id, partitions)| partitions.iter().map(|partition| (*topic_id, *partition)))
+            .collect()
+    }
+
+    /// The state the group is in given its members, updated if it changed
+    pub fn update_state(&mut self, now_ms: i64) {
+        let state = if self.members.is_empty() {
+            ConsumerGroupState::Empty
+        } else if self.group_epoch > self.assignment_epoch {
+            ConsumerGroupState::Assigning
+        } else if self
+            .members
+            .values()
+            .any(|member| member.member_epoch != self.assignment_epoch || member.state != MemberState::Stable)
+        {
+            ConsumerGroupState::Reconciling
+        } else {
+            ConsumerGroupState::Stable
+        };
+        if state != self.state {
+            self.state = state;
+            self.state_timestamp_ms = Some(now_ms);
+        }
+    }
+}
+
+/// A regex subscription, which like Kafka's must match whole topic names
+pub fn topic_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+fn difference(a: &Assignment, b: &Assignment) -> Assignment {
+    a.iter()
+        .filter_map(|(topic_id, partitions)| {
+            let left: BTreeSet<i32> = match b.get(topic_id) {
+                Some(other) => partitions.difference(other).copied().collect(),
+                None => partitions.clone(),
+            };
+            (!left.is_empty()).then_some((*topic_id, left))
+        })
+        .collect()
+}
+
+fn intersects(a: &Assignment, b: &Assignment) -> bool {
+    a.iter().any(|(topic_id, partitions)| b.get(topic_id).is_some_and(|other| !partitions.is_disjoint(other)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(partitions: &[(u8, &[i32])]) -> Assignment {
+        partitions.iter().map(|(topic, partitions)| (KafUuid([*topic; 16]), partitions.iter().copied().collect())).collect()
+    }
+
+    fn member(member_id: &str, epoch: i32, assigned: Assignment) -> ConsumerGroupMember {
+        ConsumerGroupMember {
+            member_epoch: epoch,
+            assigned_partitions: assigned,
+            rebalance_timeout_ms: 1_000,
+            ..ConsumerGroupMember::new(member_id, 0)
+        }
+    }
+
+    #[test]
+    fn partitions_move_once_their_owner_revoked_them() {
+        let mut group = ConsumerGroup::new("group", 0);
+        group.add_member(member("a", 1, assignment(&[(1, &[0, 1, 2, 3])])));
+        group.add_member(member("b", 0, Assignment::new()));
+        group.group_epoch = 2;
+        group.assignment_epoch = 2;
+        group.target_assignment = BTreeMap::from([
+            ("a".to_string(), assignment(&[(1, &[0, 1])])),
+            ("b".to_string(), assignment(&[(1, &[2, 3])])),
+        ]);
+
+        // b reaches epoch 2 but a still owns its partitions
+        assert!(!group.reconcile("b", None, 0));
+        assert_eq!((group.members["b"].member_epoch, group.members["b"].state), (2, MemberState::UnreleasedPartitions));
+
+        // a is told to revoke 2 and 3, keeping epoch 1 until it did
+        assert!(group.reconcile("a", None, 0));
+        let a = &group.members["a"];
+        assert_eq!((a.member_epoch, a.state), (1, MemberState::UnrevokedPartitions));
+        assert_eq!(a.partitions_pending_revocation, assignment(&[(1, &[2, 3])]));
+        assert!(!group.reconcile("a", Some(&assignment(&[(1, &[0, 1, 2, 3])])), 0));
+        assert!(!group.reconcile("b", None, 0));
+        group.update_state(0);
+        assert_eq!(group.state, ConsumerGroupState::Reconciling);
+
+        assert!(!group.reconcile("a", Some(&assignment(&[(1, &[0, 1])])), 0));
+        assert_eq!((group.members["a"].member_epoch, group.members["a"].state), (2, MemberState::Stable));
+        assert!(group.reconcile("b", None, 0));
+        assert_eq!(group.members["b"].assigned_partitions, assignment(&[(1, &[2, 3])]));
+        group.update_state(5);
+        assert_eq!((group.state, group.state_timestamp_ms), (ConsumerGroupState::Stable, Some(5)));
+    }
+
+    #[test]
+    fn regexes_match_whole_topic_names_and_skip_internal_topics() {
+        let topics: BTreeMap<String, TopicMetadata> = ["orders", "orders-dlq", "payments", "__consumer_offsets"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let topic = TopicMetadata {
+                    topic_id: KafUuid([i as u8; 16]),
+                    name: name.to_string(),
+                    num_partitions: 1,
+                };
+                (name.to_string(), topic)
+            })
+            .collect();
+        let mut group = ConsumerGroup::new("group", 0);
+        let mut subscriber = member("a", 0, Assignment::new());
+        subscriber.subscribed_topic_names = vec!["payments".to_string()];
+        subscriber.subscribed_topic_regex = Some("orders|__.*".to_string());
+        group.add_member(subscriber);
+        let topics: Vec<String> = group.subscribed_topics(&topics).into_iter().collect();
+        assert_eq!(topics, vec!["orders", "payments"]);
+        assert!(topic_regex("orders(").is_err());
+    }
+}
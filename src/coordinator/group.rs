@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use bytes::Bytes;
+
+use crate::{
+    common::{
+        codec::{read_array, read_string},
+        error::error_code,
+        request::join_group::JoinGroupRequestProtocol,
+        response::join_group::{JoinGroupResponse, JoinGroupResponseMember},
+        EncodingError,
+    },
+    utils::parse_primitive_types::read_i16_be,
+};
+
+/// The protocol_type of groups of Kafka consumers, whose protocol metadata
+/// we can read
+pub const CONSUMER_PROTOCOL_TYPE: &str = "consumer";
+
+/// The group type ListGroups gives groups of the classic protocol
+pub const CLASSIC_GROUP_TYPE: &str = "classic";
+
+/// Where a classic group is in its rebalance protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupState {
+    /// no members, possibly still committed offsets
+    Empty,
+    /// waiting for every member to (re)join
+    PreparingRebalance,
+    /// joined, waiting for the leader's assignments
+    CompletingRebalance,
+    Stable,
+    /// removed, any request for it is retried elsewhere
+    Dead,
+}
+
+impl GroupState {
+    /// As DescribeGroups and ListGroups name it
+    pub fn name(&self) -> &'static str {
+        match self {
+            GroupState::Empty => "Empty",
+            GroupState::PreparingRebalance => "PreparingRebalance",
+            GroupState::CompletingRebalance => "CompletingRebalance",
+            GroupState::Stable => "Stable",
+            GroupState::Dead => "Dead",
+        }
+    }
+
+    fn valid_previous_states(&self) -> &'static [GroupState] {
+        use GroupState::*;
+        match self {
+            Empty => &[PreparingRebalance],
+            PreparingRebalance => &[Empty, CompletingRebalance, Stable],
+            CompletingRebalance => &[PreparingRebalance],
+            Stable => &[CompletingRebalance],
+            Dead => &[Empty, PreparingRebalance, CompletingRebalance, Stable, Dead],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupMember {
+    pub member_id: String,
+    /// group.instance.id of static members
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub session_timeout_ms: i32,
+    pub rebalance_timeout_ms: i32,
+    /// in the member's order of preference
+    pub protocols: Vec<JoinGroupRequestProtocol>,
+    /// from the leader's SyncGroup, empty until then
+    pub assignment: Bytes,
+    /// last heartbeat, join or sync
+    pub last_heartbeat_ms: i64,
+    /// a JoinGroup of this member waits for the join phase to complete
+    pub awaiting_join: bool,
+    /// the JoinGroup response once it did, taken by the waiting request
+    pub join_response: Option<JoinGroupResponse>,
+    /// a SyncGroup of this member waits for the leader's assignments
+    pub awaiting_sync: bool,
+}
+
+impl GroupMember {
+    pub fn metadata(&self, protocol: &str) -> Option<&Bytes> {
+        self.protocols.iter().find(|p| p.name == protocol).map(|p| &p.metadata)
+    }
+
+    fn supports(&self, protocol: &str) -> bool {
+        self.metadata(protocol).is_some()
+    }
+
+    /// A member whose JoinGroup is waiting isn't expected to heartbeat
+    pub fn has_expired(&self, now_ms: i64) -> bool {
+        !self.awaiting_join && !self.awaiting_sync && now_ms - self.last_heartbeat_ms > self.session_timeout_ms as i64
+    }
+}
+
+/// A group using the classic JoinGroup / SyncGroup rebalance protocol
+#[derive(Debug, Clone)]
+pub struct ClassicGroup {
+    pub group_id: String,
+    pub state: GroupState,
+    /// when the group got into its state, None for groups only known from
+    /// their committed offsets
+    pub state_timestamp_ms: Option<i64>,
+    /// bumped each time a join phase completes
+    pub generation_id: i32,
+    /// `consumer` for consumer groups, set by the first member
+    pub protocol_type: Option<String>,
+    /// the protocol chosen for the current generation
+    pub protocol_name: Option<String>,
+    pub leader_id: Option<String>,
+    pub members: BTreeMap<String, GroupMember>,
+    /// member ids given out by JoinGroup v4+ that haven't joined with them
+    /// yet, with when they're forgotten
+    pub pending_members: HashMap<String, i64>,
+    /// group.instance.id of static members to their current member id
+    pub static_members: HashMap<String, String>,
+    pub rebalance_start_ms: i64,
+    /// when the join phase stops waiting for members that haven't rejoined
+    pub rebalance_deadline_ms: i64,
+    /// the first join phase of an empty group waits out
+    /// group.initial.rebalance.delay.ms, even once everyone joined
+    pub initial_rebalance: bool,
+}
+
+impl ClassicGroup {
+    pub fn new(group_id: &str) -> Self {
+        ClassicGroup {
+            group_id: group_id.to_string(),
+            state: GroupState::Empty,
+            state_timestamp_ms: None,
+            generation_id: 0,
+            protocol_type: None,
+            protocol_name: None,
+            leader_id: None,
+            members: BTreeMap::new(),
+            pending_members: HashMap::new(),
+            static_members: HashMap::new(),
+            rebalance_start_ms: 0,
+            rebalance_deadline_ms: 0,
+            initial_rebalance: false,
+        }
+    }
+
+    pub fn is(&self, state: GroupState) -> bool {
+        self.state == state
+    }
+
+    pub fn transition_to(&mut self, state: GroupState, now_ms: i64) {
+        debug_assert!(
+            state.valid_previous_states().contains(&self.state),
+            "group {} can't go from {:?} to {:?}",
+            self.group_id,
+            self.state,
+            state
+        );
+        self.state = state;
+        self.state_timestamp_ms = Some(now_ms);
+    }
+
+    /// Whether `member_id` may join with these protocols: the protocol type
+    /// of the group and one protocol every other member supports
+    pub fn supports_protocols(&self, member_id: &str, protocol_type: &str, protocols: &[JoinGroupRequestProtocol]) -> bool {
+        if protocol_type.is_empty() || protocols.is_empty() {
+            return false;
+        }
+        let mut others = self.members.values().filter(|member| member.member_id != member_id).peekable();
+        if others.peek().is_none() {
+            return true;
+        }
+        let others: Vec<_> = others.collect();
+        self.protocol_type.as_deref() == Some(protocol_type)
+            && protocols.iter().any(|protocol| others.iter().all(|member| member.supports(&protocol.name)))
+    }
+
+    /// The protocol every member supports that most members prefer
+    pub fn select_protocol(&self) -> Option<String> {
+        let first = self.members.values().next()?;
+        let candidates: Vec<&str> = first
+            .protocols
+            .iter()
+            .map(|protocol| protocol.name.as_str())
+            .filter(|name| self.members.values().all(|member| member.supports(name)))
+            .collect();
+        let votes: Vec<&str> = self
+            .members
+            .values()
+            .filter_map(|member| member.protocols.iter().map(|p| p.name.as_str()).find(|name| candidates.contains(name)))
+            .collect();
+        let mut selected: Option<(&str, usize)> = None;
+        for candidate in candidates {
+            let count = votes.iter().filter(|vote| **vote == candidate).count();
+            if selected.map_or(true, |(_, most)| count > most) {
+                selected = Some((candidate, count));
+            }
+        }
+        selected.map(|(name, _)| name.to_string())
+    }
+
+    /// Every member has rejoined and no member id handed out is still to come
+    pub fn all_members_joined(&self) -> bool {
+        self.pending_members.is_empty() && self.members.values().all(|member| member.awaiting_join)
+    }
+
+    pub fn max_rebalance_timeout_ms(&self) -> i64 {
+        self.members.values().map(|member| member.rebalance_timeout_ms as i64).max().unwrap_or(0)
+    }
+
+    pub fn add_member(&mut self, member: GroupMember) {
+        if self.leader_id.is_none() {
+            self.leader_id = Some(member.member_id.clone());
+        }
+        if let Some(instance_id) = &member.group_instance_id {
+            self.static_members.insert(instance_id.clone(), member.member_id.clone());
+        }
+        self.members.insert(member.member_id.clone(), member);
+    }
+
+    pub fn remove_member(&mut self, member_id: &str) -> Option<GroupMember> {
+        let member = self.members.remove(member_id)?;
+        if let Some(instance_id) = &member.group_instance_id {
+            if self.static_members.get(instance_id).map(String::as_str) == Some(member_id) {
+                self.static_members.remove(instance_id);
+            }
+        }
+        if self.leader_id.as_deref() == Some(member_id) {
+            self.leader_id = self.members.keys().next().cloned();
+        }
+        Some(member)
+    }
+
+    /// Give a static member that joined again without its member id a new
+    /// one, the old one is fenced
+    pub fn replace_static_member(&mut self, old_member_id: &str, new_member_id: &str) {
+        let Some(mut member) = self.members.remove(old_member_id) else {
+            return;
+        };
+        member.member_id = new_member_id.to_string();
+        member.awaiting_join = false;
+        member.join_response = None;
+        if let Some(instance_id) = &member.group_instance_id {
+            self.static_members.insert(instance_id.clone(), new_member_id.to_string());
+        }
+        if self.leader_id.as_deref() == Some(old_member_id) {
+            self.leader_id = Some(new_member_id.to_string());
+        }
+        self.members.insert(new_member_id.to_string(), member);
+    }
+
+    /// The topics the members of a consumer group subscribed to, None when
+    /// that isn't known: not a consumer group, no protocol chosen yet or a
+    /// subscription we can't read.
+    pub fn subscribed_topics(&self) -> Option<HashSet<String>> {
+        if self.members.is_empty() {
+            return Some(HashSet::new());
+        }
+        if self.protocol_type.as_deref() != Some(CONSUMER_PROTOCOL_TYPE) {
+            return None;
+        }
+        let protocol = self.protocol_name.as_deref()?;
+        let mut topics = HashSet::new();
+        for member in self.members.values() {
+            topics.extend(subscription_topics(member.metadata(protocol)?).ok()?);
+        }
+        Some(topics)
+    }
+
+    /// What a member's JoinGroup gets for the current generation. Only the
+    /// leader gets every member's metadata, it computes the assignments.
+    pub fn join_response(&self, member_id: &str) -> JoinGroupResponse {
+        let protocol = self.protocol_name.as_deref().unwrap_or_default();
+        let is_leader = self.leader_id.as_deref() == Some(member_id);
+        JoinGroupResponse {
+            throttle_time_ms: 0,
+            error_code: error_code::NONE,
+            generation_id: self.generation_id,
+            protocol_type: self.protocol_type.clone(),
+            protocol_name: self.protocol_name.clone(),
+            leader: self.leader_id.clone().unwrap_or_default(),
+            skip_assignment: false,
+            member_id: member_id.to_string(),
+            members: if is_leader {
+                self.members
+                    .values()
+                    .map(|member| JoinGroupResponseMember {
+                        member_id: member.member_id.clone(),
+                        group_instance_id: member.group_instance_id.clone(),
+                        metadata: member.metadata(protocol).cloned().unwrap_or_default(),
+                    })
+                    .collect()
+            } else {
+                vec![]
+            },
+        }
+    }
+}
+
+/*
+* ConsumerProtocolSubscription (Version: 0-3) => [topics] user_data owned_partitions (v1+) generation_id (v2+) rack_id (v3+)
+*   topics => string
+* Only the version and topics, which every version starts with, are read.
+*/
+fn subscription_topics(metadata: &[u8]) -> Result<Vec<String>, EncodingError> {
+    let offset = &mut 0;
+    read_i16_be(metadata, offset)?;
+    read_array(metadata, offset, false, |input, offset| read_string(input, offset, false))
+}
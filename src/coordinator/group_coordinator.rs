@@ -0,0 +1,1689 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+use crate::{
+    common::{
+        config::BrokerConfig,
+        config_def::list_items,
+        error::error_code,
+        request::{
+            consumer_group_heartbeat::{
+                ConsumerGroupHeartbeatBody, TopicPartitions, JOIN_GROUP_MEMBER_EPOCH, LEAVE_GROUP_MEMBER_EPOCH,
+                LEAVE_GROUP_STATIC_MEMBER_EPOCH,
+            },
+            heartbeat::HeartbeatBody,
+            join_group::{JoinGroupBody, UNKNOWN_MEMBER_ID},
+            leave_group::MemberIdentity,
+            offset_commit::{OffsetCommitBody, OffsetCommitRequestPartition},
+            sync_group::SyncGroupBody,
+        },
+        response::{
+            consumer_group_describe::{AssignedTopicPartitions, DescribedConsumerGroup, DescribedConsumerGroupMember},
+            consumer_group_heartbeat::ConsumerGroupHeartbeatResponse,
+            describe_groups::{DescribedGroup, DescribedGroupMember},
+            join_group::JoinGroupResponse,
+            leave_group::{LeaveGroupResponse, MemberResponse},
+            list_groups::{ListGroupsResponse, ListedGroup},
+            sync_group::SyncGroupResponse,
+        },
+        topic_partition::TopicPartition,
+        uuid::KafUuid,
+    },
+    coordinator::{
+        assignor::{Assignment, ConsumerGroupAssignor, TopicMetadata},
+        consumer_group::{topic_regex, ConsumerGroup, ConsumerGroupMember, ConsumerGroupState, CONSUMER_GROUP_TYPE},
+        group::{ClassicGroup, GroupMember, GroupState, CLASSIC_GROUP_TYPE, CONSUMER_PROTOCOL_TYPE},
+        offset_manager::OffsetManager,
+        records::OffsetAndMetadata,
+    },
+    log::LogError,
+    server::purgatory::DelayedOperationPurgatory,
+    utils::{clock::Clock, scheduler},
+};
+
+pub const DEFAULT_GROUP_MIN_SESSION_TIMEOUT_MS: i32 = 6_000;
+pub const DEFAULT_GROUP_MAX_SESSION_TIMEOUT_MS: i32 = 1_800_000;
+pub const DEFAULT_GROUP_INITIAL_REBALANCE_DELAY_MS: i32 = 3_000;
+pub const DEFAULT_GROUP_MAX_SIZE: i32 = i32::MAX;
+pub const DEFAULT_GROUP_CONSUMER_HEARTBEAT_INTERVAL_MS: i32 = 5_000;
+pub const DEFAULT_GROUP_CONSUMER_SESSION_TIMEOUT_MS: i32 = 45_000;
+pub const DEFAULT_GROUP_CONSUMER_MAX_SIZE: i32 = i32::MAX;
+
+/// How often members that stopped heartbeating are looked for
+const HEARTBEAT_EXPIRY_CHECK_INTERVAL_MS: u64 = 500;
+
+/// The `group.*` broker configs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupConfig {
+    pub min_session_timeout_ms: i32,
+    pub max_session_timeout_ms: i32,
+    pub initial_rebalance_delay_ms: i32,
+    pub max_size: i32,
+    pub consumer_heartbeat_interval_ms: i32,
+    pub consumer_session_timeout_ms: i32,
+    pub consumer_max_size: i32,
+    /// the assignors consumer groups may pick, the first one is the default
+    pub consumer_assignors: Vec<ConsumerGroupAssignor>,
+}
+
+impl Default for GroupConfig {
+    fn default() -> Self {
+        GroupConfig {
+            min_session_timeout_ms: DEFAULT_GROUP_MIN_SESSION_TIMEOUT_MS,
+            max_session_timeout_ms: DEFAULT_GROUP_MAX_SESSION_TIMEOUT_MS,
+            initial_rebalance_delay_ms: DEFAULT_GROUP_INITIAL_REBALANCE_DELAY_MS,
+            max_size: DEFAULT_GROUP_MAX_SIZE,
+            consumer_heartbeat_interval_ms: DEFAULT_GROUP_CONSUMER_HEARTBEAT_INTERVAL_MS,
+            consumer_session_timeout_ms: DEFAULT_GROUP_CONSUMER_SESSION_TIMEOUT_MS,
+            consumer_max_size: DEFAULT_GROUP_CONSUMER_MAX_SIZE,
+            consumer_assignors: vec![ConsumerGroupAssignor::Uniform, ConsumerGroupAssignor::Range],
+        }
+    }
+}
+
+impl GroupConfig {
+    pub fn from_broker_config(config: &BrokerConfig) -> Self {
+        let get = |name, default: i32| config.get_i64(name).map_or(default, |v| v.clamp(0, i32::MAX as i64) as i32);
+        GroupConfig {
+            min_session_timeout_ms: get("group.min.session.timeout.ms", DEFAULT_GROUP_MIN_SESSION_TIMEOUT_MS),
+            max_session_timeout_ms: get("group.max.session.timeout.ms", DEFAULT_GROUP_MAX_SESSION_TIMEOUT_MS),
+            initial_rebalance_delay_ms: get("group.initial.rebalance.delay.ms", DEFAULT_GROUP_INITIAL_REBALANCE_DELAY_MS),
+            max_size: get("group.max.size", DEFAULT_GROUP_MAX_SIZE),
+            consumer_heartbeat_interval_ms: get("group.consumer.heartbeat.interval.ms", DEFAULT_GROUP_CONSUMER_HEARTBEAT_INTERVAL_MS),
+            consumer_session_timeout_ms: get("group.consumer.session.timeout.ms", DEFAULT_GROUP_CONSUMER_SESSION_TIMEOUT_MS),
+            consumer_max_size: get("group.consumer.max.size", DEFAULT_GROUP_CONSUMER_MAX_SIZE),
+            consumer_assignors: config
+                .get("group.consumer.assignors")
+                .map(|names| list_items(names).into_iter().filter_map(ConsumerGroupAssignor::from_name).collect::<Vec<_>>())
+                .filter(|assignors| !assignors.is_empty())
+                .unwrap_or_else(|| GroupConfig::default().consumer_assignors),
+        }
+    }
+}
+
+/// The groups of both protocols, a group id is either kind
+#[derive(Debug, Default)]
+struct Groups {
+    classic: HashMap<String, ClassicGroup>,
+    consumer: HashMap<String, ConsumerGroup>,
+}
+
+/// Who is joining, besides what the JoinGroup request says
+#[derive(Debug, Clone, Copy)]
+pub struct JoinContext<'a> {
+    pub client_id: &'a str,
+    pub client_host: &'a str,
+    /// JoinGroup v4+: a new member first gets its member id back with
+    /// MEMBER_ID_REQUIRED and joins again with it
+    pub require_known_member_id: bool,
+}
+
+/// Who is heartbeating to a consumer group, besides what the request says
+#[derive(Debug, Clone, Copy)]
+pub struct ConsumerContext<'a> {
+    pub client_id: &'a str,
+    pub client_host: &'a str,
+    /// the topics of the cluster, by name, which subscriptions resolve to
+    pub topics: &'a BTreeMap<String, TopicMetadata>,
+}
+
+/// What a JoinGroup does once its member is added or updated
+#[derive(Debug)]
+enum Joined {
+    /// wait for the join phase to complete, as this member
+    Waiting(String),
+    Done(JoinGroupResponse),
+}
+
+/// Runs the classic rebalance protocol of the groups this broker coordinates,
+/// like Kafka's GroupCoordinator: members join (JoinGroup) until everyone
+/// has, the leader they're given assigns partitions (SyncGroup), and members
+/// heartbeat to stay in the group.
+///
+/// JoinGroups and follower SyncGroups park in a purgatory keyed by group id,
+/// woken by whatever changes the group.
+///
+/// Groups also commit the offsets they consumed up to, see `OffsetManager`.
+/// Until the __consumer_offsets partition of a group is loaded, requests for
+/// it get COORDINATOR_LOAD_IN_PROGRESS.
+#[derive(Debug)]
+pub struct GroupCoordinator {
+    config: GroupConfig,
+    groups: Mutex<Groups>,
+    offsets: OffsetManager,
+    purgatory: DelayedOperationPurgatory<String>,
+    clock: Arc<dyn Clock>,
+}
+
+fn join_error(member_id: &str, error_code: i16) -> JoinGroupResponse {
+    JoinGroupResponse {
+        error_code,
+        generation_id: -1,
+        member_id: member_id.to_string(),
+        ..Default::default()
+    }
+}
+
+fn sync_error(error_code: i16) -> SyncGroupResponse {
+    SyncGroupResponse {
+        error_code,
+        ..Default::default()
+    }
+}
+
+fn sync_response(group: &ClassicGroup, assignment: Bytes) -> SyncGroupResponse {
+    SyncGroupResponse {
+        throttle_time_ms: 0,
+        error_code: error_code::NONE,
+        protocol_type: group.protocol_type.clone(),
+        protocol_name: group.protocol_name.clone(),
+        assignment,
+    }
+}
+
+fn consumer_heartbeat_error(error_code: i16) -> ConsumerGroupHeartbeatResponse {
+    ConsumerGroupHeartbeatResponse::error(error_code, None)
+}
+
+fn to_assignment(topic_partitions: &[TopicPartitions]) -> Assignment {
+    let mut assignment = Assignment::new();
+    for topic in topic_partitions.iter().filter(|topic| !topic.partitions.is_empty()) {
+        assignment.entry(topic.topic_id).or_default().extend(&topic.partitions);
+    }
+    assignment
+}
+
+fn to_topic_partitions(assignment: &Assignment) -> Vec<TopicPartitions> {
+    assignment
+        .iter()
+        .map(|(topic_id, partitions)| TopicPartitions {
+            topic_id: *topic_id,
+            partitions: partitions.iter().copied().collect(),
+        })
+        .collect()
+}
+
+fn is_subset(a: &Assignment, b: &Assignment) -> bool {
+    a.iter().all(|(topic_id, partitions)| b.get(topic_id).is_some_and(|other| partitions.is_subset(other)))
+}
+
+/// The consumer group, an empty classic group of that id becoming one if
+/// `create`, as a group only known from its offsets after a restart does
+fn consumer_group<'a>(groups: &'a mut Groups, group_id: &str, create: bool, now_ms: i64) -> Result<&'a mut ConsumerGroup, i16> {
+    match groups.classic.get(group_id) {
+        Some(group) if create && group.is(GroupState::Empty) && group.pending_members.is_empty() => {
+            groups.classic.remove(group_id);
+        }
+        Some(_) => return Err(error_code::GROUP_ID_NOT_FOUND),
+        None => {}
+    }
+    if create {
+        return Ok(groups.consumer.entry(group_id.to_string()).or_insert_with(|| ConsumerGroup::new(group_id, now_ms)));
+    }
+    groups.consumer.get_mut(group_id).ok_or(error_code::GROUP_ID_NOT_FOUND)
+}
+
+impl GroupCoordinator {
+    pub fn new(config: GroupConfig, offsets: OffsetManager, clock: Arc<dyn Clock>) -> Self {
+        GroupCoordinator {
+            config,
+            groups: Mutex::new(Groups::default()),
+            offsets,
+            purgatory: DelayedOperationPurgatory::default(),
+            clock,
+        }
+    }
+
+    /// Load the offsets found on startup, then periodically remove members
+    /// whose session timed out and offsets past their retention
+    pub fn start_background_tasks(self: &Arc<Self>) -> io::Result<()> {
+        let coordinator = self.clone();
+        thread::Builder::new()
+            .name("kafka-group-metadata-load".to_string())
+            .spawn(move || coordinator.load_offsets())?;
+
+        let coordinator = self.clone();
+        scheduler::schedule(
+            "kafka-group-heartbeat-expiry",
+            Duration::from_millis(HEARTBEAT_EXPIRY_CHECK_INTERVAL_MS),
+            move || coordinator.expire_members(),
+        )?;
+
+        let coordinator = self.clone();
+        scheduler::schedule(
+            "kafka-offsets-retention",
+            Duration::from_millis(self.offsets.config().retention_check_interval_ms.max(1) as u64),
+            move || coordinator.expire_offsets(),
+        )?;
+        Ok(())
+    }
+
+    pub fn offsets(&self) -> &OffsetManager {
+        &self.offsets
+    }
+
+    /// Replay the __consumer_offsets partitions marked for loading. Groups
+    /// with committed offsets are known again, as Empty groups.
+    pub fn load_offsets(&self) {
+        self.offsets.load();
+        let mut groups = self.groups.lock().unwrap();
+        for group_id in self.offsets.groups() {
+            if !groups.consumer.contains_key(&group_id) {
+                groups.classic.entry(group_id.clone()).or_insert_with(|| ClassicGroup::new(&group_id));
+            }
+        }
+    }
+
+    /// Current state of a group, None if we don't know it
+    pub fn group_state(&self, group_id: &str) -> Option<GroupState> {
+        self.groups.lock().unwrap().classic.get(group_id).map(|group| group.state)
+    }
+
+    /// Current state of a consumer group, None if we don't know it
+    pub fn consumer_group_state(&self, group_id: &str) -> Option<ConsumerGroupState> {
+        self.groups.lock().unwrap().consumer.get(group_id).map(|group| group.state)
+    }
+
+    /// Add the member to the group, or update it, and wait for the group's
+    /// join phase to complete
+    pub fn join_group(&self, body: &JoinGroupBody, context: JoinContext) -> JoinGroupResponse {
+        if body.group_id.is_empty() {
+            return join_error(&body.member_id, error_code::INVALID_GROUP_ID);
+        }
+        if self.offsets.is_loading(&body.group_id) {
+            return join_error(&body.member_id, error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        }
+        if !(self.config.min_session_timeout_ms..=self.config.max_session_timeout_ms).contains(&body.session_timeout_ms) {
+            return join_error(&body.member_id, error_code::INVALID_SESSION_TIMEOUT);
+        }
+        let joined = {
+            let mut groups = self.groups.lock().unwrap();
+            match groups.consumer.get(&body.group_id) {
+                Some(group) if !group.members.is_empty() => {
+                    return join_error(&body.member_id, error_code::INCONSISTENT_GROUP_PROTOCOL);
+                }
+                // an empty consumer group becomes a classic one
+                Some(_) => {
+                    groups.consumer.remove(&body.group_id);
+                }
+                None => {}
+            }
+            let group = groups.classic.entry(body.group_id.clone()).or_insert_with(|| ClassicGroup::new(&body.group_id));
+            self.join(group, body, context)
+        };
+        match joined {
+            Joined::Waiting(member_id) => self.await_join(&body.group_id, &member_id),
+            Joined::Done(response) => response,
+        }
+    }
+
+    fn join(&self, group: &mut ClassicGroup, body: &JoinGroupBody, context: JoinContext) -> Joined {
+        let now = self.clock.now_ms();
+        let error = |error_code| Joined::Done(join_error(&body.member_id, error_code));
+        if group.is(GroupState::Dead) {
+            return error(error_code::COORDINATOR_NOT_AVAILABLE);
+        }
+        if !group.supports_protocols(&body.member_id, &body.protocol_type, &body.protocols) {
+            return error(error_code::INCONSISTENT_GROUP_PROTOCOL);
+        }
+
+        let mut member_id = body.member_id.clone();
+        if member_id == UNKNOWN_MEMBER_ID {
+            let new_member_id = format!("{}-{}", context.client_id, KafUuid::random());
+            let known_instance = body.group_instance_id.as_ref().and_then(|id| group.static_members.get(id)).cloned();
+            match known_instance {
+                // a static member restarted, it keeps its place in the group
+                Some(old_member_id) => {
+                    group.replace_static_member(&old_member_id, &new_member_id);
+                    self.purgatory.check_and_complete(&group.group_id);
+                    member_id = new_member_id;
+                }
+                None if context.require_known_member_id && body.group_instance_id.is_none() => {
+                    let expires_ms = now + body.session_timeout_ms as i64;
+                    group.pending_members.insert(new_member_id.clone(), expires_ms);
+                    return Joined::Done(join_error(&new_member_id, error_code::MEMBER_ID_REQUIRED));
+                }
+                None => return self.add_member(group, new_member_id, body, context, now),
+            }
+        } else if group.pending_members.remove(&member_id).is_some() {
+            return self.add_member(group, member_id, body, context, now);
+        }
+
+        let state = group.state;
+        let is_leader = group.leader_id.as_deref() == Some(member_id.as_str());
+        let Some(member) = group.members.get_mut(&member_id) else {
+            return error(error_code::UNKNOWN_MEMBER_ID);
+        };
+        if member.group_instance_id != body.group_instance_id {
+            return error(error_code::FENCED_INSTANCE_ID);
+        }
+        let protocols_changed = member.protocols != body.protocols;
+        member.protocols = body.protocols.clone();
+        member.session_timeout_ms = body.session_timeout_ms;
+        member.rebalance_timeout_ms = body.rebalance_timeout_ms;
+        member.client_id = context.client_id.to_string();
+        member.client_host = context.client_host.to_string();
+        member.last_heartbeat_ms = now;
+        match state {
+            GroupState::PreparingRebalance => member.awaiting_join = true,
+            // nothing changed, the member just lost our response
+            GroupState::CompletingRebalance if !protocols_changed => {
+                return Joined::Done(group.join_response(&member_id));
+            }
+            GroupState::Stable if !protocols_changed && !is_leader => {
+                return Joined::Done(group.join_response(&member_id));
+            }
+            // the leader rejoining is how it asks for a new assignment
+            GroupState::CompletingRebalance | GroupState::Stable => {
+                member.awaiting_join = true;
+                self.prepare_rebalance(group, now);
+            }
+            GroupState::Empty | GroupState::Dead => return error(error_code::UNKNOWN_MEMBER_ID),
+        }
+        Joined::Waiting(member_id)
+    }
+
+    fn add_member(&self, group: &mut ClassicGroup, member_id: String, body: &JoinGroupBody, context: JoinContext, now: i64) -> Joined {
+        if group.members.len() >= self.config.max_size.max(1) as usize {
+            return Joined::Done(join_error(&member_id, error_code::GROUP_MAX_SIZE_REACHED));
+        }
+        if group.members.is_empty() {
+            group.protocol_type = Some(body.protocol_type.clone());
+        }
+        group.add_member(GroupMember {
+            member_id: member_id.clone(),
+            group_instance_id: body.group_instance_id.clone(),
+            client_id: context.client_id.to_string(),
+            client_host: context.client_host.to_string(),
+            session_timeout_ms: body.session_timeout_ms,
+            rebalance_timeout_ms: body.rebalance_timeout_ms,
+            protocols: body.protocols.clone(),
+            assignment: Bytes::new(),
+            last_heartbeat_ms: now,
+            awaiting_join: true,
+            join_response: None,
+            awaiting_sync: false,
+        });
+        self.prepare_rebalance(group, now);
+        Joined::Waiting(member_id)
+    }
+
+    /// Start a join phase, every member has to rejoin. The first one of an
+    /// empty group waits group.initial.rebalance.delay.ms for more members,
+    /// each new one pushing it back, up to the rebalance timeout.
+    fn prepare_rebalance(&self, group: &mut ClassicGroup, now: i64) {
+        let rebalance_timeout_ms = group.max_rebalance_timeout_ms();
+        let initial_delay_ms = self.config.initial_rebalance_delay_ms as i64;
+        match group.state {
+            GroupState::Empty | GroupState::CompletingRebalance | GroupState::Stable => {
+                let initial = group.is(GroupState::Empty) && initial_delay_ms > 0;
+                if group.is(GroupState::CompletingRebalance) {
+                    // the leader's assignments for this generation are void
+                    group.members.values_mut().for_each(|member| member.assignment = Bytes::new());
+                }
+                group.transition_to(GroupState::PreparingRebalance, now);
+                group.initial_rebalance = initial;
+                group.rebalance_start_ms = now;
+                group.rebalance_deadline_ms = now + if initial { initial_delay_ms.min(rebalance_timeout_ms) } else { rebalance_timeout_ms };
+                println!("preparing to rebalance group {} with old generation {}", group.group_id, group.generation_id);
+                // SyncGroups waiting for the old generation give up
+                self.purgatory.check_and_complete(&group.group_id);
+            }
+            GroupState::PreparingRebalance if group.initial_rebalance => {
+                let deadline = (now + initial_delay_ms).min(group.rebalance_start_ms + rebalance_timeout_ms);
+                group.rebalance_deadline_ms = group.rebalance_deadline_ms.max(deadline);
+            }
+            GroupState::PreparingRebalance | GroupState::Dead => {}
+        }
+    }
+
+    /// Complete the join phase once every member rejoined or the deadline
+    /// passed, members that didn't rejoin by then are removed
+    fn maybe_complete_join(&self, group: &mut ClassicGroup, now: i64) {
+        if !group.is(GroupState::PreparingRebalance) {
+            return;
+        }
+        let ready = now >= group.rebalance_deadline_ms || (!group.initial_rebalance && group.all_members_joined());
+        if !ready {
+            return;
+        }
+        let missing: Vec<String> = group.members.values().filter(|m| !m.awaiting_join).map(|m| m.member_id.clone()).collect();
+        for member_id in missing {
+            println!("member {} of group {} didn't rejoin in time, removing it", member_id, group.group_id);
+            group.remove_member(&member_id);
+        }
+        group.pending_members.clear();
+        group.initial_rebalance = false;
+        group.generation_id += 1;
+        if group.members.is_empty() {
+            group.protocol_name = None;
+            group.transition_to(GroupState::Empty, now);
+        } else {
+            group.protocol_name = group.select_protocol();
+            group.transition_to(GroupState::CompletingRebalance, now);
+            let responses: Vec<_> = group.members.keys().map(|member_id| group.join_response(member_id)).collect();
+            for (member, response) in group.members.values_mut().zip(responses) {
+                member.awaiting_join = false;
+                member.join_response = Some(response);
+                member.last_heartbeat_ms = now;
+            }
+        }
+        println!(
+            "group {} stabilized with generation {} and {} members",
+            group.group_id,
+            group.generation_id,
+            group.members.len()
+        );
+        self.purgatory.check_and_complete(&group.group_id);
+    }
+
+    fn await_join(&self, group_id: &str, member_id: &str) -> JoinGroupResponse {
+        let key = group_id.to_string();
+        loop {
+            let deadline_ms = match self.groups.lock().unwrap().classic.get(group_id) {
+                Some(group) => group.rebalance_deadline_ms,
+                None => return join_error(member_id, error_code::UNKNOWN_MEMBER_ID),
+            };
+            let timeout = Duration::from_millis((deadline_ms - self.clock.now_ms()).max(0) as u64);
+            let mut response = None;
+            self.purgatory.try_complete_else_watch(std::slice::from_ref(&key), timeout, || {
+                response = self.try_complete_join(group_id, member_id);
+                response.is_some()
+            });
+            // the deadline may have moved while we waited
+            if let Some(response) = response {
+                return response;
+            }
+        }
+    }
+
+    fn try_complete_join(&self, group_id: &str, member_id: &str) -> Option<JoinGroupResponse> {
+        let mut groups = self.groups.lock().unwrap();
+        let Some(group) = groups.classic.get_mut(group_id) else {
+            return Some(join_error(member_id, error_code::UNKNOWN_MEMBER_ID));
+        };
+        self.maybe_complete_join(group, self.clock.now_ms());
+        match group.members.get_mut(member_id) {
+            Some(member) => member.join_response.take(),
+            None => Some(join_error(member_id, error_code::UNKNOWN_MEMBER_ID)),
+        }
+    }
+
+    /// The leader hands out the assignments, followers wait for them
+    pub fn sync_group(&self, body: &SyncGroupBody) -> SyncGroupResponse {
+        if self.offsets.is_loading(&body.group_id) {
+            return sync_error(error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        }
+        let rebalance_timeout_ms = {
+            let mut groups = self.groups.lock().unwrap();
+            let Some(group) = groups.classic.get_mut(&body.group_id) else {
+                return sync_error(error_code::UNKNOWN_MEMBER_ID);
+            };
+            if group.is(GroupState::Dead) {
+                return sync_error(error_code::COORDINATOR_NOT_AVAILABLE);
+            }
+            let is_leader = group.leader_id.as_deref() == Some(body.member_id.as_str());
+            let Some(member) = group.members.get(&body.member_id) else {
+                return sync_error(error_code::UNKNOWN_MEMBER_ID);
+            };
+            if member.group_instance_id != body.group_instance_id {
+                return sync_error(error_code::FENCED_INSTANCE_ID);
+            }
+            if body.generation_id != group.generation_id {
+                return sync_error(error_code::ILLEGAL_GENERATION);
+            }
+            let mismatch = |given: &Option<String>, ours: &Option<String>| given.is_some() && given != ours;
+            if mismatch(&body.protocol_type, &group.protocol_type) || mismatch(&body.protocol_name, &group.protocol_name) {
+                return sync_error(error_code::INCONSISTENT_GROUP_PROTOCOL);
+            }
+            match group.state {
+                GroupState::Empty | GroupState::Dead => return sync_error(error_code::UNKNOWN_MEMBER_ID),
+                GroupState::PreparingRebalance => return sync_error(error_code::REBALANCE_IN_PROGRESS),
+                GroupState::Stable => return sync_response(group, member.assignment.clone()),
+                GroupState::CompletingRebalance => {}
+            }
+            let now = self.clock.now_ms();
+            if is_leader {
+                let assignments: HashMap<&str, &Bytes> =
+                    body.assignments.iter().map(|a| (a.member_id.as_str(), &a.assignment)).collect();
+                for member in group.members.values_mut() {
+                    member.assignment = assignments.get(member.member_id.as_str()).map_or_else(Bytes::new, |a| (*a).clone());
+                    member.last_heartbeat_ms = now;
+                }
+                group.transition_to(GroupState::Stable, now);
+                self.purgatory.check_and_complete(&group.group_id);
+                let assignment = group.members[&body.member_id].assignment.clone();
+                return sync_response(group, assignment);
+            }
+            let member = group.members.get_mut(&body.member_id).unwrap();
+            member.awaiting_sync = true;
+            member.last_heartbeat_ms = now;
+            member.rebalance_timeout_ms
+        };
+
+        let key = body.group_id.clone();
+        let mut response = None;
+        self.purgatory.try_complete_else_watch(
+            std::slice::from_ref(&key),
+            Duration::from_millis(rebalance_timeout_ms.max(0) as u64),
+            || {
+                response = self.try_complete_sync(body);
+                response.is_some()
+            },
+        );
+        response.unwrap_or_else(|| self.expire_sync(body))
+    }
+
+    fn try_complete_sync(&self, body: &SyncGroupBody) -> Option<SyncGroupResponse> {
+        let mut groups = self.groups.lock().unwrap();
+        let Some(group) = groups.classic.get_mut(&body.group_id) else {
+            return Some(sync_error(error_code::UNKNOWN_MEMBER_ID));
+        };
+        let (state, generation_id) = (group.state, group.generation_id);
+        let Some(member) = group.members.get_mut(&body.member_id) else {
+            return Some(sync_error(error_code::UNKNOWN_MEMBER_ID));
+        };
+        let response = match state {
+            _ if generation_id != body.generation_id => sync_error(error_code::REBALANCE_IN_PROGRESS),
+            GroupState::CompletingRebalance => return None,
+            GroupState::Stable => {
+                let assignment = member.assignment.clone();
+                member.awaiting_sync = false;
+                return Some(sync_response(group, assignment));
+            }
+            _ => sync_error(error_code::REBALANCE_IN_PROGRESS),
+        };
+        member.awaiting_sync = false;
+        Some(response)
+    }
+
+    /// The leader never sent its assignments, everyone rejoins
+    fn expire_sync(&self, body: &SyncGroupBody) -> SyncGroupResponse {
+        let mut groups = self.groups.lock().unwrap();
+        if let Some(group) = groups.classic.get_mut(&body.group_id) {
+            if let Some(member) = group.members.get_mut(&body.member_id) {
+                member.awaiting_sync = false;
+            }
+            if group.is(GroupState::CompletingRebalance) && group.generation_id == body.generation_id {
+                println!("leader of group {} didn't sync generation {} in time", group.group_id, group.generation_id);
+                self.prepare_rebalance(group, self.clock.now_ms());
+            }
+        }
+        sync_error(error_code::REBALANCE_IN_PROGRESS)
+    }
+
+    /// Keep the member in the group. REBALANCE_IN_PROGRESS tells it to rejoin.
+    pub fn heartbeat(&self, body: &HeartbeatBody) -> i16 {
+        if self.offsets.is_loading(&body.group_id) {
+            return error_code::COORDINATOR_LOAD_IN_PROGRESS;
+        }
+        let mut groups = self.groups.lock().unwrap();
+        let Some(group) = groups.classic.get_mut(&body.group_id) else {
+            return error_code::UNKNOWN_MEMBER_ID;
+        };
+        match group.state {
+            GroupState::Dead => return error_code::COORDINATOR_NOT_AVAILABLE,
+            GroupState::Empty => return error_code::UNKNOWN_MEMBER_ID,
+            _ => {}
+        }
+        let (state, generation_id) = (group.state, group.generation_id);
+        let Some(member) = group.members.get_mut(&body.member_id) else {
+            return error_code::UNKNOWN_MEMBER_ID;
+        };
+        if member.group_instance_id != body.group_instance_id {
+            return error_code::FENCED_INSTANCE_ID;
+        }
+        if body.generation_id != generation_id {
+            return error_code::ILLEGAL_GENERATION;
+        }
+        member.last_heartbeat_ms = self.clock.now_ms();
+        match state {
+            GroupState::PreparingRebalance => error_code::REBALANCE_IN_PROGRESS,
+            _ => error_code::NONE,
+        }
+    }
+
+    /// Remove the members from the group, each one with its own error.
+    /// Static members may leave by group.instance.id alone.
+    pub fn leave_group(&self, group_id: &str, members: &[MemberIdentity]) -> LeaveGroupResponse {
+        if self.offsets.is_loading(group_id) {
+            return LeaveGroupResponse {
+                error_code: error_code::COORDINATOR_LOAD_IN_PROGRESS,
+                ..Default::default()
+            };
+        }
+        let mut groups = self.groups.lock().unwrap();
+        let mut group = match groups.classic.get_mut(group_id) {
+            Some(group) if group.is(GroupState::Dead) => {
+                return LeaveGroupResponse {
+                    error_code: error_code::COORDINATOR_NOT_AVAILABLE,
+                    ..Default::default()
+                }
+            }
+            group => group,
+        };
+        let now = self.clock.now_ms();
+        let responses = members
+            .iter()
+            .map(|identity| {
+                let error_code = match group.as_deref_mut() {
+                    Some(group) => self.leave(group, identity, now),
+                    None => error_code::UNKNOWN_MEMBER_ID,
+                };
+                MemberResponse {
+                    member_id: identity.member_id.clone(),
+                    group_instance_id: identity.group_instance_id.clone(),
+                    error_code,
+                }
+            })
+            .collect();
+        LeaveGroupResponse {
+            members: responses,
+            ..Default::default()
+        }
+    }
+
+    fn leave(&self, group: &mut ClassicGroup, identity: &MemberIdentity, now: i64) -> i16 {
+        let member_id = match (&identity.group_instance_id, identity.member_id.as_str()) {
+            (Some(instance_id), UNKNOWN_MEMBER_ID) => match group.static_members.get(instance_id) {
+                Some(member_id) => member_id.clone(),
+                None => return error_code::UNKNOWN_MEMBER_ID,
+            },
+            (_, member_id) => member_id.to_string(),
+        };
+        if group.pending_members.remove(&member_id).is_some() {
+            self.maybe_complete_join(group, now);
+            return error_code::NONE;
+        }
+        let Some(member) = group.members.get(&member_id) else {
+            return error_code::UNKNOWN_MEMBER_ID;
+        };
+        if identity.group_instance_id.is_some() && member.group_instance_id != identity.group_instance_id {
+            return error_code::FENCED_INSTANCE_ID;
+        }
+        println!(
+            "member {} is leaving group {}{}",
+            member_id,
+            group.group_id,
+            identity.reason.as_deref().map(|reason| format!(": {}", reason)).unwrap_or_default()
+        );
+        self.remove_member_and_rebalance(group, &member_id, now);
+        error_code::NONE
+    }
+
+    fn remove_member_and_rebalance(&self, group: &mut ClassicGroup, member_id: &str, now: i64) {
+        group.remove_member(member_id);
+        if group.is(GroupState::Stable) || group.is(GroupState::CompletingRebalance) {
+            self.prepare_rebalance(group, now);
+        }
+        self.maybe_complete_join(group, now);
+        // its own JoinGroup or SyncGroup, if one is waiting, fails
+        self.purgatory.check_and_complete(&group.group_id);
+    }
+
+    /// Remove the members that stopped heartbeating and forget the member
+    /// ids nobody joined with, then complete the join phases they held up
+    pub fn expire_members(&self) {
+        let now = self.clock.now_ms();
+        let mut groups = self.groups.lock().unwrap();
+        for group in groups.classic.values_mut() {
+            group.pending_members.retain(|_, expires_ms| *expires_ms > now);
+            let expired: HashSet<String> =
+                group.members.values().filter(|member| member.has_expired(now)).map(|member| member.member_id.clone()).collect();
+            for member_id in expired {
+                println!("member {} of group {} has failed, removing it from the group", member_id, group.group_id);
+                self.remove_member_and_rebalance(group, &member_id, now);
+            }
+            self.maybe_complete_join(group, now);
+        }
+        let session_timeout_ms = self.config.consumer_session_timeout_ms as i64;
+        for group in groups.consumer.values_mut() {
+            let expired: Vec<(String, bool)> = group
+                .members
+                .values()
+                .filter_map(|member| {
+                    let unrevoked = member.revocation_deadline_ms.is_some_and(|deadline_ms| now >= deadline_ms);
+                    (unrevoked || now - member.last_heartbeat_ms > session_timeout_ms).then(|| (member.member_id.clone(), unrevoked))
+                })
+                .collect();
+            for (member_id, unrevoked) in expired {
+                if unrevoked {
+                    println!("member {} of group {} didn't revoke its partitions in time, removing it", member_id, group.group_id);
+                } else {
+                    println!("member {} of group {} has failed, removing it from the group", member_id, group.group_id);
+                }
+                group.remove_member(&member_id);
+            }
+            group.update_state(now);
+        }
+    }
+
+    /// A member of a consumer group heartbeats to join it, stay in it and
+    /// leave it. Members joining or leaving and changes to what they're
+    /// subscribed to bump the group epoch, the target assignment is then
+    /// recomputed and each member moves toward its part of it on its own
+    /// heartbeats, see `ConsumerGroup::reconcile`.
+    pub fn consumer_group_heartbeat(&self, body: &ConsumerGroupHeartbeatBody, context: ConsumerContext) -> ConsumerGroupHeartbeatResponse {
+        if let Err((error_code, message)) = self.validate_consumer_heartbeat(body) {
+            return ConsumerGroupHeartbeatResponse::error(error_code, Some(message));
+        }
+        if self.offsets.is_loading(&body.group_id) {
+            return consumer_heartbeat_error(error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        }
+        let now = self.clock.now_ms();
+        let mut groups = self.groups.lock().unwrap();
+        let response = match body.member_epoch {
+            LEAVE_GROUP_MEMBER_EPOCH | LEAVE_GROUP_STATIC_MEMBER_EPOCH => self.consumer_group_leave(&mut groups, body, now),
+            _ => self.consumer_group_member_heartbeat(&mut groups, body, context, now),
+        };
+        response.unwrap_or_else(consumer_heartbeat_error)
+    }
+
+    fn validate_consumer_heartbeat(&self, body: &ConsumerGroupHeartbeatBody) -> Result<(), (i16, String)> {
+        let invalid = |message: &str| Err((error_code::INVALID_REQUEST, message.to_string()));
+        if body.group_id.is_empty() {
+            return invalid("GroupId can't be empty.");
+        }
+        if body.instance_id.as_deref() == Some("") {
+            return invalid("InstanceId can't be empty.");
+        }
+        match body.member_epoch {
+            JOIN_GROUP_MEMBER_EPOCH => {
+                if body.rebalance_timeout_ms == -1 {
+                    return invalid("RebalanceTimeoutMs must be provided in first request.");
+                }
+                if body.topic_partitions.as_ref().is_some_and(|owned| !owned.is_empty()) {
+                    return invalid("TopicPartitions must be empty when (re-)joining.");
+                }
+                if body.subscribed_topic_names.is_none() && body.subscribed_topic_regex.is_none() {
+                    return invalid("SubscribedTopicNames or SubscribedTopicRegex must be set in first request.");
+                }
+            }
+            LEAVE_GROUP_STATIC_MEMBER_EPOCH if body.instance_id.is_none() => {
+                return invalid("InstanceId can't be null when leaving temporarily.");
+            }
+            epoch if epoch < LEAVE_GROUP_STATIC_MEMBER_EPOCH => return invalid(&format!("MemberEpoch {} is invalid.", epoch)),
+            _ if body.member_id.is_empty() => return invalid("MemberId can't be empty."),
+            _ => {}
+        }
+        if let Some(assignor) = body.server_assignor.as_deref() {
+            if !self.config.consumer_assignors.iter().any(|supported| supported.name() == assignor) {
+                let supported: Vec<&str> = self.config.consumer_assignors.iter().map(ConsumerGroupAssignor::name).collect();
+                let message = format!("ServerAssignor {} is not supported, supported assignors: {}.", assignor, supported.join(", "));
+                return Err((error_code::UNSUPPORTED_ASSIGNOR, message));
+            }
+        }
+        if let Some(pattern) = body.subscribed_topic_regex.as_deref().filter(|pattern| !pattern.is_empty()) {
+            if let Err(e) = topic_regex(pattern) {
+                return Err((error_code::INVALID_REGULAR_EXPRESSION, format!("SubscribedTopicRegex {} is invalid: {}", pattern, e)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Epoch -1 leaves for good. A static member leaving with -2 is coming
+    /// back, it keeps its partitions until then or until its session expires.
+    fn consumer_group_leave(&self, groups: &mut Groups, body: &ConsumerGroupHeartbeatBody, now: i64) -> Result<ConsumerGroupHeartbeatResponse, i16> {
+        let group = consumer_group(groups, &body.group_id, false, now)?;
+        let Some(member) = group.members.get_mut(&body.member_id) else {
+            return Err(error_code::UNKNOWN_MEMBER_ID);
+        };
+        if body.instance_id.is_some() && member.instance_id != body.instance_id {
+            return Err(error_code::FENCED_INSTANCE_ID);
+        }
+        if body.member_epoch == LEAVE_GROUP_STATIC_MEMBER_EPOCH {
+            println!("static member {} of group {} is leaving temporarily", body.member_id, group.group_id);
+            member.member_epoch = LEAVE_GROUP_STATIC_MEMBER_EPOCH;
+            member.last_heartbeat_ms = now;
+        } else {
+            println!("member {} is leaving group {}", body.member_id, group.group_id);
+            group.remove_member(&body.member_id);
+        }
+        group.update_state(now);
+        Ok(ConsumerGroupHeartbeatResponse {
+            member_id: Some(body.member_id.clone()),
+            member_epoch: body.member_epoch,
+            ..Default::default()
+        })
+    }
+
+    fn consumer_group_member_heartbeat(
+        &self,
+        groups: &mut Groups,
+        body: &ConsumerGroupHeartbeatBody,
+        context: ConsumerContext,
+        now: i64,
+    ) -> Result<ConsumerGroupHeartbeatResponse, i16> {
+        let joining = body.member_epoch == JOIN_GROUP_MEMBER_EPOCH;
+        let group = consumer_group(groups, &body.group_id, joining, now)?;
+        let owned = body.topic_partitions.as_deref().map(to_assignment);
+        let (member_id, added) = if joining {
+            self.join_consumer_group(group, body, now)?
+        } else {
+            let Some(member) = group.members.get(&body.member_id) else {
+                return Err(error_code::UNKNOWN_MEMBER_ID);
+            };
+            if body.instance_id.is_some() && member.instance_id != body.instance_id {
+                return Err(error_code::FENCED_INSTANCE_ID);
+            }
+            // the member may have missed the response bumping its epoch
+            let missed_bump = body.member_epoch == member.previous_member_epoch
+                && owned.as_ref().is_some_and(|owned| is_subset(owned, &member.assigned_partitions));
+            if body.member_epoch != member.member_epoch && !missed_bump {
+                return Err(error_code::FENCED_MEMBER_EPOCH);
+            }
+            (body.member_id.clone(), false)
+        };
+
+        let member = group.members.get_mut(&member_id).unwrap();
+        let subscription = (member.subscribed_topic_names.clone(), member.subscribed_topic_regex.clone());
+        if body.rebalance_timeout_ms != -1 {
+            member.rebalance_timeout_ms = body.rebalance_timeout_ms;
+        }
+        if let Some(rack_id) = &body.rack_id {
+            member.rack_id = Some(rack_id.clone());
+        }
+        if let Some(assignor) = &body.server_assignor {
+            member.server_assignor = Some(assignor.clone());
+        }
+        if let Some(names) = &body.subscribed_topic_names {
+            member.subscribed_topic_names = names.clone();
+        }
+        if let Some(pattern) = &body.subscribed_topic_regex {
+            member.subscribed_topic_regex = Some(pattern.clone()).filter(|pattern| !pattern.is_empty());
+        }
+        member.client_id = context.client_id.to_string();
+        member.client_host = context.client_host.to_string();
+        member.last_heartbeat_ms = now;
+        let subscription_changed = subscription != (member.subscribed_topic_names.clone(), member.subscribed_topic_regex.clone());
+        let metadata_changed = group.update_subscription_metadata(context.topics);
+        if added || subscription_changed || metadata_changed {
+            group.group_epoch += 1;
+        }
+
+        if group.group_epoch > group.assignment_epoch {
+            let assignor = self.consumer_group_assignor(group);
+            group.compute_target_assignment(assignor, context.topics);
+            println!(
+                "computed the target assignment of group {} at epoch {} with the {} assignor",
+                group.group_id,
+                group.group_epoch,
+                assignor.name()
+            );
+        }
+        let changed = group.reconcile(&member_id, owned.as_ref(), now);
+        group.update_state(now);
+
+        let member = &group.members[&member_id];
+        // the member only gets its assignment when it doesn't have it yet
+        let send_assignment = joining || changed || owned.is_some_and(|owned| owned != member.assigned_partitions);
+        Ok(ConsumerGroupHeartbeatResponse {
+            member_id: Some(member_id.clone()),
+            member_epoch: member.member_epoch,
+            heartbeat_interval_ms: self.config.consumer_heartbeat_interval_ms,
+            assignment: send_assignment.then(|| to_topic_partitions(&member.assigned_partitions)),
+            ..Default::default()
+        })
+    }
+
+    /// The member id of a member joining, added to the group unless it's
+    /// already in it. A static member coming back takes over its old self.
+    fn join_consumer_group(&self, group: &mut ConsumerGroup, body: &ConsumerGroupHeartbeatBody, now: i64) -> Result<(String, bool), i16> {
+        let member_id = if body.member_id.is_empty() { KafUuid::random().to_string() } else { body.member_id.clone() };
+        let previous = body.instance_id.as_ref().and_then(|instance_id| group.static_members.get(instance_id)).cloned();
+        if let Some(old_member_id) = previous.filter(|old_member_id| *old_member_id != member_id) {
+            if group.members[&old_member_id].member_epoch != LEAVE_GROUP_STATIC_MEMBER_EPOCH {
+                return Err(error_code::UNRELEASED_INSTANCE_ID);
+            }
+            group.replace_static_member(&old_member_id, &member_id);
+            return Ok((member_id, false));
+        }
+        if group.members.contains_key(&member_id) {
+            return Ok((member_id, false));
+        }
+        if group.members.len() >= self.config.consumer_max_size.max(1) as usize {
+            return Err(error_code::GROUP_MAX_SIZE_REACHED);
+        }
+        println!("member {} is joining group {}", member_id, group.group_id);
+        group.add_member(ConsumerGroupMember {
+            instance_id: body.instance_id.clone(),
+            ..ConsumerGroupMember::new(&member_id, now)
+        });
+        Ok((member_id, true))
+    }
+
+    /// The assignor most members asked for, the default one if none did
+    fn consumer_group_assignor(&self, group: &ConsumerGroup) -> ConsumerGroupAssignor {
+        let mut votes: BTreeMap<&str, usize> = BTreeMap::new();
+        for assignor in group.members.values().filter_map(|member| member.server_assignor.as_deref()) {
+            *votes.entry(assignor).or_default() += 1;
+        }
+        votes
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .and_then(|(name, _)| ConsumerGroupAssignor::from_name(name))
+            .or_else(|| self.config.consumer_assignors.first().copied())
+            .unwrap_or(ConsumerGroupAssignor::Uniform)
+    }
+
+    /// The consumer group, its members with their assignments and targets.
+    /// Classic groups aren't found.
+    pub fn consumer_group_describe(&self, group_id: &str, topics: &BTreeMap<String, TopicMetadata>) -> DescribedConsumerGroup {
+        if self.offsets.is_loading(group_id) {
+            return DescribedConsumerGroup::error(group_id, error_code::COORDINATOR_LOAD_IN_PROGRESS, None);
+        }
+        let groups = self.groups.lock().unwrap();
+        let Some(group) = groups.consumer.get(group_id) else {
+            return DescribedConsumerGroup::error(group_id, error_code::GROUP_ID_NOT_FOUND, Some(format!("Group {} not found.", group_id)));
+        };
+        let names: HashMap<KafUuid, &str> = topics.values().map(|topic| (topic.topic_id, topic.name.as_str())).collect();
+        let describe = |assignment: &Assignment| -> Vec<AssignedTopicPartitions> {
+            assignment
+                .iter()
+                .filter_map(|(topic_id, partitions)| {
+                    Some(AssignedTopicPartitions {
+                        topic_id: *topic_id,
+                        topic_name: names.get(topic_id)?.to_string(),
+                        partitions: partitions.iter().copied().collect(),
+                    })
+                })
+                .collect()
+        };
+        DescribedConsumerGroup {
+            error_code: error_code::NONE,
+            error_message: None,
+            group_id: group_id.to_string(),
+            group_state: group.state.name().to_string(),
+            group_epoch: group.group_epoch,
+            assignment_epoch: group.assignment_epoch,
+            assignor_name: group.assignor_name.clone(),
+            members: group
+                .members
+                .values()
+                .map(|member| DescribedConsumerGroupMember {
+                    member_id: member.member_id.clone(),
+                    instance_id: member.instance_id.clone(),
+                    rack_id: member.rack_id.clone(),
+                    member_epoch: member.member_epoch,
+                    client_id: member.client_id.clone(),
+                    client_host: member.client_host.clone(),
+                    subscribed_topic_names: member.subscribed_topic_names.clone(),
+                    subscribed_topic_regex: member.subscribed_topic_regex.clone(),
+                    assignment: describe(&member.assigned_partitions),
+                    target_assignment: describe(group.target_assignment.get(&member.member_id).unwrap_or(&Assignment::new())),
+                })
+                .collect(),
+            authorized_operations: 0,
+        }
+    }
+
+    /// Check the commit against the group's generation and store it. Returns
+    /// the error of each partition, in order.
+    pub fn commit_offsets(&self, body: &OffsetCommitBody, partitions: &[(TopicPartition, &OffsetCommitRequestPartition)]) -> Vec<i16> {
+        let all = |error_code| vec![error_code; partitions.len()];
+        if body.group_id.is_empty() {
+            return all(error_code::INVALID_GROUP_ID);
+        }
+        if self.offsets.is_loading(&body.group_id) {
+            return all(error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        }
+        let now = self.clock.now_ms();
+        // held until the commit is written, so it can't race a rebalance
+        let mut groups = self.groups.lock().unwrap();
+        if let Err(error_code) = self.validate_commit(&mut groups, body, now) {
+            return all(error_code);
+        }
+
+        let expire_timestamp_ms = (body.retention_time_ms >= 0).then(|| now + body.retention_time_ms);
+        let mut errors = all(error_code::NONE);
+        let mut commits = vec![];
+        for ((tp, partition), error) in partitions.iter().zip(errors.iter_mut()) {
+            let metadata = partition.committed_metadata.clone().unwrap_or_default();
+            if metadata.len() > self.offsets.config().max_metadata_size {
+                *error = error_code::OFFSET_METADATA_TOO_LARGE;
+                continue;
+            }
+            commits.push((
+                tp.clone(),
+                OffsetAndMetadata {
+                    offset: partition.committed_offset,
+                    leader_epoch: partition.committed_leader_epoch,
+                    metadata,
+                    commit_timestamp_ms: now,
+                    expire_timestamp_ms,
+                },
+            ));
+        }
+        if commits.is_empty() {
+            return errors;
+        }
+        if let Err(e) = self.offsets.store(&body.group_id, commits) {
+            println!("failed to commit offsets of group {}: {}", body.group_id, e);
+            let failed = match e {
+                LogError::RecordTooLarge { .. } => error_code::INVALID_COMMIT_OFFSET_SIZE,
+                // the group's partition has no log here, sending the client back won't help
+                LogError::LogDeleted(_) => error_code::COORDINATOR_NOT_AVAILABLE,
+                _ => error_code::UNKNOWN_SERVER_ERROR,
+            };
+            errors.iter_mut().filter(|error| **error == error_code::NONE).for_each(|error| *error = failed);
+        }
+        errors
+    }
+
+    fn validate_commit(&self, groups: &mut Groups, body: &OffsetCommitBody, now: i64) -> Result<(), i16> {
+        let generation_id = body.generation_id_or_member_epoch;
+        if let Some(group) = groups.consumer.get(&body.group_id) {
+            // members commit with their member epoch
+            if generation_id < 0 && group.members.is_empty() {
+                return Ok(());
+            }
+            let Some(member) = group.members.get(&body.member_id) else {
+                return Err(error_code::UNKNOWN_MEMBER_ID);
+            };
+            if generation_id != member.member_epoch {
+                return Err(error_code::STALE_MEMBER_EPOCH);
+            }
+            return Ok(());
+        }
+        let Some(group) = groups.classic.get_mut(&body.group_id) else {
+            if generation_id >= 0 {
+                return Err(error_code::ILLEGAL_GENERATION);
+            }
+            // consumers that assign partitions themselves commit without joining
+            let mut group = ClassicGroup::new(&body.group_id);
+            group.state_timestamp_ms = Some(now);
+            groups.classic.insert(body.group_id.clone(), group);
+            return Ok(());
+        };
+        match group.state {
+            GroupState::Dead => return Err(error_code::COORDINATOR_NOT_AVAILABLE),
+            GroupState::Empty if generation_id < 0 => return Ok(()),
+            _ => {}
+        }
+        let (state, group_generation_id) = (group.state, group.generation_id);
+        let Some(member) = group.members.get_mut(&body.member_id) else {
+            return Err(error_code::UNKNOWN_MEMBER_ID);
+        };
+        if body.group_instance_id.is_some() && member.group_instance_id != body.group_instance_id {
+            return Err(error_code::FENCED_INSTANCE_ID);
+        }
+        if generation_id != group_generation_id {
+            return Err(error_code::ILLEGAL_GENERATION);
+        }
+        // its assignment for this generation isn't known yet
+        if state == GroupState::CompletingRebalance {
+            return Err(error_code::REBALANCE_IN_PROGRESS);
+        }
+        member.last_heartbeat_ms = now;
+        Ok(())
+    }
+
+    /// The group's committed offsets of `partitions`, or all of them. None
+    /// for partitions without one.
+    pub fn fetch_offsets(
+        &self,
+        group_id: &str,
+        partitions: Option<&[TopicPartition]>,
+    ) -> Result<Vec<(TopicPartition, Option<OffsetAndMetadata>)>, i16> {
+        if self.offsets.is_loading(group_id) {
+            return Err(error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        }
+        Ok(self.offsets.fetch(group_id, partitions))
+    }
+
+    /// Every group in one of `states` and of one of `types`, an empty filter
+    /// matching all of them. COORDINATOR_LOAD_IN_PROGRESS while some may
+    /// still be missing.
+    pub fn list_groups(&self, states: &[String], types: &[String]) -> ListGroupsResponse {
+        let matches = |filter: &[String], value: &str| filter.is_empty() || filter.iter().any(|f| f.eq_ignore_ascii_case(value));
+        let groups = self.groups.lock().unwrap();
+        let classic = groups.classic.values().map(|group| ListedGroup {
+            group_id: group.group_id.clone(),
+            protocol_type: group.protocol_type.clone().unwrap_or_default(),
+            group_state: group.state.name().to_string(),
+            group_type: CLASSIC_GROUP_TYPE.to_string(),
+        });
+        let consumer = groups.consumer.values().map(|group| ListedGroup {
+            group_id: group.group_id.clone(),
+            protocol_type: CONSUMER_PROTOCOL_TYPE.to_string(),
+            group_state: group.state.name().to_string(),
+            group_type: CONSUMER_GROUP_TYPE.to_string(),
+        });
+        let groups = classic
+            .chain(consumer)
+            .filter(|group| matches(states, &group.group_state) && matches(types, &group.group_type))
+            .collect();
+        ListGroupsResponse {
+            throttle_time_ms: 0,
+            error_code: if self.offsets.is_loading_any() { error_code::COORDINATOR_LOAD_IN_PROGRESS } else { error_code::NONE },
+            groups,
+        }
+    }
+
+    /// The group and its members. Their metadata and assignments are only
+    /// described once the group is Stable, unknown groups are Dead.
+    pub fn describe_group(&self, group_id: &str) -> DescribedGroup {
+        if self.offsets.is_loading(group_id) {
+            return DescribedGroup::error(group_id, error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        }
+        let groups = self.groups.lock().unwrap();
+        if groups.consumer.contains_key(group_id) {
+            return DescribedGroup::error(group_id, error_code::GROUP_ID_NOT_FOUND);
+        }
+        let Some(group) = groups.classic.get(group_id) else {
+            return DescribedGroup {
+                group_state: GroupState::Dead.name().to_string(),
+                ..DescribedGroup::error(group_id, error_code::NONE)
+            };
+        };
+        let protocol = group.protocol_name.as_deref().filter(|_| group.is(GroupState::Stable));
+        DescribedGroup {
+            error_code: error_code::NONE,
+            group_id: group_id.to_string(),
+            group_state: group.state.name().to_string(),
+            protocol_type: group.protocol_type.clone().unwrap_or_default(),
+            protocol_data: protocol.unwrap_or_default().to_string(),
+            members: group
+                .members
+                .values()
+                .map(|member| DescribedGroupMember {
+                    member_id: member.member_id.clone(),
+                    group_instance_id: member.group_instance_id.clone(),
+                    client_id: member.client_id.clone(),
+                    client_host: member.client_host.clone(),
+                    member_metadata: protocol.and_then(|protocol| member.metadata(protocol).cloned()).unwrap_or_default(),
+                    member_assignment: if protocol.is_some() { member.assignment.clone() } else { Bytes::new() },
+                })
+                .collect(),
+            authorized_operations: 0,
+        }
+    }
+
+    /// Delete an Empty group and its committed offsets
+    pub fn delete_group(&self, group_id: &str) -> i16 {
+        if group_id.is_empty() {
+            return error_code::INVALID_GROUP_ID;
+        }
+        if self.offsets.is_loading(group_id) {
+            return error_code::COORDINATOR_LOAD_IN_PROGRESS;
+        }
+        let mut groups = self.groups.lock().unwrap();
+        if let Some(group) = groups.consumer.get(group_id) {
+            if !group.is(ConsumerGroupState::Empty) {
+                return error_code::NON_EMPTY_GROUP;
+            }
+            if let Err(e) = self.offsets.remove_offsets(group_id, |_, _| true) {
+                println!("failed to delete the offsets of group {}: {}", group_id, e);
+                return error_code::UNKNOWN_SERVER_ERROR;
+            }
+            groups.consumer.remove(group_id);
+            println!("deleted group {}", group_id);
+            return error_code::NONE;
+        }
+        match groups.classic.get(group_id) {
+            None => return error_code::GROUP_ID_NOT_FOUND,
+            Some(group) if group.is(GroupState::Dead) => return error_code::GROUP_ID_NOT_FOUND,
+            Some(group) if !group.is(GroupState::Empty) => return error_code::NON_EMPTY_GROUP,
+            Some(_) => {}
+        }
+        if let Err(e) = self.offsets.remove_offsets(group_id, |_, _| true) {
+            println!("failed to delete the offsets of group {}: {}", group_id, e);
+            return error_code::UNKNOWN_SERVER_ERROR;
+        }
+        if let Some(mut group) = groups.classic.remove(group_id) {
+            group.transition_to(GroupState::Dead, self.clock.now_ms());
+        }
+        println!("deleted group {}", group_id);
+        error_code::NONE
+    }
+
+    /// Delete the group's committed offsets of `partitions`. A consumer group
+    /// with members keeps those of the topics it's subscribed to. Returns the
+    /// error of each partition, or of the group.
+    pub fn delete_offsets(&self, group_id: &str, partitions: &[TopicPartition]) -> Result<Vec<i16>, i16> {
+        if group_id.is_empty() {
+            return Err(error_code::INVALID_GROUP_ID);
+        }
+        if self.offsets.is_loading(group_id) {
+            return Err(error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        }
+        let groups = self.groups.lock().unwrap();
+        let subscribed = match (groups.consumer.get(group_id), groups.classic.get(group_id)) {
+            (Some(group), _) if group.members.is_empty() => HashSet::new(),
+            // the topics it was subscribed to when its epoch was last bumped
+            (Some(group), _) => group.subscription_metadata.keys().cloned().collect(),
+            (None, Some(group)) if group.is(GroupState::Dead) => return Err(error_code::GROUP_ID_NOT_FOUND),
+            (None, Some(group)) if group.is(GroupState::Empty) => HashSet::new(),
+            (None, Some(group)) if group.protocol_type.as_deref() != Some(CONSUMER_PROTOCOL_TYPE) => {
+                return Err(error_code::NON_EMPTY_GROUP)
+            }
+            // unknown subscriptions might be to any topic
+            (None, Some(group)) => match group.subscribed_topics() {
+                Some(topics) => topics,
+                None => return Ok(vec![error_code::GROUP_SUBSCRIBED_TO_TOPIC; partitions.len()]),
+            },
+            (None, None) => return Err(error_code::GROUP_ID_NOT_FOUND),
+        };
+        let errors: Vec<i16> = partitions
+            .iter()
+            .map(|tp| if subscribed.contains(&tp.topic) { error_code::GROUP_SUBSCRIBED_TO_TOPIC } else { error_code::NONE })
+            .collect();
+        let deleted: HashSet<&TopicPartition> =
+            partitions.iter().zip(&errors).filter(|(_, error)| **error == error_code::NONE).map(|(tp, _)| tp).collect();
+        match self.offsets.remove_offsets(group_id, |tp, _| deleted.contains(tp)) {
+            Ok(_) => Ok(errors),
+            Err(e) => {
+                println!("failed to delete offsets of group {}: {}", group_id, e);
+                Err(error_code::UNKNOWN_SERVER_ERROR)
+            }
+        }
+    }
+
+    /// Remove the offsets past their retention: those committed with their
+    /// own once it passed, the others offsets.retention.minutes after the
+    /// later of their commit and the group emptying. Empty groups left
+    /// without offsets are removed.
+    pub fn expire_offsets(&self) {
+        let now = self.clock.now_ms();
+        let retention_ms = self.offsets.config().retention_ms;
+        let mut groups = self.groups.lock().unwrap();
+        for group_id in self.offsets.groups() {
+            if self.offsets.is_loading(&group_id) {
+                continue;
+            }
+            // members may still be consuming from what they committed
+            let (active, empty_since_ms) = match (groups.classic.get(&group_id), groups.consumer.get(&group_id)) {
+                (Some(group), _) => (!group.is(GroupState::Empty) && !group.is(GroupState::Dead), group.state_timestamp_ms),
+                (_, Some(group)) => (!group.is(ConsumerGroupState::Empty), group.state_timestamp_ms),
+                (None, None) => (false, None),
+            };
+            let empty_since_ms = empty_since_ms.unwrap_or(i64::MIN);
+            let removed = self.offsets.remove_offsets(&group_id, |_, committed| match committed.expire_timestamp_ms {
+                Some(expire_timestamp_ms) => now >= expire_timestamp_ms,
+                None => !active && now - committed.commit_timestamp_ms.max(empty_since_ms) >= retention_ms,
+            });
+            match removed {
+                Ok(0) => {}
+                Ok(count) => println!("removed {} expired offsets of group {}", count, group_id),
+                Err(e) => println!("error while removing expired offsets of group {}: {}", group_id, e),
+            }
+        }
+        groups.classic.retain(|group_id, group| {
+            let forgotten = group.is(GroupState::Empty) && group.pending_members.is_empty() && !self.offsets.has_offsets(group_id);
+            if forgotten {
+                group.transition_to(GroupState::Dead, now);
+                println!("group {} is empty and has no offsets left, removing it", group_id);
+            }
+            !forgotten
+        });
+        groups.consumer.retain(|group_id, group| {
+            let forgotten = group.is(ConsumerGroupState::Empty) && !self.offsets.has_offsets(group_id);
+            if forgotten {
+                println!("group {} is empty and has no offsets left, removing it", group_id);
+            }
+            !forgotten
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        common::request::{
+            join_group::JoinGroupRequestProtocol,
+            offset_commit::{OffsetCommitRequestTopic, NO_GENERATION_ID},
+        },
+        coordinator::offset_manager::OffsetConfig,
+        log::LogManager,
+        metadata::CONSUMER_OFFSETS_TOPIC,
+        utils::clock::MockClock,
+    };
+
+    fn coordinator(dir: &std::path::Path, clock: Arc<MockClock>) -> Arc<GroupCoordinator> {
+        let broker_config = BrokerConfig::from_props(HashMap::from([
+            ("log.dirs".to_string(), dir.to_str().unwrap().to_string()),
+            ("offsets.topic.num.partitions".to_string(), "1".to_string()),
+        ]));
+        let log_manager = Arc::new(LogManager::startup(&broker_config, clock.clone()).unwrap());
+        log_manager.get_or_create_log(&TopicPartition::new(CONSUMER_OFFSETS_TOPIC, 0), &HashMap::new()).unwrap();
+        let config = GroupConfig {
+            initial_rebalance_delay_ms: 0,
+            ..Default::default()
+        };
+        let offsets = OffsetManager::new(OffsetConfig::from_broker_config(&broker_config), log_manager);
+        Arc::new(GroupCoordinator::new(config, offsets, clock))
+    }
+
+    fn join_body(member_id: &str, protocols: &[&str]) -> JoinGroupBody {
+        JoinGroupBody {
+            group_id: "group".to_string(),
+            session_timeout_ms: 10_000,
+            rebalance_timeout_ms: 60_000,
+            member_id: member_id.to_string(),
+            group_instance_id: None,
+            protocol_type: "consumer".to_string(),
+            protocols: protocols
+                .iter()
+                .map(|name| JoinGroupRequestProtocol {
+                    name: name.to_string(),
+                    metadata: Bytes::from(format!("{}-{}", member_id, name)),
+                })
+                .collect(),
+            reason: None,
+        }
+    }
+
+    const CONTEXT: JoinContext = JoinContext {
+        client_id: "consumer",
+        client_host: "/127.0.0.1",
+        require_known_member_id: true,
+    };
+
+    /// Join as a new member, getting a member id first
+    fn join_new(coordinator: &GroupCoordinator, protocols: &[&str]) -> JoinGroupResponse {
+        let response = coordinator.join_group(&join_body(UNKNOWN_MEMBER_ID, protocols), CONTEXT);
+        assert_eq!(response.error_code, error_code::MEMBER_ID_REQUIRED);
+        assert!(response.member_id.starts_with("consumer-"));
+        coordinator.join_group(&join_body(&response.member_id, protocols), CONTEXT)
+    }
+
+    fn sync_body(member_id: &str, generation_id: i32, assignments: &[(&str, &str)]) -> SyncGroupBody {
+        SyncGroupBody {
+            group_id: "group".to_string(),
+            generation_id,
+            member_id: member_id.to_string(),
+            group_instance_id: None,
+            protocol_type: Some("consumer".to_string()),
+            protocol_name: None,
+            assignments: assignments
+                .iter()
+                .map(|(member_id, assignment)| crate::common::request::sync_group::SyncGroupRequestAssignment {
+                    member_id: member_id.to_string(),
+                    assignment: Bytes::from(assignment.to_string()),
+                })
+                .collect(),
+        }
+    }
+
+    fn heartbeat(coordinator: &GroupCoordinator, member_id: &str, generation_id: i32) -> i16 {
+        coordinator.heartbeat(&HeartbeatBody {
+            group_id: "group".to_string(),
+            generation_id,
+            member_id: member_id.to_string(),
+            group_instance_id: None,
+        })
+    }
+
+    /// Wait for a thread blocked in the coordinator to be parked
+    fn wait_for_delayed(coordinator: &GroupCoordinator, delayed: usize) {
+        while coordinator.purgatory.delayed() < delayed {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn members_join_and_get_the_leaders_assignment() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(0));
+        let coordinator = coordinator(dir.path(), clock.clone());
+
+        let first = join_new(&coordinator, &["range", "roundrobin"]);
+        assert_eq!((first.error_code, first.generation_id), (error_code::NONE, 1));
+        assert_eq!(first.leader, first.member_id);
+        assert_eq!(first.protocol_name.as_deref(), Some("range"));
+        assert_eq!(coordinator.group_state("group"), Some(GroupState::CompletingRebalance));
+        let response = coordinator.sync_group(&sync_body(&first.member_id, 1, &[(&first.member_id, "p0,p1")]));
+        assert_eq!((response.error_code, &response.assignment[..]), (error_code::NONE, &b"p0,p1"[..]));
+        assert_eq!(heartbeat(&coordinator, &first.member_id, 1), error_code::NONE);
+
+        // a second member starts a rebalance the first one only hears about
+        // from its heartbeat
+        let second = thread::spawn({
+            let coordinator = coordinator.clone();
+            move || join_new(&coordinator, &["roundrobin"])
+        });
+        wait_for_delayed(&coordinator, 1);
+        assert_eq!(heartbeat(&coordinator, &first.member_id, 1), error_code::REBALANCE_IN_PROGRESS);
+        let first = coordinator.join_group(&join_body(&first.member_id, &["range", "roundrobin"]), CONTEXT);
+        let second = second.join().unwrap();
+        assert_eq!((first.generation_id, second.generation_id), (2, 2));
+        // the only protocol both support
+        assert_eq!(first.protocol_name.as_deref(), Some("roundrobin"));
+        assert_eq!(second.leader, first.member_id);
+        assert!(second.members.is_empty());
+        let metadata: Vec<_> = first.members.iter().map(|m| (m.member_id.clone(), m.metadata.clone())).collect();
+        assert_eq!(metadata.len(), 2);
+        assert!(metadata.iter().all(|(id, metadata)| metadata == &Bytes::from(format!("{}-roundrobin", id))));
+
+        // the follower waits for the leader's assignments
+        let follower = thread::spawn({
+            let coordinator = coordinator.clone();
+            let member_id = second.member_id.clone();
+            move || coordinator.sync_group(&sync_body(&member_id, 2, &[]))
+        });
+        wait_for_delayed(&coordinator, 1);
+        let assignments = [(first.member_id.as_str(), "p0"), (second.member_id.as_str(), "p1")];
+        let leader = coordinator.sync_group(&sync_body(&first.member_id, 2, &assignments));
+        assert_eq!(&leader.assignment[..], b"p0");
+        let follower = follower.join().unwrap();
+        assert_eq!((follower.error_code, &follower.assignment[..]), (error_code::NONE, &b"p1"[..]));
+        assert_eq!(follower.protocol_name.as_deref(), Some("roundrobin"));
+        assert_eq!(coordinator.group_state("group"), Some(GroupState::Stable));
+        assert_eq!(heartbeat(&coordinator, &second.member_id, 1), error_code::ILLEGAL_GENERATION);
+        assert_eq!(heartbeat(&coordinator, "unknown", 2), error_code::UNKNOWN_MEMBER_ID);
+
+        // a member that doesn't support any protocol in use can't join
+        let response = coordinator.join_group(&join_body(UNKNOWN_MEMBER_ID, &["sticky"]), CONTEXT);
+        assert_eq!(response.error_code, error_code::INCONSISTENT_GROUP_PROTOCOL);
+    }
+
+    #[test]
+    fn members_that_leave_or_stop_heartbeating_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(0));
+        let coordinator = coordinator(dir.path(), clock.clone());
+        let first = join_new(&coordinator, &["range"]);
+        coordinator.sync_group(&sync_body(&first.member_id, 1, &[]));
+
+        let second = thread::spawn({
+            let coordinator = coordinator.clone();
+            move || join_new(&coordinator, &["range"])
+        });
+        wait_for_delayed(&coordinator, 1);
+        // the first member leaving completes the join phase without it
+        let response = coordinator.leave_group(
+            "group",
+            &[
+                MemberIdentity {
+                    member_id: first.member_id.clone(),
+                    group_instance_id: None,
+                    reason: None,
+                },
+                MemberIdentity {
+                    member_id: "unknown".to_string(),
+                    group_instance_id: None,
+                    reason: None,
+                },
+            ],
+        );
+        let errors: Vec<_> = response.members.iter().map(|m| m.error_code).collect();
+        assert_eq!(errors, [error_code::NONE, error_code::UNKNOWN_MEMBER_ID]);
+        let second = second.join().unwrap();
+        assert_eq!((second.generation_id, second.leader.as_str()), (2, second.member_id.as_str()));
+        coordinator.sync_group(&sync_body(&second.member_id, 2, &[]));
+
+        clock.advance(9_000);
+        assert_eq!(heartbeat(&coordinator, &second.member_id, 2), error_code::NONE);
+        clock.advance(9_000);
+        coordinator.expire_members();
+        assert_eq!(coordinator.group_state("group"), Some(GroupState::Stable));
+        clock.advance(10_001);
+        coordinator.expire_members();
+        assert_eq!(coordinator.group_state("group"), Some(GroupState::Empty));
+        assert_eq!(heartbeat(&coordinator, &second.member_id, 2), error_code::UNKNOWN_MEMBER_ID);
+    }
+
+    fn commit(coordinator: &GroupCoordinator, member_id: &str, generation_id: i32, retention_time_ms: i64) -> Vec<i16> {
+        let body = OffsetCommitBody {
+            group_id: "group".to_string(),
+            generation_id_or_member_epoch: generation_id,
+            member_id: member_id.to_string(),
+            group_instance_id: None,
+            retention_time_ms,
+            topics: vec![OffsetCommitRequestTopic {
+                name: "orders".to_string(),
+                partitions: vec![OffsetCommitRequestPartition {
+                    partition_index: 0,
+                    committed_offset: 42,
+                    committed_leader_epoch: 0,
+                    committed_metadata: None,
+                }],
+            }],
+        };
+        let partitions: Vec<_> = body.topics[0].partitions.iter().map(|p| (TopicPartition::new("orders", 0), p)).collect();
+        coordinator.commit_offsets(&body, &partitions)
+    }
+
+    #[test]
+    fn offsets_are_committed_by_the_current_generation_and_expire_once_the_group_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(0));
+        let coordinator = coordinator(dir.path(), clock.clone());
+        let retention_ms = coordinator.offsets().config().retention_ms;
+        let committed = |coordinator: &GroupCoordinator| coordinator.fetch_offsets("group", None).unwrap();
+
+        let member = join_new(&coordinator, &["range"]);
+        assert_eq!(commit(&coordinator, &member.member_id, 1, -1), vec![error_code::REBALANCE_IN_PROGRESS]);
+        coordinator.sync_group(&sync_body(&member.member_id, 1, &[(&member.member_id, "orders-0")]));
+        assert_eq!(commit(&coordinator, &member.member_id, 0, -1), vec![error_code::ILLEGAL_GENERATION]);
+        assert_eq!(commit(&coordinator, "someone", 1, -1), vec![error_code::UNKNOWN_MEMBER_ID]);
+        assert_eq!(commit(&coordinator, &member.member_id, 1, -1), vec![error_code::NONE]);
+        assert_eq!(committed(&coordinator)[0].1.as_ref().map(|c| c.offset), Some(42));
+
+        // nothing expires while the group has members
+        clock.advance(retention_ms);
+        heartbeat(&coordinator, &member.member_id, 1);
+        coordinator.expire_offsets();
+        assert_eq!(committed(&coordinator).len(), 1);
+
+        coordinator.leave_group(
+            "group",
+            &[MemberIdentity {
+                member_id: member.member_id.clone(),
+                group_instance_id: None,
+                reason: None,
+            }],
+        );
+        clock.advance(retention_ms - 1);
+        coordinator.expire_offsets();
+        assert_eq!(committed(&coordinator).len(), 1);
+        clock.advance(1);
+        coordinator.expire_offsets();
+        assert!(committed(&coordinator).is_empty());
+        assert_eq!(coordinator.group_state("group"), None);
+
+        // a commit's own retention applies whatever the group does
+        assert_eq!(commit(&coordinator, "", NO_GENERATION_ID, 1_000), vec![error_code::NONE]);
+        clock.advance(1_000);
+        coordinator.expire_offsets();
+        assert!(committed(&coordinator).is_empty());
+    }
+
+    fn consumer_heartbeat(
+        coordinator: &GroupCoordinator,
+        member_id: &str,
+        member_epoch: i32,
+        owned: Option<&[i32]>,
+    ) -> ConsumerGroupHeartbeatResponse {
+        let topics = BTreeMap::from([(
+            "orders".to_string(),
+            TopicMetadata {
+                topic_id: KafUuid([1; 16]),
+                name: "orders".to_string(),
+                num_partitions: 4,
+            },
+        )]);
+        let body = ConsumerGroupHeartbeatBody {
+            group_id: "group".to_string(),
+            member_id: member_id.to_string(),
+            member_epoch,
+            rebalance_timeout_ms: if member_epoch == JOIN_GROUP_MEMBER_EPOCH { 30_000 } else { -1 },
+            subscribed_topic_names: (member_epoch == JOIN_GROUP_MEMBER_EPOCH).then(|| vec!["orders".to_string()]),
+            topic_partitions: owned.map(|partitions| {
+                vec![TopicPartitions {
+                    topic_id: KafUuid([1; 16]),
+                    partitions: partitions.to_vec(),
+                }]
+            }),
+            ..Default::default()
+        };
+        let context = ConsumerContext {
+            client_id: "consumer",
+            client_host: "/127.0.0.1",
+            topics: &topics,
+        };
+        coordinator.consumer_group_heartbeat(&body, context)
+    }
+
+    fn assigned(response: &ConsumerGroupHeartbeatResponse) -> Option<Vec<i32>> {
+        response.assignment.as_ref().map(|topics| topics.iter().flat_map(|topic| topic.partitions.clone()).collect())
+    }
+
+    #[test]
+    fn consumer_group_members_revoke_partitions_before_others_get_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(0));
+        let coordinator = coordinator(dir.path(), clock.clone());
+
+        let a = consumer_heartbeat(&coordinator, "a", 0, None);
+        assert_eq!((a.error_code, a.member_epoch, assigned(&a)), (error_code::NONE, 1, Some(vec![0, 1, 2, 3])));
+        assert_eq!(coordinator.consumer_group_state("group"), Some(ConsumerGroupState::Stable));
+
+        // b joins at epoch 2 but gets nothing until a revoked half of its partitions
+        let b = consumer_heartbeat(&coordinator, "b", 0, None);
+        assert_eq!((b.member_epoch, assigned(&b)), (2, Some(vec![])));
+        let a = consumer_heartbeat(&coordinator, "a", 1, Some(&[0, 1, 2, 3]));
+        assert_eq!((a.member_epoch, assigned(&a)), (1, Some(vec![0, 1])));
+        assert_eq!(commit(&coordinator, "a", 1, -1), vec![error_code::NONE]);
+        assert_eq!(commit(&coordinator, "a", 2, -1), vec![error_code::STALE_MEMBER_EPOCH]);
+        let b = consumer_heartbeat(&coordinator, "b", 2, Some(&[]));
+        assert_eq!((b.member_epoch, assigned(&b)), (2, None));
+        assert_eq!(coordinator.consumer_group_state("group"), Some(ConsumerGroupState::Reconciling));
+
+        let a = consumer_heartbeat(&coordinator, "a", 1, Some(&[0, 1]));
+        assert_eq!((a.member_epoch, assigned(&a)), (2, None));
+        let b = consumer_heartbeat(&coordinator, "b", 2, Some(&[]));
+        assert_eq!(assigned(&b), Some(vec![2, 3]));
+        assert_eq!(coordinator.consumer_group_state("group"), Some(ConsumerGroupState::Stable));
+
+        // a member that missed the bump may still use its previous epoch
+        assert_eq!(consumer_heartbeat(&coordinator, "a", 1, Some(&[0, 1])).error_code, error_code::NONE);
+        assert_eq!(consumer_heartbeat(&coordinator, "a", 3, Some(&[0, 1])).error_code, error_code::FENCED_MEMBER_EPOCH);
+        assert_eq!(consumer_heartbeat(&coordinator, "c", 1, None).error_code, error_code::UNKNOWN_MEMBER_ID);
+        // classic members can't join a group of the consumer protocol
+        let response = coordinator.join_group(&join_body(UNKNOWN_MEMBER_ID, &["range"]), CONTEXT);
+        assert_eq!(response.error_code, error_code::INCONSISTENT_GROUP_PROTOCOL);
+        let listed = coordinator.list_groups(&[], &["consumer".to_string()]);
+        assert_eq!(listed.groups.iter().map(|group| group.group_state.as_str()).collect::<Vec<_>>(), vec!["Stable"]);
+
+        // b leaving frees its partitions for a
+        let b = consumer_heartbeat(&coordinator, "b", LEAVE_GROUP_MEMBER_EPOCH, None);
+        assert_eq!((b.error_code, b.member_epoch), (error_code::NONE, LEAVE_GROUP_MEMBER_EPOCH));
+        let a = consumer_heartbeat(&coordinator, "a", 2, Some(&[0, 1]));
+        assert_eq!((a.member_epoch, assigned(&a)), (3, Some(vec![0, 1, 2, 3])));
+
+        clock.advance(DEFAULT_GROUP_CONSUMER_SESSION_TIMEOUT_MS as i64 + 1);
+        coordinator.expire_members();
+        assert_eq!(coordinator.consumer_group_state("group"), Some(ConsumerGroupState::Empty));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod assignor;
+pub mod consumer_group;
+pub mod group;
+pub mod group_coordinator;
+pub mod offset_manager;
+pub mod records;
@@ -0,0 +1,285 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{
+    common::{config::BrokerConfig, topic_partition::TopicPartition},
+    coordinator::records::{offsets_partition_for, OffsetAndMetadata, OffsetsRecordKey},
+    log::{partition_log::FetchIsolation, LogError, LogManager},
+    metadata::CONSUMER_OFFSETS_TOPIC,
+    records::{MemoryRecords, Record, RecordBatchBuilder, RecordError},
+    utils::clock::Clock,
+};
+
+pub const DEFAULT_OFFSETS_TOPIC_NUM_PARTITIONS: i32 = 50;
+pub const DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR: i16 = 1;
+pub const DEFAULT_OFFSETS_TOPIC_SEGMENT_BYTES: i64 = 104_857_600;
+pub const DEFAULT_OFFSETS_RETENTION_MINUTES: i64 = 10_080;
+pub const DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS: i64 = 600_000;
+pub const DEFAULT_OFFSET_METADATA_MAX_BYTES: usize = 4096;
+
+/// How much of a __consumer_offsets partition is read at a time when loading it
+const LOAD_FETCH_BYTES: u64 = 1024 * 1024;
+
+/// The `offsets.*` broker configs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetConfig {
+    pub num_partitions: i32,
+    pub replication_factor: i16,
+    pub segment_bytes: i64,
+    pub retention_ms: i64,
+    pub retention_check_interval_ms: i64,
+    /// longest metadata a commit may carry
+    pub max_metadata_size: usize,
+}
+
+impl Default for OffsetConfig {
+    fn default() -> Self {
+        OffsetConfig {
+            num_partitions: DEFAULT_OFFSETS_TOPIC_NUM_PARTITIONS,
+            replication_factor: DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR,
+            segment_bytes: DEFAULT_OFFSETS_TOPIC_SEGMENT_BYTES,
+            retention_ms: DEFAULT_OFFSETS_RETENTION_MINUTES * 60_000,
+            retention_check_interval_ms: DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS,
+            max_metadata_size: DEFAULT_OFFSET_METADATA_MAX_BYTES,
+        }
+    }
+}
+
+impl OffsetConfig {
+    pub fn from_broker_config(config: &BrokerConfig) -> Self {
+        OffsetConfig {
+            num_partitions: config
+                .get_i64("offsets.topic.num.partitions")
+                .map_or(DEFAULT_OFFSETS_TOPIC_NUM_PARTITIONS, |n| n.clamp(1, i32::MAX as i64) as i32),
+            replication_factor: config
+                .get_i64("offsets.topic.replication.factor")
+                .map_or(DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR, |n| n.clamp(1, i16::MAX as i64) as i16),
+            segment_bytes: config.get_i64("offsets.topic.segment.bytes").unwrap_or(DEFAULT_OFFSETS_TOPIC_SEGMENT_BYTES),
+            retention_ms: config.get_i64("offsets.retention.minutes").unwrap_or(DEFAULT_OFFSETS_RETENTION_MINUTES) * 60_000,
+            retention_check_interval_ms: config
+                .get_i64("offsets.retention.check.interval.ms")
+                .unwrap_or(DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS),
+            max_metadata_size: config
+                .get_i64("offset.metadata.max.bytes")
+                .map_or(DEFAULT_OFFSET_METADATA_MAX_BYTES, |n| n.max(0) as usize),
+        }
+    }
+
+    /// The topic configs __consumer_offsets is created with. It's compacted,
+    /// so only the latest commit of each group and partition is kept.
+    pub fn topic_configs(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("cleanup.policy".to_string(), "compact".to_string()),
+            ("segment.bytes".to_string(), self.segment_bytes.to_string()),
+            ("compression.type".to_string(), "producer".to_string()),
+        ])
+    }
+}
+
+/// The committed offsets of every group, kept in memory and written to the
+/// __consumer_offsets log, which is replayed into the cache on startup.
+#[derive(Debug)]
+pub struct OffsetManager {
+    config: OffsetConfig,
+    /// of __consumer_offsets, the configured count until the topic exists
+    partition_count: AtomicI32,
+    log_manager: Arc<LogManager>,
+    offsets: Mutex<HashMap<String, BTreeMap<TopicPartition, OffsetAndMetadata>>>,
+    /// __consumer_offsets partitions not replayed into the cache yet
+    loading: Mutex<HashSet<i32>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl OffsetManager {
+    pub fn new(config: OffsetConfig, log_manager: Arc<LogManager>) -> Self {
+        OffsetManager {
+            partition_count: AtomicI32::new(config.num_partitions),
+            config,
+            clock: log_manager.clock(),
+            log_manager,
+            offsets: Mutex::new(HashMap::new()),
+            loading: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn config(&self) -> &OffsetConfig {
+        &self.config
+    }
+
+    /// Groups are spread over the partitions __consumer_offsets has, which
+    /// may not be what offsets.topic.num.partitions says now
+    pub fn set_partition_count(&self, partition_count: i32) {
+        self.partition_count.store(partition_count, Ordering::Relaxed);
+    }
+
+    pub fn partition_for(&self, group_id: &str) -> i32 {
+        offsets_partition_for(group_id, self.partition_count.load(Ordering::Relaxed))
+    }
+
+    /// Whether the offsets of the group are still being loaded
+    pub fn is_loading(&self, group_id: &str) -> bool {
+        self.loading.lock().unwrap().contains(&self.partition_for(group_id))
+    }
+
+    /// Whether any partition is still being loaded, some groups may be missing
+    pub fn is_loading_any(&self) -> bool {
+        !self.loading.lock().unwrap().is_empty()
+    }
+
+    /// Mark partitions found on startup as not loaded yet, until `load` replays them
+    pub fn schedule_load(&self, partitions: impl IntoIterator<Item = i32>) {
+        self.loading.lock().unwrap().extend(partitions);
+    }
+
+    /// Replay the partitions marked for loading into the cache. A partition
+    /// that fails to load stays marked, its groups keep getting
+    /// COORDINATOR_LOAD_IN_PROGRESS.
+    pub fn load(&self) {
+        let mut partitions: Vec<i32> = self.loading.lock().unwrap().iter().copied().collect();
+        partitions.sort();
+        for partition in partitions {
+            let start_ms = self.clock.now_ms();
+            match self.load_partition(partition) {
+                Ok(count) => {
+                    self.loading.lock().unwrap().remove(&partition);
+                    println!(
+                        "loaded {} offsets of {}-{} in {} ms",
+                        count,
+                        CONSUMER_OFFSETS_TOPIC,
+                        partition,
+                        self.clock.now_ms() - start_ms
+                    );
+                }
+                Err(e) => println!("error while loading {}-{}: {}", CONSUMER_OFFSETS_TOPIC, partition, e),
+            }
+        }
+    }
+
+    fn load_partition(&self, partition: i32) -> Result<usize, LogError> {
+        let Some(log) = self.log_manager.get_log(&TopicPartition::new(CONSUMER_OFFSETS_TOPIC, partition)) else {
+            return Ok(0);
+        };
+        let mut loaded: HashMap<String, BTreeMap<TopicPartition, OffsetAndMetadata>> = HashMap::new();
+        let mut offset = log.log_start_offset();
+        while offset < log.log_end_offset() {
+            let read = log.read(offset, LOAD_FETCH_BYTES, FetchIsolation::LogEnd, true)?;
+            let start = offset;
+            for batch in read.records.batches() {
+                let batch = batch?;
+                offset = batch.next_offset();
+                if batch.is_control_batch() {
+                    continue;
+                }
+                for record in batch.records()? {
+                    let record = record?;
+                    let Some(key) = &record.key else {
+                        continue;
+                    };
+                    let OffsetsRecordKey::OffsetCommit { group_id, topic_partition } =
+                        OffsetsRecordKey::read(key).map_err(RecordError::from)?
+                    else {
+                        continue;
+                    };
+                    match &record.value {
+                        Some(value) => {
+                            let committed = OffsetAndMetadata::read(value).map_err(RecordError::from)?;
+                            loaded.entry(group_id).or_default().insert(topic_partition, committed);
+                        }
+                        None => {
+                            if let Some(offsets) = loaded.get_mut(&group_id) {
+                                offsets.remove(&topic_partition);
+                            }
+                        }
+                    }
+                }
+            }
+            if offset == start {
+                break;
+            }
+        }
+        loaded.retain(|_, offsets| !offsets.is_empty());
+        let count = loaded.values().map(BTreeMap::len).sum();
+        self.offsets.lock().unwrap().extend(loaded);
+        Ok(count)
+    }
+
+    /// Groups with committed offsets
+    pub fn groups(&self) -> Vec<String> {
+        self.offsets.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn has_offsets(&self, group_id: &str) -> bool {
+        self.offsets.lock().unwrap().contains_key(group_id)
+    }
+
+    /// The group's committed offsets of `partitions`, or all of them
+    pub fn fetch(&self, group_id: &str, partitions: Option<&[TopicPartition]>) -> Vec<(TopicPartition, Option<OffsetAndMetadata>)> {
+        let cache = self.offsets.lock().unwrap();
+        let offsets = cache.get(group_id);
+        match partitions {
+            Some(partitions) => partitions
+                .iter()
+                .map(|tp| (tp.clone(), offsets.and_then(|offsets| offsets.get(tp)).cloned()))
+                .collect(),
+            None => offsets
+                .into_iter()
+                .flatten()
+                .map(|(tp, committed)| (tp.clone(), Some(committed.clone())))
+                .collect(),
+        }
+    }
+
+    /// Write the commits to the group's __consumer_offsets partition, then
+    /// make them visible
+    pub fn store(&self, group_id: &str, commits: Vec<(TopicPartition, OffsetAndMetadata)>) -> Result<(), LogError> {
+        let records = commits.iter().map(|(tp, committed)| (tp, Some(committed))).collect::<Vec<_>>();
+        self.append(group_id, &records)?;
+        self.offsets.lock().unwrap().entry(group_id.to_string()).or_default().extend(commits);
+        Ok(())
+    }
+
+    /// Remove the group's offsets that `is_expired` says are, with a
+    /// tombstone each. Returns how many were removed.
+    pub fn remove_offsets(&self, group_id: &str, is_expired: impl Fn(&TopicPartition, &OffsetAndMetadata) -> bool) -> Result<usize, LogError> {
+        let expired: Vec<TopicPartition> = match self.offsets.lock().unwrap().get(group_id) {
+            Some(offsets) => offsets.iter().filter(|(tp, committed)| is_expired(tp, committed)).map(|(tp, _)| tp.clone()).collect(),
+            None => return Ok(0),
+        };
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        let tombstones: Vec<_> = expired.iter().map(|tp| (tp, None)).collect();
+        self.append(group_id, &tombstones)?;
+        let mut cache = self.offsets.lock().unwrap();
+        if let Some(offsets) = cache.get_mut(group_id) {
+            expired.iter().for_each(|tp| {
+                offsets.remove(tp);
+            });
+            if offsets.is_empty() {
+                cache.remove(group_id);
+            }
+        }
+        Ok(expired.len())
+    }
+
+    fn append(&self, group_id: &str, records: &[(&TopicPartition, Option<&OffsetAndMetadata>)]) -> Result<(), LogError> {
+        let tp = TopicPartition::new(CONSUMER_OFFSETS_TOPIC, self.partition_for(group_id));
+        let log = self.log_manager.get_log(&tp).ok_or(LogError::LogDeleted(tp))?;
+        let now = self.clock.now_ms();
+        let mut builder = RecordBatchBuilder::new(0);
+        for (topic_partition, committed) in records {
+            let key = OffsetsRecordKey::OffsetCommit {
+                group_id: group_id.to_string(),
+                topic_partition: (*topic_partition).clone(),
+            };
+            builder.append(&Record::new(now, Some(key.to_bytes()), committed.map(|c| c.to_value())));
+        }
+        log.append_as_leader(&MemoryRecords::from_batches(&[builder.build()?]))?;
+        Ok(())
+    }
+}
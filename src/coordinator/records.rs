@@ -0,0 +1,182 @@
+use bytes::Bytes;
+
+use crate::{
+    common::{
+        codec::{read_string, write_string},
+        topic_partition::TopicPartition,
+        EncodeToBytes, EncodingError,
+    },
+    utils::parse_primitive_types::{read_i16_be, read_i32_be, read_i64_be},
+};
+
+/*
+* Records of the __consumer_offsets log. Keys start with their version:
+*   OffsetCommitKey (version 0-1) => group topic partition
+*   GroupMetadataKey (version 2) => group
+*
+* OffsetCommitValue (version 0) => offset metadata commit_timestamp
+*                   (version 1) => offset metadata commit_timestamp expire_timestamp
+*                   (version 2) => offset metadata commit_timestamp
+*                   (version 3) => offset leader_epoch metadata commit_timestamp
+*
+* A null value removes the key, compaction eventually drops both.
+*/
+const OFFSET_COMMIT_KEY_VERSION: i16 = 1;
+const GROUP_METADATA_KEY_VERSION: i16 = 2;
+
+const OFFSET_COMMIT_VALUE_VERSION: i16 = 3;
+/// the version that has expire_timestamp, for commits with their own retention
+const OFFSET_COMMIT_VALUE_VERSION_WITH_EXPIRY: i16 = 1;
+
+/// committed_leader_epoch of commits that don't know it
+pub const NO_LEADER_EPOCH: i32 = -1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OffsetsRecordKey {
+    OffsetCommit { group_id: String, topic_partition: TopicPartition },
+    /// the group's membership, which we don't persist
+    GroupMetadata { group_id: String },
+}
+
+impl OffsetsRecordKey {
+    pub fn read(key: &[u8]) -> Result<Self, EncodingError> {
+        let offset = &mut 0;
+        let version = read_i16_be(key, offset)?;
+        let group_id = read_string(key, offset, false)?;
+        match version {
+            0 | OFFSET_COMMIT_KEY_VERSION => {
+                let topic = read_string(key, offset, false)?;
+                let partition = read_i32_be(key, offset)?;
+                Ok(OffsetsRecordKey::OffsetCommit {
+                    group_id,
+                    topic_partition: TopicPartition::new(&topic, partition),
+                })
+            }
+            GROUP_METADATA_KEY_VERSION => Ok(OffsetsRecordKey::GroupMetadata { group_id }),
+            version => Err(EncodingError::UnsupportedVersion(version as i64)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = vec![];
+        match self {
+            OffsetsRecordKey::OffsetCommit { group_id, topic_partition } => {
+                buf.extend(OFFSET_COMMIT_KEY_VERSION.encode_to_bytes());
+                write_string(&mut buf, group_id, false);
+                write_string(&mut buf, &topic_partition.topic, false);
+                buf.extend(topic_partition.partition.encode_to_bytes());
+            }
+            OffsetsRecordKey::GroupMetadata { group_id } => {
+                buf.extend(GROUP_METADATA_KEY_VERSION.encode_to_bytes());
+                write_string(&mut buf, group_id, false);
+            }
+        }
+        Bytes::from(buf)
+    }
+}
+
+/// A committed offset, the value of an OffsetCommitKey
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetAndMetadata {
+    pub offset: i64,
+    pub leader_epoch: i32,
+    pub metadata: String,
+    pub commit_timestamp_ms: i64,
+    /// set by commits of OffsetCommit v2-4 with a retention time, otherwise
+    /// the offset expires offsets.retention.minutes after the group emptied
+    pub expire_timestamp_ms: Option<i64>,
+}
+
+impl OffsetAndMetadata {
+    pub fn read(value: &[u8]) -> Result<Self, EncodingError> {
+        let offset = &mut 0;
+        let version = read_i16_be(value, offset)?;
+        if !(0..=OFFSET_COMMIT_VALUE_VERSION).contains(&version) {
+            return Err(EncodingError::UnsupportedVersion(version as i64));
+        }
+        let committed_offset = read_i64_be(value, offset)?;
+        let leader_epoch = if version >= 3 { read_i32_be(value, offset)? } else { NO_LEADER_EPOCH };
+        let metadata = read_string(value, offset, false)?;
+        let commit_timestamp_ms = read_i64_be(value, offset)?;
+        let expire_timestamp_ms = if version == OFFSET_COMMIT_VALUE_VERSION_WITH_EXPIRY {
+            Some(read_i64_be(value, offset)?)
+        } else {
+            None
+        };
+        Ok(OffsetAndMetadata {
+            offset: committed_offset,
+            leader_epoch,
+            metadata,
+            commit_timestamp_ms,
+            expire_timestamp_ms,
+        })
+    }
+
+    pub fn to_value(&self) -> Bytes {
+        let mut buf = vec![];
+        match self.expire_timestamp_ms {
+            // the only version with an expiry has no leader epoch
+            Some(expire_timestamp_ms) => {
+                buf.extend(OFFSET_COMMIT_VALUE_VERSION_WITH_EXPIRY.encode_to_bytes());
+                buf.extend(self.offset.encode_to_bytes());
+                write_string(&mut buf, &self.metadata, false);
+                buf.extend(self.commit_timestamp_ms.encode_to_bytes());
+                buf.extend(expire_timestamp_ms.encode_to_bytes());
+            }
+            None => {
+                buf.extend(OFFSET_COMMIT_VALUE_VERSION.encode_to_bytes());
+                buf.extend(self.offset.encode_to_bytes());
+                buf.extend(self.leader_epoch.encode_to_bytes());
+                write_string(&mut buf, &self.metadata, false);
+                buf.extend(self.commit_timestamp_ms.encode_to_bytes());
+            }
+        }
+        Bytes::from(buf)
+    }
+}
+
+/// The __consumer_offsets partition of a group, like Kafka's: the group id's
+/// Java hash code, made non-negative, modulo the partition count
+pub fn offsets_partition_for(group_id: &str, num_partitions: i32) -> i32 {
+    let hash = group_id.encode_utf16().fold(0i32, |hash, c| hash.wrapping_mul(31).wrapping_add(c as i32));
+    (hash & 0x7fff_ffff) % num_partitions.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_and_values_round_trip_in_kafkas_format() {
+        let key = OffsetsRecordKey::OffsetCommit {
+            group_id: "orders-app".to_string(),
+            topic_partition: TopicPartition::new("orders", 3),
+        };
+        let bytes = key.to_bytes();
+        assert_eq!(&bytes[..2], &[0, 1]);
+        assert_eq!(OffsetsRecordKey::read(&bytes).unwrap(), key);
+        let group_key = OffsetsRecordKey::GroupMetadata { group_id: "orders-app".to_string() };
+        assert_eq!(OffsetsRecordKey::read(&group_key.to_bytes()).unwrap(), group_key);
+
+        let mut value = OffsetAndMetadata {
+            offset: 42,
+            leader_epoch: 7,
+            metadata: "meta".to_string(),
+            commit_timestamp_ms: 1_000,
+            expire_timestamp_ms: None,
+        };
+        assert_eq!(OffsetAndMetadata::read(&value.to_value()).unwrap(), value);
+        value.expire_timestamp_ms = Some(5_000);
+        let read = OffsetAndMetadata::read(&value.to_value()).unwrap();
+        assert_eq!((read.expire_timestamp_ms, read.leader_epoch), (Some(5_000), NO_LEADER_EPOCH));
+    }
+
+    #[test]
+    fn groups_are_partitioned_by_their_java_hash_code() {
+        // "orders-app".hashCode() in Java is 772757305
+        assert_eq!(offsets_partition_for("orders-app", 50), 772757305 % 50);
+        assert_eq!(offsets_partition_for("", 50), 0);
+        // -736326230, with its sign bit cleared
+        assert_eq!(offsets_partition_for("a negative hash code, probably", 50), (-736326230i32 & 0x7fff_ffff) % 50);
+    }
+}
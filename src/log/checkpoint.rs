@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::common::topic_partition::TopicPartition;
+
+pub const RECOVERY_POINT_CHECKPOINT_FILE: &str = "recovery-point-offset-checkpoint";
+pub const LOG_START_OFFSET_CHECKPOINT_FILE: &str = "log-start-offset-checkpoint";
+
+const CHECKPOINT_VERSION: i32 = 0;
+
+/// An offset per partition, kept in a text file at the root of a log dir in
+/// Kafka's format:
+///
+/// ```text
+/// 0               <- version
+/// 2               <- number of entries
+/// orders 0 1234   <- topic partition offset
+/// orders 1 99
+/// ```
+#[derive(Debug, Clone)]
+pub struct OffsetCheckpointFile {
+    path: PathBuf,
+}
+
+fn invalid(path: &Path, line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed line in checkpoint file {}: {:?}", path.display(), line),
+    )
+}
+
+impl OffsetCheckpointFile {
+    pub fn new(log_dir: &Path, name: &str) -> Self {
+        OffsetCheckpointFile {
+            path: log_dir.join(name),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The checkpointed offsets, empty if the file doesn't exist yet
+    pub fn read(&self) -> io::Result<HashMap<TopicPartition, i64>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut lines = contents.lines();
+        let version = lines.next().unwrap_or_default();
+        if version.trim().parse::<i32>().ok() != Some(CHECKPOINT_VERSION) {
+            return Err(invalid(&self.path, version));
+        }
+        let count = lines.next().unwrap_or_default();
+        let count: usize = count.trim().parse().map_err(|_| invalid(&self.path, count))?;
+
+        let mut offsets = HashMap::with_capacity(count);
+        for line in lines.by_ref().take(count) {
+            let mut fields = line.split_whitespace();
+            let (Some(topic), Some(partition), Some(offset), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid(&self.path, line));
+            };
+            let partition = partition.parse().map_err(|_| invalid(&self.path, line))?;
+            let offset = offset.parse().map_err(|_| invalid(&self.path, line))?;
+            offsets.insert(TopicPartition::new(topic, partition), offset);
+        }
+        if offsets.len() != count {
+            return Err(invalid(&self.path, "<missing entries>"));
+        }
+        Ok(offsets)
+    }
+
+    /// Replace the checkpoint: written to a temp file first and renamed over
+    /// the old one, so a crash leaves either the old or the new offsets.
+    pub fn write(&self, offsets: &HashMap<TopicPartition, i64>) -> io::Result<()> {
+        let mut entries: Vec<_> = offsets.iter().collect();
+        entries.sort();
+
+        let mut contents = format!("{}\n{}\n", CHECKPOINT_VERSION, entries.len());
+        for (tp, offset) in entries {
+            contents.push_str(&format!("{} {} {}\n", tp.topic, tp.partition, offset));
+        }
+
+        let tmp = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        if let Some(dir) = self.path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_offsets() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = OffsetCheckpointFile::new(dir.path(), RECOVERY_POINT_CHECKPOINT_FILE);
+        assert!(checkpoint.read().unwrap().is_empty());
+
+        let offsets = HashMap::from([
+            (TopicPartition::new("orders", 0), 1234),
+            (TopicPartition::new("my-topic", 3), 7),
+        ]);
+        checkpoint.write(&offsets).unwrap();
+        assert_eq!(
+            fs::read_to_string(checkpoint.path()).unwrap(),
+            "0\n2\nmy-topic 3 7\norders 0 1234\n"
+        );
+        assert_eq!(checkpoint.read().unwrap(), offsets);
+
+        fs::write(checkpoint.path(), "0\n2\norders 0 1234\n").unwrap();
+        assert!(checkpoint.read().is_err());
+    }
+}
@@ -0,0 +1,633 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    common::topic_partition::TopicPartition,
+    log::{
+        checkpoint::OffsetCheckpointFile, partition_log::LogState, segment_file, LogConfig, LogError, LogSegment,
+        PartitionLog, CLEANED_FILE_SUFFIX, LOG_FILE_SUFFIX, SWAP_FILE_SUFFIX,
+    },
+    records::{
+        control::control_record_type, ControlRecordType, LogEntry, MemoryRecords, RecordBatch, RecordBatchBuilder,
+    },
+};
+
+pub const CLEANER_OFFSET_CHECKPOINT_FILE: &str = "cleaner-offset-checkpoint";
+
+/// What one cleaning pass did to a log
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CleanerStats {
+    pub segments_cleaned: usize,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub records_read: usize,
+    pub records_retained: usize,
+    /// offsets below this are now compacted
+    pub end_offset: i64,
+}
+
+/// A transaction found in the log: the offset of its first batch and of its
+/// end marker
+#[derive(Debug, Clone, Copy)]
+struct CompletedTxn {
+    first_offset: i64,
+    marker_offset: i64,
+    aborted: bool,
+}
+
+/// Where the transactions in a log start and end, so the cleaner can drop
+/// aborted data and knows not to touch transactions still in progress
+#[derive(Debug, Default)]
+struct TxnIndex {
+    completed: HashMap<i64, Vec<CompletedTxn>>,
+    first_unstable_offset: Option<i64>,
+}
+
+impl TxnIndex {
+    fn build(state: &LogState) -> Result<Self, LogError> {
+        let mut index = TxnIndex::default();
+        let mut ongoing: HashMap<i64, i64> = HashMap::new();
+
+        for segment in state.segments.values() {
+            for batch in read_batches(segment) {
+                let LogEntry::Batch(batch) = batch? else {
+                    continue;
+                };
+                if !batch.is_transactional() {
+                    continue;
+                }
+                let producer_id = batch.producer_id();
+                if batch.is_control_batch() {
+                    let first_offset = ongoing.remove(&producer_id).unwrap_or(batch.base_offset());
+                    index.completed.entry(producer_id).or_default().push(CompletedTxn {
+                        first_offset,
+                        marker_offset: batch.base_offset(),
+                        aborted: marker_type(&batch)? == Some(ControlRecordType::Abort),
+                    });
+                } else {
+                    ongoing.entry(producer_id).or_insert(batch.base_offset());
+                }
+            }
+        }
+
+        index.first_unstable_offset = ongoing.values().min().copied();
+        Ok(index)
+    }
+
+    fn is_aborted(&self, batch: &RecordBatch) -> bool {
+        self.completed.get(&batch.producer_id()).is_some_and(|txns| {
+            txns.iter()
+                .any(|txn| txn.aborted && txn.first_offset <= batch.base_offset() && batch.base_offset() < txn.marker_offset)
+        })
+    }
+}
+
+fn marker_type(batch: &RecordBatch) -> Result<Option<ControlRecordType>, LogError> {
+    Ok(match batch.records()?.next() {
+        Some(record) => control_record_type(&record?),
+        None => None,
+    })
+}
+
+/// Every batch of a closed segment, read one at a time
+fn read_batches(segment: &LogSegment) -> impl Iterator<Item = Result<LogEntry, LogError>> + '_ {
+    segment.batches_from(0).map(|batch| {
+        let batch = batch?;
+        let bytes = segment.read_bytes(batch.position, batch.size)?;
+        match MemoryRecords::new(bytes).entries().next() {
+            Some(entry) => Ok(entry?),
+            None => Err(LogError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated batch"))),
+        }
+    })
+}
+
+/// Compacts logs with `cleanup.policy=compact`: rewrites their closed
+/// segments keeping only the latest record for each key.
+///
+/// A pass looks at the segments below the active one, the high watermark and
+/// any transaction still in progress. The part not compacted yet (the dirty
+/// section, tracked per partition in `cleaner-offset-checkpoint`) decides
+/// which record is the latest for each key, then every segment from the log
+/// start is rewritten without the records those replace. Tombstones and
+/// transaction markers are kept for `delete.retention.ms` after the pass that
+/// first finds them removable, so consumers that are behind still see them.
+#[derive(Debug)]
+pub struct LogCleaner {
+    log_dirs: Vec<PathBuf>,
+    /// first offset that hasn't been compacted, per partition
+    checkpoints: Mutex<HashMap<TopicPartition, i64>>,
+}
+
+impl LogCleaner {
+    pub fn new(log_dirs: &[PathBuf]) -> Self {
+        let mut checkpoints = HashMap::new();
+        for log_dir in log_dirs {
+            match OffsetCheckpointFile::new(log_dir, CLEANER_OFFSET_CHECKPOINT_FILE).read() {
+                Ok(offsets) => checkpoints.extend(offsets),
+                Err(e) => println!("{}, logs in {} will be compacted from the start", e, log_dir.display()),
+            }
+        }
+        LogCleaner {
+            log_dirs: log_dirs.to_vec(),
+            checkpoints: Mutex::new(checkpoints),
+        }
+    }
+
+    pub fn first_dirty_offset(&self, tp: &TopicPartition) -> i64 {
+        self.checkpoints.lock().unwrap().get(tp).copied().unwrap_or(0)
+    }
+
+    /// Compact every log that's dirty enough, then checkpoint how far each got
+    pub fn clean_logs(&self, logs: &[Arc<PartitionLog>]) -> Result<Vec<(TopicPartition, CleanerStats)>, LogError> {
+        let mut cleaned = vec![];
+        for log in logs {
+            match self.clean_log(log) {
+                Ok(Some(stats)) => cleaned.push((log.topic_partition().clone(), stats)),
+                Ok(None) => {}
+                Err(e) => println!("{}: error while compacting: {}", log.topic_partition(), e),
+            }
+        }
+        if !cleaned.is_empty() {
+            self.write_checkpoints(logs)?;
+        }
+        Ok(cleaned)
+    }
+
+    fn write_checkpoints(&self, logs: &[Arc<PartitionLog>]) -> Result<(), LogError> {
+        let checkpoints = self.checkpoints.lock().unwrap();
+        for log_dir in &self.log_dirs {
+            let offsets = logs
+                .iter()
+                .filter(|log| log.dir().parent() == Some(log_dir.as_path()))
+                .filter_map(|log| {
+                    let tp = log.topic_partition();
+                    checkpoints.get(tp).map(|offset| (tp.clone(), *offset))
+                })
+                .collect();
+            OffsetCheckpointFile::new(log_dir, CLEANER_OFFSET_CHECKPOINT_FILE).write(&offsets)?;
+        }
+        Ok(())
+    }
+
+    /// Compact one log if it's compacted and at least min.cleanable.dirty.ratio
+    /// of it is dirty. Appends to the log wait for the pass to finish.
+    pub fn clean_log(&self, log: &PartitionLog) -> Result<Option<CleanerStats>, LogError> {
+        let config = log.config();
+        if !config.cleanup_policy.compact {
+            return Ok(None);
+        }
+        let now = log.clock().now_ms();
+        let mut state = log.state();
+        if state.deleted {
+            return Ok(None);
+        }
+        let txns = TxnIndex::build(&state)?;
+
+        // closed segments entirely below the high watermark and the first unstable offset
+        let cleanable_end = txns.first_unstable_offset.unwrap_or(i64::MAX).min(state.high_watermark);
+        let bases: Vec<i64> = state.segments.keys().copied().collect();
+        let cleanable: Vec<i64> = bases
+            .windows(2)
+            .take_while(|pair| pair[1] <= cleanable_end)
+            .map(|pair| pair[0])
+            .collect();
+        if cleanable.is_empty() {
+            return Ok(None);
+        }
+        let end_offset = bases[cleanable.len()];
+
+        let first_dirty = self.first_dirty_offset(log.topic_partition()).max(state.log_start_offset);
+        let (mut clean_bytes, mut dirty_bytes) = (0, 0);
+        for (i, base) in cleanable.iter().enumerate() {
+            let size = state.segments[base].size();
+            if bases[i + 1] <= first_dirty {
+                clean_bytes += size;
+            } else {
+                dirty_bytes += size;
+            }
+        }
+        if dirty_bytes == 0 || (dirty_bytes as f64 / (clean_bytes + dirty_bytes) as f64) < config.min_cleanable_dirty_ratio
+        {
+            return Ok(None);
+        }
+
+        let offset_map = build_offset_map(&state, &cleanable, first_dirty, &txns)?;
+
+        let mut stats = CleanerStats {
+            end_offset,
+            ..Default::default()
+        };
+        let mut filter = RecordFilter {
+            offset_map: &offset_map,
+            txns: &txns,
+            retained_in_txn: HashMap::new(),
+            changed: false,
+            now,
+            delete_retention_ms: config.delete_retention_ms,
+        };
+        for base in &cleanable {
+            let segment = &state.segments[base];
+            filter.changed = false;
+            let cleaned = filter.clean_segment(segment, &mut stats)?;
+            stats.segments_cleaned += 1;
+            stats.bytes_read += segment.size();
+            stats.bytes_written += cleaned.len() as u64;
+            if filter.changed {
+                replace_segment(log, &mut state, *base, &cleaned, &config, now)?;
+            }
+        }
+
+        self.checkpoints.lock().unwrap().insert(log.topic_partition().clone(), end_offset);
+        println!(
+            "{}: compacted {} segments up to offset {}, {} -> {} bytes",
+            log.topic_partition(),
+            stats.segments_cleaned,
+            end_offset,
+            stats.bytes_read,
+            stats.bytes_written
+        );
+        Ok(Some(stats))
+    }
+}
+
+/// Latest offset of every key in the dirty section, ignoring aborted data
+fn build_offset_map(
+    state: &LogState,
+    cleanable: &[i64],
+    first_dirty: i64,
+    txns: &TxnIndex,
+) -> Result<HashMap<Bytes, i64>, LogError> {
+    let mut offset_map = HashMap::new();
+    for (i, base) in cleanable.iter().enumerate() {
+        let next_base = cleanable.get(i + 1).copied().unwrap_or(i64::MAX);
+        if next_base <= first_dirty {
+            continue;
+        }
+        for entry in read_batches(&state.segments[base]) {
+            let records = match entry? {
+                LogEntry::Batch(batch) if batch.is_control_batch() || txns.is_aborted(&batch) => continue,
+                LogEntry::Batch(batch) => batch.records()?.collect::<Result<Vec<_>, _>>()?,
+                LogEntry::Legacy(legacy) => legacy.inner_records()?.iter().map(|r| r.to_record()).collect(),
+            };
+            for record in records {
+                if let Some(key) = record.key {
+                    offset_map.insert(key, record.offset);
+                }
+            }
+        }
+    }
+    Ok(offset_map)
+}
+
+struct RecordFilter<'a> {
+    offset_map: &'a HashMap<Bytes, i64>,
+    txns: &'a TxnIndex,
+    /// records kept so far in each producer's current transaction
+    retained_in_txn: HashMap<i64, usize>,
+    /// a batch of the segment being cleaned was dropped or rewritten
+    changed: bool,
+    now: i64,
+    delete_retention_ms: i64,
+}
+
+impl RecordFilter<'_> {
+    fn clean_segment(&mut self, segment: &LogSegment, stats: &mut CleanerStats) -> Result<BytesMut, LogError> {
+        let mut cleaned = BytesMut::with_capacity(segment.size() as usize);
+        for entry in read_batches(segment) {
+            match entry? {
+                // we only ever write v2 batches, older formats are left as they are
+                LogEntry::Legacy(legacy) => cleaned.extend_from_slice(legacy.as_bytes()),
+                LogEntry::Batch(batch) => {
+                    let original = batch.as_bytes().clone();
+                    let kept = match batch.is_control_batch() {
+                        true => self.clean_marker(batch)?,
+                        false => self.clean_batch(batch, stats)?,
+                    };
+                    match kept {
+                        Some(kept) => {
+                            self.changed |= *kept.as_bytes() != original;
+                            cleaned.extend_from_slice(kept.as_bytes());
+                        }
+                        None => self.changed = true,
+                    }
+                }
+            }
+        }
+        Ok(cleaned)
+    }
+
+    fn horizon_passed(&self, batch: &RecordBatch) -> bool {
+        batch.delete_horizon_ms().is_some_and(|horizon| self.now >= horizon)
+    }
+
+    /// A transaction marker goes once none of its transaction's data is left
+    /// and the delete horizon has passed
+    fn clean_marker(&mut self, batch: RecordBatch) -> Result<Option<RecordBatch>, LogError> {
+        let retained = self.retained_in_txn.remove(&batch.producer_id()).unwrap_or(0);
+        if retained > 0 {
+            return Ok(Some(batch));
+        }
+        if self.horizon_passed(&batch) {
+            return Ok(None);
+        }
+        if batch.has_delete_horizon() {
+            return Ok(Some(batch));
+        }
+
+        let mut builder = RecordBatchBuilder::like(&batch).delete_horizon_ms(self.now + self.delete_retention_ms);
+        for record in batch.records()? {
+            let record = record?;
+            builder.append_with_offset(record.offset, &record);
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    fn clean_batch(&mut self, batch: RecordBatch, stats: &mut CleanerStats) -> Result<Option<RecordBatch>, LogError> {
+        let records = batch.records()?.collect::<Result<Vec<_>, _>>()?;
+        stats.records_read += records.len();
+        if batch.is_transactional() && self.txns.is_aborted(&batch) {
+            return Ok(None);
+        }
+
+        let remove_tombstones = self.horizon_passed(&batch);
+        let retained: Vec<_> = records
+            .iter()
+            .filter(|record| match &record.key {
+                // keyless records predate the topic being compacted, nothing replaces them
+                None => true,
+                Some(key) if self.offset_map.get(key).is_some_and(|latest| *latest > record.offset) => false,
+                Some(_) => !(record.is_tombstone() && remove_tombstones),
+            })
+            .collect();
+        stats.records_retained += retained.len();
+        if batch.is_transactional() {
+            *self.retained_in_txn.entry(batch.producer_id()).or_default() += retained.len();
+        }
+
+        let has_tombstones = retained.iter().any(|record| record.is_tombstone());
+        let needs_horizon = has_tombstones && !batch.has_delete_horizon();
+        if retained.is_empty() {
+            return Ok(None);
+        }
+        if retained.len() == records.len() && !needs_horizon {
+            return Ok(Some(batch));
+        }
+
+        let mut builder = RecordBatchBuilder::like(&batch);
+        if let Some(horizon) = batch.delete_horizon_ms().filter(|_| has_tombstones) {
+            builder = builder.delete_horizon_ms(horizon);
+        } else if needs_horizon {
+            builder = builder.delete_horizon_ms(self.now + self.delete_retention_ms);
+        }
+        for record in retained {
+            builder.append_with_offset(record.offset, record);
+        }
+        Ok(Some(builder.build()?))
+    }
+}
+
+/// Swap a segment for its cleaned version. The new file is complete under
+/// its `.swap` name before the old one goes, see `PartitionLog::open` for
+/// how a crash in between is recovered from.
+fn replace_segment(
+    log: &PartitionLog,
+    state: &mut LogState,
+    base_offset: i64,
+    cleaned: &[u8],
+    config: &LogConfig,
+    now: i64,
+) -> Result<(), LogError> {
+    let dir = log.dir();
+    let log_file = segment_file(dir, base_offset, LOG_FILE_SUFFIX);
+    let cleaned_file = PathBuf::from(format!("{}{}", log_file.display(), CLEANED_FILE_SUFFIX));
+    let swap_file = PathBuf::from(format!("{}{}", log_file.display(), SWAP_FILE_SUFFIX));
+
+    let mut file = fs::File::create(&cleaned_file)?;
+    file.write_all(cleaned)?;
+    file.sync_all()?;
+    fs::rename(&cleaned_file, &swap_file)?;
+
+    if let Some(old) = state.segments.remove(&base_offset) {
+        old.delete()?;
+    }
+    fs::rename(&swap_file, &log_file)?;
+    let mut segment = LogSegment::open(dir, base_offset, config, now)?;
+    segment.recover(config.max_index_size)?;
+    state.segments.insert(base_offset, segment);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        log::{config::CleanupPolicy, partition_log::FetchIsolation},
+        records::Record,
+        utils::clock::MockClock,
+    };
+
+    fn compacted_config() -> LogConfig {
+        LogConfig {
+            cleanup_policy: CleanupPolicy::from_name("compact").unwrap(),
+            delete_retention_ms: 1_000,
+            min_cleanable_dirty_ratio: 0.1,
+            ..Default::default()
+        }
+    }
+
+    fn open(dir: &Path, config: LogConfig, clock: Arc<MockClock>) -> PartitionLog {
+        PartitionLog::open(dir.join("topic-0"), TopicPartition::new("topic", 0), config, 0, 0, clock).unwrap()
+    }
+
+    fn record(key: &str, value: Option<&str>) -> Record {
+        Record::new(
+            1_000,
+            Some(Bytes::copy_from_slice(key.as_bytes())),
+            value.map(|v| Bytes::copy_from_slice(v.as_bytes())),
+        )
+    }
+
+    /// Append `key=value` in its own segment
+    fn append_and_roll(log: &PartitionLog, key: &str, value: Option<&str>) {
+        let batch = RecordBatchBuilder::new(0).append(&record(key, value)).build().unwrap();
+        log.append_as_leader(&MemoryRecords::from_batches(&[batch])).unwrap();
+        log.roll().unwrap();
+    }
+
+    type Entry = (i64, String, Option<String>);
+
+    /// (offset, key, value) of every record, and the offsets of control batches
+    fn contents(log: &PartitionLog) -> (Vec<Entry>, Vec<i64>) {
+        let (mut records, mut markers) = (vec![], vec![]);
+        let mut offset = log.log_start_offset();
+        while offset < log.log_end_offset() {
+            let fetched = log.read(offset, u64::MAX, FetchIsolation::LogEnd, true).unwrap();
+            if fetched.records.is_empty() {
+                break;
+            }
+            for batch in fetched.records.batches() {
+                let batch = batch.unwrap();
+                batch.ensure_valid().unwrap();
+                offset = batch.next_offset();
+                if batch.is_control_batch() {
+                    markers.push(batch.base_offset());
+                    continue;
+                }
+                for r in batch.records().unwrap() {
+                    let r = r.unwrap();
+                    let text = |b: Bytes| String::from_utf8(b.to_vec()).unwrap();
+                    records.push((r.offset, text(r.key.unwrap()), r.value.map(text)));
+                }
+            }
+        }
+        (records, markers)
+    }
+
+    fn entry(offset: i64, key: &str, value: Option<&str>) -> Entry {
+        (offset, key.to_string(), value.map(str::to_string))
+    }
+
+    #[test]
+    fn keeps_the_latest_record_per_key_and_expires_tombstones() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(10_000));
+        let log = open(dir.path(), compacted_config(), clock.clone());
+        let cleaner = LogCleaner::new(&[dir.path().to_path_buf()]);
+
+        append_and_roll(&log, "k1", Some("v1"));
+        append_and_roll(&log, "k2", Some("v1"));
+        append_and_roll(&log, "k1", Some("v2"));
+        append_and_roll(&log, "k3", Some("v1"));
+        append_and_roll(&log, "k2", None);
+        // the active segment is never cleaned
+        let batch = RecordBatchBuilder::new(0).append(&record("k1", Some("v3"))).build().unwrap();
+        log.append_as_leader(&MemoryRecords::from_batches(&[batch])).unwrap();
+
+        let stats = cleaner.clean_log(&log).unwrap().unwrap();
+        assert_eq!((stats.records_read, stats.records_retained, stats.end_offset), (5, 3, 5));
+        assert_eq!(
+            contents(&log).0,
+            vec![entry(2, "k1", Some("v2")), entry(3, "k3", Some("v1")), entry(4, "k2", None), entry(5, "k1", Some("v3"))]
+        );
+        // nothing new to compact
+        assert_eq!(cleaner.clean_log(&log).unwrap(), None);
+
+        log.roll().unwrap();
+        append_and_roll(&log, "k1", Some("v4"));
+        clock.advance(2_000);
+        cleaner.clean_log(&log).unwrap().unwrap();
+        assert_eq!(contents(&log).0, vec![entry(3, "k3", Some("v1")), entry(6, "k1", Some("v4"))]);
+        assert_eq!(log.log_end_offset(), 7);
+
+        // cleaned segments survive a restart with their indexes rebuilt
+        drop(log);
+        let log = open(dir.path(), compacted_config(), clock);
+        assert_eq!(contents(&log).0, vec![entry(3, "k3", Some("v1")), entry(6, "k1", Some("v4"))]);
+    }
+
+    #[test]
+    fn drops_aborted_data_and_expires_markers_once_their_transaction_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let txn_batch = |offset: i64, producer_id: i64, key: &str| {
+            RecordBatchBuilder::new(offset)
+                .producer(producer_id, 0, 0)
+                .transactional(true)
+                .append(&record(key, Some("txn")))
+                .build()
+                .unwrap()
+        };
+        let marker = |offset: i64, producer_id: i64, control_type: ControlRecordType| {
+            RecordBatchBuilder::new(offset)
+                .producer(producer_id, 0, -1)
+                .transactional(true)
+                .control(true)
+                .append(&control_type.end_txn_marker(0, 1_000))
+                .build()
+                .unwrap()
+        };
+        let batches = [
+            txn_batch(0, 7, "k1"),
+            txn_batch(1, 8, "k2"),
+            marker(2, 7, ControlRecordType::Abort),
+            marker(3, 8, ControlRecordType::Commit),
+            txn_batch(4, 9, "k4"),
+        ];
+        fs::create_dir_all(dir.path().join("topic-0")).unwrap();
+        fs::write(
+            segment_file(&dir.path().join("topic-0"), 0, LOG_FILE_SUFFIX),
+            MemoryRecords::from_batches(&batches).as_bytes(),
+        )
+        .unwrap();
+
+        let clock = Arc::new(MockClock::new(10_000));
+        let log = open(dir.path(), compacted_config(), clock.clone());
+        log.roll().unwrap();
+        let cleaner = LogCleaner::new(&[dir.path().to_path_buf()]);
+
+        // producer 9's transaction is still open, its segment can't be cleaned
+        assert_eq!(cleaner.clean_log(&log).unwrap(), None);
+
+        fs::write(
+            segment_file(&dir.path().join("topic-0"), 5, LOG_FILE_SUFFIX),
+            MemoryRecords::from_batches(&[marker(5, 9, ControlRecordType::Commit)]).as_bytes(),
+        )
+        .unwrap();
+        drop(log);
+        let log = open(dir.path(), compacted_config(), clock.clone());
+        log.roll().unwrap();
+
+        // the aborted record goes, its marker stays until delete.retention.ms has passed
+        cleaner.clean_log(&log).unwrap().unwrap();
+        let (records, markers) = contents(&log);
+        assert_eq!(records, vec![entry(1, "k2", Some("txn")), entry(4, "k4", Some("txn"))]);
+        assert_eq!(markers, vec![2, 3, 5]);
+
+        // the committed record is replaced, then its marker can go too
+        append_and_roll(&log, "k2", Some("plain"));
+        clock.advance(2_000);
+        cleaner.clean_log(&log).unwrap().unwrap();
+        assert_eq!(contents(&log).1, vec![3, 5]);
+
+        append_and_roll(&log, "k5", Some("plain"));
+        clock.advance(2_000);
+        cleaner.clean_log(&log).unwrap().unwrap();
+        let (records, markers) = contents(&log);
+        assert_eq!(markers, vec![5]);
+        assert_eq!(records.iter().map(|r| r.0).collect::<Vec<_>>(), vec![4, 6, 7]);
+    }
+
+    #[test]
+    fn compact_delete_policy_also_enforces_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(10_000));
+        let config = LogConfig {
+            retention_ms: 1_000,
+            ..compacted_config()
+        };
+        let log = open(dir.path(), config.clone(), clock.clone());
+        append_and_roll(&log, "k1", Some("v1"));
+        append_and_roll(&log, "k2", Some("v1"));
+        clock.advance(100_000);
+
+        assert_eq!(log.delete_old_segments().unwrap(), 0);
+        log.update_config(LogConfig {
+            cleanup_policy: CleanupPolicy::from_name("compact,delete").unwrap(),
+            ..config
+        });
+        assert_eq!(log.delete_old_segments().unwrap(), 2);
+        assert_eq!(CleanupPolicy::from_name("delete,compact"), CleanupPolicy::from_name("compact,delete"));
+        assert_eq!(CleanupPolicy::from_name("compact,nope"), None);
+
+        let keyless = RecordBatchBuilder::new(0).append(&Record::new(0, None, None)).build().unwrap();
+        assert!(log.append_as_leader(&MemoryRecords::from_batches(&[keyless])).is_err());
+    }
+}
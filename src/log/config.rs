@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::{
+    common::{
+        config::BrokerConfig,
+        config_def::{ConfigDef, ConfigKey, ConfigType, Validator},
+    },
+    records::{compression::BrokerCompressionType, TimestampType},
+};
+
+pub const DEFAULT_SEGMENT_BYTES: u64 = 1024 * 1024 * 1024;
+pub const DEFAULT_SEGMENT_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+pub const DEFAULT_INDEX_INTERVAL_BYTES: u64 = 4096;
+pub const DEFAULT_MAX_INDEX_SIZE: usize = 10 * 1024 * 1024;
+pub const DEFAULT_RETENTION_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+pub const DEFAULT_DELETE_RETENTION_MS: i64 = 24 * 60 * 60 * 1000;
+pub const DEFAULT_MIN_CLEANABLE_DIRTY_RATIO: f64 = 0.5;
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 1024 * 1024 + 12;
+
+pub const CLEANUP_POLICIES: &[&str] = &["delete", "compact"];
+pub const COMPRESSION_TYPES: &[&str] = &["uncompressed", "zstd", "lz4", "snappy", "gzip", "producer"];
+pub const TIMESTAMP_TYPES: &[&str] = &["CreateTime", "LogAppendTime"];
+
+lazy_static! {
+    /// The topic level configs, which can be set on any topic
+    pub static ref TOPIC_CONFIGS: ConfigDef = ConfigDef::new(vec![
+        ConfigKey::new("segment.bytes", ConfigType::Int, Some("1073741824"), Validator::AtLeast(14),
+            "Roll a new segment once the active one would grow past this size."),
+        ConfigKey::new("segment.ms", ConfigType::Long, Some("604800000"), Validator::AtLeast(1),
+            "Roll a new segment once the active one is this old, even if it isn't full."),
+        ConfigKey::new("index.interval.bytes", ConfigType::Int, Some("4096"), Validator::AtLeast(0),
+            "How often an entry is added to the offset index, in bytes of batches."),
+        ConfigKey::new("segment.index.bytes", ConfigType::Int, Some("10485760"), Validator::AtLeast(4),
+            "Size of the offset and time index files of a segment."),
+        ConfigKey::new("retention.ms", ConfigType::Long, Some("604800000"), Validator::AtLeast(-1),
+            "Delete segments whose newest record is older than this, -1 for no time limit."),
+        ConfigKey::new("retention.bytes", ConfigType::Long, Some("-1"), Validator::AtLeast(-1),
+            "Delete the oldest segments while a partition is bigger than this, -1 for no size limit."),
+        ConfigKey::new("cleanup.policy", ConfigType::List, Some("delete"), Validator::ListOf(CLEANUP_POLICIES),
+            "`delete` to drop old segments, `compact` to keep the latest record of each key, or both."),
+        ConfigKey::new("delete.retention.ms", ConfigType::Long, Some("86400000"), Validator::AtLeast(0),
+            "How long tombstones and transaction markers stay in a compacted topic."),
+        ConfigKey::new("min.cleanable.dirty.ratio", ConfigType::Double, Some("0.5"), Validator::Between(0.0, 1.0),
+            "Compact once this share of the log hasn't been compacted yet."),
+        ConfigKey::new("compression.type", ConfigType::String, Some("producer"), Validator::OneOf(COMPRESSION_TYPES),
+            "Codec batches are stored with, `producer` keeps the one the producer used."),
+        ConfigKey::new("message.timestamp.type", ConfigType::String, Some("CreateTime"), Validator::OneOf(TIMESTAMP_TYPES),
+            "Whether record timestamps are the producer's or the broker's append time."),
+        ConfigKey::new("flush.messages", ConfigType::Long, Some("9223372036854775807"), Validator::AtLeast(1),
+            "Fsync the log once this many messages haven't been flushed."),
+        ConfigKey::new("flush.ms", ConfigType::Long, Some("9223372036854775807"), Validator::AtLeast(0),
+            "Fsync the log once the last flush is this old."),
+        ConfigKey::new("max.message.bytes", ConfigType::Int, Some("1048588"), Validator::AtLeast(0),
+            "Largest record batch a producer may append."),
+    ]);
+}
+
+/// The broker configs a topic config takes its value from when it isn't
+/// set on the topic, in order of precedence, with what their value is
+/// multiplied by to get the topic config's unit (e.g. hours to ms)
+pub fn topic_config_synonyms(name: &str) -> &'static [(&'static str, i64)] {
+    match name {
+        "segment.bytes" => &[("log.segment.bytes", 1)],
+        "segment.ms" => &[("log.roll.ms", 1), ("log.roll.hours", 60 * 60 * 1000)],
+        "index.interval.bytes" => &[("log.index.interval.bytes", 1)],
+        "segment.index.bytes" => &[("log.index.size.max.bytes", 1)],
+        "retention.ms" => &[
+            ("log.retention.ms", 1),
+            ("log.retention.minutes", 60 * 1000),
+            ("log.retention.hours", 60 * 60 * 1000),
+        ],
+        "retention.bytes" => &[("log.retention.bytes", 1)],
+        "cleanup.policy" => &[("log.cleanup.policy", 1)],
+        "delete.retention.ms" => &[("log.cleaner.delete.retention.ms", 1)],
+        "min.cleanable.dirty.ratio" => &[("log.cleaner.min.cleanable.ratio", 1)],
+        "compression.type" => &[("compression.type", 1)],
+        "message.timestamp.type" => &[("log.message.timestamp.type", 1)],
+        "flush.messages" => &[("log.flush.interval.messages", 1)],
+        "flush.ms" => &[("log.flush.interval.ms", 1), ("log.flush.scheduler.interval.ms", 1)],
+        "max.message.bytes" => &[("message.max.bytes", 1)],
+        _ => &[],
+    }
+}
+
+/// Whether a broker config can be changed at runtime: the topic defaults can
+pub fn is_dynamic_broker_config(name: &str) -> bool {
+    TOPIC_CONFIGS
+        .keys()
+        .any(|key| topic_config_synonyms(key.name).iter().any(|(synonym, _)| *synonym == name))
+}
+
+/// A broker config's value in the unit of the topic config it's a synonym of
+pub fn convert_synonym_value(value: &str, multiplier: i64) -> String {
+    match value.trim().parse::<i64>() {
+        Ok(value) if multiplier != 1 => value.saturating_mul(multiplier).to_string(),
+        _ => value.trim().to_string(),
+    }
+}
+
+/// cleanup.policy: what happens to old segments, a topic can have both
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanupPolicy {
+    /// delete whole segments past retention.ms / retention.bytes
+    pub delete: bool,
+    /// keep only the latest record for each key
+    pub compact: bool,
+}
+
+impl CleanupPolicy {
+    pub const DELETE: CleanupPolicy = CleanupPolicy {
+        delete: true,
+        compact: false,
+    };
+
+    /// A comma separated list of `delete` and `compact`
+    pub fn from_name(value: &str) -> Option<Self> {
+        let mut policy = CleanupPolicy {
+            delete: false,
+            compact: false,
+        };
+        for name in value.split(',').map(str::trim) {
+            match name {
+                "delete" => policy.delete = true,
+                "compact" => policy.compact = true,
+                _ => return None,
+            }
+        }
+        Some(policy)
+    }
+}
+
+/// Per partition log settings: broker wide `log.*` defaults with the topic's
+/// overrides applied on top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogConfig {
+    /// segment.bytes: roll a new segment once the active one would grow past this
+    pub segment_bytes: u64,
+    /// segment.ms: roll a new segment once the active one is this old
+    pub segment_ms: i64,
+    /// index.interval.bytes: add an index entry after this many bytes of batches
+    pub index_interval_bytes: u64,
+    /// segment.index.bytes: size of the offset and time index files
+    pub max_index_size: usize,
+    /// retention.ms: delete segments whose newest record is older than this, -1 for no limit
+    pub retention_ms: i64,
+    /// retention.bytes: delete the oldest segments while the log is bigger than this, -1 for no limit
+    pub retention_bytes: i64,
+    /// cleanup.policy
+    pub cleanup_policy: CleanupPolicy,
+    /// delete.retention.ms: how long tombstones and transaction markers stay in a compacted log
+    pub delete_retention_ms: i64,
+    /// min.cleanable.dirty.ratio: compact once this share of the log hasn't been compacted yet
+    pub min_cleanable_dirty_ratio: f64,
+    /// compression.type
+    pub compression_type: BrokerCompressionType,
+    /// message.timestamp.type
+    pub message_timestamp_type: TimestampType,
+    /// flush.messages: fsync once this many messages haven't been flushed
+    pub flush_messages: i64,
+    /// flush.ms: fsync once the last flush is this old
+    pub flush_ms: i64,
+    /// max.message.bytes: largest batch a producer may append
+    pub max_message_bytes: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            segment_bytes: DEFAULT_SEGMENT_BYTES,
+            segment_ms: DEFAULT_SEGMENT_MS,
+            index_interval_bytes: DEFAULT_INDEX_INTERVAL_BYTES,
+            max_index_size: DEFAULT_MAX_INDEX_SIZE,
+            retention_ms: DEFAULT_RETENTION_MS,
+            retention_bytes: -1,
+            cleanup_policy: CleanupPolicy::DELETE,
+            delete_retention_ms: DEFAULT_DELETE_RETENTION_MS,
+            min_cleanable_dirty_ratio: DEFAULT_MIN_CLEANABLE_DIRTY_RATIO,
+            compression_type: BrokerCompressionType::Producer,
+            message_timestamp_type: TimestampType::CreateTime,
+            // leave flushing to the OS, durability comes from replication
+            flush_messages: i64::MAX,
+            flush_ms: i64::MAX,
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+        }
+    }
+}
+
+fn parse_timestamp_type(value: &str) -> Option<TimestampType> {
+    match value {
+        "CreateTime" => Some(TimestampType::CreateTime),
+        "LogAppendTime" => Some(TimestampType::LogAppendTime),
+        _ => None,
+    }
+}
+
+impl LogConfig {
+    /// Topic defaults from the broker's `log.*` settings
+    pub fn from_broker_config(config: &BrokerConfig) -> Self {
+        let defaults = TOPIC_CONFIGS
+            .keys()
+            .filter_map(|key| {
+                topic_config_synonyms(key.name).iter().find_map(|(synonym, multiplier)| {
+                    let value = config.get(synonym)?;
+                    Some((key.name.to_string(), convert_synonym_value(value, *multiplier)))
+                })
+            })
+            .collect();
+        LogConfig::default().with_overrides(&defaults)
+    }
+
+    /// Apply topic level overrides (`segment.bytes`, ...). Unparseable values
+    /// are ignored, they are rejected when the topic config is set.
+    pub fn with_overrides(&self, overrides: &HashMap<String, String>) -> Self {
+        let mut log_config = self.clone();
+
+        for (key, value) in overrides {
+            match key.as_str() {
+                "segment.bytes" => {
+                    if let Ok(bytes) = value.parse() {
+                        log_config.segment_bytes = bytes;
+                    }
+                }
+                "segment.ms" => {
+                    if let Ok(ms) = value.parse() {
+                        log_config.segment_ms = ms;
+                    }
+                }
+                "index.interval.bytes" => {
+                    if let Ok(bytes) = value.parse() {
+                        log_config.index_interval_bytes = bytes;
+                    }
+                }
+                "segment.index.bytes" => {
+                    if let Ok(bytes) = value.parse() {
+                        log_config.max_index_size = bytes;
+                    }
+                }
+                "retention.ms" => {
+                    if let Ok(ms) = value.parse() {
+                        log_config.retention_ms = ms;
+                    }
+                }
+                "retention.bytes" => {
+                    if let Ok(bytes) = value.parse() {
+                        log_config.retention_bytes = bytes;
+                    }
+                }
+                "cleanup.policy" => {
+                    if let Some(policy) = CleanupPolicy::from_name(value) {
+                        log_config.cleanup_policy = policy;
+                    }
+                }
+                "delete.retention.ms" => {
+                    if let Ok(ms) = value.parse() {
+                        log_config.delete_retention_ms = ms;
+                    }
+                }
+                "min.cleanable.dirty.ratio" => {
+                    if let Ok(ratio) = value.parse() {
+                        log_config.min_cleanable_dirty_ratio = ratio;
+                    }
+                }
+                "compression.type" => {
+                    if let Some(compression) = BrokerCompressionType::from_name(value) {
+                        log_config.compression_type = compression;
+                    }
+                }
+                "message.timestamp.type" => {
+                    if let Some(ts_type) = parse_timestamp_type(value) {
+                        log_config.message_timestamp_type = ts_type;
+                    }
+                }
+                "flush.messages" => {
+                    if let Ok(messages) = value.parse() {
+                        log_config.flush_messages = messages;
+                    }
+                }
+                "flush.ms" => {
+                    if let Ok(ms) = value.parse() {
+                        log_config.flush_ms = ms;
+                    }
+                }
+                "max.message.bytes" => {
+                    if let Ok(bytes) = value.parse() {
+                        log_config.max_message_bytes = bytes;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        log_config
+    }
+}
+
+/// Check a topic level override before it's set, Err with the reason if
+/// it isn't a topic config we know or its value is invalid
+pub fn validate_topic_config(key: &str, value: &str) -> Result<(), String> {
+    TOPIC_CONFIGS.validate(key, value)
+}
@@ -0,0 +1,409 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
+
+use memmap2::MmapMut;
+
+use crate::records::NO_TIMESTAMP;
+
+/// A memory-mapped file of fixed size entries sorted by a key, the part
+/// shared by the offset and time indexes.
+///
+/// The file of the active segment is preallocated (`resize`) so entries can
+/// be appended without remapping, and trimmed to its entries when the
+/// segment is rolled. An existing file is taken to be all entries.
+#[derive(Debug)]
+struct MmapIndex {
+    path: PathBuf,
+    file: File,
+    mmap: MmapMut,
+    entry_size: usize,
+    entries: usize,
+    max_entries: usize,
+}
+
+impl MmapIndex {
+    fn open(path: PathBuf, entry_size: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+        let entries = file.metadata()?.len() as usize / entry_size;
+        file.set_len((entries * entry_size) as u64)?;
+        // the file is only ever resized through this index, which remaps it
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+
+        Ok(MmapIndex {
+            path,
+            file,
+            mmap,
+            entry_size,
+            entries,
+            max_entries: entries,
+        })
+    }
+
+    fn entry(&self, n: usize) -> &[u8] {
+        &self.mmap[n * self.entry_size..(n + 1) * self.entry_size]
+    }
+
+    fn push(&mut self, entry: &[u8]) {
+        debug_assert_eq!(entry.len(), self.entry_size);
+        let start = self.entries * self.entry_size;
+        self.mmap[start..start + self.entry_size].copy_from_slice(entry);
+        self.entries += 1;
+    }
+
+    fn is_full(&self) -> bool {
+        self.entries >= self.max_entries
+    }
+
+    /// Index of the last entry whose key is <= target
+    fn largest_lower_bound(&self, target: i64, key: impl Fn(&[u8]) -> i64) -> Option<usize> {
+        // number of entries with key <= target
+        let (mut lo, mut hi) = (0, self.entries);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if key(self.entry(mid)) <= target {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo.checked_sub(1)
+    }
+
+    /// Drop the entries from `n` onwards
+    fn truncate_entries(&mut self, n: usize) {
+        self.entries = self.entries.min(n);
+    }
+
+    /// Shrink the file to the entries it holds, once no more will be appended
+    fn trim_to_valid_size(&mut self) -> io::Result<()> {
+        self.resize(self.entries * self.entry_size)
+    }
+
+    /// Make room for `len` bytes of entries, never dropping existing ones
+    fn resize(&mut self, len: usize) -> io::Result<()> {
+        let len = (len / self.entry_size).max(self.entries) * self.entry_size;
+        self.mmap.flush()?;
+        self.file.set_len(len as u64)?;
+        self.mmap = unsafe { MmapMut::map_mut(&self.file)? };
+        self.max_entries = len / self.entry_size;
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.mmap.flush()
+    }
+}
+
+/// Offset and file position of a batch, as stored in the offset index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetPosition {
+    pub offset: i64,
+    pub position: u64,
+}
+
+/// Timestamp and offset of a record, as stored in the time index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampOffset {
+    pub timestamp: i64,
+    pub offset: i64,
+}
+
+pub const OFFSET_INDEX_ENTRY_SIZE: usize = 8;
+pub const TIME_INDEX_ENTRY_SIZE: usize = 12;
+
+/// `<baseOffset>.index`: maps offsets to positions in the segment file.
+/// Each entry is 4 bytes of offset relative to the segment's base offset
+/// and 4 bytes of position. It's sparse, the position of an offset that
+/// isn't indexed is found by scanning forward from the closest entry.
+#[derive(Debug)]
+pub struct OffsetIndex {
+    base_offset: i64,
+    index: MmapIndex,
+}
+
+impl OffsetIndex {
+    pub fn open(path: PathBuf, base_offset: i64) -> io::Result<Self> {
+        Ok(OffsetIndex {
+            base_offset,
+            index: MmapIndex::open(path, OFFSET_INDEX_ENTRY_SIZE)?,
+        })
+    }
+
+    fn parse(&self, entry: &[u8]) -> OffsetPosition {
+        OffsetPosition {
+            offset: self.base_offset + u32::from_be_bytes(entry[0..4].try_into().unwrap()) as i64,
+            position: u32::from_be_bytes(entry[4..8].try_into().unwrap()) as u64,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.index.path
+    }
+
+    pub fn entries(&self) -> usize {
+        self.index.entries
+    }
+
+    pub fn entry(&self, n: usize) -> OffsetPosition {
+        self.parse(self.index.entry(n))
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.index.is_full()
+    }
+
+    pub fn last_entry(&self) -> OffsetPosition {
+        match self.index.entries {
+            0 => OffsetPosition {
+                offset: self.base_offset,
+                position: 0,
+            },
+            n => self.entry(n - 1),
+        }
+    }
+
+    /// Index `offset` as starting at `position`, offsets must be increasing
+    pub fn append(&mut self, offset: i64, position: u64) {
+        if self.index.entries > 0 && offset <= self.last_entry().offset {
+            return;
+        }
+        let mut entry = [0u8; OFFSET_INDEX_ENTRY_SIZE];
+        entry[0..4].copy_from_slice(&((offset - self.base_offset) as u32).to_be_bytes());
+        entry[4..8].copy_from_slice(&(position as u32).to_be_bytes());
+        self.index.push(&entry);
+    }
+
+    /// The closest indexed position at or before `target_offset`, the start
+    /// of the segment if there's none
+    pub fn lookup(&self, target_offset: i64) -> OffsetPosition {
+        let relative = target_offset - self.base_offset;
+        match self
+            .index
+            .largest_lower_bound(relative, |e| u32::from_be_bytes(e[0..4].try_into().unwrap()) as i64)
+        {
+            Some(n) => self.entry(n),
+            None => OffsetPosition {
+                offset: self.base_offset,
+                position: 0,
+            },
+        }
+    }
+
+    /// Remove entries for offsets >= `offset`
+    pub fn truncate_to(&mut self, offset: i64) {
+        let n = (0..self.entries()).position(|n| self.entry(n).offset >= offset).unwrap_or(self.entries());
+        self.index.truncate_entries(n);
+    }
+
+    pub fn reset(&mut self) {
+        self.index.truncate_entries(0);
+    }
+
+    /// Whether the entries make sense for a segment of `segment_size` bytes.
+    /// An index preallocated before a crash is padded with zero entries, which
+    /// fail this because offsets and positions have to be increasing.
+    pub fn sanity_check(&self, segment_size: u64) -> bool {
+        let mut previous: Option<OffsetPosition> = None;
+        for n in 0..self.entries() {
+            let entry = self.entry(n);
+            if entry.position >= segment_size
+                || previous.is_some_and(|p| entry.offset <= p.offset || entry.position <= p.position)
+            {
+                return false;
+            }
+            previous = Some(entry);
+        }
+        true
+    }
+
+    pub fn trim_to_valid_size(&mut self) -> io::Result<()> {
+        self.index.trim_to_valid_size()
+    }
+
+    pub fn resize(&mut self, max_index_size: usize) -> io::Result<()> {
+        self.index.resize(max_index_size)
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.index.flush()
+    }
+}
+
+/// `<baseOffset>.timeindex`: maps timestamps to the offset of the first
+/// record at or after them. Each entry is an 8 byte timestamp and a 4 byte
+/// relative offset, timestamps are strictly increasing.
+#[derive(Debug)]
+pub struct TimeIndex {
+    base_offset: i64,
+    index: MmapIndex,
+}
+
+impl TimeIndex {
+    pub fn open(path: PathBuf, base_offset: i64) -> io::Result<Self> {
+        Ok(TimeIndex {
+            base_offset,
+            index: MmapIndex::open(path, TIME_INDEX_ENTRY_SIZE)?,
+        })
+    }
+
+    fn parse(&self, entry: &[u8]) -> TimestampOffset {
+        TimestampOffset {
+            timestamp: i64::from_be_bytes(entry[0..8].try_into().unwrap()),
+            offset: self.base_offset + u32::from_be_bytes(entry[8..12].try_into().unwrap()) as i64,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.index.path
+    }
+
+    pub fn entries(&self) -> usize {
+        self.index.entries
+    }
+
+    pub fn entry(&self, n: usize) -> TimestampOffset {
+        self.parse(self.index.entry(n))
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.index.is_full()
+    }
+
+    pub fn last_entry(&self) -> TimestampOffset {
+        match self.index.entries {
+            0 => TimestampOffset {
+                timestamp: NO_TIMESTAMP,
+                offset: self.base_offset,
+            },
+            n => self.entry(n - 1),
+        }
+    }
+
+    /// Add an entry unless it wouldn't move the index forward. With
+    /// `skip_full_check` the file grows by one entry if it's full, for the
+    /// final entry written when the segment is rolled.
+    pub fn maybe_append(&mut self, timestamp: i64, offset: i64, skip_full_check: bool) -> io::Result<()> {
+        let last = self.last_entry();
+        if self.index.entries > 0 && (timestamp <= last.timestamp || offset < last.offset) {
+            return Ok(());
+        }
+        if timestamp == NO_TIMESTAMP {
+            return Ok(());
+        }
+        if self.is_full() {
+            if !skip_full_check {
+                return Ok(());
+            }
+            self.index.resize((self.index.entries + 1) * TIME_INDEX_ENTRY_SIZE)?;
+        }
+        let mut entry = [0u8; TIME_INDEX_ENTRY_SIZE];
+        entry[0..8].copy_from_slice(&timestamp.to_be_bytes());
+        entry[8..12].copy_from_slice(&((offset - self.base_offset) as u32).to_be_bytes());
+        self.index.push(&entry);
+        Ok(())
+    }
+
+    /// The last entry with a timestamp <= `target_timestamp`. Records from its
+    /// offset onwards are the only ones that can have timestamps >= target.
+    pub fn lookup(&self, target_timestamp: i64) -> TimestampOffset {
+        match self
+            .index
+            .largest_lower_bound(target_timestamp, |e| i64::from_be_bytes(e[0..8].try_into().unwrap()))
+        {
+            Some(n) => self.entry(n),
+            None => TimestampOffset {
+                timestamp: NO_TIMESTAMP,
+                offset: self.base_offset,
+            },
+        }
+    }
+
+    /// Remove entries for offsets >= `offset`
+    pub fn truncate_to(&mut self, offset: i64) {
+        let n = (0..self.entries()).position(|n| self.entry(n).offset >= offset).unwrap_or(self.entries());
+        self.index.truncate_entries(n);
+    }
+
+    pub fn reset(&mut self) {
+        self.index.truncate_entries(0);
+    }
+
+    /// Whether timestamps are increasing and offsets never go back, see
+    /// `OffsetIndex::sanity_check`
+    pub fn sanity_check(&self) -> bool {
+        let mut previous: Option<TimestampOffset> = None;
+        for n in 0..self.entries() {
+            let entry = self.entry(n);
+            if previous.is_some_and(|p| entry.timestamp <= p.timestamp || entry.offset < p.offset) {
+                return false;
+            }
+            previous = Some(entry);
+        }
+        true
+    }
+
+    pub fn trim_to_valid_size(&mut self) -> io::Result<()> {
+        self.index.trim_to_valid_size()
+    }
+
+    pub fn resize(&mut self, max_index_size: usize) -> io::Result<()> {
+        self.index.resize(max_index_size)
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.index.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_index_lookup_and_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("00000000000000000100.index");
+        let mut index = OffsetIndex::open(path.clone(), 100).unwrap();
+        index.resize(64).unwrap();
+        assert_eq!(index.lookup(150), OffsetPosition { offset: 100, position: 0 });
+
+        index.append(110, 4096);
+        index.append(120, 8192);
+        index.append(120, 9000); // not increasing, ignored
+        assert_eq!(index.entries(), 2);
+        assert_eq!(index.lookup(105).position, 0);
+        assert_eq!(index.lookup(115), OffsetPosition { offset: 110, position: 4096 });
+        assert_eq!(index.lookup(500), OffsetPosition { offset: 120, position: 8192 });
+
+        index.trim_to_valid_size().unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 16);
+        drop(index);
+
+        let mut index = OffsetIndex::open(path, 100).unwrap();
+        assert_eq!(index.entries(), 2);
+        index.truncate_to(115);
+        assert_eq!(index.last_entry().offset, 110);
+    }
+
+    #[test]
+    fn time_index_only_moves_forward() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = TimeIndex::open(dir.path().join("0.timeindex"), 0).unwrap();
+        index.resize(24).unwrap();
+        index.maybe_append(1_000, 5, false).unwrap();
+        index.maybe_append(900, 7, false).unwrap();
+        index.maybe_append(2_000, 10, false).unwrap();
+        assert!(index.is_full());
+        index.maybe_append(3_000, 20, false).unwrap();
+        assert_eq!(index.entries(), 2);
+        index.maybe_append(3_000, 20, true).unwrap();
+        assert_eq!(index.entries(), 3);
+
+        assert_eq!(index.lookup(500).offset, 0);
+        assert_eq!(index.lookup(1_500), TimestampOffset { timestamp: 1_000, offset: 5 });
+        assert_eq!(index.lookup(2_000).offset, 10);
+    }
+}
@@ -0,0 +1,569 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use crate::{
+    common::{config::BrokerConfig, topic_partition::TopicPartition, uuid::KafUuid},
+    log::{
+        checkpoint::{OffsetCheckpointFile, LOG_START_OFFSET_CHECKPOINT_FILE, RECOVERY_POINT_CHECKPOINT_FILE},
+        cleaner::LogCleaner,
+        LogConfig, LogError, PartitionLog, DELETE_DIR_SUFFIX,
+    },
+    utils::{
+        clock::{Clock, SystemClock},
+        scheduler,
+    },
+};
+
+pub const DEFAULT_RETENTION_CHECK_INTERVAL_MS: i64 = 5 * 60 * 1000;
+pub const DEFAULT_CLEANER_BACKOFF_MS: i64 = 15 * 1000;
+pub const DEFAULT_RECOVERY_POINT_CHECKPOINT_INTERVAL_MS: i64 = 60 * 1000;
+pub const DEFAULT_LOG_START_OFFSET_CHECKPOINT_INTERVAL_MS: i64 = 60 * 1000;
+pub const DEFAULT_FILE_DELETE_DELAY_MS: i64 = 60 * 1000;
+
+/// Owns every partition log on this broker, spread over the `log.dirs`
+#[derive(Debug)]
+pub struct LogManager {
+    log_dirs: Vec<PathBuf>,
+    /// topic defaults, changed by dynamic broker configs
+    default_config: RwLock<LogConfig>,
+    /// log.retention.check.interval.ms
+    retention_check_interval_ms: i64,
+    /// log.cleaner.backoff.ms, None when log.cleaner.enable is false
+    cleaner_backoff_ms: Option<i64>,
+    /// log.flush.scheduler.interval.ms, None when logs are only flushed by flush.messages
+    flush_check_interval_ms: Option<i64>,
+    /// log.flush.offset.checkpoint.interval.ms
+    recovery_point_checkpoint_interval_ms: i64,
+    /// log.flush.start.offset.checkpoint.interval.ms
+    log_start_offset_checkpoint_interval_ms: i64,
+    /// log.segment.delete.delay.ms: how long a deleted log's files stay
+    /// around, for readers that still have them open
+    file_delete_delay_ms: i64,
+    cleaner: LogCleaner,
+    logs: RwLock<HashMap<TopicPartition, Arc<PartitionLog>>>,
+    /// renamed dirs of deleted logs and when to remove them
+    logs_to_delete: Mutex<Vec<(PathBuf, i64)>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for LogManager {
+    fn default() -> Self {
+        LogManager::new(&BrokerConfig::default(), Arc::new(SystemClock))
+    }
+}
+
+impl LogManager {
+    pub fn new(config: &BrokerConfig, clock: Arc<dyn Clock>) -> Self {
+        LogManager {
+            log_dirs: config.log_dirs.clone(),
+            default_config: RwLock::new(LogConfig::from_broker_config(config)),
+            retention_check_interval_ms: config
+                .get_i64("log.retention.check.interval.ms")
+                .unwrap_or(DEFAULT_RETENTION_CHECK_INTERVAL_MS),
+            cleaner_backoff_ms: match config.get("log.cleaner.enable") {
+                Some("false") => None,
+                _ => Some(config.get_i64("log.cleaner.backoff.ms").unwrap_or(DEFAULT_CLEANER_BACKOFF_MS)),
+            },
+            flush_check_interval_ms: config.get_i64("log.flush.scheduler.interval.ms"),
+            recovery_point_checkpoint_interval_ms: config
+                .get_i64("log.flush.offset.checkpoint.interval.ms")
+                .unwrap_or(DEFAULT_RECOVERY_POINT_CHECKPOINT_INTERVAL_MS),
+            log_start_offset_checkpoint_interval_ms: config
+                .get_i64("log.flush.start.offset.checkpoint.interval.ms")
+                .unwrap_or(DEFAULT_LOG_START_OFFSET_CHECKPOINT_INTERVAL_MS),
+            file_delete_delay_ms: config
+                .get_i64("log.segment.delete.delay.ms")
+                .unwrap_or(DEFAULT_FILE_DELETE_DELAY_MS),
+            cleaner: LogCleaner::new(&config.log_dirs),
+            logs: RwLock::new(HashMap::new()),
+            logs_to_delete: Mutex::new(vec![]),
+            clock,
+        }
+    }
+
+    /// Open every `<topic>-<partition>` directory found in the log dirs,
+    /// recovering each from its checkpointed recovery point and restoring
+    /// its checkpointed log start offset
+    pub fn startup(config: &BrokerConfig, clock: Arc<dyn Clock>) -> Result<Self, LogError> {
+        let manager = LogManager::new(config, clock);
+
+        let mut logs = HashMap::new();
+        for log_dir in &manager.log_dirs {
+            fs::create_dir_all(log_dir)?;
+            let recovery_points = OffsetCheckpointFile::new(log_dir, RECOVERY_POINT_CHECKPOINT_FILE)
+                .read()
+                .unwrap_or_else(|e| {
+                    // recovering everything is slow but safe
+                    println!("{}, recovering all logs in {}", e, log_dir.display());
+                    HashMap::new()
+                });
+            let log_start_offsets = OffsetCheckpointFile::new(log_dir, LOG_START_OFFSET_CHECKPOINT_FILE)
+                .read()
+                .unwrap_or_else(|e| {
+                    // the first segment's base offset is the next best thing
+                    println!("{}, using the first segment of each log in {}", e, log_dir.display());
+                    HashMap::new()
+                });
+            for entry in fs::read_dir(log_dir)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                // deleted before we were stopped
+                if entry.file_name().to_str().is_some_and(|name| name.ends_with(DELETE_DIR_SUFFIX)) {
+                    manager.logs_to_delete.lock().unwrap().push((entry.path(), 0));
+                    continue;
+                }
+                let Some(tp) = entry.file_name().to_str().and_then(TopicPartition::from_dir_name) else {
+                    continue;
+                };
+                let log = PartitionLog::open(
+                    entry.path(),
+                    tp.clone(),
+                    manager.default_config(),
+                    recovery_points.get(&tp).copied().unwrap_or(0),
+                    log_start_offsets.get(&tp).copied().unwrap_or(0),
+                    manager.clock.clone(),
+                )?;
+                logs.insert(tp, Arc::new(log));
+            }
+        }
+        *manager.logs.write().unwrap() = logs;
+        manager.checkpoint_recovery_points()?;
+        manager.checkpoint_log_start_offsets()?;
+
+        Ok(manager)
+    }
+
+    /// Write each log dir's `recovery-point-offset-checkpoint`
+    pub fn checkpoint_recovery_points(&self) -> Result<(), LogError> {
+        self.write_checkpoints(RECOVERY_POINT_CHECKPOINT_FILE, PartitionLog::recovery_point)
+    }
+
+    /// Write each log dir's `log-start-offset-checkpoint`, so records deleted
+    /// by DeleteRecords stay deleted after a restart
+    pub fn checkpoint_log_start_offsets(&self) -> Result<(), LogError> {
+        self.write_checkpoints(LOG_START_OFFSET_CHECKPOINT_FILE, PartitionLog::log_start_offset)
+    }
+
+    fn write_checkpoints(&self, name: &str, offset: impl Fn(&PartitionLog) -> i64) -> Result<(), LogError> {
+        let logs = self.all_logs();
+        for log_dir in &self.log_dirs {
+            let offsets = logs
+                .iter()
+                .filter(|log| log.dir().parent() == Some(log_dir.as_path()))
+                .map(|log| (log.topic_partition().clone(), offset(log)))
+                .collect();
+            OffsetCheckpointFile::new(log_dir, name).write(&offsets)?;
+        }
+        Ok(())
+    }
+
+    pub fn log_dirs(&self) -> &[PathBuf] {
+        &self.log_dirs
+    }
+
+    pub fn default_config(&self) -> LogConfig {
+        self.default_config.read().unwrap().clone()
+    }
+
+    /// New topic defaults, for logs created from now on. Callers apply them
+    /// to existing logs, with their topic's overrides.
+    pub fn set_default_config(&self, config: LogConfig) {
+        *self.default_config.write().unwrap() = config;
+    }
+
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    pub fn get_log(&self, tp: &TopicPartition) -> Option<Arc<PartitionLog>> {
+        self.logs.read().unwrap().get(tp).cloned()
+    }
+
+    /// The partition's log, created in the least loaded log dir if it doesn't exist yet
+    pub fn get_or_create_log(
+        &self,
+        tp: &TopicPartition,
+        overrides: &HashMap<String, String>,
+    ) -> Result<Arc<PartitionLog>, LogError> {
+        if let Some(log) = self.get_log(tp) {
+            return Ok(log);
+        }
+
+        let mut logs = self.logs.write().unwrap();
+        if let Some(log) = logs.get(tp) {
+            return Ok(log.clone());
+        }
+
+        let log_dir = self
+            .log_dirs
+            .iter()
+            .min_by_key(|dir| logs.values().filter(|log| log.dir().parent() == Some(dir.as_path())).count())
+            .expect("at least one log dir");
+        let log = Arc::new(PartitionLog::open(
+            log_dir.join(tp.dir_name()),
+            tp.clone(),
+            self.default_config().with_overrides(overrides),
+            0,
+            0,
+            self.clock.clone(),
+        )?);
+        logs.insert(tp.clone(), log.clone());
+
+        Ok(log)
+    }
+
+    /// Name of the topic with this id, if any of its partitions is on this broker
+    pub fn topic_name(&self, topic_id: &KafUuid) -> Option<String> {
+        self.logs
+            .read()
+            .unwrap()
+            .values()
+            .find(|log| log.topic_id().as_ref() == Some(topic_id))
+            .map(|log| log.topic_partition().topic.clone())
+    }
+
+    pub fn all_logs(&self) -> Vec<Arc<PartitionLog>> {
+        self.logs.read().unwrap().values().cloned().collect()
+    }
+
+    /// Stop serving the log and rename its dir with the `-delete` suffix, its
+    /// files are removed after log.segment.delete.delay.ms by `delete_logs`.
+    /// Returns the log, None if there was none.
+    pub fn async_delete_log(&self, tp: &TopicPartition) -> Result<Option<Arc<PartitionLog>>, LogError> {
+        let Some(log) = self.logs.write().unwrap().remove(tp) else {
+            return Ok(None);
+        };
+        let unique_id: String = KafUuid::random().0.iter().map(|b| format!("{:02x}", b)).collect();
+        let deleted_dir = log
+            .dir()
+            .with_file_name(format!("{}.{}{}", tp.dir_name(), unique_id, DELETE_DIR_SUFFIX));
+        log.rename_for_deletion(&deleted_dir)?;
+        self.logs_to_delete
+            .lock()
+            .unwrap()
+            .push((deleted_dir, self.clock.now_ms() + self.file_delete_delay_ms));
+        self.checkpoint_recovery_points()?;
+        self.checkpoint_log_start_offsets()?;
+        Ok(Some(log))
+    }
+
+    /// Remove the dirs of deleted logs whose delay has passed, returns how
+    /// many were removed
+    pub fn delete_logs(&self) -> usize {
+        let now = self.clock.now_ms();
+        let due: Vec<PathBuf> = {
+            let mut logs_to_delete = self.logs_to_delete.lock().unwrap();
+            let (due, waiting) = logs_to_delete.drain(..).partition(|(_, delete_at)| *delete_at <= now);
+            *logs_to_delete = waiting;
+            due.into_iter().map(|(dir, _)| dir).collect()
+        };
+        let mut deleted = 0;
+        for dir in due {
+            match fs::remove_dir_all(&dir) {
+                Ok(()) => deleted += 1,
+                Err(e) => println!("unable to delete {}: {}", dir.display(), e),
+            }
+        }
+        deleted
+    }
+
+    /// Enforce retention on every log, returns the number of segments deleted.
+    /// A failing log doesn't stop the others from being cleaned up.
+    pub fn cleanup_logs(&self) -> usize {
+        let mut deleted = 0;
+        for log in self.all_logs() {
+            match log.delete_old_segments() {
+                Ok(n) => deleted += n,
+                Err(e) => println!("{}: error while enforcing retention: {}", log.topic_partition(), e),
+            }
+        }
+        deleted
+    }
+
+    /// Flush the logs whose flush.ms has passed, then checkpoint the new
+    /// recovery points. Returns the number of logs flushed.
+    pub fn flush_dirty_logs(&self) -> Result<usize, LogError> {
+        let mut flushed = 0;
+        for log in self.all_logs() {
+            match log.flush_if_due() {
+                Ok(true) => flushed += 1,
+                Ok(false) => {}
+                Err(e) => println!("{}: error while flushing: {}", log.topic_partition(), e),
+            }
+        }
+        if flushed > 0 {
+            self.checkpoint_recovery_points()?;
+        }
+        Ok(flushed)
+    }
+
+    /// Compact the logs of compacted topics that are dirty enough
+    pub fn clean_logs(&self) -> Result<(), LogError> {
+        self.cleaner.clean_logs(&self.all_logs())?;
+        Ok(())
+    }
+
+    /// Start the periodic retention check, log cleaner, flusher and recovery
+    /// point and log start offset checkpointing
+    pub fn start_background_tasks(self: &Arc<Self>) -> io::Result<()> {
+        let manager = self.clone();
+        scheduler::schedule(
+            "kafka-log-retention",
+            Duration::from_millis(self.retention_check_interval_ms.max(1) as u64),
+            move || {
+                manager.cleanup_logs();
+            },
+        )?;
+
+        if let Some(backoff_ms) = self.cleaner_backoff_ms {
+            let manager = self.clone();
+            scheduler::schedule("kafka-log-cleaner", Duration::from_millis(backoff_ms.max(1) as u64), move || {
+                if let Err(e) = manager.clean_logs() {
+                    println!("error while compacting logs: {}", e);
+                }
+            })?;
+        }
+
+        if let Some(interval_ms) = self.flush_check_interval_ms {
+            let manager = self.clone();
+            scheduler::schedule("kafka-log-flusher", Duration::from_millis(interval_ms.max(1) as u64), move || {
+                if let Err(e) = manager.flush_dirty_logs() {
+                    println!("error while flushing logs: {}", e);
+                }
+            })?;
+        }
+
+        let manager = self.clone();
+        scheduler::schedule(
+            "kafka-delete-logs",
+            Duration::from_millis(self.file_delete_delay_ms.max(1) as u64),
+            move || {
+                manager.delete_logs();
+            },
+        )?;
+
+        // picks up flushes done by flush.messages and by rolling segments
+        let manager = self.clone();
+        scheduler::schedule(
+            "kafka-recovery-point-checkpoint",
+            Duration::from_millis(self.recovery_point_checkpoint_interval_ms.max(1) as u64),
+            move || {
+                if let Err(e) = manager.checkpoint_recovery_points() {
+                    println!("error while checkpointing recovery points: {}", e);
+                }
+            },
+        )?;
+
+        // picks up log start offsets moved by retention
+        let manager = self.clone();
+        scheduler::schedule(
+            "kafka-log-start-offset-checkpoint",
+            Duration::from_millis(self.log_start_offset_checkpoint_interval_ms.max(1) as u64),
+            move || {
+                if let Err(e) = manager.checkpoint_log_start_offsets() {
+                    println!("error while checkpointing log start offsets: {}", e);
+                }
+            },
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{
+        log::partition_log::FetchIsolation,
+        records::{MemoryRecords, Record, RecordBatchBuilder},
+        utils::clock::MockClock,
+    };
+
+    #[test]
+    fn creates_and_reloads_partition_dirs() {
+        let dirs = [tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap()];
+        let config = BrokerConfig::from_props(HashMap::from([(
+            "log.dirs".to_string(),
+            dirs.iter().map(|d| d.path().to_str().unwrap()).collect::<Vec<_>>().join(","),
+        )]));
+
+        let manager = LogManager::startup(&config, Arc::new(SystemClock)).unwrap();
+        let a = manager.get_or_create_log(&TopicPartition::new("my-topic", 0), &HashMap::new()).unwrap();
+        let b = manager.get_or_create_log(&TopicPartition::new("my-topic", 1), &HashMap::new()).unwrap();
+        // spread over both dirs
+        assert_ne!(a.dir().parent(), b.dir().parent());
+        drop(manager);
+
+        let manager = LogManager::startup(&config, Arc::new(SystemClock)).unwrap();
+        assert_eq!(manager.all_logs().len(), 2);
+        assert!(manager.get_log(&TopicPartition::new("my-topic", 1)).is_some());
+    }
+
+    #[test]
+    fn retention_honours_topic_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = BrokerConfig::from_props(HashMap::from([
+            ("log.dirs".to_string(), dir.path().to_str().unwrap().to_string()),
+            ("log.retention.hours".to_string(), "1".to_string()),
+        ]));
+        let clock = Arc::new(MockClock::new(0));
+        let manager = LogManager::startup(&config, clock.clone()).unwrap();
+
+        let short = TopicPartition::new("short", 0);
+        let default = TopicPartition::new("default", 0);
+        manager
+            .get_or_create_log(&short, &HashMap::from([("retention.ms".to_string(), "1000".to_string())]))
+            .unwrap();
+        manager.get_or_create_log(&default, &HashMap::new()).unwrap();
+        for tp in [&short, &default] {
+            let log = manager.get_log(tp).unwrap();
+            for _ in 0..3 {
+                let batch = RecordBatchBuilder::new(0)
+                    .append(&Record::new(clock.now_ms(), None, Some(Bytes::from_static(b"v"))))
+                    .build()
+                    .unwrap();
+                log.append_as_leader(&MemoryRecords::from_batches(&[batch])).unwrap();
+                log.roll().unwrap();
+            }
+        }
+
+        clock.advance(60_000);
+        assert_eq!(manager.cleanup_logs(), 3);
+        assert_eq!(manager.get_log(&short).unwrap().log_start_offset(), 3);
+        assert_eq!(manager.get_log(&default).unwrap().log_start_offset(), 0);
+
+        clock.advance(60 * 60 * 1000);
+        assert_eq!(manager.cleanup_logs(), 3);
+        assert_eq!(manager.get_log(&default).unwrap().log_start_offset(), 3);
+    }
+
+    #[test]
+    fn flushes_by_message_count_and_age_and_checkpoints_the_recovery_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = BrokerConfig::from_props(HashMap::from([
+            ("log.dirs".to_string(), dir.path().to_str().unwrap().to_string()),
+            ("log.flush.interval.ms".to_string(), "1000".to_string()),
+        ]));
+        let clock = Arc::new(MockClock::new(0));
+        let manager = LogManager::startup(&config, clock.clone()).unwrap();
+        let by_count = TopicPartition::new("by-count", 0);
+        let by_age = TopicPartition::new("by-age", 0);
+        manager
+            .get_or_create_log(&by_count, &HashMap::from([("flush.messages".to_string(), "3".to_string())]))
+            .unwrap();
+        manager.get_or_create_log(&by_age, &HashMap::new()).unwrap();
+        let append = |tp: &TopicPartition| {
+            let batch = RecordBatchBuilder::new(0)
+                .append(&Record::new(clock.now_ms(), None, Some(Bytes::from_static(b"v"))))
+                .build()
+                .unwrap();
+            manager.get_log(tp).unwrap().append_as_leader(&MemoryRecords::from_batches(&[batch])).unwrap();
+        };
+        let checkpointed = |tp: &TopicPartition| {
+            OffsetCheckpointFile::new(dir.path(), RECOVERY_POINT_CHECKPOINT_FILE).read().unwrap().get(tp).copied()
+        };
+
+        for _ in 0..4 {
+            append(&by_count);
+            append(&by_age);
+        }
+        // flushed on the third message, not yet the fourth
+        assert_eq!(manager.get_log(&by_count).unwrap().recovery_point(), 3);
+        assert_eq!(manager.get_log(&by_count).unwrap().unflushed_messages(), 1);
+        assert_eq!(manager.get_log(&by_age).unwrap().recovery_point(), 0);
+        assert_eq!(manager.flush_dirty_logs().unwrap(), 0);
+
+        clock.advance(1000);
+        // the by-count log also has a message waiting past flush.ms
+        assert_eq!(manager.flush_dirty_logs().unwrap(), 2);
+        assert_eq!(manager.get_log(&by_age).unwrap().recovery_point(), 4);
+        assert_eq!(checkpointed(&by_age), Some(4));
+        assert_eq!(checkpointed(&by_count), Some(4));
+        assert_eq!(manager.flush_dirty_logs().unwrap(), 0);
+    }
+
+    #[test]
+    fn deleted_logs_are_renamed_then_removed_after_the_delay() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = BrokerConfig::from_props(HashMap::from([
+            ("log.dirs".to_string(), dir.path().to_str().unwrap().to_string()),
+            ("log.segment.delete.delay.ms".to_string(), "1000".to_string()),
+        ]));
+        let clock = Arc::new(MockClock::new(0));
+        let manager = LogManager::startup(&config, clock.clone()).unwrap();
+        let tp = TopicPartition::new("my-topic", 0);
+        manager.get_or_create_log(&tp, &HashMap::new()).unwrap();
+        manager.get_or_create_log(&TopicPartition::new("my-topic", 1), &HashMap::new()).unwrap();
+
+        let log = manager.async_delete_log(&tp).unwrap().unwrap();
+        assert!(manager.get_log(&tp).is_none());
+        assert!(manager.async_delete_log(&tp).unwrap().is_none());
+        assert!(!dir.path().join("my-topic-0").exists());
+        let renamed = log.dir().with_file_name(
+            fs::read_dir(dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .find(|name| name.to_str().unwrap().ends_with(DELETE_DIR_SUFFIX))
+                .unwrap(),
+        );
+        assert!(matches!(log.flush(), Err(LogError::LogDeleted(_))));
+        assert_eq!(manager.delete_logs(), 0);
+        clock.advance(1000);
+        assert_eq!(manager.delete_logs(), 1);
+        assert!(!renamed.exists());
+
+        // one left behind by a broker stopped before removing it is removed after a restart
+        manager.async_delete_log(&TopicPartition::new("my-topic", 1)).unwrap();
+        drop(manager);
+        let manager = LogManager::startup(&config, clock.clone()).unwrap();
+        assert!(manager.all_logs().is_empty());
+        assert_eq!(manager.delete_logs(), 1);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().filter(|e| e.as_ref().unwrap().path().is_dir()).count(), 0);
+    }
+
+    #[test]
+    fn deleted_records_stay_deleted_after_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = BrokerConfig::from_props(HashMap::from([
+            ("log.dirs".to_string(), dir.path().to_str().unwrap().to_string()),
+            ("log.segment.bytes".to_string(), "150".to_string()),
+        ]));
+        let manager = LogManager::startup(&config, Arc::new(SystemClock)).unwrap();
+        let tp = TopicPartition::new("my-topic", 0);
+        let log = manager.get_or_create_log(&tp, &HashMap::new()).unwrap();
+        for _ in 0..5 {
+            let batch = RecordBatchBuilder::new(0)
+                .append(&Record::new(1_000, None, Some(Bytes::from(vec![b'v'; 50]))))
+                .append(&Record::new(1_000, None, Some(Bytes::from(vec![b'v'; 50]))))
+                .build()
+                .unwrap();
+            log.append_as_leader(&MemoryRecords::from_batches(&[batch])).unwrap();
+        }
+        assert_eq!(log.num_segments(), 5);
+
+        assert!(matches!(log.delete_records_before(11), Err(LogError::OffsetOutOfRange { .. })));
+        // the segment of offsets 0-1 goes, 2-3 is kept but 2 can't be read
+        assert_eq!(log.delete_records_before(3).unwrap(), 3);
+        assert_eq!(log.num_segments(), 4);
+        assert!(matches!(log.read(2, 1024, FetchIsolation::HighWatermark, true), Err(LogError::OffsetOutOfRange { .. })));
+        // never backwards
+        assert_eq!(log.delete_records_before(1).unwrap(), 3);
+        manager.checkpoint_log_start_offsets().unwrap();
+        drop((log, manager));
+
+        let manager = LogManager::startup(&config, Arc::new(SystemClock)).unwrap();
+        let log = manager.get_log(&tp).unwrap();
+        assert_eq!((log.log_start_offset(), log.log_end_offset()), (3, 10));
+        assert_eq!(log.delete_records_before(10).unwrap(), 10);
+        // the active segment stays, empty as far as readers are concerned
+        assert_eq!(log.num_segments(), 1);
+    }
+}
@@ -0,0 +1,84 @@
+pub mod checkpoint;
+pub mod cleaner;
+pub mod config;
+pub mod index;
+pub mod log_manager;
+pub mod partition_log;
+pub mod partition_metadata;
+pub mod segment;
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    common::{error::error_code, topic_partition::TopicPartition, uuid::KafUuid},
+    records::RecordError,
+};
+
+pub use config::LogConfig;
+pub use log_manager::LogManager;
+pub use partition_log::PartitionLog;
+pub use segment::LogSegment;
+
+pub const LOG_FILE_SUFFIX: &str = ".log";
+pub const INDEX_FILE_SUFFIX: &str = ".index";
+pub const TIME_INDEX_FILE_SUFFIX: &str = ".timeindex";
+pub const CLEANED_FILE_SUFFIX: &str = ".cleaned";
+pub const SWAP_FILE_SUFFIX: &str = ".swap";
+/// A deleted partition's dir, `<topic>-<partition>.<unique id>-delete`,
+/// waiting to be removed
+pub const DELETE_DIR_SUFFIX: &str = "-delete";
+
+#[derive(thiserror::Error, Debug)]
+pub enum LogError {
+    #[error("i/o error: {0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Record(#[from] RecordError),
+    #[error("batch of {size} bytes is larger than max.message.bytes ({max})")]
+    RecordTooLarge { size: usize, max: usize },
+    #[error("topic id {found} of the log doesn't match {expected}")]
+    InconsistentTopicId { expected: KafUuid, found: KafUuid },
+    #[error("log of {0} was deleted")]
+    LogDeleted(TopicPartition),
+    #[error("offset {offset} is out of range [{log_start_offset}, {log_end_offset}]")]
+    OffsetOutOfRange {
+        offset: i64,
+        log_start_offset: i64,
+        log_end_offset: i64,
+    },
+}
+
+impl LogError {
+    pub fn error_code(&self) -> i16 {
+        match self {
+            LogError::Io(_) => error_code::KAFKA_STORAGE_ERROR,
+            LogError::Record(e) => e.error_code(),
+            LogError::RecordTooLarge { .. } => error_code::MESSAGE_TOO_LARGE,
+            LogError::InconsistentTopicId { .. } => error_code::INCONSISTENT_TOPIC_ID,
+            LogError::LogDeleted(_) => error_code::UNKNOWN_TOPIC_OR_PARTITION,
+            LogError::OffsetOutOfRange { .. } => error_code::OFFSET_OUT_OF_RANGE,
+        }
+    }
+}
+
+/// Segment files are named after their base offset, zero padded to 20 digits
+/// like Kafka's so `kafka-dump-log.sh` and friends can read our log dirs.
+pub fn file_name_for_offset(base_offset: i64, suffix: &str) -> String {
+    format!("{:020}{}", base_offset, suffix)
+}
+
+pub fn segment_file(dir: &Path, base_offset: i64, suffix: &str) -> PathBuf {
+    dir.join(file_name_for_offset(base_offset, suffix))
+}
+
+/// Base offset of a segment file named by `file_name_for_offset`
+pub fn offset_from_file_name(name: &str, suffix: &str) -> Option<i64> {
+    let digits = name.strip_suffix(suffix)?;
+    if digits.len() != 20 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
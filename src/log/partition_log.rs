@@ -0,0 +1,934 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+};
+
+use crate::{
+    common::{topic_partition::TopicPartition, uuid::KafUuid},
+    log::{
+        index::TimestampOffset, offset_from_file_name, partition_metadata::PartitionMetadataFile, segment_file, LogConfig, LogError, LogSegment,
+        CLEANED_FILE_SUFFIX, INDEX_FILE_SUFFIX, LOG_FILE_SUFFIX, SWAP_FILE_SUFFIX, TIME_INDEX_FILE_SUFFIX,
+    },
+    records::{validation::validate_records, MemoryRecords, RecordError, TimestampType, NO_TIMESTAMP},
+    utils::clock::Clock,
+};
+
+/// What happened to a batch of records appended to the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogAppendInfo {
+    pub first_offset: i64,
+    pub last_offset: i64,
+    pub max_timestamp: i64,
+    /// the broker's timestamp when the topic uses LogAppendTime, NO_TIMESTAMP otherwise
+    pub log_append_time: i64,
+    pub log_start_offset: i64,
+    pub num_records: usize,
+    pub valid_bytes: usize,
+}
+
+/// Records read from the log, with a snapshot of the log's offsets at read time
+#[derive(Debug, Clone, Default)]
+pub struct FetchDataInfo {
+    pub records: MemoryRecords,
+    pub high_watermark: i64,
+    pub last_stable_offset: i64,
+    pub log_start_offset: i64,
+    pub log_end_offset: i64,
+}
+
+/// Up to where a reader may see the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchIsolation {
+    /// everything that's been written, for replicas
+    LogEnd,
+    /// committed data only, for consumers
+    HighWatermark,
+    /// committed data that isn't part of an open transaction, for read_committed consumers
+    TxnCommitted,
+}
+
+#[derive(Debug)]
+pub(super) struct LogState {
+    pub(super) segments: BTreeMap<i64, LogSegment>,
+    pub(super) log_start_offset: i64,
+    pub(super) log_end_offset: i64,
+    pub(super) high_watermark: i64,
+    /// everything before this offset is known to be on disk
+    pub(super) recovery_point: i64,
+    pub(super) last_flush_ms: i64,
+    /// the topic was deleted and the dir renamed, its files are going away
+    pub(super) deleted: bool,
+}
+
+impl LogState {
+    pub(super) fn active_segment(&mut self) -> &mut LogSegment {
+        self.segments.values_mut().next_back().expect("a log always has an active segment")
+    }
+}
+
+/// The log of one topic-partition: a directory of segments, the last of
+/// which (the active segment) takes all appends.
+#[derive(Debug)]
+pub struct PartitionLog {
+    topic_partition: TopicPartition,
+    dir: PathBuf,
+    config: RwLock<LogConfig>,
+    /// from partition.metadata, None for logs created before topics had ids
+    topic_id: RwLock<Option<KafUuid>>,
+    state: Mutex<LogState>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PartitionLog {
+    /// Open the log in `dir`, creating the directory and a first segment if
+    /// needed. Segments past `recovery_point` may not have made it to disk
+    /// whole, so they're checked and truncated at the first invalid batch.
+    /// Records before the checkpointed `log_start_offset` stay hidden even
+    /// if their segment is still around.
+    pub fn open(
+        dir: PathBuf,
+        topic_partition: TopicPartition,
+        config: LogConfig,
+        recovery_point: i64,
+        log_start_offset: i64,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, LogError> {
+        fs::create_dir_all(&dir)?;
+        let now = clock.now_ms();
+
+        let mut segments = BTreeMap::new();
+        let mut index_files = vec![];
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            // a segment rewritten by the cleaner: `.cleaned` while it's being
+            // written, `.swap` once complete and about to replace the original
+            if name.ends_with(CLEANED_FILE_SUFFIX) {
+                println!("{}: deleting incomplete cleaned segment {}", topic_partition, name);
+                fs::remove_file(entry.path())?;
+                continue;
+            }
+            if let Some(log_name) = name.strip_suffix(SWAP_FILE_SUFFIX) {
+                if let Some(base_offset) = offset_from_file_name(log_name, LOG_FILE_SUFFIX) {
+                    println!("{}: completing swap of cleaned segment {}", topic_partition, log_name);
+                    for suffix in [INDEX_FILE_SUFFIX, TIME_INDEX_FILE_SUFFIX] {
+                        let _ = fs::remove_file(segment_file(&dir, base_offset, suffix));
+                    }
+                    fs::rename(entry.path(), dir.join(log_name))?;
+                    segments.insert(base_offset, LogSegment::open(&dir, base_offset, &config, now)?);
+                }
+                continue;
+            }
+            if segments.contains_key(&offset_from_file_name(name, LOG_FILE_SUFFIX).unwrap_or(-1)) {
+                continue;
+            }
+            if let Some(base_offset) = offset_from_file_name(name, LOG_FILE_SUFFIX) {
+                segments.insert(base_offset, LogSegment::open(&dir, base_offset, &config, now)?);
+            } else if let Some(base_offset) = offset_from_file_name(name, INDEX_FILE_SUFFIX)
+                .or_else(|| offset_from_file_name(name, TIME_INDEX_FILE_SUFFIX))
+            {
+                index_files.push((base_offset, entry.path()));
+            }
+        }
+        for (base_offset, path) in index_files {
+            if !segments.contains_key(&base_offset) {
+                println!("{}: deleting orphaned index {}", topic_partition, path.display());
+                fs::remove_file(path)?;
+            }
+        }
+
+        Self::recover_segments(&topic_partition, &mut segments, &config, recovery_point)?;
+        if segments.is_empty() {
+            segments.insert(0, LogSegment::create(&dir, 0, &config, now)?);
+        }
+        segments.values_mut().next_back().unwrap().resize_indexes(config.max_index_size)?;
+
+        let (&last_base, last) = segments.iter().next_back().unwrap();
+        let log_end_offset = last.read_next_offset()?.unwrap_or(last_base);
+        let log_start_offset = log_start_offset.max(*segments.keys().next().unwrap()).min(log_end_offset);
+
+        let topic_id = PartitionMetadataFile::new(&dir).read()?;
+
+        Ok(PartitionLog {
+            topic_partition,
+            dir,
+            config: RwLock::new(config),
+            topic_id: RwLock::new(topic_id),
+            state: Mutex::new(LogState {
+                segments,
+                log_start_offset,
+                log_end_offset,
+                // nothing to replicate to, whatever made it to disk is committed
+                high_watermark: log_end_offset,
+                recovery_point: recovery_point.min(log_end_offset),
+                last_flush_ms: clock.now_ms(),
+                deleted: false,
+            }),
+            clock,
+        })
+    }
+
+    /// Validate the segments that may hold offsets >= `recovery_point`, and
+    /// any whose indexes need rebuilding. Everything after the first invalid
+    /// batch is discarded, including later segments.
+    fn recover_segments(
+        topic_partition: &TopicPartition,
+        segments: &mut BTreeMap<i64, LogSegment>,
+        config: &LogConfig,
+        recovery_point: i64,
+    ) -> Result<(), LogError> {
+        let first_unflushed = segments
+            .range(..=recovery_point)
+            .next_back()
+            .map(|(base, _)| *base)
+            .unwrap_or(recovery_point);
+
+        let mut truncated = false;
+        for base_offset in segments.keys().copied().collect::<Vec<_>>() {
+            if truncated {
+                let segment = segments.remove(&base_offset).unwrap();
+                println!(
+                    "{}: deleting segment {} after a truncated one",
+                    topic_partition,
+                    segment.path().display()
+                );
+                segment.delete()?;
+                continue;
+            }
+
+            let segment = segments.get_mut(&base_offset).unwrap();
+            if base_offset < first_unflushed && !segment.needs_index_rebuild() {
+                continue;
+            }
+            if segment.needs_index_rebuild() {
+                println!("{}: rebuilding indexes of {}", topic_partition, segment.path().display());
+            }
+            let discarded = segment.recover(config.max_index_size)?;
+            if discarded > 0 {
+                println!(
+                    "{}: discarded {} invalid bytes at the end of {}, log now ends at offset {}",
+                    topic_partition,
+                    discarded,
+                    segment.path().display(),
+                    segment.read_next_offset()?.unwrap_or(base_offset)
+                );
+                truncated = true;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn state(&self) -> MutexGuard<'_, LogState> {
+        self.state.lock().unwrap()
+    }
+
+    /// The state of a log that hasn't been deleted
+    fn live_state(&self) -> Result<MutexGuard<'_, LogState>, LogError> {
+        let state = self.state();
+        if state.deleted {
+            return Err(LogError::LogDeleted(self.topic_partition.clone()));
+        }
+        Ok(state)
+    }
+
+    pub(super) fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    pub fn topic_partition(&self) -> &TopicPartition {
+        &self.topic_partition
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn config(&self) -> LogConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn update_config(&self, config: LogConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    pub fn topic_id(&self) -> Option<KafUuid> {
+        *self.topic_id.read().unwrap()
+    }
+
+    /// Record the id of the topic this partition belongs to in partition.metadata.
+    /// A log keeps its id for life, a different one means the dir belongs to
+    /// another incarnation of the topic.
+    pub fn assign_topic_id(&self, topic_id: KafUuid) -> Result<(), LogError> {
+        let mut current = self.topic_id.write().unwrap();
+        match *current {
+            Some(existing) if existing == topic_id => Ok(()),
+            Some(existing) => Err(LogError::InconsistentTopicId {
+                expected: topic_id,
+                found: existing,
+            }),
+            None => {
+                PartitionMetadataFile::new(&self.dir).write(topic_id)?;
+                *current = Some(topic_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Move the log's dir to `new_dir` for deleting later. The log can't be
+    /// read or written from then on, callers see `LogError::LogDeleted`.
+    pub fn rename_for_deletion(&self, new_dir: &Path) -> Result<(), LogError> {
+        let mut state = self.live_state()?;
+        fs::rename(&self.dir, new_dir)?;
+        state.deleted = true;
+        Ok(())
+    }
+
+    pub fn log_start_offset(&self) -> i64 {
+        self.state().log_start_offset
+    }
+
+    pub fn log_end_offset(&self) -> i64 {
+        self.state().log_end_offset
+    }
+
+    pub fn high_watermark(&self) -> i64 {
+        self.state().high_watermark
+    }
+
+    /// Consumers reading committed data only may not go past the first open
+    /// transaction. Without a transaction coordinator none are ever open.
+    pub fn last_stable_offset(&self) -> i64 {
+        self.state().high_watermark
+    }
+
+    pub fn recovery_point(&self) -> i64 {
+        self.state().recovery_point
+    }
+
+    /// Offsets appended since the last flush
+    pub fn unflushed_messages(&self) -> i64 {
+        let state = self.state();
+        state.log_end_offset - state.recovery_point
+    }
+
+    pub fn last_flush_ms(&self) -> i64 {
+        self.state().last_flush_ms
+    }
+
+    pub fn num_segments(&self) -> usize {
+        self.state().segments.len()
+    }
+
+    pub fn size_in_bytes(&self) -> u64 {
+        self.state().segments.values().map(LogSegment::size).sum()
+    }
+
+    /// Move the high watermark, it can never go past the log end offset
+    pub fn update_high_watermark(&self, offset: i64) -> i64 {
+        let mut state = self.state();
+        state.high_watermark = offset.clamp(state.log_start_offset, state.log_end_offset);
+        state.high_watermark
+    }
+
+    /// Validate records from a producer, assign them offsets starting at the
+    /// log end offset and write them to the active segment, rolling first if
+    /// the segment is full or too old.
+    pub fn append_as_leader(&self, records: &MemoryRecords) -> Result<LogAppendInfo, LogError> {
+        let config = self.config();
+        for entry in records.entries() {
+            let size = entry?.as_bytes().len();
+            if size > config.max_message_bytes {
+                return Err(LogError::RecordTooLarge {
+                    size,
+                    max: config.max_message_bytes,
+                });
+            }
+        }
+        let validated = validate_records(records, config.compression_type)?;
+        if config.cleanup_policy.compact {
+            for batch in validated.records.batches() {
+                for record in batch?.records()? {
+                    if record?.key.is_none() {
+                        return Err(RecordError::InvalidRecord("compacted topic cannot accept message without key".into()).into());
+                    }
+                }
+            }
+        }
+        let now = self.clock.now_ms();
+
+        let mut state = self.live_state()?;
+        let first_offset = state.log_end_offset;
+        let log_append_time = match config.message_timestamp_type {
+            TimestampType::LogAppendTime => now,
+            TimestampType::CreateTime => NO_TIMESTAMP,
+        };
+
+        let mut next_offset = first_offset;
+        let mut batches = vec![];
+        for batch in validated.records.batches() {
+            let mut batch = batch?;
+            batch.set_base_offset(next_offset);
+            if config.message_timestamp_type == TimestampType::LogAppendTime {
+                batch.set_max_timestamp(TimestampType::LogAppendTime, now);
+            }
+            next_offset = batch.next_offset();
+            batches.push(batch);
+        }
+        let assigned = MemoryRecords::from_batches(&batches);
+
+        self.maybe_roll(&mut state, &config, assigned.size_in_bytes() as u64, next_offset - 1, now)?;
+        state.active_segment().append(&assigned)?;
+        state.log_end_offset = next_offset;
+        state.high_watermark = next_offset;
+        if state.log_end_offset - state.recovery_point >= config.flush_messages {
+            self.flush_locked(&mut state)?;
+        }
+
+        Ok(LogAppendInfo {
+            first_offset,
+            last_offset: next_offset - 1,
+            max_timestamp: if log_append_time != NO_TIMESTAMP { now } else { validated.max_timestamp },
+            log_append_time,
+            log_start_offset: state.log_start_offset,
+            num_records: validated.num_records,
+            valid_bytes: assigned.size_in_bytes(),
+        })
+    }
+
+    fn maybe_roll(
+        &self,
+        state: &mut LogState,
+        config: &LogConfig,
+        incoming_bytes: u64,
+        last_offset: i64,
+        now: i64,
+    ) -> Result<(), LogError> {
+        let active = state.active_segment();
+        let full = active.size() + incoming_bytes > config.segment_bytes || active.indexes_full(last_offset);
+        let expired = now - active.created_ms() >= config.segment_ms;
+        if !active.is_empty() && (full || expired) {
+            self.roll_locked(state, config)?;
+        }
+        Ok(())
+    }
+
+    fn roll_locked(&self, state: &mut LogState, config: &LogConfig) -> Result<(), LogError> {
+        let new_base = state.log_end_offset;
+        let active = state.active_segment();
+        if active.base_offset() == new_base && active.is_empty() {
+            return Ok(());
+        }
+        // closing flushes the segment, and every segment before it was
+        // flushed when it was rolled
+        active.close_for_appends()?;
+        state.recovery_point = state.recovery_point.max(new_base);
+        let segment = LogSegment::create(&self.dir, new_base, config, self.clock.now_ms())?;
+        state.segments.insert(new_base, segment);
+        Ok(())
+    }
+
+    /// Close the active segment and start a new one at the log end offset
+    pub fn roll(&self) -> Result<(), LogError> {
+        let config = self.config();
+        let mut state = self.live_state()?;
+        self.roll_locked(&mut state, &config)
+    }
+
+    /// Fsync everything appended since the last flush and move the recovery
+    /// point up to the log end offset
+    pub fn flush(&self) -> Result<(), LogError> {
+        let mut state = self.live_state()?;
+        self.flush_locked(&mut state)
+    }
+
+    /// Flush if flush.ms has passed since the last flush and there's anything
+    /// to flush, returns whether it did
+    pub fn flush_if_due(&self) -> Result<bool, LogError> {
+        let config = self.config();
+        let mut state = self.state();
+        let due = self.clock.now_ms() - state.last_flush_ms >= config.flush_ms;
+        if state.deleted || !due || state.log_end_offset <= state.recovery_point {
+            return Ok(false);
+        }
+        self.flush_locked(&mut state)?;
+        Ok(true)
+    }
+
+    fn flush_locked(&self, state: &mut LogState) -> Result<(), LogError> {
+        let first_unflushed = state
+            .segments
+            .range(..=state.recovery_point)
+            .next_back()
+            .map(|(&base, _)| base)
+            .unwrap_or(state.log_start_offset);
+        for segment in state.segments.range(first_unflushed..).map(|(_, segment)| segment) {
+            segment.flush()?;
+        }
+        state.recovery_point = state.log_end_offset;
+        state.last_flush_ms = self.clock.now_ms();
+        Ok(())
+    }
+
+    /// Delete segments past retention.ms or retention.bytes, or entirely
+    /// before the log start offset, moving the log start offset up to the
+    /// first remaining segment. Returns the number of segments deleted.
+    pub fn delete_old_segments(&self) -> Result<usize, LogError> {
+        let config = self.config();
+        let now = self.clock.now_ms();
+        let mut state = self.state();
+        if state.deleted {
+            return Ok(0);
+        }
+
+        let log_start_offset = state.log_start_offset;
+        let mut deleted = self.delete_segments_while(&mut state, "log start offset breach", |_, next_base| {
+            next_base <= log_start_offset
+        })?;
+
+        // compacted topics only lose records to the cleaner, unless they're also `delete`
+        if !config.cleanup_policy.delete {
+            return Ok(deleted);
+        }
+
+        if config.retention_ms >= 0 {
+            deleted += self.delete_segments_while(&mut state, "retention.ms breach", |segment, _| {
+                now - segment.largest_timestamp() > config.retention_ms
+            })?;
+        }
+
+        if config.retention_bytes >= 0 {
+            let mut excess = state.segments.values().map(LogSegment::size).sum::<u64>() as i64 - config.retention_bytes;
+            deleted += self.delete_segments_while(&mut state, "retention.bytes breach", |segment, _| {
+                let fits = excess - segment.size() as i64 >= 0;
+                if fits {
+                    excess -= segment.size() as i64;
+                }
+                fits
+            })?;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Move the log start offset up to `offset` for DeleteRecords: records
+    /// before it can't be read anymore, and segments entirely before it are
+    /// deleted. It can't go past the high watermark. Returns the new log start offset.
+    pub fn delete_records_before(&self, offset: i64) -> Result<i64, LogError> {
+        let mut state = self.live_state()?;
+        if offset < 0 || offset > state.high_watermark {
+            return Err(LogError::OffsetOutOfRange {
+                offset,
+                log_start_offset: state.log_start_offset,
+                log_end_offset: state.high_watermark,
+            });
+        }
+        if offset > state.log_start_offset {
+            state.log_start_offset = offset;
+            self.delete_segments_while(&mut state, "log start offset breach", |_, next_base| next_base <= offset)?;
+        }
+        Ok(state.log_start_offset)
+    }
+
+    /// Delete segments from the oldest for as long as `predicate(segment,
+    /// next segment's base offset)` holds. The active segment and segments
+    /// holding offsets past the high watermark are never deleted.
+    fn delete_segments_while(
+        &self,
+        state: &mut LogState,
+        reason: &str,
+        mut predicate: impl FnMut(&LogSegment, i64) -> bool,
+    ) -> Result<usize, LogError> {
+        let bases: Vec<i64> = state.segments.keys().copied().collect();
+        let mut deletable = vec![];
+        for pair in bases.windows(2) {
+            let (base, next_base) = (pair[0], pair[1]);
+            if next_base > state.high_watermark || !predicate(&state.segments[&base], next_base) {
+                break;
+            }
+            deletable.push(base);
+        }
+
+        for base in &deletable {
+            let segment = state.segments.remove(base).unwrap();
+            println!("{}: deleting segment {} ({})", self.topic_partition, segment.path().display(), reason);
+            segment.delete()?;
+        }
+        if let Some(&first_base) = state.segments.keys().next() {
+            state.log_start_offset = state.log_start_offset.max(first_base);
+            state.recovery_point = state.recovery_point.max(state.log_start_offset);
+        }
+        Ok(deletable.len())
+    }
+
+    /// The first record with a timestamp >= `timestamp`, using the segments'
+    /// time indexes. None if every record is older.
+    pub fn fetch_offset_by_timestamp(&self, timestamp: i64) -> Result<Option<TimestampOffset>, LogError> {
+        let state = self.live_state()?;
+        for segment in state.segments.values() {
+            if segment.max_timestamp_so_far().timestamp < timestamp {
+                continue;
+            }
+            if let Some(found) = segment.find_offset_by_timestamp(timestamp, state.log_start_offset)? {
+                return Ok(Some(found));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The first record with the largest timestamp in the log, None if no
+    /// record has one
+    pub fn fetch_offset_of_max_timestamp(&self) -> Result<Option<TimestampOffset>, LogError> {
+        let max_timestamp = self.state().segments.values().map(|s| s.max_timestamp_so_far().timestamp).max();
+        match max_timestamp {
+            // segments only know the batch it's in, so look the record up
+            Some(timestamp) if timestamp > NO_TIMESTAMP => self.fetch_offset_by_timestamp(timestamp),
+            _ => Ok(None),
+        }
+    }
+
+    /// Read batches starting at the one containing `start_offset`, see `LogSegment::read`
+    pub fn read(
+        &self,
+        start_offset: i64,
+        max_bytes: u64,
+        isolation: FetchIsolation,
+        min_one_message: bool,
+    ) -> Result<FetchDataInfo, LogError> {
+        let state = self.live_state()?;
+
+        if start_offset < state.log_start_offset || start_offset > state.log_end_offset {
+            return Err(LogError::OffsetOutOfRange {
+                offset: start_offset,
+                log_start_offset: state.log_start_offset,
+                log_end_offset: state.log_end_offset,
+            });
+        }
+
+        let max_offset = match isolation {
+            FetchIsolation::LogEnd => state.log_end_offset,
+            FetchIsolation::HighWatermark => state.high_watermark,
+            FetchIsolation::TxnCommitted => state.high_watermark,
+        };
+
+        let mut records = MemoryRecords::empty();
+        if start_offset < max_offset {
+            // start with the segment that may contain start_offset, a batch can
+            // straddle the boundary if the log was compacted, so keep looking
+            let first_base = state
+                .segments
+                .range(..=start_offset)
+                .next_back()
+                .map(|(base, _)| *base)
+                .unwrap_or(start_offset);
+            for segment in state.segments.range(first_base..).map(|(_, s)| s) {
+                if let Some(read) = segment.read(start_offset, max_bytes, Some(max_offset), min_one_message)? {
+                    records = read;
+                    break;
+                }
+            }
+        }
+
+        Ok(FetchDataInfo {
+            records,
+            high_watermark: state.high_watermark,
+            last_stable_offset: state.high_watermark,
+            log_start_offset: state.log_start_offset,
+            log_end_offset: state.log_end_offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{
+        records::{Record, RecordBatchBuilder},
+        utils::clock::{MockClock, SystemClock},
+    };
+
+    fn records(count: usize, value_size: usize) -> MemoryRecords {
+        records_at(1_000, count, value_size)
+    }
+
+    fn records_at(timestamp: i64, count: usize, value_size: usize) -> MemoryRecords {
+        let mut builder = RecordBatchBuilder::new(0);
+        for i in 0..count {
+            builder.append(&Record::new(timestamp + i as i64, None, Some(Bytes::from(vec![b'x'; value_size]))));
+        }
+        MemoryRecords::from_batches(&[builder.build().unwrap()])
+    }
+
+    fn open(dir: &Path, config: LogConfig) -> PartitionLog {
+        open_with_clock(dir, config, Arc::new(SystemClock))
+    }
+
+    fn open_with_clock(dir: &Path, config: LogConfig, clock: Arc<dyn Clock>) -> PartitionLog {
+        PartitionLog::open(dir.join("topic-0"), TopicPartition::new("topic", 0), config, 0, 0, clock).unwrap()
+    }
+
+    #[test]
+    fn assigns_offsets_and_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open(dir.path(), LogConfig::default());
+
+        let first = log.append_as_leader(&records(3, 10)).unwrap();
+        let second = log.append_as_leader(&records(2, 10)).unwrap();
+        assert_eq!((first.first_offset, first.last_offset), (0, 2));
+        assert_eq!((second.first_offset, second.last_offset), (3, 4));
+        assert_eq!(log.log_end_offset(), 5);
+        assert_eq!(log.high_watermark(), 5);
+
+        let fetched = log.read(4, 1024 * 1024, FetchIsolation::HighWatermark, true).unwrap();
+        let batches: Vec<_> = fetched.records.batches().map(|b| b.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].base_offset(), 3);
+        batches[0].ensure_valid().unwrap();
+
+        assert!(log.read(5, 1024, FetchIsolation::HighWatermark, true).unwrap().records.is_empty());
+        assert!(matches!(
+            log.read(6, 1024, FetchIsolation::HighWatermark, true),
+            Err(LogError::OffsetOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn read_committed_reads_past_transactional_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open(dir.path(), LogConfig::default());
+        let mut builder = RecordBatchBuilder::new(0).producer(7, 0, 0).transactional(true);
+        builder.append(&Record::new(1_000, None, Some(Bytes::from_static(b"t"))));
+        log.append_as_leader(&MemoryRecords::from_batches(&[builder.build().unwrap()])).unwrap();
+        log.append_as_leader(&records(2, 10)).unwrap();
+        assert_eq!(log.last_stable_offset(), 3);
+
+        let fetched = log.read(0, 1024 * 1024, FetchIsolation::TxnCommitted, true).unwrap();
+        let offsets: Vec<_> = fetched.records.batches().map(|b| b.unwrap().base_offset()).collect();
+        assert_eq!((offsets, fetched.last_stable_offset), (vec![0, 1], 3));
+
+        // and nothing holds it back after a restart
+        drop(log);
+        let log = open(dir.path(), LogConfig::default());
+        assert_eq!(log.last_stable_offset(), log.high_watermark());
+    }
+
+    #[test]
+    fn rolls_segments_by_size_and_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig {
+            segment_bytes: 300,
+            ..Default::default()
+        };
+        let log = open(dir.path(), config.clone());
+        for _ in 0..5 {
+            log.append_as_leader(&records(1, 100)).unwrap();
+        }
+        assert_eq!(log.num_segments(), 5);
+        assert!(dir.path().join("topic-0/00000000000000000004.log").exists());
+
+        // a read spanning segments only returns what's in the first one
+        let fetched = log.read(1, 10_000, FetchIsolation::LogEnd, true).unwrap();
+        assert_eq!(fetched.records.batches().count(), 1);
+
+        drop(log);
+        let log = open(dir.path(), config);
+        assert_eq!(log.log_end_offset(), 5);
+        assert_eq!(log.append_as_leader(&records(1, 1)).unwrap().first_offset, 5);
+    }
+
+    #[test]
+    fn max_bytes_limits_the_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open(dir.path(), LogConfig::default());
+        for _ in 0..3 {
+            log.append_as_leader(&records(1, 100)).unwrap();
+        }
+        let one_batch = log.read(0, 1, FetchIsolation::LogEnd, true).unwrap();
+        assert_eq!(one_batch.records.batches().count(), 1);
+        let nothing = log.read(0, 1, FetchIsolation::LogEnd, false).unwrap();
+        assert!(nothing.records.is_empty());
+    }
+
+    #[test]
+    fn indexes_position_reads_and_timestamp_lookups() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig {
+            index_interval_bytes: 200,
+            ..Default::default()
+        };
+        let log = open(dir.path(), config.clone());
+        // batches of 2 records, timestamps 10_000, 10_001, 10_100, 10_101, ...
+        for i in 0..20 {
+            log.append_as_leader(&records_at(10_000 + i * 100, 2, 100)).unwrap();
+        }
+        {
+            let state = log.state();
+            let segment = state.segments.values().next().unwrap();
+            assert!(segment.offset_index().entries() > 5);
+            assert!(segment.time_index().entries() > 5);
+        }
+
+        let fetched = log.read(25, 1, FetchIsolation::LogEnd, true).unwrap();
+        assert_eq!(fetched.records.batches().next().unwrap().unwrap().base_offset(), 24);
+
+        let found = log.fetch_offset_by_timestamp(10_750).unwrap().unwrap();
+        assert_eq!(found, TimestampOffset { timestamp: 10_800, offset: 16 });
+        let found = log.fetch_offset_by_timestamp(10_801).unwrap().unwrap();
+        assert_eq!(found, TimestampOffset { timestamp: 10_801, offset: 17 });
+        assert_eq!(log.fetch_offset_by_timestamp(20_000).unwrap(), None);
+        assert_eq!(log.fetch_offset_of_max_timestamp().unwrap(), Some(TimestampOffset { timestamp: 11_901, offset: 39 }));
+
+        // indexes are trimmed on close and picked up again when reopened
+        drop(log);
+        let index_len = std::fs::metadata(dir.path().join("topic-0/00000000000000000000.index")).unwrap().len();
+        assert_eq!(index_len % 8, 0);
+        assert!(index_len < config.max_index_size as u64);
+        let log = open(dir.path(), config);
+        assert_eq!(log.fetch_offset_by_timestamp(10_750).unwrap().unwrap().offset, 16);
+        assert_eq!(log.append_as_leader(&records(1, 1)).unwrap().first_offset, 40);
+        // an older record doesn't move the max, a batch with a newer one in the middle does
+        assert_eq!(log.fetch_offset_of_max_timestamp().unwrap().unwrap().offset, 39);
+        let mut builder = RecordBatchBuilder::new(0);
+        for timestamp in [12_000, 50_000, 13_000] {
+            builder.append(&Record::new(timestamp, None, Some(Bytes::from_static(b"x"))));
+        }
+        log.append_as_leader(&MemoryRecords::from_batches(&[builder.build().unwrap()])).unwrap();
+        assert_eq!(log.fetch_offset_of_max_timestamp().unwrap(), Some(TimestampOffset { timestamp: 50_000, offset: 42 }));
+    }
+
+    #[test]
+    fn recovers_a_segment_torn_at_any_byte() {
+        let source = tempfile::tempdir().unwrap();
+        let config = LogConfig {
+            index_interval_bytes: 100,
+            ..Default::default()
+        };
+        let log = open(source.path(), config.clone());
+        // (end position, next offset) of each batch
+        let mut boundaries = vec![(0, 0)];
+        for i in 0..6 {
+            let info = log.append_as_leader(&records(1 + i % 3, 20 + i * 7)).unwrap();
+            boundaries.push((boundaries.last().unwrap().0 + info.valid_bytes, info.last_offset + 1));
+        }
+        drop(log);
+
+        let files: Vec<_> = [LOG_FILE_SUFFIX, INDEX_FILE_SUFFIX, TIME_INDEX_FILE_SUFFIX]
+            .iter()
+            .map(|suffix| {
+                let name = format!("00000000000000000000{}", suffix);
+                (name.clone(), fs::read(source.path().join("topic-0").join(name)).unwrap())
+            })
+            .collect();
+        let segment_len = files[0].1.len();
+        assert_eq!(segment_len, boundaries.last().unwrap().0);
+
+        for cut in 0..=segment_len {
+            let dir = tempfile::tempdir().unwrap();
+            fs::create_dir(dir.path().join("topic-0")).unwrap();
+            for (name, contents) in &files {
+                let contents = if name.ends_with(LOG_FILE_SUFFIX) { &contents[..cut] } else { &contents[..] };
+                fs::write(dir.path().join("topic-0").join(name), contents).unwrap();
+            }
+
+            let log = open(dir.path(), config.clone());
+            let &(valid_bytes, next_offset) = boundaries.iter().rev().find(|(end, _)| *end <= cut).unwrap();
+            assert_eq!(log.log_end_offset(), next_offset, "cut at {}", cut);
+            assert_eq!(log.size_in_bytes(), valid_bytes as u64, "cut at {}", cut);
+
+            let fetched = log.read(0, u64::MAX, FetchIsolation::LogEnd, true).unwrap();
+            assert_eq!(fetched.records.size_in_bytes(), valid_bytes);
+            for batch in fetched.records.batches() {
+                batch.unwrap().ensure_valid().unwrap();
+            }
+            assert_eq!(log.append_as_leader(&records(1, 1)).unwrap().first_offset, next_offset);
+        }
+    }
+
+    #[test]
+    fn truncates_at_a_corrupt_batch_and_drops_later_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig {
+            segment_bytes: 150,
+            ..Default::default()
+        };
+        let log = open(dir.path(), config.clone());
+        for _ in 0..5 {
+            log.append_as_leader(&records(1, 100)).unwrap();
+        }
+        assert_eq!(log.num_segments(), 5);
+        drop(log);
+
+        // flip a byte in the value of the record at offset 2
+        let path = dir.path().join("topic-0/00000000000000000002.log");
+        let mut contents = fs::read(&path).unwrap();
+        *contents.last_mut().unwrap() ^= 0xff;
+        fs::write(&path, contents).unwrap();
+
+        let log = open(dir.path(), config);
+        assert_eq!(log.num_segments(), 3);
+        assert_eq!(log.log_end_offset(), 2);
+        assert!(!dir.path().join("topic-0/00000000000000000003.log").exists());
+        assert!(!dir.path().join("topic-0/00000000000000000004.index").exists());
+        assert_eq!(log.append_as_leader(&records(1, 1)).unwrap().first_offset, 2);
+    }
+
+    #[test]
+    fn retention_ms_deletes_old_segments_but_never_the_active_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new(100_000));
+        let config = LogConfig {
+            segment_bytes: 150,
+            retention_ms: 75_000,
+            ..Default::default()
+        };
+        let log = open_with_clock(dir.path(), config, clock.clone());
+        for i in 1..=5 {
+            log.append_as_leader(&records_at(i * 10_000, 1, 100)).unwrap();
+        }
+        assert_eq!(log.num_segments(), 5);
+
+        // 10_000 and 20_000 are more than 75s old
+        assert_eq!(log.delete_old_segments().unwrap(), 2);
+        assert_eq!(log.log_start_offset(), 2);
+        assert!(!dir.path().join("topic-0/00000000000000000001.log").exists());
+        assert!(matches!(
+            log.read(1, 1024, FetchIsolation::LogEnd, true),
+            Err(LogError::OffsetOutOfRange { .. })
+        ));
+
+        clock.advance(1_000_000);
+        assert_eq!(log.delete_old_segments().unwrap(), 2);
+        assert_eq!(log.num_segments(), 1);
+        assert_eq!(log.log_start_offset(), 4);
+        assert_eq!(log.log_end_offset(), 5);
+    }
+
+    #[test]
+    fn retention_bytes_keeps_the_log_under_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig {
+            segment_bytes: 150,
+            ..Default::default()
+        };
+        let log = open_with_clock(dir.path(), config.clone(), Arc::new(MockClock::new(20_000)));
+        for _ in 0..5 {
+            log.append_as_leader(&records(1, 100)).unwrap();
+        }
+        let segment_size = log.size_in_bytes() as i64 / 5;
+        assert_eq!(log.delete_old_segments().unwrap(), 0);
+
+        log.update_config(LogConfig {
+            retention_bytes: 2 * segment_size + 1,
+            ..config
+        });
+        assert_eq!(log.delete_old_segments().unwrap(), 2);
+        assert_eq!(log.log_start_offset(), 2);
+        assert_eq!(log.size_in_bytes() as i64, 3 * segment_size);
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::common::uuid::KafUuid;
+
+pub const PARTITION_METADATA_FILE: &str = "partition.metadata";
+
+const PARTITION_METADATA_VERSION: i32 = 0;
+
+/// The id of the topic a partition dir belongs to, so a dir left over from a
+/// deleted topic isn't mistaken for a new topic with the same name:
+///
+/// ```text
+/// version: 0
+/// topic_id: 3Brj9K1uSa2YCwHnMMtBWg
+/// ```
+#[derive(Debug, Clone)]
+pub struct PartitionMetadataFile {
+    path: PathBuf,
+}
+
+fn invalid(path: &Path, line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed line in partition metadata file {}: {:?}", path.display(), line),
+    )
+}
+
+impl PartitionMetadataFile {
+    pub fn new(partition_dir: &Path) -> Self {
+        PartitionMetadataFile {
+            path: partition_dir.join(PARTITION_METADATA_FILE),
+        }
+    }
+
+    /// The topic id, None if it hasn't been written yet
+    pub fn read(&self) -> io::Result<Option<KafUuid>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut lines = contents.lines();
+        let version = lines.next().unwrap_or_default();
+        if version.strip_prefix("version:").and_then(|v| v.trim().parse::<i32>().ok()) != Some(PARTITION_METADATA_VERSION) {
+            return Err(invalid(&self.path, version));
+        }
+        let topic_id = lines.next().unwrap_or_default();
+        topic_id
+            .strip_prefix("topic_id:")
+            .and_then(|id| id.trim().parse().ok())
+            .map(Some)
+            .ok_or_else(|| invalid(&self.path, topic_id))
+    }
+
+    pub fn write(&self, topic_id: KafUuid) -> io::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        write!(file, "version: {}\ntopic_id: {}\n", PARTITION_METADATA_VERSION, topic_id)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+}
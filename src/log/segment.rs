@@ -0,0 +1,504 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+
+use crate::{
+    log::{
+        index::{OffsetIndex, TimeIndex, TimestampOffset},
+        segment_file, LogConfig, LogError, INDEX_FILE_SUFFIX, LOG_FILE_SUFFIX, TIME_INDEX_FILE_SUFFIX,
+    },
+    records::{
+        record_batch::RECORD_BATCH_OVERHEAD, LogEntry, MemoryRecords, Record, CURRENT_MAGIC_VALUE, LOG_OVERHEAD,
+        MAGIC_OFFSET, MAGIC_VALUE_V0, NO_TIMESTAMP,
+    },
+};
+
+/// Where a batch sits in a segment file, read from its header only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchPosition {
+    pub base_offset: i64,
+    pub last_offset: i64,
+    pub max_timestamp: i64,
+    pub position: u64,
+    pub size: u64,
+}
+
+impl BatchPosition {
+    pub fn next_offset(&self) -> i64 {
+        self.last_offset + 1
+    }
+
+    pub fn end_position(&self) -> u64 {
+        self.position + self.size
+    }
+}
+
+/// Parse the header of the batch at the start of `header`, which holds
+/// `available` bytes from `position` onwards (or at least a full v2 header's
+/// worth). None when there isn't a complete batch.
+fn parse_batch_header(header: &[u8], position: u64, available: u64) -> Option<BatchPosition> {
+    if available < LOG_OVERHEAD as u64 {
+        return None;
+    }
+    let base_offset = i64::from_be_bytes(header[0..8].try_into().unwrap());
+    let length = i32::from_be_bytes(header[8..12].try_into().unwrap());
+    if length < 0 {
+        return None;
+    }
+    let size = LOG_OVERHEAD as u64 + length as u64;
+    if size > available || size <= MAGIC_OFFSET as u64 {
+        return None;
+    }
+
+    let header_len = size.min(RECORD_BATCH_OVERHEAD as u64) as usize;
+    let magic = header[MAGIC_OFFSET] as i8;
+    let (last_offset, max_timestamp) = if magic == CURRENT_MAGIC_VALUE {
+        if header_len < RECORD_BATCH_OVERHEAD {
+            return None;
+        }
+        let last_offset_delta = i32::from_be_bytes(header[23..27].try_into().unwrap());
+        let max_timestamp = i64::from_be_bytes(header[35..43].try_into().unwrap());
+        (base_offset + last_offset_delta as i64, max_timestamp)
+    } else if magic > MAGIC_VALUE_V0 && header_len >= 26 {
+        // legacy entries carry their (last) offset directly
+        (base_offset, i64::from_be_bytes(header[18..26].try_into().unwrap()))
+    } else {
+        (base_offset, NO_TIMESTAMP)
+    };
+
+    Some(BatchPosition {
+        base_offset,
+        last_offset,
+        max_timestamp,
+        position,
+        size,
+    })
+}
+
+/// Records of an entry with their absolute offsets, inflating compressed ones
+fn entry_records(entry: LogEntry) -> Result<Vec<Record>, LogError> {
+    Ok(match entry {
+        LogEntry::Batch(batch) => batch.records()?.collect::<Result<_, _>>()?,
+        LogEntry::Legacy(legacy) => legacy.inner_records()?.iter().map(|r| r.to_record()).collect(),
+    })
+}
+
+/// One `<baseOffset>.log` file holding batches with offsets >= base offset,
+/// with its `.index` and `.timeindex`. Only the last segment of a log is
+/// ever appended to.
+#[derive(Debug)]
+pub struct LogSegment {
+    base_offset: i64,
+    path: PathBuf,
+    file: File,
+    size: u64,
+    created_ms: i64,
+    offset_index: OffsetIndex,
+    time_index: TimeIndex,
+    index_interval_bytes: u64,
+    bytes_since_last_index_entry: u64,
+    /// largest timestamp in the segment and the offset of the batch holding it
+    max_timestamp_so_far: TimestampOffset,
+    /// an index was missing or failed its sanity check when the segment was opened
+    needs_index_rebuild: bool,
+}
+
+impl LogSegment {
+    /// Create a new, empty segment file and its indexes
+    pub fn create(dir: &Path, base_offset: i64, config: &LogConfig, now: i64) -> io::Result<Self> {
+        let path = segment_file(dir, base_offset, LOG_FILE_SUFFIX);
+        let file = OpenOptions::new().read(true).append(true).create_new(true).open(&path)?;
+        let mut offset_index = OffsetIndex::open(segment_file(dir, base_offset, INDEX_FILE_SUFFIX), base_offset)?;
+        let mut time_index = TimeIndex::open(segment_file(dir, base_offset, TIME_INDEX_FILE_SUFFIX), base_offset)?;
+        offset_index.resize(config.max_index_size)?;
+        time_index.resize(config.max_index_size)?;
+        Ok(LogSegment {
+            base_offset,
+            path,
+            file,
+            size: 0,
+            created_ms: now,
+            offset_index,
+            time_index,
+            index_interval_bytes: config.index_interval_bytes,
+            bytes_since_last_index_entry: 0,
+            max_timestamp_so_far: TimestampOffset {
+                timestamp: NO_TIMESTAMP,
+                offset: base_offset,
+            },
+            needs_index_rebuild: false,
+        })
+    }
+
+    /// Open an existing segment file and its indexes. Missing or corrupt
+    /// indexes are left empty and flagged for `recover` to rebuild.
+    pub fn open(dir: &Path, base_offset: i64, config: &LogConfig, now: i64) -> io::Result<Self> {
+        let path = segment_file(dir, base_offset, LOG_FILE_SUFFIX);
+        let file = OpenOptions::new().read(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let offset_index_path = segment_file(dir, base_offset, INDEX_FILE_SUFFIX);
+        let time_index_path = segment_file(dir, base_offset, TIME_INDEX_FILE_SUFFIX);
+        let indexes_missing = !offset_index_path.exists() || !time_index_path.exists();
+
+        let mut segment = LogSegment {
+            base_offset,
+            path,
+            file,
+            size: metadata.len(),
+            created_ms: now,
+            offset_index: OffsetIndex::open(offset_index_path, base_offset)?,
+            time_index: TimeIndex::open(time_index_path, base_offset)?,
+            index_interval_bytes: config.index_interval_bytes,
+            bytes_since_last_index_entry: 0,
+            max_timestamp_so_far: TimestampOffset {
+                timestamp: NO_TIMESTAMP,
+                offset: base_offset,
+            },
+            needs_index_rebuild: false,
+        };
+        let indexes_valid = segment.offset_index.sanity_check(segment.size) && segment.time_index.sanity_check();
+        if (indexes_missing && segment.size > 0) || !indexes_valid {
+            segment.offset_index.reset();
+            segment.time_index.reset();
+            segment.needs_index_rebuild = true;
+        }
+        segment.load_largest_timestamp()?;
+        // for segment.ms, an old segment is as old as its first batch
+        segment.created_ms = match segment.batch_at(0)? {
+            Some(batch) if batch.max_timestamp != NO_TIMESTAMP => batch.max_timestamp,
+            _ => metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(now),
+        };
+        Ok(segment)
+    }
+
+    pub fn base_offset(&self) -> i64 {
+        self.base_offset
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn created_ms(&self) -> i64 {
+        self.created_ms
+    }
+
+    pub fn offset_index(&self) -> &OffsetIndex {
+        &self.offset_index
+    }
+
+    pub fn time_index(&self) -> &TimeIndex {
+        &self.time_index
+    }
+
+    /// Largest timestamp in the segment and the offset of the batch holding it
+    pub fn max_timestamp_so_far(&self) -> TimestampOffset {
+        self.max_timestamp_so_far
+    }
+
+    /// What retention.ms is checked against: the newest record's timestamp,
+    /// or when the segment was last written if its records have none
+    pub fn largest_timestamp(&self) -> i64 {
+        if self.max_timestamp_so_far.timestamp > NO_TIMESTAMP {
+            return self.max_timestamp_so_far.timestamp;
+        }
+        self.file
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(self.created_ms)
+    }
+
+    pub fn needs_index_rebuild(&self) -> bool {
+        self.needs_index_rebuild
+    }
+
+    /// The last time index entry is the largest timestamp as of some offset,
+    /// batches written after that entry may have larger ones
+    fn load_largest_timestamp(&mut self) -> io::Result<()> {
+        let mut largest = self.time_index.last_entry();
+        let position = self.offset_index.lookup(largest.offset).position;
+        for batch in self.batches_from(position) {
+            let batch = batch?;
+            if batch.max_timestamp > largest.timestamp {
+                largest = TimestampOffset {
+                    timestamp: batch.max_timestamp,
+                    offset: batch.last_offset,
+                };
+            }
+        }
+        self.max_timestamp_so_far = largest;
+        Ok(())
+    }
+
+    /// Whether appending up to `last_offset` needs a new segment because
+    /// the indexes can't take it
+    pub fn indexes_full(&self, last_offset: i64) -> bool {
+        self.offset_index.is_full() || self.time_index.is_full() || last_offset - self.base_offset > i32::MAX as i64
+    }
+
+    /// Account for a batch written at `batch.position`, adding an index entry
+    /// every `index.interval.bytes`
+    fn index_batch(&mut self, batch: &BatchPosition) -> io::Result<()> {
+        if batch.max_timestamp > self.max_timestamp_so_far.timestamp {
+            self.max_timestamp_so_far = TimestampOffset {
+                timestamp: batch.max_timestamp,
+                offset: batch.last_offset,
+            };
+        }
+        if self.bytes_since_last_index_entry > self.index_interval_bytes {
+            self.offset_index.append(batch.last_offset, batch.position);
+            self.time_index
+                .maybe_append(self.max_timestamp_so_far.timestamp, self.max_timestamp_so_far.offset, false)?;
+            self.bytes_since_last_index_entry = 0;
+        }
+        self.bytes_since_last_index_entry += batch.size;
+        Ok(())
+    }
+
+    /// Append batches that already have their offsets assigned
+    pub fn append(&mut self, records: &MemoryRecords) -> io::Result<()> {
+        let buf = records.as_bytes();
+        let mut position = 0;
+        while let Some(batch) = parse_batch_header(&buf[position..], self.size + position as u64, (buf.len() - position) as u64) {
+            self.index_batch(&batch)?;
+            position += batch.size as usize;
+        }
+
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Check every batch in the segment and rebuild the indexes from them,
+    /// truncating the file at the first batch that's torn or fails its CRC.
+    /// Returns the number of bytes discarded.
+    pub fn recover(&mut self, max_index_size: usize) -> io::Result<u64> {
+        self.offset_index.reset();
+        self.time_index.reset();
+        self.offset_index.resize(max_index_size)?;
+        self.time_index.resize(max_index_size)?;
+        self.bytes_since_last_index_entry = 0;
+        self.max_timestamp_so_far = TimestampOffset {
+            timestamp: NO_TIMESTAMP,
+            offset: self.base_offset,
+        };
+
+        let mut valid_bytes = 0;
+        while let Some(batch) = self.batch_at(valid_bytes)? {
+            let records = MemoryRecords::new(self.read_bytes(batch.position, batch.size)?);
+            let valid = match records.entries().next() {
+                Some(Ok(entry)) => entry.ensure_valid().is_ok() && batch.base_offset >= self.base_offset,
+                _ => false,
+            };
+            if !valid {
+                break;
+            }
+            self.index_batch(&batch)?;
+            valid_bytes = batch.end_position();
+        }
+
+        let truncated = self.size - valid_bytes;
+        if truncated > 0 {
+            self.file.set_len(valid_bytes)?;
+            self.size = valid_bytes;
+        }
+        self.offset_index.trim_to_valid_size()?;
+        self.time_index.trim_to_valid_size()?;
+        self.needs_index_rebuild = false;
+        Ok(truncated)
+    }
+
+    /// Called when the segment is rolled: records its final max timestamp and
+    /// shrinks the index files to their entries
+    pub fn close_for_appends(&mut self) -> io::Result<()> {
+        self.time_index
+            .maybe_append(self.max_timestamp_so_far.timestamp, self.max_timestamp_so_far.offset, true)?;
+        self.offset_index.trim_to_valid_size()?;
+        self.time_index.trim_to_valid_size()?;
+        self.flush()
+    }
+
+    /// Preallocate the index files so the segment can take appends again
+    pub fn resize_indexes(&mut self, max_index_size: usize) -> io::Result<()> {
+        self.offset_index.resize(max_index_size)?;
+        self.time_index.resize(max_index_size)
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.offset_index.flush()?;
+        self.time_index.flush()
+    }
+
+    pub fn read_bytes(&self, position: u64, len: u64) -> io::Result<Bytes> {
+        let mut buf = vec![0u8; len as usize];
+        self.file.read_exact_at(&mut buf, position)?;
+        Ok(Bytes::from(buf))
+    }
+
+    /// Read the header of the batch starting at `position`. None when there
+    /// isn't a complete batch there (end of file, or a torn write).
+    pub fn batch_at(&self, position: u64) -> io::Result<Option<BatchPosition>> {
+        let available = self.size.saturating_sub(position);
+        let mut header = vec![0u8; available.min(RECORD_BATCH_OVERHEAD as u64) as usize];
+        self.file.read_exact_at(&mut header, position)?;
+        Ok(parse_batch_header(&header, position, available))
+    }
+
+    /// Walk batch headers starting at `position`
+    pub fn batches_from(&self, position: u64) -> SegmentBatches<'_> {
+        SegmentBatches {
+            segment: self,
+            position,
+        }
+    }
+
+    /// The first batch at or after `start_position` containing offsets >= `offset`
+    pub fn translate_offset(&self, offset: i64, start_position: u64) -> io::Result<Option<BatchPosition>> {
+        for batch in self.batches_from(start_position) {
+            let batch = batch?;
+            if batch.last_offset >= offset {
+                return Ok(Some(batch));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read whole batches starting with the one containing `start_offset`, up to
+    /// `max_size` bytes and stopping before any batch starting at or after
+    /// `max_offset`. With `min_one_message` the first batch is returned even
+    /// if it's bigger than `max_size`.
+    pub fn read(
+        &self,
+        start_offset: i64,
+        max_size: u64,
+        max_offset: Option<i64>,
+        min_one_message: bool,
+    ) -> io::Result<Option<MemoryRecords>> {
+        let start_position = self.offset_index.lookup(start_offset).position;
+        let Some(first) = self.translate_offset(start_offset, start_position)? else {
+            return Ok(None);
+        };
+
+        let mut end = first.position;
+        for batch in self.batches_from(first.position) {
+            let batch = batch?;
+            if max_offset.is_some_and(|max| batch.base_offset >= max) {
+                break;
+            }
+            let read_so_far = end - first.position;
+            if read_so_far + batch.size > max_size && !(read_so_far == 0 && min_one_message) {
+                break;
+            }
+            end = batch.end_position();
+        }
+
+        Ok(Some(MemoryRecords::new(self.read_bytes(first.position, end - first.position)?)))
+    }
+
+    /// Offset after the last complete batch, None if the segment holds no batches
+    pub fn read_next_offset(&self) -> io::Result<Option<i64>> {
+        let mut next = None;
+        for batch in self.batches_from(0) {
+            next = Some(batch?.next_offset());
+        }
+        Ok(next)
+    }
+
+    /// The first record at or after `start_offset` with a timestamp >= `timestamp`
+    pub fn find_offset_by_timestamp(
+        &self,
+        timestamp: i64,
+        start_offset: i64,
+    ) -> Result<Option<TimestampOffset>, LogError> {
+        let indexed = self.time_index.lookup(timestamp);
+        let position = self.offset_index.lookup(indexed.offset.max(start_offset)).position;
+
+        for batch in self.batches_from(position) {
+            let batch = batch?;
+            if batch.max_timestamp < timestamp || batch.last_offset < start_offset {
+                continue;
+            }
+            let records = MemoryRecords::new(self.read_bytes(batch.position, batch.size)?);
+            for entry in records.entries() {
+                for record in entry_records(entry?)? {
+                    if record.offset >= start_offset && record.timestamp >= timestamp {
+                        return Ok(Some(TimestampOffset {
+                            timestamp: record.timestamp,
+                            offset: record.offset,
+                        }));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn delete(self) -> io::Result<()> {
+        let paths = [
+            self.path.clone(),
+            self.offset_index.path().to_path_buf(),
+            self.time_index.path().to_path_buf(),
+        ];
+        drop(self);
+        for path in paths {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LogSegment {
+    fn drop(&mut self) {
+        // leave index files holding just their entries on a clean shutdown
+        let _ = self.offset_index.trim_to_valid_size();
+        let _ = self.time_index.trim_to_valid_size();
+    }
+}
+
+pub struct SegmentBatches<'a> {
+    segment: &'a LogSegment,
+    position: u64,
+}
+
+impl Iterator for SegmentBatches<'_> {
+    type Item = io::Result<BatchPosition>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.segment.batch_at(self.position) {
+            Ok(Some(batch)) => {
+                self.position = batch.end_position();
+                Some(Ok(batch))
+            }
+            Ok(None) => None,
+            Err(e) => {
+                self.position = self.segment.size;
+                Some(Err(e))
+            }
+        }
+    }
+}
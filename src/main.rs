@@ -1,22 +1,37 @@
 #![allow(unused_imports)]
-use std::net::TcpListener;
+use std::{net::TcpListener, path::Path, sync::Arc, thread};
 
-use crate::server::handle_stream;
+use crate::{common::config::BrokerConfig, server::{broker::Broker, handle_stream}};
 
 pub mod common;
+pub mod coordinator;
+pub mod log;
+pub mod metadata;
+pub mod records;
 pub mod utils;
 pub mod server;
 
 pub type StrError = String;
 
 fn main() {
+    // started as `your_program.sh /tmp/server.properties`
+    let config = match std::env::args().nth(1) {
+        Some(path) => BrokerConfig::from_file(Path::new(&path)).expect("unable to read server.properties"),
+        None => BrokerConfig::default(),
+    };
+    let broker = Arc::new(Broker::new(config).expect("unable to load logs"));
     let listener = TcpListener::bind("127.0.0.1:9092").unwrap();
 
     for stream in listener.incoming() {
         match stream {
             Ok(_stream) => {
                 println!("accepted new connection");
-                let _ = handle_stream(_stream);
+                let broker = broker.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_stream(_stream, &broker) {
+                        println!("closing connection: {}", e);
+                    }
+                });
             }
             Err(e) => {
                 println!("error: {}", e);
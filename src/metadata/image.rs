@@ -0,0 +1,150 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    common::{topic_partition::TopicPartition, uuid::KafUuid},
+    metadata::records::{MetadataRecord, PartitionRecord, BROKER_RESOURCE_TYPE, TOPIC_RESOURCE_TYPE},
+};
+
+/// Leadership and replicas of one partition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionRegistration {
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub partition_epoch: i32,
+}
+
+impl From<&PartitionRecord> for PartitionRegistration {
+    fn from(record: &PartitionRecord) -> Self {
+        PartitionRegistration {
+            replicas: record.replicas.clone(),
+            isr: record.isr.clone(),
+            leader: record.leader,
+            leader_epoch: record.leader_epoch,
+            partition_epoch: record.partition_epoch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicImage {
+    pub name: String,
+    pub topic_id: KafUuid,
+    pub partitions: BTreeMap<i32, PartitionRegistration>,
+}
+
+impl TopicImage {
+    pub fn topic_partitions(&self) -> impl Iterator<Item = TopicPartition> + '_ {
+        self.partitions.keys().map(|partition| TopicPartition::new(&self.name, *partition))
+    }
+}
+
+/// The cluster's metadata as of some offset of the metadata log: what every
+/// request that looks up topics reads. Images are never changed once
+/// published, a new one replaces them, so readers see all of a change or
+/// none of it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataImage {
+    topics: BTreeMap<String, TopicImage>,
+    topic_names: HashMap<KafUuid, String>,
+    /// topic configs set on the topic, not the broker defaults
+    topic_configs: HashMap<String, BTreeMap<String, String>>,
+    /// dynamic broker configs by broker id, "" for the cluster wide defaults
+    broker_configs: HashMap<String, BTreeMap<String, String>>,
+    features: BTreeMap<String, i16>,
+}
+
+impl MetadataImage {
+    pub fn apply(&mut self, record: &MetadataRecord) {
+        match record {
+            MetadataRecord::Topic(record) => {
+                self.topic_names.insert(record.topic_id, record.name.clone());
+                self.topics.insert(
+                    record.name.clone(),
+                    TopicImage {
+                        name: record.name.clone(),
+                        topic_id: record.topic_id,
+                        partitions: BTreeMap::new(),
+                    },
+                );
+            }
+            MetadataRecord::Partition(record) => {
+                let Some(topic) = self.topic_names.get(&record.topic_id).and_then(|name| self.topics.get_mut(name)) else {
+                    println!("partition {} of unknown topic id {} in the metadata log", record.partition_id, record.topic_id);
+                    return;
+                };
+                topic.partitions.insert(record.partition_id, record.into());
+            }
+            MetadataRecord::Config(record)
+                if record.resource_type == TOPIC_RESOURCE_TYPE || record.resource_type == BROKER_RESOURCE_TYPE =>
+            {
+                let resources = if record.resource_type == TOPIC_RESOURCE_TYPE {
+                    &mut self.topic_configs
+                } else {
+                    &mut self.broker_configs
+                };
+                let configs = resources.entry(record.resource_name.clone()).or_default();
+                match &record.value {
+                    Some(value) => configs.insert(record.name.clone(), value.clone()),
+                    None => configs.remove(&record.name),
+                };
+                if configs.is_empty() {
+                    resources.remove(&record.resource_name);
+                }
+            }
+            MetadataRecord::RemoveTopic(record) => {
+                if let Some(name) = self.topic_names.remove(&record.topic_id) {
+                    self.topics.remove(&name);
+                    self.topic_configs.remove(&name);
+                }
+            }
+            MetadataRecord::FeatureLevel(record) => {
+                self.features.insert(record.name.clone(), record.feature_level);
+            }
+            MetadataRecord::Config(_) | MetadataRecord::Unknown { .. } => {}
+        }
+    }
+
+    pub fn topic(&self, name: &str) -> Option<&TopicImage> {
+        self.topics.get(name)
+    }
+
+    pub fn topic_by_id(&self, topic_id: &KafUuid) -> Option<&TopicImage> {
+        self.topic_names.get(topic_id).and_then(|name| self.topics.get(name))
+    }
+
+    /// Another topic whose name is the same once '.' and '_' are treated
+    /// alike, which Kafka doesn't allow because they'd share metric names
+    pub fn colliding_topic(&self, name: &str) -> Option<&str> {
+        let unify = |name: &str| name.replace('.', "_");
+        let unified = unify(name);
+        self.topics.keys().map(String::as_str).find(|existing| *existing != name && unify(existing) == unified)
+    }
+
+    /// In name order
+    pub fn topics(&self) -> impl Iterator<Item = &TopicImage> {
+        self.topics.values()
+    }
+
+    pub fn topic_configs(&self, name: &str) -> HashMap<String, String> {
+        self.configs(TOPIC_RESOURCE_TYPE, name)
+    }
+
+    /// The configs set on a topic or broker resource, see `ConfigRecord`
+    pub fn configs(&self, resource_type: i8, name: &str) -> HashMap<String, String> {
+        let resources = match resource_type {
+            TOPIC_RESOURCE_TYPE => &self.topic_configs,
+            BROKER_RESOURCE_TYPE => &self.broker_configs,
+            _ => return HashMap::new(),
+        };
+        resources
+            .get(name)
+            .map(|configs| configs.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn feature_level(&self, name: &str) -> Option<i16> {
+        self.features.get(name).copied()
+    }
+}
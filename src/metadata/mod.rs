@@ -0,0 +1,376 @@
+pub mod image;
+pub mod records;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+};
+
+use crate::{
+    common::{error::error_code, topic_partition::TopicPartition, uuid::KafUuid, EncodingError},
+    log::{partition_log::FetchIsolation, LogError, LogManager, PartitionLog},
+    metadata::{
+        image::MetadataImage,
+        records::{ConfigRecord, MetadataRecord, PartitionRecord, RemoveTopicRecord, TopicRecord, TOPIC_RESOURCE_TYPE},
+    },
+    records::{MemoryRecords, Record, RecordBatchBuilder, RecordError},
+    utils::clock::Clock,
+};
+
+/// The KRaft metadata log, `__cluster_metadata-0` in the log dir
+pub const CLUSTER_METADATA_TOPIC: &str = "__cluster_metadata";
+
+pub const CONSUMER_OFFSETS_TOPIC: &str = "__consumer_offsets";
+pub const TRANSACTION_STATE_TOPIC: &str = "__transaction_state";
+
+/// How much of the metadata log is read at a time when replaying it
+const REPLAY_FETCH_BYTES: u64 = 1024 * 1024;
+
+/// Long enough for a partition's dir name, `<topic>-<partition>-delete`
+pub const MAX_TOPIC_NAME_LENGTH: usize = 249;
+
+pub fn is_internal_topic(name: &str) -> bool {
+    name == CONSUMER_OFFSETS_TOPIC || name == TRANSACTION_STATE_TOPIC
+}
+
+/// Err with the reason if `name` can't be a topic's
+pub fn validate_topic_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("topic name is empty".to_string());
+    }
+    if name == CLUSTER_METADATA_TOPIC {
+        return Err(format!("topic name {} is reserved", name));
+    }
+    if name == "." || name == ".." {
+        return Err(format!("topic name cannot be \"{}\"", name));
+    }
+    if name.len() > MAX_TOPIC_NAME_LENGTH {
+        return Err(format!("topic name is longer than {} characters", MAX_TOPIC_NAME_LENGTH));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')) {
+        return Err(format!("topic name {} has characters other than ASCII alphanumerics, '.', '_' and '-'", name));
+    }
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MetadataError {
+    #[error("{0}")]
+    Log(#[from] LogError),
+    #[error("{0}")]
+    Record(#[from] RecordError),
+    #[error("bad metadata record: {0}")]
+    Encoding(#[from] EncodingError),
+    #[error("topic {0} already exists")]
+    TopicAlreadyExists(String),
+    #[error("no topic with id {0}")]
+    UnknownTopicId(KafUuid),
+    #[error("{0}")]
+    InvalidPartitions(String),
+    #[error("unknown topic {0}")]
+    UnknownTopic(String),
+    #[error("{0}")]
+    InvalidConfig(String),
+}
+
+impl MetadataError {
+    pub fn error_code(&self) -> i16 {
+        match self {
+            MetadataError::Log(e) => e.error_code(),
+            MetadataError::Record(_) | MetadataError::Encoding(_) => error_code::UNKNOWN_SERVER_ERROR,
+            MetadataError::TopicAlreadyExists(_) => error_code::TOPIC_ALREADY_EXISTS,
+            MetadataError::UnknownTopicId(_) => error_code::UNKNOWN_TOPIC_ID,
+            MetadataError::InvalidPartitions(_) => error_code::INVALID_PARTITIONS,
+            MetadataError::UnknownTopic(_) => error_code::UNKNOWN_TOPIC_OR_PARTITION,
+            MetadataError::InvalidConfig(_) => error_code::INVALID_CONFIG,
+        }
+    }
+}
+
+/// Replica assignments for new partitions, spread round robin over the
+/// brokers starting after the partitions' leaders. None if there aren't
+/// enough brokers for the replication factor.
+pub fn assign_replicas(brokers: &[i32], num_partitions: i32, replication_factor: i16, first_partition: i32) -> Option<Vec<Vec<i32>>> {
+    if replication_factor < 1 || replication_factor as usize > brokers.len() {
+        return None;
+    }
+    let assignments = (first_partition..first_partition + num_partitions)
+        .map(|partition| {
+            (0..replication_factor as usize)
+                .map(|replica| brokers[(partition as usize + replica) % brokers.len()])
+                .collect()
+        })
+        .collect();
+    Some(assignments)
+}
+
+/// Owns the metadata log and the image built from it. Changes are appended
+/// to the log and then published as a new image.
+#[derive(Debug)]
+pub struct MetadataManager {
+    log: Arc<PartitionLog>,
+    image: RwLock<Arc<MetadataImage>>,
+    /// one writer at a time, so what a writer checked in the image still
+    /// holds when its records are appended
+    write_lock: Mutex<()>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MetadataManager {
+    /// Open the metadata log and replay it into an image
+    pub fn load(log_manager: &LogManager) -> Result<Self, MetadataError> {
+        let overrides = metadata_log_configs();
+        let log = log_manager.get_or_create_log(&TopicPartition::new(CLUSTER_METADATA_TOPIC, 0), &overrides)?;
+        log.update_config(log_manager.default_config().with_overrides(&overrides));
+
+        let mut image = MetadataImage::default();
+        let mut offset = log.log_start_offset();
+        while offset < log.log_end_offset() {
+            let read = log.read(offset, REPLAY_FETCH_BYTES, FetchIsolation::LogEnd, true)?;
+            let start = offset;
+            for batch in read.records.batches() {
+                let batch = batch?;
+                offset = batch.next_offset();
+                // leader changes and snapshot markers
+                if batch.is_control_batch() {
+                    continue;
+                }
+                for record in batch.records()? {
+                    if let Some(value) = record?.value {
+                        image.apply(&MetadataRecord::read(&value)?);
+                    }
+                }
+            }
+            if offset == start {
+                break;
+            }
+        }
+
+        Ok(MetadataManager {
+            log,
+            image: RwLock::new(Arc::new(image)),
+            write_lock: Mutex::new(()),
+            clock: log_manager.clock(),
+        })
+    }
+
+    /// The latest published image
+    pub fn image(&self) -> Arc<MetadataImage> {
+        self.image.read().unwrap().clone()
+    }
+
+    pub fn writer(&self) -> MetadataWriter<'_> {
+        MetadataWriter {
+            _lock: self.write_lock.lock().unwrap(),
+            manager: self,
+        }
+    }
+
+    /// Write a new topic, one replica assignment per partition. The first
+    /// replica of each is its leader.
+    pub fn create_topic(
+        &self,
+        name: &str,
+        assignments: &[Vec<i32>],
+        configs: &HashMap<String, String>,
+    ) -> Result<Arc<MetadataImage>, MetadataError> {
+        let mut writer = self.writer();
+        let image = writer.image();
+        if image.topic(name).is_some() {
+            return Err(MetadataError::TopicAlreadyExists(name.to_string()));
+        }
+        let topic_id = loop {
+            let id = KafUuid::random();
+            if image.topic_by_id(&id).is_none() {
+                break id;
+            }
+        };
+        writer.append(&create_topic_records(name, topic_id, assignments, configs))
+    }
+
+    /// Remove a topic with its partitions and configs
+    pub fn delete_topic(&self, topic_id: KafUuid) -> Result<Arc<MetadataImage>, MetadataError> {
+        let mut writer = self.writer();
+        if writer.image().topic_by_id(&topic_id).is_none() {
+            return Err(MetadataError::UnknownTopicId(topic_id));
+        }
+        writer.append(&[MetadataRecord::RemoveTopic(RemoveTopicRecord { topic_id })])
+    }
+}
+
+/// The records that create a topic
+pub fn create_topic_records(
+    name: &str,
+    topic_id: KafUuid,
+    assignments: &[Vec<i32>],
+    configs: &HashMap<String, String>,
+) -> Vec<MetadataRecord> {
+    let mut records = vec![MetadataRecord::Topic(TopicRecord {
+        name: name.to_string(),
+        topic_id,
+    })];
+    records.extend(configs.iter().map(|(key, value)| {
+        MetadataRecord::Config(ConfigRecord {
+            resource_type: TOPIC_RESOURCE_TYPE,
+            resource_name: name.to_string(),
+            name: key.clone(),
+            value: Some(value.clone()),
+        })
+    }));
+    records.extend(partition_records(topic_id, 0, assignments));
+    records
+}
+
+/// The records of new partitions numbered from `first_partition`, one
+/// replica assignment each
+pub fn partition_records(topic_id: KafUuid, first_partition: i32, assignments: &[Vec<i32>]) -> Vec<MetadataRecord> {
+    assignments
+        .iter()
+        .enumerate()
+        .map(|(i, replicas)| MetadataRecord::Partition(PartitionRecord {
+            partition_id: first_partition + i as i32,
+            topic_id,
+            replicas: replicas.clone(),
+            isr: replicas.clone(),
+            removing_replicas: vec![],
+            adding_replicas: vec![],
+            leader: replicas.first().copied().unwrap_or(-1),
+            leader_epoch: 0,
+            partition_epoch: 0,
+        }))
+        .collect()
+}
+
+/// Records that change the configs set on a resource from `current` to `altered`
+pub fn config_records(
+    resource_type: i8,
+    resource_name: &str,
+    current: &HashMap<String, String>,
+    altered: &HashMap<String, String>,
+) -> Vec<MetadataRecord> {
+    let record = |name: &str, value: Option<&String>| {
+        MetadataRecord::Config(ConfigRecord {
+            resource_type,
+            resource_name: resource_name.to_string(),
+            name: name.to_string(),
+            value: value.cloned(),
+        })
+    };
+    let mut records: Vec<_> = current
+        .keys()
+        .filter(|name| !altered.contains_key(*name))
+        .map(|name| record(name, None))
+        .collect();
+    records.extend(
+        altered
+            .iter()
+            .filter(|(name, value)| current.get(*name) != Some(value))
+            .map(|(name, value)| record(name, Some(value))),
+    );
+    records
+}
+
+/// Topic configs of the metadata log: records are only ever superseded, never expire
+pub fn metadata_log_configs() -> HashMap<String, String> {
+    HashMap::from([
+        ("retention.ms".to_string(), "-1".to_string()),
+        ("retention.bytes".to_string(), "-1".to_string()),
+    ])
+}
+
+/// Exclusive access to change the metadata
+pub struct MetadataWriter<'a> {
+    _lock: MutexGuard<'a, ()>,
+    manager: &'a MetadataManager,
+}
+
+impl MetadataWriter<'_> {
+    pub fn image(&self) -> Arc<MetadataImage> {
+        self.manager.image()
+    }
+
+    /// Append the records to the metadata log as one batch and publish the
+    /// image with them applied
+    pub fn append(&mut self, records: &[MetadataRecord]) -> Result<Arc<MetadataImage>, MetadataError> {
+        let mut image = MetadataImage::clone(&self.manager.image());
+        records.iter().for_each(|record| image.apply(record));
+
+        let now = self.manager.clock.now_ms();
+        let mut builder = RecordBatchBuilder::new(0);
+        for record in records {
+            builder.append(&Record::new(now, None, Some(record.to_value())));
+        }
+        let log = &self.manager.log;
+        log.append_as_leader(&MemoryRecords::from_batches(&[builder.build()?]))?;
+        // the change is acknowledged once it's durable
+        log.flush()?;
+
+        let image = Arc::new(image);
+        *self.manager.image.write().unwrap() = image.clone();
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{common::config::BrokerConfig, utils::clock::SystemClock};
+
+    fn log_manager(dir: &std::path::Path) -> LogManager {
+        let config = BrokerConfig::from_props(HashMap::from([(
+            "log.dirs".to_string(),
+            dir.to_str().unwrap().to_string(),
+        )]));
+        LogManager::startup(&config, Arc::new(SystemClock)).unwrap()
+    }
+
+    #[test]
+    fn topics_survive_a_restart_through_the_metadata_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = MetadataManager::load(&log_manager(dir.path())).unwrap();
+        assert_eq!(manager.image().topics().count(), 0);
+
+        let configs = HashMap::from([("cleanup.policy".to_string(), "compact".to_string())]);
+        let image = manager.create_topic("orders", &[vec![1], vec![1]], &configs).unwrap();
+        let orders = image.topic("orders").unwrap().clone();
+        assert!(!orders.topic_id.is_zero());
+        assert_eq!(orders.partitions.len(), 2);
+        assert_eq!((orders.partitions[&1].leader, &orders.partitions[&1].isr), (1, &vec![1]));
+        assert!(matches!(
+            manager.create_topic("orders", &[vec![1]], &HashMap::new()),
+            Err(MetadataError::TopicAlreadyExists(_))
+        ));
+        manager.create_topic("payments", &[vec![1]], &HashMap::new()).unwrap();
+        let refunds = manager.create_topic("refunds", &[vec![1]], &configs).unwrap().topic("refunds").unwrap().topic_id;
+        assert!(manager.delete_topic(refunds).unwrap().topic("refunds").is_none());
+        assert!(matches!(manager.delete_topic(refunds), Err(MetadataError::UnknownTopicId(_))));
+        drop(manager);
+
+        let reloaded = MetadataManager::load(&log_manager(dir.path())).unwrap().image();
+        assert_eq!(reloaded.topic("orders"), Some(&orders));
+        assert_eq!(reloaded.topic_by_id(&orders.topic_id).map(|t| t.name.as_str()), Some("orders"));
+        assert_eq!(reloaded.topic_configs("orders"), configs);
+        assert!(reloaded.topic_configs("refunds").is_empty());
+        let names: Vec<_> = reloaded.topics().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["orders", "payments"]);
+    }
+
+    #[test]
+    fn topic_names_are_validated() {
+        assert!(validate_topic_name("orders.v2_eu-west").is_ok());
+        assert!(validate_topic_name(&"a".repeat(MAX_TOPIC_NAME_LENGTH)).is_ok());
+        for name in ["", ".", "..", CLUSTER_METADATA_TOPIC, "orders/v2", "ordërs", &"a".repeat(MAX_TOPIC_NAME_LENGTH + 1)] {
+            assert!(validate_topic_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn replicas_are_assigned_round_robin() {
+        assert_eq!(assign_replicas(&[1, 2, 3], 3, 2, 0), Some(vec![vec![1, 2], vec![2, 3], vec![3, 1]]));
+        assert_eq!(assign_replicas(&[1, 2, 3], 1, 1, 4), Some(vec![vec![2]]));
+        assert_eq!(assign_replicas(&[1], 2, 2, 0), None);
+        assert_eq!(assign_replicas(&[1], 2, 0, 0), None);
+    }
+}
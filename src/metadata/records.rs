@@ -0,0 +1,256 @@
+use bytes::Bytes;
+
+use crate::{
+    common::{
+        codec::{read_array, read_nullable_string, read_string, skip_tagged_fields, write_array, write_nullable_string, write_string, write_tagged_fields},
+        uuid::KafUuid,
+        DecodeFromBytes, EncodeToBytes, EncodingError,
+    },
+    utils::parse_primitive_types::{encode_unsigned_varint, read_i16_be, read_i32_be, read_i8_be, read_unsigned_varint},
+};
+
+/*
+* Metadata records are the values of the __cluster_metadata log's records:
+*   frame_version: UVARINT (1)
+*   api_key: UVARINT
+*   version: UVARINT
+*   the record, always in the flexible encoding
+*/
+const FRAME_VERSION: u32 = 1;
+
+const TOPIC_RECORD: u32 = 2;
+const PARTITION_RECORD: u32 = 3;
+const CONFIG_RECORD: u32 = 4;
+const REMOVE_TOPIC_RECORD: u32 = 9;
+const FEATURE_LEVEL_RECORD: u32 = 12;
+
+/// `ConfigRecord.resource_type` of topic configs
+pub const TOPIC_RESOURCE_TYPE: i8 = 2;
+/// `ConfigRecord.resource_type` of broker configs, named after the node id,
+/// or "" for the defaults of every broker
+pub const BROKER_RESOURCE_TYPE: i8 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataRecord {
+    Topic(TopicRecord),
+    Partition(PartitionRecord),
+    Config(ConfigRecord),
+    RemoveTopic(RemoveTopicRecord),
+    FeatureLevel(FeatureLevelRecord),
+    /// brokers, producer ids, acls... which the image doesn't keep
+    Unknown { api_key: u32, version: u32 },
+}
+
+/*
+* TopicRecord (api key 2, version 0) => name topic_id _tagged_fields
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicRecord {
+    pub name: String,
+    pub topic_id: KafUuid,
+}
+
+/*
+* PartitionRecord (api key 3, versions 0-2) => partition_id topic_id [replicas] [isr] [removing_replicas]
+*                                              [adding_replicas] leader leader_epoch partition_epoch
+*                                              [directories] (v1+) _tagged_fields
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionRecord {
+    pub partition_id: i32,
+    pub topic_id: KafUuid,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+    pub removing_replicas: Vec<i32>,
+    pub adding_replicas: Vec<i32>,
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub partition_epoch: i32,
+}
+
+/*
+* ConfigRecord (api key 4, version 0) => resource_type resource_name name value _tagged_fields
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigRecord {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub name: String,
+    /// None deletes the config
+    pub value: Option<String>,
+}
+
+/*
+* RemoveTopicRecord (api key 9, version 0) => topic_id _tagged_fields
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoveTopicRecord {
+    pub topic_id: KafUuid,
+}
+
+/*
+* FeatureLevelRecord (api key 12, version 0) => name feature_level _tagged_fields
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureLevelRecord {
+    pub name: String,
+    pub feature_level: i16,
+}
+
+fn read_i32_array(input: &[u8], offset: &mut usize) -> Result<Vec<i32>, EncodingError> {
+    read_array(input, offset, true, read_i32_be)
+}
+
+fn write_i32_array(buf: &mut Vec<u8>, values: &[i32]) {
+    write_array(buf, values, true, |buf, value| buf.extend(value.encode_to_bytes()));
+}
+
+impl MetadataRecord {
+    /// Decode a record's value
+    pub fn read(value: &[u8]) -> Result<Self, EncodingError> {
+        let offset = &mut 0;
+        let frame_version = read_unsigned_varint(value, offset)?;
+        if frame_version != FRAME_VERSION {
+            return Err(EncodingError::UnsupportedVersion(frame_version as i64));
+        }
+        let api_key = read_unsigned_varint(value, offset)?;
+        let version = read_unsigned_varint(value, offset)?;
+        let record = match api_key {
+            TOPIC_RECORD => MetadataRecord::Topic(TopicRecord {
+                name: read_string(value, offset, true)?,
+                topic_id: KafUuid::read_from_u8(value, offset)?,
+            }),
+            PARTITION_RECORD => {
+                let record = PartitionRecord {
+                    partition_id: read_i32_be(value, offset)?,
+                    topic_id: KafUuid::read_from_u8(value, offset)?,
+                    replicas: read_i32_array(value, offset)?,
+                    isr: read_i32_array(value, offset)?,
+                    removing_replicas: read_i32_array(value, offset)?,
+                    adding_replicas: read_i32_array(value, offset)?,
+                    leader: read_i32_be(value, offset)?,
+                    leader_epoch: read_i32_be(value, offset)?,
+                    partition_epoch: read_i32_be(value, offset)?,
+                };
+                if version >= 1 {
+                    // the log dir of each replica, we only have the one broker
+                    read_array(value, offset, true, KafUuid::read_from_u8)?;
+                }
+                MetadataRecord::Partition(record)
+            }
+            CONFIG_RECORD => MetadataRecord::Config(ConfigRecord {
+                resource_type: read_i8_be(value, offset)?,
+                resource_name: read_string(value, offset, true)?,
+                name: read_string(value, offset, true)?,
+                value: read_nullable_string(value, offset, true)?,
+            }),
+            REMOVE_TOPIC_RECORD => MetadataRecord::RemoveTopic(RemoveTopicRecord {
+                topic_id: KafUuid::read_from_u8(value, offset)?,
+            }),
+            FEATURE_LEVEL_RECORD => MetadataRecord::FeatureLevel(FeatureLevelRecord {
+                name: read_string(value, offset, true)?,
+                feature_level: read_i16_be(value, offset)?,
+            }),
+            _ => return Ok(MetadataRecord::Unknown { api_key, version }),
+        };
+        skip_tagged_fields(value, offset, true)?;
+        Ok(record)
+    }
+
+    /// Encode as a record value, at version 0
+    pub fn to_value(&self) -> Bytes {
+        let (api_key, mut buf) = match self {
+            MetadataRecord::Topic(record) => {
+                let mut buf = vec![];
+                write_string(&mut buf, &record.name, true);
+                buf.extend(record.topic_id.encode_to_bytes());
+                (TOPIC_RECORD, buf)
+            }
+            MetadataRecord::Partition(record) => {
+                let mut buf = vec![];
+                buf.extend(record.partition_id.encode_to_bytes());
+                buf.extend(record.topic_id.encode_to_bytes());
+                write_i32_array(&mut buf, &record.replicas);
+                write_i32_array(&mut buf, &record.isr);
+                write_i32_array(&mut buf, &record.removing_replicas);
+                write_i32_array(&mut buf, &record.adding_replicas);
+                buf.extend(record.leader.encode_to_bytes());
+                buf.extend(record.leader_epoch.encode_to_bytes());
+                buf.extend(record.partition_epoch.encode_to_bytes());
+                (PARTITION_RECORD, buf)
+            }
+            MetadataRecord::Config(record) => {
+                let mut buf = vec![];
+                buf.extend(record.resource_type.encode_to_bytes());
+                write_string(&mut buf, &record.resource_name, true);
+                write_string(&mut buf, &record.name, true);
+                write_nullable_string(&mut buf, record.value.as_deref(), true);
+                (CONFIG_RECORD, buf)
+            }
+            MetadataRecord::RemoveTopic(record) => (REMOVE_TOPIC_RECORD, record.topic_id.encode_to_bytes()),
+            MetadataRecord::FeatureLevel(record) => {
+                let mut buf = vec![];
+                write_string(&mut buf, &record.name, true);
+                buf.extend(record.feature_level.encode_to_bytes());
+                (FEATURE_LEVEL_RECORD, buf)
+            }
+            MetadataRecord::Unknown { api_key, version } => {
+                panic!("can't encode metadata record {} v{} we don't know", api_key, version)
+            }
+        };
+        write_tagged_fields(&mut buf, true);
+
+        let mut value = encode_unsigned_varint(FRAME_VERSION);
+        value.extend(encode_unsigned_varint(api_key));
+        value.extend(encode_unsigned_varint(0));
+        value.extend(buf);
+        Bytes::from(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_round_trip_and_partition_record_v1_is_read() {
+        let topic_id = KafUuid([3; 16]);
+        let records = vec![
+            MetadataRecord::FeatureLevel(FeatureLevelRecord { name: "metadata.version".to_string(), feature_level: 20 }),
+            MetadataRecord::Topic(TopicRecord { name: "orders".to_string(), topic_id }),
+            MetadataRecord::Partition(PartitionRecord {
+                partition_id: 1,
+                topic_id,
+                replicas: vec![1],
+                isr: vec![1],
+                removing_replicas: vec![],
+                adding_replicas: vec![],
+                leader: 1,
+                leader_epoch: 0,
+                partition_epoch: 0,
+            }),
+            MetadataRecord::Config(ConfigRecord {
+                resource_type: TOPIC_RESOURCE_TYPE,
+                resource_name: "orders".to_string(),
+                name: "cleanup.policy".to_string(),
+                value: None,
+            }),
+            MetadataRecord::RemoveTopic(RemoveTopicRecord { topic_id }),
+        ];
+        for record in &records {
+            assert_eq!(&MetadataRecord::read(&record.to_value()).unwrap(), record);
+        }
+
+        // a v1 PartitionRecord, as written by Kafka 3.7+, has each replica's log dir
+        let mut value = records[2].to_value().to_vec();
+        value[2] = 1;
+        value.pop();
+        write_array(&mut value, &[KafUuid([9; 16])], true, |buf, dir| buf.extend(dir.encode_to_bytes()));
+        // LeaderRecoveryState as tag 0
+        value.extend([1, 0, 1, 0]);
+        assert_eq!(MetadataRecord::read(&value).unwrap(), records[2]);
+
+        let broker_registration = [1, 0, 3, 0];
+        assert_eq!(MetadataRecord::read(&broker_registration).unwrap(), MetadataRecord::Unknown { api_key: 0, version: 3 });
+    }
+}
@@ -0,0 +1,220 @@
+#[allow(unused_imports)]
+use std::io::{Read, Write};
+
+use crate::records::{CompressionType, RecordError};
+
+/// Compression configured on a topic (`compression.type`). `Producer` keeps
+/// whatever codec the producer used, anything else forces that codec on write.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BrokerCompressionType {
+    #[default]
+    Producer,
+    Codec(CompressionType),
+}
+
+impl BrokerCompressionType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "producer" => Some(BrokerCompressionType::Producer),
+            "uncompressed" => Some(BrokerCompressionType::Codec(CompressionType::None)),
+            other => CompressionType::from_name(other)
+                .filter(|c| *c != CompressionType::None)
+                .map(BrokerCompressionType::Codec),
+        }
+    }
+
+    /// The codec a batch written by a producer using `source` ends up with
+    pub fn target(&self, source: CompressionType) -> CompressionType {
+        match self {
+            BrokerCompressionType::Producer => source,
+            BrokerCompressionType::Codec(codec) => *codec,
+        }
+    }
+}
+
+impl CompressionType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionType::None => "none",
+            CompressionType::Gzip => "gzip",
+            CompressionType::Snappy => "snappy",
+            CompressionType::Lz4 => "lz4",
+            CompressionType::Zstd => "zstd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(CompressionType::None),
+            "gzip" => Some(CompressionType::Gzip),
+            "snappy" => Some(CompressionType::Snappy),
+            "lz4" => Some(CompressionType::Lz4),
+            "zstd" => Some(CompressionType::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Whether this build can read and write the codec
+    pub fn is_supported(&self) -> bool {
+        match self {
+            CompressionType::None => true,
+            CompressionType::Gzip => cfg!(feature = "gzip"),
+            CompressionType::Snappy => cfg!(feature = "snappy"),
+            CompressionType::Lz4 => cfg!(feature = "lz4"),
+            CompressionType::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    fn unsupported(&self) -> RecordError {
+        RecordError::UnsupportedCompression(i16::from(*self))
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, RecordError> {
+        match self {
+            CompressionType::None => Ok(data.to_vec()),
+            #[cfg(feature = "gzip")]
+            CompressionType::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(data).map_err(io_error)?;
+                encoder.finish().map_err(io_error)
+            }
+            #[cfg(feature = "snappy")]
+            CompressionType::Snappy => xerial::compress(data),
+            #[cfg(feature = "lz4")]
+            CompressionType::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(vec![]);
+                encoder.write_all(data).map_err(io_error)?;
+                encoder.finish().map_err(|e| RecordError::Corrupt(format!("lz4: {}", e)))
+            }
+            #[cfg(feature = "zstd")]
+            CompressionType::Zstd => zstd::stream::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL).map_err(io_error),
+            #[allow(unreachable_patterns)]
+            other => Err(other.unsupported()),
+        }
+    }
+
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, RecordError> {
+        match self {
+            CompressionType::None => Ok(data.to_vec()),
+            #[cfg(feature = "gzip")]
+            CompressionType::Gzip => read_all(flate2::read::GzDecoder::new(data)),
+            #[cfg(feature = "snappy")]
+            CompressionType::Snappy => xerial::decompress(data),
+            #[cfg(feature = "lz4")]
+            CompressionType::Lz4 => read_all(lz4_flex::frame::FrameDecoder::new(data)),
+            #[cfg(feature = "zstd")]
+            CompressionType::Zstd => zstd::stream::decode_all(data).map_err(io_error),
+            #[allow(unreachable_patterns)]
+            other => Err(other.unsupported()),
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn io_error(e: std::io::Error) -> RecordError {
+    RecordError::Corrupt(format!("compression error: {}", e))
+}
+
+#[allow(dead_code)]
+fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>, RecordError> {
+    let mut out = vec![];
+    reader.read_to_end(&mut out).map_err(io_error)?;
+    Ok(out)
+}
+
+/// The Java client wraps snappy in snappy-java's "xerial" stream framing:
+///   header: 0x82 "SNAPPY" 0x00, version: INT32, compatible version: INT32
+///   blocks: length: INT32, raw snappy block
+/// Other clients (librdkafka) may send a bare snappy block, so both are read.
+#[cfg(feature = "snappy")]
+mod xerial {
+    use crate::records::RecordError;
+
+    const MAGIC: [u8; 8] = [0x82, b'S', b'N', b'A', b'P', b'P', b'Y', 0];
+    const HEADER_LEN: usize = 16;
+    const BLOCK_SIZE: usize = 32 * 1024;
+
+    fn snappy_error(e: snap::Error) -> RecordError {
+        RecordError::Corrupt(format!("snappy: {}", e))
+    }
+
+    pub fn compress(data: &[u8]) -> Result<Vec<u8>, RecordError> {
+        let mut out = MAGIC.to_vec();
+        out.extend(1i32.to_be_bytes());
+        out.extend(1i32.to_be_bytes());
+
+        let mut encoder = snap::raw::Encoder::new();
+        for block in data.chunks(BLOCK_SIZE) {
+            let compressed = encoder.compress_vec(block).map_err(snappy_error)?;
+            out.extend((compressed.len() as i32).to_be_bytes());
+            out.extend(compressed);
+        }
+        Ok(out)
+    }
+
+    pub fn decompress(data: &[u8]) -> Result<Vec<u8>, RecordError> {
+        let mut decoder = snap::raw::Decoder::new();
+        if data.len() < HEADER_LEN || data[..MAGIC.len()] != MAGIC {
+            return decoder.decompress_vec(data).map_err(snappy_error);
+        }
+
+        let mut out = vec![];
+        let mut pos = HEADER_LEN;
+        while pos < data.len() {
+            if pos + 4 > data.len() {
+                return Err(RecordError::Corrupt("truncated snappy block length".to_string()));
+            }
+            let len = i32::from_be_bytes(data[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            if len < 0 || pos + len as usize > data.len() {
+                return Err(RecordError::Corrupt(format!("invalid snappy block length {}", len)));
+            }
+            out.extend(decoder.decompress_vec(&data[pos..pos + len as usize]).map_err(snappy_error)?);
+            pos += len as usize;
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_every_supported_codec() {
+        let data: Vec<u8> = (0..100_000u32).flat_map(|i| (i % 251).to_be_bytes()).collect();
+
+        for codec in [
+            CompressionType::None,
+            CompressionType::Gzip,
+            CompressionType::Snappy,
+            CompressionType::Lz4,
+            CompressionType::Zstd,
+        ] {
+            if !codec.is_supported() {
+                assert!(matches!(codec.compress(&data), Err(RecordError::UnsupportedCompression(_))));
+                continue;
+            }
+            let compressed = codec.compress(&data).unwrap();
+            assert_eq!(codec.decompress(&compressed).unwrap(), data, "{}", codec.name());
+        }
+    }
+
+    #[cfg(feature = "snappy")]
+    #[test]
+    fn reads_unframed_snappy() {
+        let raw = snap::raw::Encoder::new().compress_vec(b"hello hello hello").unwrap();
+        assert_eq!(CompressionType::Snappy.decompress(&raw).unwrap(), b"hello hello hello");
+    }
+
+    #[test]
+    fn topic_compression_names() {
+        assert_eq!(BrokerCompressionType::from_name("producer"), Some(BrokerCompressionType::Producer));
+        assert_eq!(
+            BrokerCompressionType::from_name("uncompressed"),
+            Some(BrokerCompressionType::Codec(CompressionType::None))
+        );
+        assert_eq!(BrokerCompressionType::from_name("none"), None);
+        assert_eq!(BrokerCompressionType::Producer.target(CompressionType::Lz4), CompressionType::Lz4);
+    }
+}
@@ -0,0 +1,43 @@
+use bytes::{BufMut, BytesMut};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::records::Record;
+
+const CONTROL_RECORD_KEY_VERSION: i16 = 0;
+const END_TXN_MARKER_VERSION: i16 = 0;
+
+/// Type of the single record in a control batch, stored in its key
+/// (version: INT16, type: INT16)
+#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i16)]
+pub enum ControlRecordType {
+    Abort = 0,
+    Commit = 1,
+}
+
+impl ControlRecordType {
+    /// The type of a control record from its key
+    pub fn parse(key: &[u8]) -> Option<Self> {
+        let control_type = i16::from_be_bytes(key.get(2..4)?.try_into().ok()?);
+        ControlRecordType::try_from(control_type).ok()
+    }
+
+    /// An end transaction marker: this type as the key and
+    /// (version: INT16, coordinator_epoch: INT32) as the value
+    pub fn end_txn_marker(self, coordinator_epoch: i32, timestamp: i64) -> Record {
+        let mut key = BytesMut::with_capacity(4);
+        key.put_i16(CONTROL_RECORD_KEY_VERSION);
+        key.put_i16(self.into());
+
+        let mut value = BytesMut::with_capacity(6);
+        value.put_i16(END_TXN_MARKER_VERSION);
+        value.put_i32(coordinator_epoch);
+
+        Record::new(timestamp, Some(key.freeze()), Some(value.freeze()))
+    }
+}
+
+/// Type of the marker in a control batch, None if it's not one we know
+pub fn control_record_type(record: &Record) -> Option<ControlRecordType> {
+    record.key.as_deref().and_then(ControlRecordType::parse)
+}
@@ -0,0 +1,254 @@
+use crate::records::{
+    legacy::LegacyRecord, memory_records::LogEntry, MemoryRecords, RecordBatchBuilder,
+    RecordError, CURRENT_MAGIC_VALUE, MAGIC_VALUE_V0, MAGIC_VALUE_V1,
+};
+
+/// Convert v0/v1 messages to v2 batches, e.g. for a produce from an old
+/// client. Consecutive legacy messages end up in one batch with sequential
+/// offsets starting at the first message's offset (the log reassigns offsets
+/// on append anyway). Entries that are already v2 are kept as they are.
+pub fn up_convert(records: &MemoryRecords) -> Result<MemoryRecords, RecordError> {
+    if records.is_current_format() {
+        return Ok(records.clone());
+    }
+
+    let mut batches = vec![];
+    let mut pending: Option<RecordBatchBuilder> = None;
+
+    for entry in records.entries() {
+        match entry? {
+            LogEntry::Batch(batch) => {
+                if let Some(builder) = pending.take() {
+                    batches.push(builder.build()?);
+                }
+                batches.push(batch);
+            }
+            LogEntry::Legacy(message) => {
+                message.ensure_valid()?;
+                // the new batch is written uncompressed, validation applies the topic's codec
+                for inner in message.inner_records()? {
+                    pending
+                        .get_or_insert_with(|| RecordBatchBuilder::new(inner.offset()))
+                        .append(&inner.to_record());
+                }
+            }
+        }
+    }
+
+    if let Some(builder) = pending.take() {
+        batches.push(builder.build()?);
+    }
+
+    Ok(MemoryRecords::from_batches(&batches))
+}
+
+/// Convert everything to `to_magic` (0 or 1) for a Fetch from an old client.
+/// Records before `first_offset` are dropped, as are control batches which
+/// legacy formats can't represent, and a partial batch at the end of a read.
+/// Fails with `UnsupportedForMessageFormat` if a record has headers, since
+/// v0/v1 messages have nowhere to put them.
+pub fn down_convert(records: &MemoryRecords, to_magic: i8, first_offset: i64) -> Result<MemoryRecords, RecordError> {
+    if to_magic >= CURRENT_MAGIC_VALUE {
+        return Err(RecordError::InvalidMagic(to_magic));
+    }
+    if !records.has_magic_greater_than(to_magic) {
+        return Ok(records.clone());
+    }
+
+    let mut converted: Vec<LegacyRecord> = vec![];
+
+    for entry in records.complete_entries() {
+        match entry? {
+            LogEntry::Legacy(message) if message.magic() <= to_magic => {
+                if message.offset() >= first_offset {
+                    converted.push(message);
+                }
+            }
+            LogEntry::Legacy(message) => {
+                // only v1 -> v0 ends up here, which just drops the timestamp
+                for inner in message.inner_records()? {
+                    if inner.offset() >= first_offset {
+                        converted.push(LegacyRecord::build(
+                            to_magic,
+                            inner.offset(),
+                            inner.timestamp(),
+                            inner.timestamp_type(),
+                            inner.key().as_deref(),
+                            inner.value().as_deref(),
+                        ));
+                    }
+                }
+            }
+            LogEntry::Batch(batch) => {
+                if batch.is_control_batch() {
+                    continue;
+                }
+                for record in batch.records()? {
+                    let record = record?;
+                    if record.offset < first_offset {
+                        continue;
+                    }
+                    if !record.headers.is_empty() {
+                        return Err(RecordError::UnsupportedForMessageFormat(format!(
+                            "record at offset {} has headers which can't be represented in magic v{}",
+                            record.offset, to_magic
+                        )));
+                    }
+                    converted.push(LegacyRecord::build(
+                        to_magic,
+                        record.offset,
+                        record.timestamp,
+                        batch.timestamp_type(),
+                        record.key.as_deref(),
+                        record.value.as_deref(),
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(MemoryRecords::from_legacy_records(&converted))
+}
+
+/// The message format a Fetch of the given version can understand
+pub fn magic_for_fetch_version(version: i16) -> i8 {
+    match version {
+        0..=1 => MAGIC_VALUE_V0,
+        2..=3 => MAGIC_VALUE_V1,
+        _ => CURRENT_MAGIC_VALUE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::records::{Record, RecordHeader, TimestampType, NO_TIMESTAMP};
+
+    fn legacy(magic: i8, offset: i64, value: &'static [u8]) -> LegacyRecord {
+        LegacyRecord::build(magic, offset, 1_234, TimestampType::CreateTime, Some(b"key"), Some(value))
+    }
+
+    #[test]
+    fn legacy_round_trip() {
+        let message = legacy(MAGIC_VALUE_V1, 3, b"hello");
+        let parsed = LegacyRecord::from_bytes(message.into_bytes()).unwrap();
+        parsed.ensure_valid().unwrap();
+        assert_eq!(parsed.offset(), 3);
+        assert_eq!(parsed.timestamp(), 1_234);
+        assert_eq!(parsed.key().as_deref(), Some(&b"key"[..]));
+        assert_eq!(parsed.value().as_deref(), Some(&b"hello"[..]));
+
+        let v0 = legacy(MAGIC_VALUE_V0, 0, b"x");
+        assert_eq!(LegacyRecord::from_bytes(v0.into_bytes()).unwrap().timestamp(), NO_TIMESTAMP);
+    }
+
+    #[test]
+    fn up_converts_legacy_messages_into_one_batch() {
+        let records = MemoryRecords::from_legacy_records(&[
+            legacy(MAGIC_VALUE_V1, 0, b"a"),
+            legacy(MAGIC_VALUE_V1, 1, b"b"),
+            legacy(MAGIC_VALUE_V0, 2, b"c"),
+        ]);
+
+        let converted = up_convert(&records).unwrap();
+        let batches: Vec<_> = converted.batches().map(|b| b.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        batches[0].ensure_valid().unwrap();
+
+        let values: Vec<_> = batches[0].records().unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[2].offset, 2);
+        assert_eq!(values[2].value.as_deref(), Some(&b"c"[..]));
+        assert_eq!(values[2].timestamp, NO_TIMESTAMP);
+    }
+
+    #[test]
+    fn down_converts_from_fetch_offset() {
+        let mut builder = RecordBatchBuilder::new(10);
+        builder
+            .append(&Record::new(5, None, Some(Bytes::from_static(b"a"))))
+            .append(&Record::new(6, None, Some(Bytes::from_static(b"b"))));
+        let records = MemoryRecords::from_batches(&[builder.build().unwrap()]);
+
+        let converted = down_convert(&records, MAGIC_VALUE_V1, 11).unwrap();
+        let messages: Vec<_> = converted.legacy_records().map(|m| m.unwrap()).collect();
+        assert_eq!(messages.len(), 1);
+        messages[0].ensure_valid().unwrap();
+        assert_eq!(messages[0].offset(), 11);
+        assert_eq!(messages[0].timestamp(), 6);
+        assert_eq!(messages[0].value().as_deref(), Some(&b"b"[..]));
+
+        // a read can end in the middle of a batch
+        let mut partial = records.as_bytes().to_vec();
+        partial.extend_from_slice(&records.as_bytes()[..records.size_in_bytes() - 3]);
+        let converted = down_convert(&MemoryRecords::new(Bytes::from(partial)), MAGIC_VALUE_V0, 0).unwrap();
+        assert_eq!(converted.legacy_records().count(), 2);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn up_converts_compressed_wrapper() {
+        use crate::records::CompressionType;
+
+        // v1 wrapper: inner offsets are relative, the wrapper has the last absolute offset
+        let inner = MemoryRecords::from_legacy_records(&[legacy(MAGIC_VALUE_V1, 0, b"a"), legacy(MAGIC_VALUE_V1, 1, b"b")]);
+        let compressed = CompressionType::Gzip.compress(inner.as_bytes()).unwrap();
+        let wrapper = LegacyRecord::build_with_attributes(
+            MAGIC_VALUE_V1,
+            41,
+            1_234,
+            TimestampType::CreateTime,
+            CompressionType::Gzip,
+            None,
+            Some(&compressed),
+        );
+
+        let records: Vec<_> = wrapper.inner_records().unwrap();
+        assert_eq!(records.iter().map(|r| r.offset()).collect::<Vec<_>>(), vec![40, 41]);
+
+        let converted = up_convert(&MemoryRecords::from_legacy_records(&[wrapper])).unwrap();
+        let batch = converted.batches().next().unwrap().unwrap();
+        assert_eq!(batch.base_offset(), 40);
+        assert_eq!(batch.record_count(), 2);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn nested_compressed_wrappers_are_corrupt() {
+        use crate::records::CompressionType;
+
+        let wrap = |inner: &MemoryRecords| {
+            let compressed = CompressionType::Gzip.compress(inner.as_bytes()).unwrap();
+            LegacyRecord::build_with_attributes(
+                MAGIC_VALUE_V1,
+                0,
+                1_234,
+                TimestampType::CreateTime,
+                CompressionType::Gzip,
+                None,
+                Some(&compressed),
+            )
+        };
+        let inner = wrap(&MemoryRecords::from_legacy_records(&[legacy(MAGIC_VALUE_V1, 0, b"a")]));
+        let wrapper = wrap(&MemoryRecords::from_legacy_records(&[inner]));
+
+        let err = wrapper.inner_records().unwrap_err();
+        assert_eq!(err.error_code(), crate::common::error::error_code::CORRUPT_MESSAGE);
+        assert!(up_convert(&MemoryRecords::from_legacy_records(&[wrapper])).is_err());
+    }
+
+    #[test]
+    fn headers_cannot_be_down_converted() {
+        let mut builder = RecordBatchBuilder::new(0);
+        builder.append(&Record::new(5, None, None).with_headers(vec![RecordHeader {
+            key: "h".to_string(),
+            value: None,
+        }]));
+        let records = MemoryRecords::from_batches(&[builder.build().unwrap()]);
+
+        let err = down_convert(&records, MAGIC_VALUE_V1, 0).unwrap_err();
+        assert_eq!(err.error_code(), crate::common::error::error_code::UNSUPPORTED_FOR_MESSAGE_FORMAT);
+    }
+}
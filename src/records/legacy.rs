@@ -0,0 +1,330 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    common::EncodeToBytes,
+    records::{
+        CompressionType, MemoryRecords, Record, RecordError, TimestampType, LOG_OVERHEAD, MAGIC_OFFSET,
+        MAGIC_VALUE_V0, MAGIC_VALUE_V1, NO_TIMESTAMP,
+    },
+};
+
+/*
+* Message (magic v0/v1), each one is its own log entry =>
+*   offset: INT64
+*   messageSize: INT32
+*   crc: UINT32         (CRC-32 of everything from magic to the end)
+*   magic: INT8         (0 or 1)
+*   attributes: INT8
+*       bit 0~2: compression (0: none, 1: gzip, 2: snappy, 3: lz4)
+*       bit 3: timestampType (v1 only)
+*   timestamp: INT64    (v1 only)
+*   key: BYTES
+*   value: BYTES
+*/
+const OFFSET_OFFSET: usize = 0;
+const CRC_OFFSET: usize = 12;
+const ATTRIBUTES_OFFSET: usize = 17;
+const TIMESTAMP_OFFSET: usize = 18;
+const KEY_SIZE_OFFSET_V0: usize = 18;
+const KEY_SIZE_OFFSET_V1: usize = 26;
+
+const COMPRESSION_CODEC_MASK: i8 = 0x07;
+const TIMESTAMP_TYPE_MASK: i8 = 0x08;
+
+pub const LEGACY_RECORD_OVERHEAD_V0: usize = KEY_SIZE_OFFSET_V0 + 8;
+pub const LEGACY_RECORD_OVERHEAD_V1: usize = KEY_SIZE_OFFSET_V1 + 8;
+
+/// A single v0/v1 message backed by its serialized bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyRecord {
+    buffer: Bytes,
+}
+
+impl LegacyRecord {
+    pub fn from_bytes(buffer: Bytes) -> Result<LegacyRecord, RecordError> {
+        if buffer.len() <= MAGIC_OFFSET {
+            return Err(RecordError::Corrupt(format!("message of {} bytes is too small", buffer.len())));
+        }
+        let magic = buffer[MAGIC_OFFSET] as i8;
+        let overhead = match magic {
+            MAGIC_VALUE_V0 => LEGACY_RECORD_OVERHEAD_V0,
+            MAGIC_VALUE_V1 => LEGACY_RECORD_OVERHEAD_V1,
+            _ => return Err(RecordError::InvalidMagic(magic)),
+        };
+        if buffer.len() < overhead {
+            return Err(RecordError::Corrupt(format!(
+                "v{} message of {} bytes is smaller than the minimum of {}",
+                magic,
+                buffer.len(),
+                overhead
+            )));
+        }
+
+        let record = LegacyRecord { buffer };
+        // make sure the key and value lengths are consistent with the message size
+        let value_end = record.value_range()?.map(|(_, end)| end).unwrap_or(record.value_size_offset()? + 4);
+        if value_end != record.buffer.len() {
+            return Err(RecordError::Corrupt(format!(
+                "message size {} doesn't match its key and value",
+                record.buffer.len() - LOG_OVERHEAD
+            )));
+        }
+        Ok(record)
+    }
+
+    /// Build an uncompressed message
+    pub fn build(
+        magic: i8,
+        offset: i64,
+        timestamp: i64,
+        timestamp_type: TimestampType,
+        key: Option<&[u8]>,
+        value: Option<&[u8]>,
+    ) -> LegacyRecord {
+        Self::build_with_attributes(magic, offset, timestamp, timestamp_type, CompressionType::None, key, value)
+    }
+
+    pub(crate) fn build_with_attributes(
+        magic: i8,
+        offset: i64,
+        timestamp: i64,
+        timestamp_type: TimestampType,
+        compression: CompressionType,
+        key: Option<&[u8]>,
+        value: Option<&[u8]>,
+    ) -> LegacyRecord {
+        let mut attributes = (i16::from(compression) as i8) & COMPRESSION_CODEC_MASK;
+        if magic > MAGIC_VALUE_V0 && timestamp_type == TimestampType::LogAppendTime {
+            attributes |= TIMESTAMP_TYPE_MASK;
+        }
+
+        let mut body: Vec<u8> = vec![];
+        body.extend(magic.encode_to_bytes());
+        body.extend(attributes.encode_to_bytes());
+        if magic > MAGIC_VALUE_V0 {
+            body.extend(timestamp.encode_to_bytes());
+        }
+        write_bytes(&mut body, key);
+        write_bytes(&mut body, value);
+
+        let crc = crc32fast::hash(&body);
+
+        let mut res: Vec<u8> = Vec::with_capacity(LOG_OVERHEAD + 4 + body.len());
+        res.extend(offset.encode_to_bytes());
+        res.extend(((4 + body.len()) as i32).encode_to_bytes());
+        res.extend(crc.to_be_bytes());
+        res.extend(body);
+
+        LegacyRecord { buffer: Bytes::from(res) }
+    }
+
+    fn i32_at(&self, pos: usize) -> i32 {
+        i32::from_be_bytes(self.buffer[pos..pos + 4].try_into().unwrap())
+    }
+
+    fn i64_at(&self, pos: usize) -> i64 {
+        i64::from_be_bytes(self.buffer[pos..pos + 8].try_into().unwrap())
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.i64_at(OFFSET_OFFSET)
+    }
+
+    pub fn magic(&self) -> i8 {
+        self.buffer[MAGIC_OFFSET] as i8
+    }
+
+    pub fn checksum(&self) -> u32 {
+        self.i32_at(CRC_OFFSET) as u32
+    }
+
+    pub fn attributes(&self) -> i8 {
+        self.buffer[ATTRIBUTES_OFFSET] as i8
+    }
+
+    pub fn compression_type(&self) -> Result<CompressionType, RecordError> {
+        let codec = (self.attributes() & COMPRESSION_CODEC_MASK) as i16;
+        match CompressionType::try_from(codec) {
+            // zstd was only ever supported with v2 batches
+            Ok(CompressionType::Zstd) | Err(_) => Err(RecordError::UnsupportedCompression(codec)),
+            Ok(compression) => Ok(compression),
+        }
+    }
+
+    pub fn timestamp_type(&self) -> TimestampType {
+        if self.magic() > MAGIC_VALUE_V0 && self.attributes() & TIMESTAMP_TYPE_MASK != 0 {
+            TimestampType::LogAppendTime
+        } else {
+            TimestampType::CreateTime
+        }
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        match self.magic() {
+            MAGIC_VALUE_V0 => NO_TIMESTAMP,
+            _ => self.i64_at(TIMESTAMP_OFFSET),
+        }
+    }
+
+    fn key_size_offset(&self) -> usize {
+        match self.magic() {
+            MAGIC_VALUE_V0 => KEY_SIZE_OFFSET_V0,
+            _ => KEY_SIZE_OFFSET_V1,
+        }
+    }
+
+    fn bytes_range(&self, size_offset: usize) -> Result<Option<(usize, usize)>, RecordError> {
+        if size_offset + 4 > self.buffer.len() {
+            return Err(RecordError::Corrupt("message is truncated".to_string()));
+        }
+        let size = self.i32_at(size_offset);
+        if size < 0 {
+            return Ok(None);
+        }
+        let start = size_offset + 4;
+        let end = start + size as usize;
+        if end > self.buffer.len() {
+            return Err(RecordError::Corrupt("message is truncated".to_string()));
+        }
+        Ok(Some((start, end)))
+    }
+
+    fn value_size_offset(&self) -> Result<usize, RecordError> {
+        let key_size_offset = self.key_size_offset();
+        Ok(match self.bytes_range(key_size_offset)? {
+            Some((_, end)) => end,
+            None => key_size_offset + 4,
+        })
+    }
+
+    fn value_range(&self) -> Result<Option<(usize, usize)>, RecordError> {
+        self.bytes_range(self.value_size_offset()?)
+    }
+
+    pub fn key(&self) -> Option<Bytes> {
+        // from_bytes already checked the ranges
+        self.bytes_range(self.key_size_offset())
+            .ok()
+            .flatten()
+            .map(|(start, end)| self.buffer.slice(start..end))
+    }
+
+    pub fn value(&self) -> Option<Bytes> {
+        self.value_range()
+            .ok()
+            .flatten()
+            .map(|(start, end)| self.buffer.slice(start..end))
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.buffer
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.buffer
+    }
+
+    pub fn compute_checksum(&self) -> u32 {
+        crc32fast::hash(&self.buffer[MAGIC_OFFSET..])
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.checksum() == self.compute_checksum()
+    }
+
+    pub fn ensure_valid(&self) -> Result<(), RecordError> {
+        let computed = self.compute_checksum();
+        if self.checksum() != computed {
+            return Err(RecordError::InvalidCrc {
+                stored: self.checksum(),
+                computed,
+            });
+        }
+        Ok(())
+    }
+
+    /// The offset isn't covered by the CRC
+    pub fn set_offset(&mut self, offset: i64) {
+        let mut buf = BytesMut::from(&self.buffer[..]);
+        buf[OFFSET_OFFSET..OFFSET_OFFSET + 8].copy_from_slice(&offset.to_be_bytes());
+        self.buffer = buf.freeze();
+    }
+
+    /// The messages wrapped by a compressed message, with absolute offsets.
+    /// An uncompressed message is returned as is.
+    pub fn inner_records(&self) -> Result<Vec<LegacyRecord>, RecordError> {
+        let compression = self.compression_type()?;
+        if compression == CompressionType::None {
+            return Ok(vec![self.clone()]);
+        }
+
+        let value = self
+            .value()
+            .ok_or_else(|| RecordError::Corrupt("compressed message has a null value".to_string()))?;
+        let inner = MemoryRecords::new(Bytes::from(compression.decompress(&value)?));
+        let mut records = inner.legacy_records().collect::<Result<Vec<_>, _>>()?;
+        for record in &records {
+            record.ensure_valid()?;
+            if record.compression_type()? != CompressionType::None {
+                return Err(RecordError::Corrupt("compressed message contains a compressed message".to_string()));
+            }
+        }
+
+        if self.magic() == MAGIC_VALUE_V0 {
+            // v0 inner messages already carry absolute offsets
+            return Ok(records);
+        }
+
+        // v1 wrappers carry the absolute offset of the last inner message and
+        // inner offsets are relative to the first one
+        let last_relative = records.last().map(|r| r.offset()).unwrap_or(0);
+        let base_offset = self.offset() - last_relative;
+        for record in records.iter_mut() {
+            if self.timestamp_type() == TimestampType::LogAppendTime {
+                // the wrapper's timestamp overrides whatever the producer put inside
+                *record = LegacyRecord::build(
+                    record.magic(),
+                    record.offset(),
+                    self.timestamp(),
+                    TimestampType::LogAppendTime,
+                    record.key().as_deref(),
+                    record.value().as_deref(),
+                );
+            }
+            let relative = record.offset();
+            record.set_offset(base_offset + relative);
+        }
+        Ok(records)
+    }
+
+    /// View this message as a v2-style record
+    pub fn to_record(&self) -> Record {
+        Record {
+            offset: self.offset(),
+            timestamp: self.timestamp(),
+            key: self.key(),
+            value: self.value(),
+            ..Default::default()
+        }
+    }
+}
+
+impl EncodeToBytes for LegacyRecord {
+    fn encode_to_bytes(&self) -> Vec<u8> {
+        self.buffer.to_vec()
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: Option<&[u8]>) {
+    match bytes {
+        None => out.extend((-1i32).encode_to_bytes()),
+        Some(b) => {
+            out.extend((b.len() as i32).encode_to_bytes());
+            out.extend_from_slice(b);
+        }
+    }
+}
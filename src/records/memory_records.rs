@@ -0,0 +1,225 @@
+use std::marker::PhantomData;
+
+use bytes::{Bytes, BytesMut};
+
+use crate::records::{
+    legacy::LegacyRecord, RecordBatch, RecordError, CURRENT_MAGIC_VALUE, LOG_OVERHEAD, MAGIC_OFFSET,
+};
+
+/// A buffer of back-to-back log entries, as found in Produce requests, Fetch
+/// responses and log segments. Entries are v2 record batches, or v0/v1
+/// messages for data written by older clients.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryRecords {
+    buffer: Bytes,
+}
+
+/// One entry of a `MemoryRecords`, in whichever format it was written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEntry {
+    Batch(RecordBatch),
+    Legacy(LegacyRecord),
+}
+
+impl LogEntry {
+    pub fn magic(&self) -> i8 {
+        match self {
+            LogEntry::Batch(batch) => batch.magic(),
+            LogEntry::Legacy(record) => record.magic(),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        match self {
+            LogEntry::Batch(batch) => batch.as_bytes(),
+            LogEntry::Legacy(record) => record.as_bytes(),
+        }
+    }
+
+    pub fn ensure_valid(&self) -> Result<(), RecordError> {
+        match self {
+            LogEntry::Batch(batch) => batch.ensure_valid(),
+            LogEntry::Legacy(record) => record.ensure_valid(),
+        }
+    }
+}
+
+impl MemoryRecords {
+    pub fn new(buffer: Bytes) -> Self {
+        MemoryRecords { buffer }
+    }
+
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn from_batches<'a, I: IntoIterator<Item = &'a RecordBatch>>(batches: I) -> Self {
+        let mut buf = BytesMut::new();
+        for batch in batches {
+            buf.extend_from_slice(batch.as_bytes());
+        }
+        MemoryRecords { buffer: buf.freeze() }
+    }
+
+    pub fn from_legacy_records<'a, I: IntoIterator<Item = &'a LegacyRecord>>(records: I) -> Self {
+        let mut buf = BytesMut::new();
+        for record in records {
+            buf.extend_from_slice(record.as_bytes());
+        }
+        MemoryRecords { buffer: buf.freeze() }
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.buffer
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.buffer
+    }
+
+    /// Magic of the first entry, None when empty or too short to tell
+    pub fn magic(&self) -> Option<i8> {
+        self.buffer.get(MAGIC_OFFSET).map(|m| *m as i8)
+    }
+
+    /// Whether any entry in the buffer uses a format newer than `magic`
+    pub fn has_magic_greater_than(&self, magic: i8) -> bool {
+        self.entries().any(|entry| entry.map(|e| e.magic() > magic).unwrap_or(false))
+    }
+
+    /// Whether every entry is already a v2 batch
+    pub fn is_current_format(&self) -> bool {
+        self.entries().all(|entry| entry.map(|e| e.magic() == CURRENT_MAGIC_VALUE).unwrap_or(false))
+    }
+
+    /// Iterate over the v2 batches in the buffer, legacy entries are reported as
+    /// `InvalidMagic`. A truncated trailing batch is reported as an error; use
+    /// `complete_batches` to silently drop it instead (fetch responses are
+    /// allowed to end with a partial batch).
+    pub fn batches(&self) -> BatchIter {
+        EntryIter::new(self.buffer.clone(), false)
+    }
+
+    pub fn complete_batches(&self) -> BatchIter {
+        EntryIter::new(self.buffer.clone(), true)
+    }
+
+    pub fn legacy_records(&self) -> LegacyIter {
+        EntryIter::new(self.buffer.clone(), false)
+    }
+
+    /// Iterate over entries of any format
+    pub fn entries(&self) -> EntryIter<LogEntry> {
+        EntryIter::new(self.buffer.clone(), false)
+    }
+
+    /// `entries` without a truncated trailing entry, like `complete_batches`
+    pub fn complete_entries(&self) -> EntryIter<LogEntry> {
+        EntryIter::new(self.buffer.clone(), true)
+    }
+}
+
+/// How to turn one `offset | length | ...` frame into an entry
+pub trait FromFrame: Sized {
+    fn from_frame(frame: Bytes) -> Result<Self, RecordError>;
+}
+
+impl FromFrame for RecordBatch {
+    fn from_frame(frame: Bytes) -> Result<Self, RecordError> {
+        RecordBatch::from_bytes(frame)
+    }
+}
+
+impl FromFrame for LegacyRecord {
+    fn from_frame(frame: Bytes) -> Result<Self, RecordError> {
+        LegacyRecord::from_bytes(frame)
+    }
+}
+
+impl FromFrame for LogEntry {
+    fn from_frame(frame: Bytes) -> Result<Self, RecordError> {
+        match frame.get(MAGIC_OFFSET).map(|m| *m as i8) {
+            Some(CURRENT_MAGIC_VALUE) => Ok(LogEntry::Batch(RecordBatch::from_bytes(frame)?)),
+            _ => Ok(LogEntry::Legacy(LegacyRecord::from_bytes(frame)?)),
+        }
+    }
+}
+
+pub type BatchIter = EntryIter<RecordBatch>;
+pub type LegacyIter = EntryIter<LegacyRecord>;
+
+/// Every format frames its entries the same way: offset: INT64, length: INT32,
+/// then `length` bytes. This walks those frames.
+pub struct EntryIter<T> {
+    buffer: Bytes,
+    position: usize,
+    allow_partial: bool,
+    _entry: PhantomData<T>,
+}
+
+impl<T> EntryIter<T> {
+    fn new(buffer: Bytes, allow_partial: bool) -> Self {
+        EntryIter {
+            buffer,
+            position: 0,
+            allow_partial,
+            _entry: PhantomData,
+        }
+    }
+
+    /// Bytes consumed by the entries returned so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn truncated(&mut self, message: String) -> Option<Result<T, RecordError>> {
+        self.position = self.buffer.len();
+        if self.allow_partial {
+            None
+        } else {
+            Some(Err(RecordError::Corrupt(message)))
+        }
+    }
+}
+
+impl<T: FromFrame> Iterator for EntryIter<T> {
+    type Item = Result<T, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.buffer.len() - self.position;
+        if remaining == 0 {
+            return None;
+        }
+        if remaining < LOG_OVERHEAD {
+            return self.truncated(format!("{} trailing bytes are too few for an entry header", remaining));
+        }
+
+        let length_pos = self.position + 8;
+        let length = i32::from_be_bytes(self.buffer[length_pos..length_pos + 4].try_into().unwrap());
+        if length < 0 {
+            self.position = self.buffer.len();
+            return Some(Err(RecordError::Corrupt(format!("negative entry length {}", length))));
+        }
+
+        let size = LOG_OVERHEAD + length as usize;
+        if remaining < size {
+            return self.truncated(format!("entry of {} bytes but only {} remaining", size, remaining));
+        }
+
+        let frame = self.buffer.slice(self.position..self.position + size);
+        self.position += size;
+        let entry = T::from_frame(frame);
+        if entry.is_err() {
+            self.position = self.buffer.len();
+        }
+        Some(entry)
+    }
+}
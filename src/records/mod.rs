@@ -0,0 +1,84 @@
+pub mod compression;
+pub mod control;
+pub mod convert;
+pub mod legacy;
+pub mod memory_records;
+pub mod record;
+pub mod record_batch;
+pub mod validation;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::common::{error::error_code, EncodingError};
+
+pub use control::ControlRecordType;
+pub use legacy::LegacyRecord;
+pub use memory_records::{LogEntry, MemoryRecords};
+pub use record::{Record, RecordHeader};
+pub use record_batch::{RecordBatch, RecordBatchBuilder};
+
+pub const MAGIC_VALUE_V0: i8 = 0;
+pub const MAGIC_VALUE_V1: i8 = 1;
+pub const MAGIC_VALUE_V2: i8 = 2;
+pub const CURRENT_MAGIC_VALUE: i8 = MAGIC_VALUE_V2;
+
+// every format (legacy messages and v2 batches) starts with offset: INT64, length: INT32
+pub const LOG_OVERHEAD: usize = 12;
+// the magic byte sits at the same position in every format
+pub const MAGIC_OFFSET: usize = 16;
+
+pub const NO_TIMESTAMP: i64 = -1;
+pub const NO_PRODUCER_ID: i64 = -1;
+pub const NO_PRODUCER_EPOCH: i16 = -1;
+pub const NO_SEQUENCE: i32 = -1;
+pub const NO_PARTITION_LEADER_EPOCH: i32 = -1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RecordError {
+    #[error("encoding error: {0}")]
+    Encoding(#[from] EncodingError),
+    #[error("record is corrupt (stored crc = {stored}, computed crc = {computed})")]
+    InvalidCrc { stored: u32, computed: u32 },
+    #[error("unsupported magic value {0}")]
+    InvalidMagic(i8),
+    #[error("corrupt record batch: {0}")]
+    Corrupt(String),
+    #[error("invalid record: {0}")]
+    InvalidRecord(String),
+    #[error("unsupported compression type {0}")]
+    UnsupportedCompression(i16),
+    #[error("unsupported for message format: {0}")]
+    UnsupportedForMessageFormat(String),
+}
+
+impl RecordError {
+    pub fn error_code(&self) -> i16 {
+        match self {
+            RecordError::Encoding(_) | RecordError::InvalidCrc { .. } | RecordError::Corrupt(_) => {
+                error_code::CORRUPT_MESSAGE
+            }
+            RecordError::InvalidMagic(_) => error_code::UNSUPPORTED_FOR_MESSAGE_FORMAT,
+            RecordError::InvalidRecord(_) => error_code::INVALID_RECORD,
+            RecordError::UnsupportedCompression(_) => error_code::UNSUPPORTED_COMPRESSION_TYPE,
+            RecordError::UnsupportedForMessageFormat(_) => error_code::UNSUPPORTED_FOR_MESSAGE_FORMAT,
+        }
+    }
+}
+
+/// Lower 3 bits of the batch attributes
+#[derive(TryFromPrimitive, IntoPrimitive, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(i16)]
+pub enum CompressionType {
+    None = 0,
+    Gzip = 1,
+    Snappy = 2,
+    Lz4 = 3,
+    Zstd = 4,
+}
+
+/// Bit 3 of the batch attributes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimestampType {
+    CreateTime,
+    LogAppendTime,
+}
@@ -0,0 +1,154 @@
+use bytes::Bytes;
+
+use crate::{
+    records::RecordError,
+    utils::parse_primitive_types::{encode_varint, encode_varlong, read_exact, read_i8_be, read_varint, read_varlong},
+};
+
+/*
+* Record (inside a v2 RecordBatch) =>
+*   length: VARINT
+*   attributes: INT8 (unused)
+*   timestampDelta: VARLONG
+*   offsetDelta: VARINT
+*   keyLength: VARINT (-1 = null)
+*   key: BYTES
+*   valueLen: VARINT (-1 = null)
+*   value: BYTES
+*   headersCount: VARINT
+*   headers => headerKeyLength: VARINT, headerKey: STRING, headerValueLength: VARINT, value: BYTES
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    pub attributes: i8,
+    pub offset: i64,
+    pub timestamp: i64,
+    pub key: Option<Bytes>,
+    pub value: Option<Bytes>,
+    pub headers: Vec<RecordHeader>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordHeader {
+    pub key: String,
+    pub value: Option<Bytes>,
+}
+
+impl Record {
+    pub fn new(timestamp: i64, key: Option<Bytes>, value: Option<Bytes>) -> Self {
+        Record {
+            timestamp,
+            key,
+            value,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Vec<RecordHeader>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// A record with a key and a null value, which compaction treats as a delete
+    pub fn is_tombstone(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// Decode a single record from `input`, slicing key/value/header values out of
+    /// `input` instead of copying them.
+    pub(crate) fn read_from_bytes(
+        input: &Bytes,
+        off: &mut usize,
+        base_offset: i64,
+        base_timestamp: i64,
+        log_append_time: Option<i64>,
+    ) -> Result<Record, RecordError> {
+        let length = read_varint(input, off)?;
+        if length < 0 {
+            return Err(RecordError::InvalidRecord(format!("negative record length {}", length)));
+        }
+        let start = *off;
+        let end = start + length as usize;
+        if end > input.len() {
+            return Err(RecordError::Corrupt("record extends past the end of the batch".to_string()));
+        }
+
+        let attributes = read_i8_be(input, off)?;
+        let timestamp_delta = read_varlong(input, off)?;
+        let offset_delta = read_varint(input, off)?;
+        let key = read_varint_bytes(input, off)?;
+        let value = read_varint_bytes(input, off)?;
+
+        let headers_count = read_varint(input, off)?;
+        if headers_count < 0 {
+            return Err(RecordError::InvalidRecord(format!("negative header count {}", headers_count)));
+        }
+        let mut headers = Vec::with_capacity(headers_count.min(64) as usize);
+        for _ in 0..headers_count {
+            let key = read_varint_bytes(input, off)?
+                .ok_or_else(|| RecordError::InvalidRecord("null header key".to_string()))?;
+            let key = std::str::from_utf8(&key)
+                .map_err(|e| RecordError::InvalidRecord(format!("header key is not utf-8: {}", e)))?
+                .to_string();
+            let value = read_varint_bytes(input, off)?;
+            headers.push(RecordHeader { key, value });
+        }
+
+        if *off != end {
+            return Err(RecordError::InvalidRecord(format!(
+                "record length {} doesn't match the {} bytes read",
+                length,
+                *off - start
+            )));
+        }
+
+        Ok(Record {
+            attributes,
+            offset: base_offset + offset_delta as i64,
+            timestamp: log_append_time.unwrap_or(base_timestamp + timestamp_delta),
+            key,
+            value,
+            headers,
+        })
+    }
+
+    /// Encode the record relative to its batch's base offset and base timestamp
+    pub(crate) fn encode_to_bytes(&self, offset_delta: i32, timestamp_delta: i64) -> Vec<u8> {
+        let mut body: Vec<u8> = vec![];
+
+        body.push(self.attributes as u8);
+        body.extend(encode_varlong(timestamp_delta));
+        body.extend(encode_varint(offset_delta));
+        write_varint_bytes(&mut body, self.key.as_deref());
+        write_varint_bytes(&mut body, self.value.as_deref());
+        body.extend(encode_varint(self.headers.len() as i32));
+        for header in &self.headers {
+            write_varint_bytes(&mut body, Some(header.key.as_bytes()));
+            write_varint_bytes(&mut body, header.value.as_deref());
+        }
+
+        let mut res = encode_varint(body.len() as i32);
+        res.extend(body);
+        res
+    }
+}
+
+fn read_varint_bytes(input: &Bytes, off: &mut usize) -> Result<Option<Bytes>, RecordError> {
+    let len = read_varint(input, off)?;
+    if len < 0 {
+        return Ok(None);
+    }
+    let start = *off;
+    read_exact(input, off, len as usize)?;
+    Ok(Some(input.slice(start..*off)))
+}
+
+fn write_varint_bytes(out: &mut Vec<u8>, bytes: Option<&[u8]>) {
+    match bytes {
+        None => out.extend(encode_varint(-1)),
+        Some(b) => {
+            out.extend(encode_varint(b.len() as i32));
+            out.extend_from_slice(b);
+        }
+    }
+}
@@ -0,0 +1,608 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    common::EncodeToBytes,
+    records::{
+        CompressionType, Record, RecordError, TimestampType, CURRENT_MAGIC_VALUE, LOG_OVERHEAD,
+        MAGIC_OFFSET, NO_PARTITION_LEADER_EPOCH, NO_PRODUCER_EPOCH, NO_PRODUCER_ID, NO_SEQUENCE,
+        NO_TIMESTAMP,
+    },
+};
+
+/*
+* RecordBatch (magic v2) =>
+*   baseOffset: INT64
+*   batchLength: INT32          (bytes after this field)
+*   partitionLeaderEpoch: INT32
+*   magic: INT8                 (2)
+*   crc: UINT32                 (CRC-32C of everything from attributes to the end)
+*   attributes: INT16
+*       bit 0~2: compression (0: none, 1: gzip, 2: snappy, 3: lz4, 4: zstd)
+*       bit 3: timestampType (0: CreateTime, 1: LogAppendTime)
+*       bit 4: isTransactional
+*       bit 5: isControlBatch
+*       bit 6: hasDeleteHorizonMs
+*   lastOffsetDelta: INT32
+*   baseTimestamp: INT64
+*   maxTimestamp: INT64
+*   producerId: INT64
+*   producerEpoch: INT16
+*   baseSequence: INT32
+*   recordsCount: INT32
+*   records: [Record]
+*/
+const BASE_OFFSET_OFFSET: usize = 0;
+const LENGTH_OFFSET: usize = 8;
+const PARTITION_LEADER_EPOCH_OFFSET: usize = 12;
+const CRC_OFFSET: usize = 17;
+const ATTRIBUTES_OFFSET: usize = 21;
+const LAST_OFFSET_DELTA_OFFSET: usize = 23;
+const BASE_TIMESTAMP_OFFSET: usize = 27;
+const MAX_TIMESTAMP_OFFSET: usize = 35;
+const PRODUCER_ID_OFFSET: usize = 43;
+const PRODUCER_EPOCH_OFFSET: usize = 51;
+const BASE_SEQUENCE_OFFSET: usize = 53;
+const RECORDS_COUNT_OFFSET: usize = 57;
+pub const RECORD_BATCH_OVERHEAD: usize = 61;
+
+const COMPRESSION_CODEC_MASK: i16 = 0x07;
+const TIMESTAMP_TYPE_MASK: i16 = 0x08;
+const TRANSACTIONAL_FLAG_MASK: i16 = 0x10;
+const CONTROL_FLAG_MASK: i16 = 0x20;
+const DELETE_HORIZON_FLAG_MASK: i16 = 0x40;
+
+/// A v2 record batch backed by its serialized bytes. Header fields are read in
+/// place and records are sliced out of the buffer lazily, so nothing is copied
+/// until a batch is modified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordBatch {
+    buffer: Bytes,
+}
+
+impl RecordBatch {
+    /// Wrap `buffer`, which must hold exactly one v2 batch. The checksum is
+    /// not verified here, see `ensure_valid`.
+    pub fn from_bytes(buffer: Bytes) -> Result<RecordBatch, RecordError> {
+        if buffer.len() < RECORD_BATCH_OVERHEAD {
+            return Err(RecordError::Corrupt(format!(
+                "record batch is only {} bytes, smaller than the minimum of {}",
+                buffer.len(),
+                RECORD_BATCH_OVERHEAD
+            )));
+        }
+
+        let batch = RecordBatch { buffer };
+        if batch.magic() != CURRENT_MAGIC_VALUE {
+            return Err(RecordError::InvalidMagic(batch.magic()));
+        }
+        if batch.size_in_bytes() != LOG_OVERHEAD + batch.batch_length().max(0) as usize {
+            return Err(RecordError::Corrupt(format!(
+                "batch length {} doesn't match the {} bytes available",
+                batch.batch_length(),
+                batch.size_in_bytes() - LOG_OVERHEAD
+            )));
+        }
+        Ok(batch)
+    }
+
+    fn i16_at(&self, pos: usize) -> i16 {
+        i16::from_be_bytes(self.buffer[pos..pos + 2].try_into().unwrap())
+    }
+
+    fn i32_at(&self, pos: usize) -> i32 {
+        i32::from_be_bytes(self.buffer[pos..pos + 4].try_into().unwrap())
+    }
+
+    fn i64_at(&self, pos: usize) -> i64 {
+        i64::from_be_bytes(self.buffer[pos..pos + 8].try_into().unwrap())
+    }
+
+    pub fn base_offset(&self) -> i64 {
+        self.i64_at(BASE_OFFSET_OFFSET)
+    }
+
+    pub fn batch_length(&self) -> i32 {
+        self.i32_at(LENGTH_OFFSET)
+    }
+
+    pub fn partition_leader_epoch(&self) -> i32 {
+        self.i32_at(PARTITION_LEADER_EPOCH_OFFSET)
+    }
+
+    pub fn magic(&self) -> i8 {
+        self.buffer[MAGIC_OFFSET] as i8
+    }
+
+    pub fn checksum(&self) -> u32 {
+        self.i32_at(CRC_OFFSET) as u32
+    }
+
+    pub fn attributes(&self) -> i16 {
+        self.i16_at(ATTRIBUTES_OFFSET)
+    }
+
+    pub fn compression_type(&self) -> Result<CompressionType, RecordError> {
+        let codec = self.attributes() & COMPRESSION_CODEC_MASK;
+        CompressionType::try_from(codec).map_err(|_| RecordError::UnsupportedCompression(codec))
+    }
+
+    pub fn timestamp_type(&self) -> TimestampType {
+        match self.attributes() & TIMESTAMP_TYPE_MASK {
+            0 => TimestampType::CreateTime,
+            _ => TimestampType::LogAppendTime,
+        }
+    }
+
+    pub fn is_transactional(&self) -> bool {
+        self.attributes() & TRANSACTIONAL_FLAG_MASK != 0
+    }
+
+    pub fn is_control_batch(&self) -> bool {
+        self.attributes() & CONTROL_FLAG_MASK != 0
+    }
+
+    pub fn has_delete_horizon(&self) -> bool {
+        self.attributes() & DELETE_HORIZON_FLAG_MASK != 0
+    }
+
+    /// When the log cleaner may remove this batch's tombstones (or the batch,
+    /// for a transaction marker), stored in place of the base timestamp
+    pub fn delete_horizon_ms(&self) -> Option<i64> {
+        self.has_delete_horizon().then(|| self.base_timestamp())
+    }
+
+    pub fn last_offset_delta(&self) -> i32 {
+        self.i32_at(LAST_OFFSET_DELTA_OFFSET)
+    }
+
+    pub fn last_offset(&self) -> i64 {
+        self.base_offset() + self.last_offset_delta() as i64
+    }
+
+    pub fn next_offset(&self) -> i64 {
+        self.last_offset() + 1
+    }
+
+    pub fn base_timestamp(&self) -> i64 {
+        self.i64_at(BASE_TIMESTAMP_OFFSET)
+    }
+
+    pub fn max_timestamp(&self) -> i64 {
+        self.i64_at(MAX_TIMESTAMP_OFFSET)
+    }
+
+    pub fn producer_id(&self) -> i64 {
+        self.i64_at(PRODUCER_ID_OFFSET)
+    }
+
+    pub fn producer_epoch(&self) -> i16 {
+        self.i16_at(PRODUCER_EPOCH_OFFSET)
+    }
+
+    pub fn base_sequence(&self) -> i32 {
+        self.i32_at(BASE_SEQUENCE_OFFSET)
+    }
+
+    pub fn record_count(&self) -> i32 {
+        self.i32_at(RECORDS_COUNT_OFFSET)
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.buffer
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.buffer
+    }
+
+    /// The (possibly compressed) records section
+    pub fn records_data(&self) -> Bytes {
+        self.buffer.slice(RECORD_BATCH_OVERHEAD..)
+    }
+
+    pub fn compute_checksum(&self) -> u32 {
+        crc32c::crc32c(&self.buffer[ATTRIBUTES_OFFSET..])
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.checksum() == self.compute_checksum()
+    }
+
+    pub fn ensure_valid(&self) -> Result<(), RecordError> {
+        let computed = self.compute_checksum();
+        if self.checksum() != computed {
+            return Err(RecordError::InvalidCrc {
+                stored: self.checksum(),
+                computed,
+            });
+        }
+        if self.record_count() < 0 {
+            return Err(RecordError::Corrupt(format!("negative record count {}", self.record_count())));
+        }
+        if self.last_offset_delta() < 0 {
+            return Err(RecordError::Corrupt(format!(
+                "negative last offset delta {}",
+                self.last_offset_delta()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Iterate over the records of this batch. Keys, values and header values
+    /// share the batch's buffer.
+    pub fn records(&self) -> Result<RecordIter, RecordError> {
+        // compressed batches are inflated once, records then point into that buffer
+        let data = match self.compression_type()? {
+            CompressionType::None => self.records_data(),
+            codec => Bytes::from(codec.decompress(&self.records_data())?),
+        };
+
+        let log_append_time = match self.timestamp_type() {
+            TimestampType::LogAppendTime => Some(self.max_timestamp()),
+            TimestampType::CreateTime => None,
+        };
+
+        Ok(RecordIter {
+            data,
+            position: 0,
+            remaining: self.record_count(),
+            base_offset: self.base_offset(),
+            base_timestamp: self.base_timestamp(),
+            log_append_time,
+        })
+    }
+
+    fn modify<F: FnOnce(&mut BytesMut)>(&mut self, f: F) {
+        let mut buf = BytesMut::from(&self.buffer[..]);
+        f(&mut buf);
+        self.buffer = buf.freeze();
+    }
+
+    /// The base offset isn't covered by the CRC so no need to recompute it
+    pub fn set_base_offset(&mut self, base_offset: i64) {
+        self.modify(|buf| {
+            buf[BASE_OFFSET_OFFSET..BASE_OFFSET_OFFSET + 8].copy_from_slice(&base_offset.to_be_bytes());
+        });
+    }
+
+    /// Neither is the partition leader epoch
+    pub fn set_partition_leader_epoch(&mut self, epoch: i32) {
+        self.modify(|buf| {
+            buf[PARTITION_LEADER_EPOCH_OFFSET..PARTITION_LEADER_EPOCH_OFFSET + 4]
+                .copy_from_slice(&epoch.to_be_bytes());
+        });
+    }
+
+    /// Overwrite the timestamp type and max timestamp, e.g. for topics with
+    /// `message.timestamp.type=LogAppendTime`. Recomputes the CRC.
+    pub fn set_max_timestamp(&mut self, timestamp_type: TimestampType, max_timestamp: i64) {
+        let attributes = match timestamp_type {
+            TimestampType::CreateTime => self.attributes() & !TIMESTAMP_TYPE_MASK,
+            TimestampType::LogAppendTime => self.attributes() | TIMESTAMP_TYPE_MASK,
+        };
+        self.modify(|buf| {
+            buf[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + 2].copy_from_slice(&attributes.to_be_bytes());
+            buf[MAX_TIMESTAMP_OFFSET..MAX_TIMESTAMP_OFFSET + 8].copy_from_slice(&max_timestamp.to_be_bytes());
+            let crc = crc32c::crc32c(&buf[ATTRIBUTES_OFFSET..]);
+            buf[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_be_bytes());
+        });
+    }
+}
+
+impl EncodeToBytes for RecordBatch {
+    fn encode_to_bytes(&self) -> Vec<u8> {
+        self.buffer.to_vec()
+    }
+}
+
+pub struct RecordIter {
+    data: Bytes,
+    position: usize,
+    remaining: i32,
+    base_offset: i64,
+    base_timestamp: i64,
+    log_append_time: Option<i64>,
+}
+
+impl Iterator for RecordIter {
+    type Item = Result<Record, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining <= 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let record = Record::read_from_bytes(
+            &self.data,
+            &mut self.position,
+            self.base_offset,
+            self.base_timestamp,
+            self.log_append_time,
+        );
+        if record.is_err() {
+            // don't keep reading garbage after the first bad record
+            self.remaining = 0;
+        }
+        Some(record)
+    }
+}
+
+/// Builds a single v2 batch. Offsets are assigned sequentially from
+/// `base_offset` unless given explicitly with `append_with_offset`.
+#[derive(Debug, Clone)]
+pub struct RecordBatchBuilder {
+    base_offset: i64,
+    next_offset: i64,
+    compression: CompressionType,
+    log_append_time: Option<i64>,
+    producer_id: i64,
+    producer_epoch: i16,
+    base_sequence: i32,
+    is_transactional: bool,
+    is_control_batch: bool,
+    partition_leader_epoch: i32,
+    delete_horizon_ms: Option<i64>,
+    records: Vec<Record>,
+}
+
+impl RecordBatchBuilder {
+    pub fn new(base_offset: i64) -> Self {
+        RecordBatchBuilder {
+            base_offset,
+            next_offset: base_offset,
+            compression: CompressionType::None,
+            log_append_time: None,
+            producer_id: NO_PRODUCER_ID,
+            producer_epoch: NO_PRODUCER_EPOCH,
+            base_sequence: NO_SEQUENCE,
+            is_transactional: false,
+            is_control_batch: false,
+            partition_leader_epoch: NO_PARTITION_LEADER_EPOCH,
+            delete_horizon_ms: None,
+            records: vec![],
+        }
+    }
+
+    /// A builder producing a batch with the same producer state, flags and
+    /// timestamp type as `batch`, for rewriting it (recompression, compaction).
+    pub fn like(batch: &RecordBatch) -> Self {
+        let mut builder = RecordBatchBuilder::new(batch.base_offset())
+            .producer(batch.producer_id(), batch.producer_epoch(), batch.base_sequence())
+            .transactional(batch.is_transactional())
+            .control(batch.is_control_batch())
+            .partition_leader_epoch(batch.partition_leader_epoch());
+        if let Ok(compression) = batch.compression_type() {
+            builder = builder.compression(compression);
+        }
+        if batch.timestamp_type() == TimestampType::LogAppendTime {
+            builder = builder.log_append_time(batch.max_timestamp());
+        }
+        builder
+    }
+
+    pub fn compression(mut self, compression: CompressionType) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn log_append_time(mut self, timestamp: i64) -> Self {
+        self.log_append_time = Some(timestamp);
+        self
+    }
+
+    pub fn producer(mut self, producer_id: i64, producer_epoch: i16, base_sequence: i32) -> Self {
+        self.producer_id = producer_id;
+        self.producer_epoch = producer_epoch;
+        self.base_sequence = base_sequence;
+        self
+    }
+
+    pub fn transactional(mut self, is_transactional: bool) -> Self {
+        self.is_transactional = is_transactional;
+        self
+    }
+
+    pub fn control(mut self, is_control_batch: bool) -> Self {
+        self.is_control_batch = is_control_batch;
+        self
+    }
+
+    pub fn partition_leader_epoch(mut self, epoch: i32) -> Self {
+        self.partition_leader_epoch = epoch;
+        self
+    }
+
+    /// Set by the log cleaner once tombstones in the batch have a known removal time.
+    /// The delete horizon replaces the base timestamp.
+    pub fn delete_horizon_ms(mut self, delete_horizon_ms: i64) -> Self {
+        self.delete_horizon_ms = Some(delete_horizon_ms);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn num_records(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn append(&mut self, record: &Record) -> &mut Self {
+        let offset = self.next_offset;
+        self.append_with_offset(offset, record)
+    }
+
+    pub fn append_with_offset(&mut self, offset: i64, record: &Record) -> &mut Self {
+        assert!(
+            offset >= self.next_offset,
+            "offsets must be increasing, got {} after {}",
+            offset,
+            self.next_offset - 1
+        );
+        self.records.push(Record {
+            offset,
+            ..record.clone()
+        });
+        self.next_offset = offset + 1;
+        self
+    }
+
+    fn attributes(&self) -> i16 {
+        let mut attributes = i16::from(self.compression);
+        if self.log_append_time.is_some() {
+            attributes |= TIMESTAMP_TYPE_MASK;
+        }
+        if self.is_transactional {
+            attributes |= TRANSACTIONAL_FLAG_MASK;
+        }
+        if self.is_control_batch {
+            attributes |= CONTROL_FLAG_MASK;
+        }
+        if self.delete_horizon_ms.is_some() {
+            attributes |= DELETE_HORIZON_FLAG_MASK;
+        }
+        attributes
+    }
+
+    pub fn build(&self) -> Result<RecordBatch, RecordError> {
+        let base_timestamp = self
+            .delete_horizon_ms
+            .or_else(|| self.records.first().map(|r| r.timestamp))
+            .unwrap_or(NO_TIMESTAMP);
+        let max_timestamp = self.log_append_time.unwrap_or_else(|| {
+            self.records.iter().map(|r| r.timestamp).max().unwrap_or(NO_TIMESTAMP)
+        });
+        let last_offset_delta = (self.next_offset - 1 - self.base_offset).max(0) as i32;
+
+        let mut records: Vec<u8> = vec![];
+        for record in &self.records {
+            records.extend(record.encode_to_bytes(
+                (record.offset - self.base_offset) as i32,
+                record.timestamp - base_timestamp,
+            ));
+        }
+        let records = self.compression.compress(&records)?;
+
+        let mut res: Vec<u8> = Vec::with_capacity(RECORD_BATCH_OVERHEAD + records.len());
+        res.extend(self.base_offset.encode_to_bytes());
+        res.extend(((RECORD_BATCH_OVERHEAD - LOG_OVERHEAD + records.len()) as i32).encode_to_bytes());
+        res.extend(self.partition_leader_epoch.encode_to_bytes());
+        res.extend(CURRENT_MAGIC_VALUE.encode_to_bytes());
+        res.extend(0u32.to_be_bytes()); // crc placeholder
+        res.extend(self.attributes().encode_to_bytes());
+        res.extend(last_offset_delta.encode_to_bytes());
+        res.extend(base_timestamp.encode_to_bytes());
+        res.extend(max_timestamp.encode_to_bytes());
+        res.extend(self.producer_id.encode_to_bytes());
+        res.extend(self.producer_epoch.encode_to_bytes());
+        res.extend(self.base_sequence.encode_to_bytes());
+        res.extend((self.records.len() as i32).encode_to_bytes());
+        res.extend(records);
+
+        let crc = crc32c::crc32c(&res[ATTRIBUTES_OFFSET..]);
+        res[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_be_bytes());
+
+        Ok(RecordBatch {
+            buffer: Bytes::from(res),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::RecordHeader;
+
+    fn sample_batch() -> RecordBatch {
+        let mut builder = RecordBatchBuilder::new(100).producer(7, 1, 0);
+        builder
+            .append(&Record::new(1_000, Some(Bytes::from_static(b"k1")), Some(Bytes::from_static(b"v1"))))
+            .append(&Record::new(1_005, None, Some(Bytes::from_static(b"v2"))).with_headers(vec![
+                RecordHeader { key: "h".to_string(), value: Some(Bytes::from_static(b"x")) },
+                RecordHeader { key: "empty".to_string(), value: None },
+            ]))
+            .append_with_offset(105, &Record::new(999, Some(Bytes::from_static(b"k3")), None));
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn crc32c_check_value() {
+        assert_eq!(crc32c::crc32c(b"123456789"), 0xE3069283);
+    }
+
+    #[test]
+    fn build_and_read_back() {
+        let batch = sample_batch();
+        let batch = RecordBatch::from_bytes(batch.into_bytes()).unwrap();
+        batch.ensure_valid().unwrap();
+
+        assert_eq!(batch.base_offset(), 100);
+        assert_eq!(batch.last_offset(), 105);
+        assert_eq!(batch.record_count(), 3);
+        assert_eq!(batch.base_timestamp(), 1_000);
+        assert_eq!(batch.max_timestamp(), 1_005);
+        assert_eq!(batch.producer_id(), 7);
+        assert_eq!(batch.producer_epoch(), 1);
+        assert_eq!(batch.timestamp_type(), TimestampType::CreateTime);
+
+        let records: Vec<Record> = batch.records().unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].offset, 100);
+        assert_eq!(records[0].key.as_deref(), Some(&b"k1"[..]));
+        assert_eq!(records[1].offset, 101);
+        assert_eq!(records[1].timestamp, 1_005);
+        assert_eq!(records[1].key, None);
+        assert_eq!(records[1].headers.len(), 2);
+        assert_eq!(records[1].headers[1].value, None);
+        assert_eq!(records[2].offset, 105);
+        assert!(records[2].is_tombstone());
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let mut bytes = sample_batch().into_bytes().to_vec();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        let batch = RecordBatch::from_bytes(Bytes::from(bytes)).unwrap();
+        assert!(matches!(batch.ensure_valid(), Err(RecordError::InvalidCrc { .. })));
+    }
+
+    #[test]
+    fn base_offset_is_not_covered_by_crc() {
+        let mut batch = sample_batch();
+        batch.set_base_offset(5_000);
+        batch.ensure_valid().unwrap();
+        assert_eq!(batch.records().unwrap().next().unwrap().unwrap().offset, 5_000);
+
+        batch.set_max_timestamp(TimestampType::LogAppendTime, 42);
+        batch.ensure_valid().unwrap();
+        assert!(batch.records().unwrap().all(|r| r.unwrap().timestamp == 42));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_batch_round_trip() {
+        let original = sample_batch();
+        let compressed = RecordBatchBuilder::like(&original)
+            .compression(CompressionType::Gzip)
+            .build()
+            .unwrap();
+        assert_eq!(compressed.compression_type().unwrap(), CompressionType::Gzip);
+        compressed.ensure_valid().unwrap();
+
+        let mut builder = RecordBatchBuilder::like(&original).compression(CompressionType::Gzip);
+        for record in original.records().unwrap() {
+            let record = record.unwrap();
+            builder.append_with_offset(record.offset, &record);
+        }
+        let compressed = builder.build().unwrap();
+        let expected: Vec<Record> = original.records().unwrap().map(|r| r.unwrap()).collect();
+        let actual: Vec<Record> = compressed.records().unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::records::{
+    compression::BrokerCompressionType, convert::up_convert, MemoryRecords, RecordBatch, RecordBatchBuilder,
+    RecordError, NO_TIMESTAMP,
+};
+
+/// Records that passed validation, ready to be appended to a log
+#[derive(Debug, Clone)]
+pub struct ValidatedRecords {
+    pub records: MemoryRecords,
+    pub num_records: usize,
+    pub max_timestamp: i64,
+    /// whether any batch had to be rewritten with a different codec
+    pub recompressed: bool,
+}
+
+/// Validate records coming from a producer: every batch must have a good CRC,
+/// every record must decode (which means inflating compressed batches) and
+/// have sequential offsets. Batches whose codec differs from the topic's
+/// `compression.type` are recompressed. Legacy messages are up-converted first.
+pub fn validate_records(
+    records: &MemoryRecords,
+    target: BrokerCompressionType,
+) -> Result<ValidatedRecords, RecordError> {
+    let records = up_convert(records)?;
+
+    let mut batches: Vec<RecordBatch> = vec![];
+    let mut num_records = 0;
+    let mut max_timestamp = NO_TIMESTAMP;
+    let mut recompressed = false;
+
+    for batch in records.batches() {
+        let batch = batch?;
+        batch.ensure_valid()?;
+
+        if batch.is_control_batch() {
+            return Err(RecordError::InvalidRecord(
+                "clients are not allowed to write control records".to_string(),
+            ));
+        }
+
+        let source_codec = batch.compression_type()?;
+        let target_codec = target.target(source_codec);
+        if !target_codec.is_supported() {
+            return Err(RecordError::UnsupportedCompression(target_codec.into()));
+        }
+
+        let mut rebuilt = (source_codec != target_codec)
+            .then(|| RecordBatchBuilder::like(&batch).compression(target_codec));
+
+        let mut count = 0;
+        for (i, record) in batch.records()?.enumerate() {
+            let record = record?;
+            let expected_offset = batch.base_offset() + i as i64;
+            if record.offset != expected_offset {
+                return Err(RecordError::InvalidRecord(format!(
+                    "inner record offsets must be sequential, expected {} but got {}",
+                    expected_offset, record.offset
+                )));
+            }
+            if let Some(builder) = rebuilt.as_mut() {
+                builder.append_with_offset(record.offset, &record);
+            }
+            count += 1;
+        }
+        if count != batch.record_count() as usize || batch.last_offset_delta() as usize + 1 != count.max(1) {
+            return Err(RecordError::InvalidRecord(format!(
+                "batch claims {} records (last offset delta {}) but has {}",
+                batch.record_count(),
+                batch.last_offset_delta(),
+                count
+            )));
+        }
+
+        num_records += count;
+        max_timestamp = max_timestamp.max(batch.max_timestamp());
+        match rebuilt {
+            Some(builder) => {
+                recompressed = true;
+                batches.push(builder.build()?);
+            }
+            None => batches.push(batch),
+        }
+    }
+
+    Ok(ValidatedRecords {
+        records: if recompressed { MemoryRecords::from_batches(&batches) } else { records },
+        num_records,
+        max_timestamp,
+        recompressed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::records::{CompressionType, Record};
+
+    fn batch(compression: CompressionType) -> RecordBatch {
+        let mut builder = RecordBatchBuilder::new(0).compression(compression);
+        builder
+            .append(&Record::new(10, Some(Bytes::from_static(b"k")), Some(Bytes::from_static(b"v1"))))
+            .append(&Record::new(20, Some(Bytes::from_static(b"k")), Some(Bytes::from_static(b"v2"))));
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn keeps_producer_codec() {
+        let records = MemoryRecords::from_batches(&[batch(CompressionType::None)]);
+        let validated = validate_records(&records, BrokerCompressionType::Producer).unwrap();
+        assert!(!validated.recompressed);
+        assert_eq!(validated.num_records, 2);
+        assert_eq!(validated.max_timestamp, 20);
+        assert_eq!(validated.records, records);
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn recompresses_to_topic_codec() {
+        let records = MemoryRecords::from_batches(&[batch(CompressionType::None)]);
+        let target = BrokerCompressionType::Codec(CompressionType::Lz4);
+        let validated = validate_records(&records, target).unwrap();
+        assert!(validated.recompressed);
+
+        let batch = validated.records.batches().next().unwrap().unwrap();
+        batch.ensure_valid().unwrap();
+        assert_eq!(batch.compression_type().unwrap(), CompressionType::Lz4);
+        assert_eq!(batch.records().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn rejects_control_batches() {
+        let mut builder = RecordBatchBuilder::new(0).control(true);
+        builder.append(&Record::new(0, None, None));
+        let records = MemoryRecords::from_batches(&[builder.build().unwrap()]);
+        assert!(matches!(
+            validate_records(&records, BrokerCompressionType::Producer),
+            Err(RecordError::InvalidRecord(_))
+        ));
+    }
+}
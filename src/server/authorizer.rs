@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+
+use crate::common::{
+    acl::{
+        AclBinding, AclOperation, AclPermissionType, KafPrincipal, ResourceType,
+        AUTHORIZED_OPERATIONS_OMITTED, WILDCARD_HOST, WILDCARD_PRINCIPAL,
+    },
+    config::BrokerConfig,
+};
+
+/// Who is making a request, and from where.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub principal: KafPrincipal,
+    pub client_host: String,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            principal: KafPrincipal::anonymous(),
+            client_host: WILDCARD_HOST.to_string(),
+        }
+    }
+}
+
+/// ACL based authorizer, roughly Kafka's `StandardAuthorizer`:
+/// - super users are allowed everything
+/// - a matching DENY always wins over a matching ALLOW
+/// - when no ACL at all matches the resource, `allow_everyone_if_no_acl_found` decides
+#[derive(Debug, Clone)]
+pub struct Authorizer {
+    acls: Vec<AclBinding>,
+    super_users: HashSet<String>,
+    allow_everyone_if_no_acl_found: bool,
+}
+
+impl Default for Authorizer {
+    // no ACLs configured means nothing is restricted
+    fn default() -> Self {
+        Authorizer {
+            acls: vec![],
+            super_users: HashSet::new(),
+            allow_everyone_if_no_acl_found: true,
+        }
+    }
+}
+
+impl Authorizer {
+    pub fn new(
+        acls: Vec<AclBinding>,
+        super_users: HashSet<String>,
+        allow_everyone_if_no_acl_found: bool,
+    ) -> Self {
+        Authorizer {
+            acls,
+            super_users,
+            allow_everyone_if_no_acl_found,
+        }
+    }
+
+    /// No ACLs yet, with the super users and the fallback the broker is
+    /// configured with: `super.users` and `allow.everyone.if.no.acl.found`
+    pub fn from_broker_config(config: &BrokerConfig) -> Self {
+        let super_users = config
+            .get("super.users")
+            .map(|users| users.split(';').map(str::trim).filter(|user| !user.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default();
+        Authorizer::new(vec![], super_users, config.get_bool("allow.everyone.if.no.acl.found").unwrap_or(true))
+    }
+
+    pub fn add_acl(&mut self, binding: AclBinding) {
+        if !self.acls.contains(&binding) {
+            self.acls.push(binding);
+        }
+    }
+
+    pub fn authorize(
+        &self,
+        session: &Session,
+        operation: AclOperation,
+        resource_type: ResourceType,
+        resource_name: &str,
+    ) -> bool {
+        let principal = session.principal.to_string();
+        if self.super_users.contains(&principal) {
+            return true;
+        }
+
+        let resource_acls: Vec<&AclBinding> = self
+            .acls
+            .iter()
+            .filter(|acl| acl.pattern.matches(resource_type, resource_name))
+            .collect();
+
+        if resource_acls.is_empty() {
+            return self.allow_everyone_if_no_acl_found;
+        }
+
+        let applicable = resource_acls.iter().filter(|acl| {
+            (acl.entry.principal == principal || acl.entry.principal == WILDCARD_PRINCIPAL)
+                && (acl.entry.host == session.client_host || acl.entry.host == WILDCARD_HOST)
+        });
+
+        let mut allowed = false;
+        for acl in applicable {
+            let grants = acl.entry.operation == operation
+                || acl.entry.operation == AclOperation::All
+                || acl.entry.operation.implied().contains(&operation);
+
+            match acl.entry.permission_type {
+                // deny only blocks the exact operation (or ALL), it does not imply anything
+                AclPermissionType::Deny
+                    if acl.entry.operation == operation
+                        || acl.entry.operation == AclOperation::All =>
+                {
+                    return false
+                }
+                AclPermissionType::Allow if grants => allowed = true,
+                _ => {}
+            }
+        }
+
+        allowed
+    }
+
+    /// Bitfield of the operations `session` may perform on the resource,
+    /// bit N set meaning the `AclOperation` with code N is allowed.
+    pub fn authorized_operations(
+        &self,
+        session: &Session,
+        resource_type: ResourceType,
+        resource_name: &str,
+    ) -> i32 {
+        resource_type
+            .supported_operations()
+            .iter()
+            .filter(|op| self.authorize(session, **op, resource_type, resource_name))
+            .fold(0i32, |bits, op| bits | (1 << i8::from(*op)))
+    }
+
+    /// Same as `authorized_operations`, but returns the INT32_MIN sentinel when
+    /// the request didn't ask for the field.
+    pub fn authorized_operations_if_requested(
+        &self,
+        include: bool,
+        session: &Session,
+        resource_type: ResourceType,
+        resource_name: &str,
+    ) -> i32 {
+        if !include {
+            return AUTHORIZED_OPERATIONS_OMITTED;
+        }
+        self.authorized_operations(session, resource_type, resource_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::acl::{AccessControlEntry, PatternType, ResourcePattern};
+
+    fn topic_acl(name: &str, pattern_type: PatternType, principal: &str, op: AclOperation, permission: AclPermissionType) -> AclBinding {
+        AclBinding {
+            pattern: ResourcePattern {
+                resource_type: ResourceType::Topic,
+                name: name.to_string(),
+                pattern_type,
+            },
+            entry: AccessControlEntry {
+                principal: principal.to_string(),
+                host: WILDCARD_HOST.to_string(),
+                operation: op,
+                permission_type: permission,
+            },
+        }
+    }
+
+    #[test]
+    fn no_acls_allows_every_topic_operation() {
+        let authorizer = Authorizer::default();
+        let ops = authorizer.authorized_operations(&Session::default(), ResourceType::Topic, "foo");
+        assert_eq!(ops, 0x00000df8);
+    }
+
+    #[test]
+    fn read_implies_describe() {
+        let authorizer = Authorizer::new(
+            vec![topic_acl("foo", PatternType::Literal, "User:ANONYMOUS", AclOperation::Read, AclPermissionType::Allow)],
+            HashSet::new(),
+            false,
+        );
+        let ops = authorizer.authorized_operations(&Session::default(), ResourceType::Topic, "foo");
+        assert_eq!(ops, (1 << 3) | (1 << 8));
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let authorizer = Authorizer::new(
+            vec![
+                topic_acl("f", PatternType::Prefixed, WILDCARD_PRINCIPAL, AclOperation::All, AclPermissionType::Allow),
+                topic_acl("foo", PatternType::Literal, "User:ANONYMOUS", AclOperation::Write, AclPermissionType::Deny),
+            ],
+            HashSet::new(),
+            false,
+        );
+        let session = Session::default();
+        assert!(!authorizer.authorize(&session, AclOperation::Write, ResourceType::Topic, "foo"));
+        assert!(authorizer.authorize(&session, AclOperation::Read, ResourceType::Topic, "foo"));
+        assert!(authorizer.authorize(&session, AclOperation::Write, ResourceType::Topic, "far"));
+        // ACLs exist but none match "bar"
+        assert!(!authorizer.authorize(&session, AclOperation::Read, ResourceType::Topic, "bar"));
+    }
+
+    #[test]
+    fn sentinel_when_not_requested() {
+        let authorizer = Authorizer::default();
+        let ops = authorizer.authorized_operations_if_requested(false, &Session::default(), ResourceType::Topic, "foo");
+        assert_eq!(ops, AUTHORIZED_OPERATIONS_OMITTED);
+    }
+
+    #[test]
+    fn super_users_and_the_fallback_come_from_the_broker_config() {
+        let config = BrokerConfig::from_props(
+            [("super.users", "User:admin; User:ops"), ("allow.everyone.if.no.acl.found", "false")]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        let authorizer = Authorizer::from_broker_config(&config);
+        assert_eq!(authorizer.authorized_operations(&Session::default(), ResourceType::Topic, "foo"), 0);
+        let ops = Session {
+            principal: KafPrincipal::user("ops"),
+            ..Session::default()
+        };
+        assert_eq!(authorizer.authorized_operations(&ops, ResourceType::Topic, "foo"), 0x00000df8);
+    }
+}
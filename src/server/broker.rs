@@ -0,0 +1,265 @@
+use std::{collections::HashMap, fs, sync::Arc};
+
+use crate::{
+    common::{
+        config::{parse_properties, BrokerConfig},
+        topic_partition::TopicPartition,
+        uuid::KafUuid,
+    },
+    coordinator::{
+        group_coordinator::{GroupConfig, GroupCoordinator},
+        offset_manager::{OffsetConfig, OffsetManager},
+    },
+    log::{config::LogConfig, LogError, LogManager},
+    metadata::{
+        assign_replicas, config_records,
+        image::{MetadataImage, TopicImage},
+        metadata_log_configs, partition_records,
+        records::TOPIC_RESOURCE_TYPE,
+        MetadataError, MetadataManager, CLUSTER_METADATA_TOPIC, CONSUMER_OFFSETS_TOPIC,
+    },
+    server::{
+        authorizer::Authorizer, dynamic_config::ConfigLayers, fetch_session::FetchSessionCache,
+        purgatory::DelayedOperationPurgatory,
+    },
+    utils::clock::{Clock, SystemClock},
+};
+
+/// Written to each log dir by `kafka-storage.sh format`
+const META_PROPERTIES: &str = "meta.properties";
+
+/// State shared by every connection handled by this broker.
+#[derive(Debug)]
+pub struct Broker {
+    pub config: BrokerConfig,
+    /// From meta.properties, None if the log dirs weren't formatted
+    pub cluster_id: Option<String>,
+    pub authorizer: Authorizer,
+    pub log_manager: Arc<LogManager>,
+    pub metadata: MetadataManager,
+    /// Fetches waiting for min_bytes, woken by appends to their partitions
+    pub fetch_purgatory: DelayedOperationPurgatory<TopicPartition>,
+    pub fetch_sessions: FetchSessionCache,
+    pub group_coordinator: Arc<GroupCoordinator>,
+}
+
+impl Broker {
+    /// Load the partition logs found in the configured log dirs and the
+    /// metadata log, and start their housekeeping
+    pub fn new(config: BrokerConfig) -> Result<Self, MetadataError> {
+        let broker = Self::load(config, Arc::new(SystemClock))?;
+        broker.log_manager.start_background_tasks().map_err(LogError::from)?;
+        broker.group_coordinator.start_background_tasks().map_err(LogError::from)?;
+        Ok(broker)
+    }
+
+    /// `new` without the background tasks
+    pub fn load(config: BrokerConfig, clock: Arc<dyn Clock>) -> Result<Self, MetadataError> {
+        let log_manager = Arc::new(LogManager::startup(&config, clock.clone())?);
+        let offsets = OffsetManager::new(OffsetConfig::from_broker_config(&config), log_manager.clone());
+        let group_coordinator = Arc::new(GroupCoordinator::new(GroupConfig::from_broker_config(&config), offsets, clock));
+        let metadata = MetadataManager::load(&log_manager)?;
+        let cluster_id = config.log_dirs.iter().find_map(|dir| {
+            let contents = fs::read_to_string(dir.join(META_PROPERTIES)).ok()?;
+            parse_properties(&contents).remove("cluster.id")
+        });
+        let broker = Broker {
+            fetch_sessions: FetchSessionCache::from_config(&config),
+            authorizer: Authorizer::from_broker_config(&config),
+            config,
+            cluster_id,
+            log_manager,
+            metadata,
+            fetch_purgatory: DelayedOperationPurgatory::default(),
+            group_coordinator,
+        };
+        // topics created while we were down, or whose logs were lost
+        let image = broker.metadata.image();
+        // topics deleted while we were down
+        for log in broker.log_manager.all_logs() {
+            let tp = log.topic_partition();
+            if tp.topic != CLUSTER_METADATA_TOPIC && image.topic(&tp.topic).is_none() {
+                println!("deleting the log of {}, its topic is not in the metadata", tp);
+                if let Err(e) = broker.log_manager.async_delete_log(tp) {
+                    println!("unable to delete the log of {}: {}", tp, e);
+                }
+            }
+        }
+        for topic in image.topics() {
+            if let Err(e) = broker.create_local_logs(&image, topic) {
+                println!("unable to create the logs of topic {}: {}", topic.name, e);
+            }
+        }
+        // logs were opened before we knew their topic's configs
+        broker.update_log_configs(&image);
+        // committed offsets are replayed by the group coordinator's background tasks
+        if let Some(topic) = image.topic(CONSUMER_OFFSETS_TOPIC) {
+            broker.group_coordinator.offsets().set_partition_count(topic.partitions.len() as i32);
+            let hosted = topic.partitions.iter().filter(|(_, registration)| registration.replicas.contains(&broker.config.node_id));
+            broker.group_coordinator.offsets().schedule_load(hosted.map(|(partition, _)| *partition));
+        }
+        Ok(broker)
+    }
+
+    /// Create __consumer_offsets, which groups commit to, unless it exists
+    pub fn ensure_offsets_topic(&self) -> Result<(), MetadataError> {
+        if let Some(topic) = self.metadata.image().topic(CONSUMER_OFFSETS_TOPIC) {
+            self.group_coordinator.offsets().set_partition_count(topic.partitions.len() as i32);
+            return Ok(());
+        }
+        let config = self.group_coordinator.offsets().config();
+        let assignments = assign_replicas(&[self.config.node_id], config.num_partitions, config.replication_factor, 0).ok_or_else(|| {
+            MetadataError::InvalidConfig(format!(
+                "offsets.topic.replication.factor {} is larger than the 1 available broker(s).",
+                config.replication_factor
+            ))
+        })?;
+        let image = match self.create_topic(CONSUMER_OFFSETS_TOPIC, &assignments, &config.topic_configs()) {
+            // someone else got there first
+            Err(MetadataError::TopicAlreadyExists(_)) => self.metadata.image(),
+            result => result?,
+        };
+        if let Some(topic) = image.topic(CONSUMER_OFFSETS_TOPIC) {
+            self.group_coordinator.offsets().set_partition_count(topic.partitions.len() as i32);
+        }
+        Ok(())
+    }
+
+    /// Write a new topic to the metadata log and create the logs of its
+    /// partitions with replicas on this broker
+    pub fn create_topic(
+        &self,
+        name: &str,
+        assignments: &[Vec<i32>],
+        configs: &HashMap<String, String>,
+    ) -> Result<Arc<MetadataImage>, MetadataError> {
+        let image = self.metadata.create_topic(name, assignments, configs)?;
+        let topic = image.topic(name).expect("topic was just created");
+        self.create_local_logs(&image, topic)?;
+        Ok(image)
+    }
+
+    /// Add partitions numbered from `first_partition`, which must still be
+    /// the topic's partition count. Their logs are created before the image
+    /// holding them is published, so anyone who sees a new partition finds its log.
+    pub fn create_partitions(
+        &self,
+        topic_id: KafUuid,
+        first_partition: i32,
+        assignments: &[Vec<i32>],
+    ) -> Result<Arc<MetadataImage>, MetadataError> {
+        let mut writer = self.metadata.writer();
+        let image = writer.image();
+        let topic = image.topic_by_id(&topic_id).ok_or(MetadataError::UnknownTopicId(topic_id))?;
+        if topic.partitions.len() as i32 != first_partition {
+            return Err(MetadataError::InvalidPartitions(format!(
+                "Topic currently has {} partitions.",
+                topic.partitions.len()
+            )));
+        }
+        let configs = image.topic_configs(&topic.name);
+        let mut created = vec![];
+        let result = assignments
+            .iter()
+            .enumerate()
+            .filter(|(_, replicas)| replicas.contains(&self.config.node_id))
+            .try_for_each(|(i, _)| {
+                let tp = TopicPartition::new(&topic.name, first_partition + i as i32);
+                self.create_local_log(&tp, topic_id, &configs)?;
+                created.push(tp);
+                Ok::<_, LogError>(())
+            })
+            .map_err(MetadataError::from)
+            .and_then(|()| writer.append(&partition_records(topic_id, first_partition, assignments)));
+        if result.is_err() {
+            // nobody could have seen these, don't leave them behind
+            for tp in created {
+                if let Err(e) = self.log_manager.async_delete_log(&tp) {
+                    println!("unable to delete the log of {}: {}", tp, e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Remove a topic from the metadata log and delete the logs of its
+    /// partitions. Their dirs are renamed right away and removed in the background.
+    pub fn delete_topic(&self, topic_id: KafUuid) -> Result<(), MetadataError> {
+        let image = self.metadata.image();
+        let topic = image.topic_by_id(&topic_id).ok_or(MetadataError::UnknownTopicId(topic_id))?;
+        self.metadata.delete_topic(topic_id)?;
+        for tp in topic.topic_partitions() {
+            match self.log_manager.async_delete_log(&tp) {
+                // fetches waiting on the partition find out it's gone
+                Ok(Some(_)) => self.fetch_purgatory.check_and_complete(&tp),
+                Ok(None) => {}
+                Err(e) => println!("unable to delete the log of {}: {}", tp, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the config overrides of a topic (`TOPIC_RESOURCE_TYPE`) or
+    /// the dynamic configs of a broker with what `change` makes of the
+    /// current ones. Logs pick up the new configs before this returns.
+    pub fn alter_configs(
+        &self,
+        resource_type: i8,
+        resource_name: &str,
+        validate_only: bool,
+        change: impl FnOnce(&HashMap<String, String>) -> Result<HashMap<String, String>, MetadataError>,
+    ) -> Result<(), MetadataError> {
+        let mut writer = self.metadata.writer();
+        let image = writer.image();
+        if resource_type == TOPIC_RESOURCE_TYPE && image.topic(resource_name).is_none() {
+            return Err(MetadataError::UnknownTopic(resource_name.to_string()));
+        }
+        let current = image.configs(resource_type, resource_name);
+        let altered = change(&current)?;
+        if validate_only {
+            return Ok(());
+        }
+        let records = config_records(resource_type, resource_name, &current, &altered);
+        if !records.is_empty() {
+            let image = writer.append(&records)?;
+            self.update_log_configs(&image);
+        }
+        Ok(())
+    }
+
+    /// The static broker config with the dynamic ones in `image` applied
+    pub fn config_layers(&self, image: &MetadataImage) -> ConfigLayers {
+        ConfigLayers::new(&self.config, image)
+    }
+
+    /// Give every log the configs of its topic in `image`, on top of the
+    /// log defaults of the dynamic broker config
+    fn update_log_configs(&self, image: &MetadataImage) {
+        let defaults = LogConfig::from_broker_config(&self.config_layers(image).effective());
+        self.log_manager.set_default_config(defaults.clone());
+        for log in self.log_manager.all_logs() {
+            let topic = &log.topic_partition().topic;
+            let overrides = if topic == CLUSTER_METADATA_TOPIC {
+                metadata_log_configs()
+            } else {
+                image.topic_configs(topic)
+            };
+            log.update_config(defaults.with_overrides(&overrides));
+        }
+    }
+
+    fn create_local_logs(&self, image: &MetadataImage, topic: &TopicImage) -> Result<(), LogError> {
+        let configs = image.topic_configs(&topic.name);
+        for (partition, registration) in &topic.partitions {
+            if registration.replicas.contains(&self.config.node_id) {
+                self.create_local_log(&TopicPartition::new(&topic.name, *partition), topic.topic_id, &configs)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn create_local_log(&self, tp: &TopicPartition, topic_id: KafUuid, configs: &HashMap<String, String>) -> Result<(), LogError> {
+        let log = self.log_manager.get_or_create_log(tp, configs)?;
+        log.assign_topic_id(topic_id)
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use crate::{
+    common::{
+        config::{BrokerConfig, BROKER_CONFIGS},
+        config_def::config_source,
+    },
+    log::config::{convert_synonym_value, topic_config_synonyms, TOPIC_CONFIGS},
+    metadata::{image::MetadataImage, records::BROKER_RESOURCE_TYPE},
+};
+
+/// Resource name of the dynamic broker configs shared by every broker
+pub const CLUSTER_DEFAULT_RESOURCE: &str = "";
+
+/// One value a config gets from one source. DescribeConfigs lists them as
+/// the config's synonyms, highest precedence first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSynonym {
+    pub name: String,
+    pub value: Option<String>,
+    pub source: i8,
+}
+
+/// Where broker configs come from: set on this broker at runtime, set for
+/// every broker at runtime, or `server.properties`
+#[derive(Debug, Clone)]
+pub struct ConfigLayers {
+    config: BrokerConfig,
+    dynamic_broker: HashMap<String, String>,
+    dynamic_default: HashMap<String, String>,
+}
+
+impl ConfigLayers {
+    pub fn new(config: &BrokerConfig, image: &MetadataImage) -> Self {
+        ConfigLayers {
+            config: config.clone(),
+            dynamic_broker: image.configs(BROKER_RESOURCE_TYPE, &config.node_id.to_string()),
+            dynamic_default: image.configs(BROKER_RESOURCE_TYPE, CLUSTER_DEFAULT_RESOURCE),
+        }
+    }
+
+    /// The broker config with the dynamic configs applied
+    pub fn effective(&self) -> BrokerConfig {
+        self.config.with_dynamic_configs(&self.dynamic_default, &self.dynamic_broker)
+    }
+
+    /// The values of a broker config, from every source it's set in
+    pub fn broker_synonyms(&self, name: &str) -> Vec<ConfigSynonym> {
+        let layers = [
+            (self.dynamic_broker.get(name).map(String::as_str), config_source::DYNAMIC_BROKER_CONFIG),
+            (self.dynamic_default.get(name).map(String::as_str), config_source::DYNAMIC_DEFAULT_BROKER_CONFIG),
+            (self.config.get(name), config_source::STATIC_BROKER_CONFIG),
+            (BROKER_CONFIGS.key(name).and_then(|key| key.default), config_source::DEFAULT_CONFIG),
+        ];
+        layers
+            .into_iter()
+            .filter_map(|(value, source)| {
+                Some(ConfigSynonym {
+                    name: name.to_string(),
+                    value: Some(value?.to_string()),
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// The values of a topic config for a topic with `overrides` set: its
+    /// own, then those of the broker configs it defaults to
+    pub fn topic_synonyms(&self, name: &str, overrides: &HashMap<String, String>) -> Vec<ConfigSynonym> {
+        let mut synonyms: Vec<_> = overrides
+            .get(name)
+            .map(|value| ConfigSynonym {
+                name: name.to_string(),
+                value: Some(value.clone()),
+                source: config_source::DYNAMIC_TOPIC_CONFIG,
+            })
+            .into_iter()
+            .collect();
+        for (synonym, _) in topic_config_synonyms(name) {
+            synonyms.extend(self.broker_synonyms(synonym));
+        }
+        if synonyms.is_empty() {
+            if let Some(default) = TOPIC_CONFIGS.key(name).and_then(|key| key.default) {
+                synonyms.push(ConfigSynonym {
+                    name: name.to_string(),
+                    value: Some(default.to_string()),
+                    source: config_source::DEFAULT_CONFIG,
+                });
+            }
+        }
+        synonyms
+    }
+
+    /// A topic config's value from its highest precedence synonym, in the
+    /// topic config's unit
+    pub fn topic_value(name: &str, synonyms: &[ConfigSynonym]) -> Option<String> {
+        let first = synonyms.first()?;
+        let multiplier = topic_config_synonyms(name)
+            .iter()
+            .find(|(synonym, _)| *synonym == first.name)
+            .map_or(1, |(_, multiplier)| *multiplier);
+        first.value.as_deref().map(|value| convert_synonym_value(value, multiplier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::records::{ConfigRecord, MetadataRecord};
+
+    #[test]
+    fn values_come_from_the_highest_precedence_source() {
+        let config = BrokerConfig::from_props(HashMap::from([("log.retention.hours".to_string(), "24".to_string())]));
+        let mut image = MetadataImage::default();
+        let layers = ConfigLayers::new(&config, &image);
+        let retention = layers.topic_synonyms("retention.ms", &HashMap::new());
+        let sources: Vec<_> = retention.iter().map(|s| (s.name.as_str(), s.source)).collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("log.retention.hours", config_source::STATIC_BROKER_CONFIG),
+                ("log.retention.hours", config_source::DEFAULT_CONFIG),
+            ]
+        );
+        assert_eq!(ConfigLayers::topic_value("retention.ms", &retention).as_deref(), Some("86400000"));
+
+        for (resource_name, value) in [(CLUSTER_DEFAULT_RESOURCE, "600000"), ("1", "300000")] {
+            image.apply(&MetadataRecord::Config(ConfigRecord {
+                resource_type: BROKER_RESOURCE_TYPE,
+                resource_name: resource_name.to_string(),
+                name: "log.retention.ms".to_string(),
+                value: Some(value.to_string()),
+            }));
+        }
+        let layers = ConfigLayers::new(&config, &image);
+        let retention = layers.topic_synonyms("retention.ms", &HashMap::new());
+        assert_eq!(retention[0].source, config_source::DYNAMIC_BROKER_CONFIG);
+        assert_eq!(retention[1].source, config_source::DYNAMIC_DEFAULT_BROKER_CONFIG);
+        assert_eq!(ConfigLayers::topic_value("retention.ms", &retention).as_deref(), Some("300000"));
+        assert_eq!(layers.effective().get_i64("log.retention.ms"), Some(300_000));
+
+        let overrides = HashMap::from([("retention.ms".to_string(), "1000".to_string())]);
+        let retention = layers.topic_synonyms("retention.ms", &overrides);
+        assert_eq!((retention.len(), retention[0].source), (5, config_source::DYNAMIC_TOPIC_CONFIG));
+        assert_eq!(ConfigLayers::topic_value("retention.ms", &retention).as_deref(), Some("1000"));
+    }
+}
@@ -0,0 +1,491 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rand::Rng;
+
+use crate::{
+    common::{
+        config::BrokerConfig,
+        error::error_code,
+        request::fetch::{FetchBody, FetchPartition, FetchTopic},
+        response::fetch::{FetchResponse, FetchableTopicResponse, PartitionData},
+        uuid::KafUuid,
+    },
+    utils::clock::{Clock, SystemClock},
+};
+
+pub const DEFAULT_MAX_INCREMENTAL_FETCH_SESSION_CACHE_SLOTS: usize = 1000;
+
+/// How long a session has to sit unused before any new session can evict it
+pub const DEFAULT_FETCH_SESSION_EVICTION_MS: i64 = 120_000;
+
+/// Session id of sessionless fetches, and of responses that didn't create one
+pub const INVALID_SESSION_ID: i32 = 0;
+/// Epoch of a full fetch that creates a session
+pub const INITIAL_EPOCH: i32 = 0;
+/// Epoch of a full fetch that doesn't want a session, or closes one
+pub const FINAL_EPOCH: i32 = -1;
+
+fn next_epoch(epoch: i32) -> i32 {
+    // wraps around to 1, 0 and -1 mean something else
+    if epoch == i32::MAX { 1 } else { epoch + 1 }
+}
+
+/// A partition in a fetch session: what the client asked for last time, and
+/// what we last told it, so unchanged partitions can be left out
+#[derive(Debug, Clone)]
+struct CachedPartition {
+    topic: String,
+    topic_id: KafUuid,
+    fetch: FetchPartition,
+    high_watermark: i64,
+    last_stable_offset: i64,
+    log_start_offset: i64,
+}
+
+impl CachedPartition {
+    fn new(topic: &FetchTopic, fetch: &FetchPartition) -> Self {
+        CachedPartition {
+            topic: topic.topic.clone(),
+            topic_id: topic.topic_id,
+            fetch: fetch.clone(),
+            high_watermark: -1,
+            last_stable_offset: -1,
+            log_start_offset: -1,
+        }
+    }
+
+    fn is(&self, topic: &str, topic_id: KafUuid, partition: i32) -> bool {
+        self.fetch.partition == partition && self.topic_id == topic_id && self.topic == topic
+    }
+
+    /// Remember what the response tells the client, true if anything changed
+    /// since the last response and the partition has to be included
+    fn update_response_data(&mut self, data: &PartitionData) -> bool {
+        let must_respond = data.error_code != error_code::NONE
+            || data.records.as_ref().is_some_and(|records| !records.is_empty())
+            || data.high_watermark != self.high_watermark
+            || data.last_stable_offset != self.last_stable_offset
+            || data.log_start_offset != self.log_start_offset;
+        self.high_watermark = data.high_watermark;
+        self.last_stable_offset = data.last_stable_offset;
+        self.log_start_offset = data.log_start_offset;
+        must_respond
+    }
+}
+
+#[derive(Debug)]
+struct FetchSession {
+    /// started by a follower rather than a consumer
+    privileged: bool,
+    uses_topic_ids: bool,
+    /// in fetch order
+    partitions: Vec<CachedPartition>,
+    /// the epoch the next incremental fetch has to carry
+    epoch: i32,
+    last_used_ms: i64,
+}
+
+impl FetchSession {
+    /// Fetch the session's partitions, grouped back into topics
+    fn fetch_topics(&self) -> Vec<FetchTopic> {
+        let mut topics: Vec<FetchTopic> = vec![];
+        for cached in &self.partitions {
+            match topics.last_mut() {
+                Some(topic) if topic.topic == cached.topic && topic.topic_id == cached.topic_id => {
+                    topic.partitions.push(cached.fetch.clone())
+                }
+                _ => topics.push(FetchTopic {
+                    topic: cached.topic.clone(),
+                    topic_id: cached.topic_id,
+                    partitions: vec![cached.fetch.clone()],
+                }),
+            }
+        }
+        topics
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FetchContextKind {
+    /// No session is created and the response has every partition
+    Sessionless,
+    /// Every partition is in the request and the response, and a new
+    /// session is created from them if there's room in the cache
+    Full { privileged: bool, uses_topic_ids: bool },
+    /// The partitions come from the session, the response only has those
+    /// that changed
+    Incremental { session_id: i32, epoch: i32 },
+}
+
+/// What a Fetch reads, worked out from its session id and epoch
+#[derive(Debug, Clone)]
+pub struct FetchContext {
+    kind: FetchContextKind,
+    /// the partitions to read
+    pub topics: Vec<FetchTopic>,
+}
+
+/// The fetch sessions of KIP-227, so consumers with lots of partitions don't
+/// have to send all of them on every Fetch. A full fetch with epoch 0 creates
+/// a session, later fetches send only the partitions whose fetch offset moved
+/// (or to forget) along with the session id and the next epoch.
+///
+/// The cache holds at most `max.incremental.fetch.session.cache.slots`
+/// sessions. When it's full a new session takes the place of the least
+/// recently used one if that has been idle for two minutes; a follower's
+/// session can also take the place of the smallest consumer session.
+/// Otherwise the fetch goes on without a session.
+#[derive(Debug)]
+pub struct FetchSessionCache {
+    max_entries: usize,
+    eviction_ms: i64,
+    sessions: Mutex<HashMap<i32, FetchSession>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for FetchSessionCache {
+    fn default() -> Self {
+        FetchSessionCache::new(
+            DEFAULT_MAX_INCREMENTAL_FETCH_SESSION_CACHE_SLOTS,
+            DEFAULT_FETCH_SESSION_EVICTION_MS,
+            Arc::new(SystemClock),
+        )
+    }
+}
+
+impl FetchSessionCache {
+    pub fn new(max_entries: usize, eviction_ms: i64, clock: Arc<dyn Clock>) -> Self {
+        FetchSessionCache {
+            max_entries,
+            eviction_ms,
+            sessions: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    pub fn from_config(config: &BrokerConfig) -> Self {
+        let max_entries = config
+            .get_i64("max.incremental.fetch.session.cache.slots")
+            .map(|slots| slots.max(0) as usize)
+            .unwrap_or(DEFAULT_MAX_INCREMENTAL_FETCH_SESSION_CACHE_SLOTS);
+        FetchSessionCache::new(max_entries, DEFAULT_FETCH_SESSION_EVICTION_MS, Arc::new(SystemClock))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Work out the partitions to read for a fetch, updating its session if it
+    /// has one. Errors are top level error codes for the response.
+    pub fn new_context(&self, body: &FetchBody, version: i16) -> Result<FetchContext, i16> {
+        let uses_topic_ids = version >= 13;
+        let mut sessions = self.sessions.lock().unwrap();
+
+        if body.session_epoch == INITIAL_EPOCH || body.session_epoch == FINAL_EPOCH {
+            // a full fetch replaces the session it names
+            if body.session_id != INVALID_SESSION_ID {
+                sessions.remove(&body.session_id);
+            }
+            let kind = if body.session_epoch == FINAL_EPOCH {
+                FetchContextKind::Sessionless
+            } else {
+                FetchContextKind::Full {
+                    privileged: body.replica_id >= 0,
+                    uses_topic_ids,
+                }
+            };
+            return Ok(FetchContext {
+                kind,
+                topics: body.topics.clone(),
+            });
+        }
+
+        let Some(session) = sessions.get_mut(&body.session_id) else {
+            return Err(error_code::FETCH_SESSION_ID_NOT_FOUND);
+        };
+        if session.epoch != body.session_epoch {
+            return Err(error_code::INVALID_FETCH_SESSION_EPOCH);
+        }
+        if session.uses_topic_ids != uses_topic_ids {
+            return Err(error_code::FETCH_SESSION_TOPIC_ID_ERROR);
+        }
+        for topic in &body.topics {
+            for fetch in &topic.partitions {
+                match session.partitions.iter_mut().find(|p| p.is(&topic.topic, topic.topic_id, fetch.partition)) {
+                    Some(cached) => cached.fetch = fetch.clone(),
+                    None => session.partitions.push(CachedPartition::new(topic, fetch)),
+                }
+            }
+        }
+        for forgotten in &body.forgotten_topics_data {
+            session.partitions.retain(|p| {
+                !forgotten.partitions.iter().any(|&partition| p.is(&forgotten.topic, forgotten.topic_id, partition))
+            });
+        }
+        session.epoch = next_epoch(session.epoch);
+        session.last_used_ms = self.clock.now_ms();
+        Ok(FetchContext {
+            kind: FetchContextKind::Incremental {
+                session_id: body.session_id,
+                epoch: session.epoch,
+            },
+            topics: session.fetch_topics(),
+        })
+    }
+
+    /// Build the response from what was read for the context's partitions,
+    /// creating its session or leaving out partitions the client is up to date on
+    pub fn update_and_generate_response(&self, context: &FetchContext, responses: Vec<FetchableTopicResponse>) -> FetchResponse {
+        match context.kind {
+            FetchContextKind::Sessionless => FetchResponse {
+                responses,
+                ..Default::default()
+            },
+            FetchContextKind::Full { privileged, uses_topic_ids } => {
+                let mut partitions = vec![];
+                for (topic, response) in context.topics.iter().zip(&responses) {
+                    for (fetch, data) in topic.partitions.iter().zip(&response.partitions) {
+                        let mut cached = CachedPartition::new(topic, fetch);
+                        cached.update_response_data(data);
+                        partitions.push(cached);
+                    }
+                }
+                FetchResponse {
+                    session_id: self.maybe_create_session(privileged, uses_topic_ids, partitions),
+                    responses,
+                    ..Default::default()
+                }
+            }
+            FetchContextKind::Incremental { session_id, epoch } => {
+                let mut sessions = self.sessions.lock().unwrap();
+                let Some(session) = sessions.get_mut(&session_id) else {
+                    return FetchResponse::error(error_code::FETCH_SESSION_ID_NOT_FOUND);
+                };
+                // another fetch for the session got in first
+                if session.epoch != epoch {
+                    return FetchResponse::error(error_code::INVALID_FETCH_SESSION_EPOCH);
+                }
+                let mut with_data = vec![];
+                let responses = responses
+                    .into_iter()
+                    .map(|mut response| {
+                        response.partitions.retain(|data| {
+                            let Some(position) = session
+                                .partitions
+                                .iter()
+                                .position(|p| p.is(&response.topic, response.topic_id, data.partition_index))
+                            else {
+                                return true;
+                            };
+                            if data.records.as_ref().is_some_and(|records| !records.is_empty()) {
+                                with_data.push(position);
+                            }
+                            session.partitions[position].update_response_data(data)
+                        });
+                        response
+                    })
+                    .filter(|response| !response.partitions.is_empty())
+                    .collect();
+                // partitions that returned data go to the back, so the ones
+                // after them get their turn at max_bytes next time
+                with_data.sort_unstable();
+                let moved: Vec<CachedPartition> = with_data.iter().rev().map(|&i| session.partitions.remove(i)).collect();
+                session.partitions.extend(moved.into_iter().rev());
+                FetchResponse {
+                    session_id,
+                    responses,
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    /// The new session's id, or 0 when there's no room for it
+    fn maybe_create_session(&self, privileged: bool, uses_topic_ids: bool, partitions: Vec<CachedPartition>) -> i32 {
+        let now = self.clock.now_ms();
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= self.max_entries && !self.try_evict(&mut sessions, privileged, now) {
+            return INVALID_SESSION_ID;
+        }
+        let mut rng = rand::thread_rng();
+        let session_id = loop {
+            let id = rng.gen_range(1..=i32::MAX);
+            if !sessions.contains_key(&id) {
+                break id;
+            }
+        };
+        sessions.insert(
+            session_id,
+            FetchSession {
+                privileged,
+                uses_topic_ids,
+                partitions,
+                epoch: next_epoch(INITIAL_EPOCH),
+                last_used_ms: now,
+            },
+        );
+        session_id
+    }
+
+    fn try_evict(&self, sessions: &mut HashMap<i32, FetchSession>, privileged: bool, now: i64) -> bool {
+        let Some((&lru, session)) = sessions.iter().min_by_key(|(_, s)| s.last_used_ms) else {
+            return false;
+        };
+        let victim = if now - session.last_used_ms >= self.eviction_ms {
+            Some(lru)
+        } else if privileged {
+            sessions
+                .iter()
+                .filter(|(_, s)| !s.privileged)
+                .min_by_key(|(_, s)| (s.partitions.len(), s.last_used_ms))
+                .map(|(&id, _)| id)
+        } else {
+            None
+        };
+        victim.is_some_and(|id| sessions.remove(&id).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::request::fetch::ForgottenTopic,
+        utils::clock::MockClock,
+    };
+
+    fn partition(partition: i32, fetch_offset: i64) -> FetchPartition {
+        FetchPartition {
+            partition,
+            current_leader_epoch: -1,
+            fetch_offset,
+            last_fetched_epoch: -1,
+            log_start_offset: -1,
+            partition_max_bytes: 1024,
+        }
+    }
+
+    fn body(session_id: i32, session_epoch: i32, partitions: &[(i32, i64)], forgotten: &[i32]) -> FetchBody {
+        FetchBody {
+            replica_id: -1,
+            max_wait_ms: 0,
+            min_bytes: 0,
+            max_bytes: 1 << 20,
+            isolation_level: 0,
+            session_id,
+            session_epoch,
+            topics: vec![FetchTopic {
+                topic: "orders".to_string(),
+                topic_id: KafUuid::ZERO,
+                partitions: partitions.iter().map(|&(p, offset)| partition(p, offset)).collect(),
+            }],
+            forgotten_topics_data: vec![ForgottenTopic {
+                topic: "orders".to_string(),
+                topic_id: KafUuid::ZERO,
+                partitions: forgotten.to_vec(),
+            }],
+            rack_id: String::new(),
+        }
+    }
+
+    /// What a read of the context's partitions would return: the given high watermarks
+    fn read(context: &FetchContext, high_watermarks: &HashMap<i32, i64>) -> Vec<FetchableTopicResponse> {
+        context
+            .topics
+            .iter()
+            .map(|topic| FetchableTopicResponse {
+                topic: topic.topic.clone(),
+                topic_id: topic.topic_id,
+                partitions: topic
+                    .partitions
+                    .iter()
+                    .map(|p| PartitionData {
+                        partition_index: p.partition,
+                        high_watermark: high_watermarks[&p.partition],
+                        ..Default::default()
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn fetched(response: &FetchResponse) -> Vec<i32> {
+        response.responses.iter().flat_map(|t| t.partitions.iter().map(|p| p.partition_index)).collect()
+    }
+
+    #[test]
+    fn incremental_fetches_add_forget_and_skip_unchanged_partitions() {
+        let cache = FetchSessionCache::default();
+        let mut high_watermarks = HashMap::from([(0, 5), (1, 5), (2, 5)]);
+
+        let context = cache.new_context(&body(0, INITIAL_EPOCH, &[(0, 0), (1, 0)], &[]), 12).unwrap();
+        let full = cache.update_and_generate_response(&context, read(&context, &high_watermarks));
+        assert_ne!(full.session_id, INVALID_SESSION_ID);
+        assert_eq!(fetched(&full), vec![0, 1]);
+        let session_id = full.session_id;
+
+        // nothing changed
+        let context = cache.new_context(&body(session_id, 1, &[], &[]), 12).unwrap();
+        assert_eq!(context.topics[0].partitions.len(), 2);
+        let response = cache.update_and_generate_response(&context, read(&context, &high_watermarks));
+        assert_eq!((response.session_id, fetched(&response)), (session_id, vec![]));
+
+        // partition 1's high watermark moved, partition 2 is added and 0 forgotten
+        high_watermarks.insert(1, 6);
+        let context = cache.new_context(&body(session_id, 2, &[(2, 3)], &[0]), 12).unwrap();
+        let offsets: Vec<_> = context.topics[0].partitions.iter().map(|p| (p.partition, p.fetch_offset)).collect();
+        assert_eq!(offsets, vec![(1, 0), (2, 3)]);
+        let response = cache.update_and_generate_response(&context, read(&context, &high_watermarks));
+        assert_eq!(fetched(&response), vec![1, 2]);
+
+        // replays and skipped epochs
+        assert_eq!(cache.new_context(&body(session_id, 2, &[], &[]), 12).unwrap_err(), error_code::INVALID_FETCH_SESSION_EPOCH);
+        assert_eq!(cache.new_context(&body(session_id, 4, &[], &[]), 12).unwrap_err(), error_code::INVALID_FETCH_SESSION_EPOCH);
+        assert_eq!(cache.new_context(&body(session_id, 3, &[], &[]), 13).unwrap_err(), error_code::FETCH_SESSION_TOPIC_ID_ERROR);
+        assert_eq!(cache.new_context(&body(42, 1, &[], &[]), 12).unwrap_err(), error_code::FETCH_SESSION_ID_NOT_FOUND);
+
+        // closing the session makes a sessionless fetch
+        let context = cache.new_context(&body(session_id, FINAL_EPOCH, &[(0, 0)], &[]), 12).unwrap();
+        let response = cache.update_and_generate_response(&context, read(&context, &high_watermarks));
+        assert_eq!((response.session_id, fetched(&response)), (INVALID_SESSION_ID, vec![0]));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn a_full_cache_evicts_idle_sessions_and_makes_way_for_followers() {
+        let clock = Arc::new(MockClock::new(0));
+        let cache = FetchSessionCache::new(2, 1_000, clock.clone());
+        let high_watermarks = HashMap::from([(0, 0), (1, 0), (2, 0)]);
+        let create = |replica_id: i32, partitions: &[(i32, i64)]| {
+            let body = FetchBody { replica_id, ..body(0, INITIAL_EPOCH, partitions, &[]) };
+            let context = cache.new_context(&body, 12).unwrap();
+            let session_id = cache.update_and_generate_response(&context, read(&context, &high_watermarks)).session_id;
+            clock.advance(10);
+            session_id
+        };
+
+        let small = create(-1, &[(0, 0)]);
+        let big = create(-1, &[(0, 0), (1, 0), (2, 0)]);
+        assert!(small != INVALID_SESSION_ID && big != INVALID_SESSION_ID);
+        // full and nothing idle for long enough, the consumer goes without a session
+        assert_eq!(create(-1, &[(0, 0), (1, 0)]), INVALID_SESSION_ID);
+        // a follower takes the smallest consumer session's place
+        let follower = create(1, &[(0, 0)]);
+        assert_ne!(follower, INVALID_SESSION_ID);
+        let sessions = cache.sessions.lock().unwrap().keys().copied().collect::<Vec<_>>();
+        assert!(sessions.contains(&big) && sessions.contains(&follower) && !sessions.contains(&small));
+
+        // once idle long enough, the least recently used goes for anyone
+        clock.advance(1_000);
+        let consumer = create(-1, &[(0, 0)]);
+        assert_ne!(consumer, INVALID_SESSION_ID);
+        assert!(!cache.sessions.lock().unwrap().contains_key(&big));
+    }
+}
@@ -1,20 +1,96 @@
+use std::{collections::{BTreeMap, HashMap}, sync::Arc, time::Duration};
+
 use crate::{
     common::{
+        acl::{AclOperation, ResourceType, AUTHORIZED_OPERATIONS_OMITTED, CLUSTER_RESOURCE},
         api::{
             api_key::KafApiKey,
             api_version_entry::ApiVersionEntry,
         }, 
-        config::SUPPORTED_API, 
+        config::{BROKER_CONFIGS, SUPPORTED_API},
+        config_def::{config_source, list_items, ConfigDef, ConfigType},
         error::error_code,
-        request::{describe_topic_partitions::DescribeTopicPartitionsBody, KafRequest, KafRequestHeader},
+        request::{
+            alter_configs::AlterConfigsResource,
+            create_partitions::CreatePartitionsTopic,
+            create_topics::CreatableTopic,
+            delete_records::{DeleteRecordsPartition, HIGH_WATERMARK},
+            delete_topics::DeleteTopicState,
+            describe_configs::DescribeConfigsResource,
+            describe_topic_partitions::DescribeTopicPartitionsBody,
+            find_coordinator::coordinator_type,
+            fetch::{FetchPartition, FetchTopic},
+            incremental_alter_configs::{config_operation, AlterableConfigOp, IncrementalAlterConfigsResource},
+            list_offsets::{
+                ListOffsetsPartition, EARLIEST_LOCAL_TIMESTAMP, EARLIEST_TIMESTAMP, LATEST_TIERED_TIMESTAMP, LATEST_TIMESTAMP,
+                MAX_TIMESTAMP,
+            },
+            offset_delete::OffsetDeleteRequestTopic,
+            offset_fetch::OffsetFetchRequestGroup,
+            produce::PartitionProduceData,
+            KafRequest,
+            KafRequestHeader,
+        },
         response::{
             self,
+            alter_configs::{AlterConfigsResourceResponse, AlterConfigsResponse},
+            consumer_group_describe::{ConsumerGroupDescribeResponse, DescribedConsumerGroup},
+            consumer_group_heartbeat::ConsumerGroupHeartbeatResponse,
+            create_partitions::{CreatePartitionsResponse, CreatePartitionsTopicResult},
+            create_topics::{CreatableTopicConfigs, CreatableTopicResult, CreateTopicsResponse},
+            delete_groups::{DeletableGroupResult, DeleteGroupsResponse},
+            delete_records::{DeleteRecordsPartitionResult, DeleteRecordsResponse, DeleteRecordsTopicResult},
+            delete_topics::{DeletableTopicResult, DeleteTopicsResponse},
+            describe_configs::{DescribeConfigsResourceResult, DescribeConfigsResponse, DescribeConfigsResult, DescribeConfigsSynonym},
+            describe_groups::{DescribeGroupsResponse, DescribedGroup},
             describe_topic_partitions::{DescribeTopicPartitionsResponse, TopicsEntry},
+            fetch::{FetchResponse, FetchableTopicResponse, PartitionData},
+            find_coordinator::{Coordinator, FindCoordinatorResponse},
+            heartbeat::HeartbeatResponse,
+            incremental_alter_configs::IncrementalAlterConfigsResponse,
+            join_group::JoinGroupResponse,
+            leave_group::LeaveGroupResponse,
+            list_groups::ListGroupsResponse,
+            list_offsets::{ListOffsetsPartitionResponse, ListOffsetsResponse, ListOffsetsTopicResponse},
+            metadata::{MetadataResponse, MetadataResponseBroker, MetadataResponsePartition, MetadataResponseTopic},
+            offset_commit::{OffsetCommitResponse, OffsetCommitResponsePartition, OffsetCommitResponseTopic},
+            offset_delete::{OffsetDeleteResponse, OffsetDeleteResponsePartition, OffsetDeleteResponseTopic},
+            offset_fetch::{OffsetFetchResponse, OffsetFetchResponseGroup, OffsetFetchResponsePartition, OffsetFetchResponseTopic},
+            produce::{PartitionProduceResponse, ProduceResponse, TopicProduceResponse},
+            sync_group::SyncGroupResponse,
             response_body::{self, ApiVersionsResponse, KafResponseBody::{self, *}},
             KafResponse,
             KafResponseHeader,
         },
-        types::CompactArray
+        topic_partition::TopicPartition,
+        types::CompactArray,
+        uuid::KafUuid,
+    },
+    coordinator::{
+        assignor::TopicMetadata,
+        group_coordinator::{ConsumerContext, JoinContext},
+        records::NO_LEADER_EPOCH,
+    },
+    log::{
+        config::{is_dynamic_broker_config, validate_topic_config, TOPIC_CONFIGS},
+        index::TimestampOffset,
+        partition_log::FetchIsolation,
+    },
+    metadata::{
+        assign_replicas,
+        image::{MetadataImage, TopicImage},
+        is_internal_topic,
+        records::{BROKER_RESOURCE_TYPE, TOPIC_RESOURCE_TYPE},
+        validate_topic_name, MetadataError, CONSUMER_OFFSETS_TOPIC,
+    },
+    records::{
+        convert::{down_convert, magic_for_fetch_version},
+        CURRENT_MAGIC_VALUE, NO_TIMESTAMP,
+    },
+    server::{
+        authorizer::Session,
+        broker::Broker,
+        dynamic_config::{ConfigLayers, ConfigSynonym, CLUSTER_DEFAULT_RESOURCE},
     },
     utils::is_api_version_compatible,
     StrError
@@ -23,57 +99,3412 @@ use crate::{
 fn handle_api_versions(request: KafRequest) -> Result<KafResponse, StrError> {
     if !is_api_version_compatible(
         request.header.request_api_key.clone(),
-        request.header.request_api_version.clone(),
+        request.header.request_api_version,
     ) {
-        Ok(KafResponse {
-            header: KafResponseHeader::v0(request.header),
-            body: ApiVersions(ApiVersionsResponse::with_error_code(error_code::UNSUPPORTED_VERSION))
-        })
+        Ok(KafResponse::new(
+            KafResponseHeader::v0(request.header),
+            ApiVersions(ApiVersionsResponse::with_error_code(error_code::UNSUPPORTED_VERSION))
+        ))
     } else {
         let api_keys_vec: Vec<&ApiVersionEntry> = SUPPORTED_API.values().collect();
-        Ok(KafResponse {
-            header: KafResponseHeader::v0(request.header),
-            body: ApiVersions(ApiVersionsResponse::new(CompactArray(Some(api_keys_vec))))
-        })
+        Ok(KafResponse::new(
+            KafResponseHeader::v0(request.header),
+            ApiVersions(ApiVersionsResponse::new(CompactArray(Some(api_keys_vec))))
+        ))
     }
 }
 
 fn handle_describe_topic_partitions_request(
+    broker: &Broker,
+    session: &Session,
     request: KafRequest,
 ) -> Result<KafResponse, StrError> {
     let body = request.body.into_describe_topic_partitions().map_err(|_| "Bad Request".to_string())?;
 
     let Some(topics) = body.topics.0 else {
-        return Ok(KafResponse {
-            header: KafResponseHeader::v1(request.header),
-            body: DescribeTopicPartitions(DescribeTopicPartitionsResponse::bad_request())
-        });
+        return Ok(KafResponse::new(
+            KafResponseHeader::v1(request.header),
+            DescribeTopicPartitions(DescribeTopicPartitionsResponse::bad_request(AUTHORIZED_OPERATIONS_OMITTED))
+        ));
     };
 
-    let response_topics = topics.iter().map(|t| TopicsEntry::unknown_topic(t.name.0.clone())).collect();
+    // DescribeTopicPartitions has no include_topic_authorized_operations flag, it always wants them
+    let response_topics = topics
+        .iter()
+        .map(|t| {
+            let authorized_operations = broker.authorizer.authorized_operations_if_requested(
+                true,
+                session,
+                ResourceType::Topic,
+                &t.name.0,
+            );
+            TopicsEntry::unknown_topic(t.name.0.clone(), authorized_operations)
+        })
+        .collect();
 
-    Ok(KafResponse {
-        header: KafResponseHeader::v1(request.header),
-        body: DescribeTopicPartitions(
+    Ok(KafResponse::new(
+        KafResponseHeader::v1(request.header),
+        DescribeTopicPartitions(
             DescribeTopicPartitionsResponse::from_topics(response_topics)
         ),
-    })
+    ))
 }
 
-fn handle_unsupported_request(request: KafRequest) -> Result<KafResponse, StrError> {
-    Ok(KafResponse {
-        header: KafResponseHeader::v0(request.header),
-        body: KafResponseBody::default()
+fn produce_to_partition(
+    broker: &Broker,
+    session: &Session,
+    topic: &str,
+    partition: &PartitionProduceData,
+    acks: i16,
+) -> PartitionProduceResponse {
+    let index = partition.index;
+    if !matches!(acks, -1..=1) {
+        return PartitionProduceResponse::error(index, error_code::INVALID_REQUIRED_ACKS, None);
+    }
+    if !broker.authorizer.authorize(session, AclOperation::Write, ResourceType::Topic, topic) {
+        return PartitionProduceResponse::error(index, error_code::TOPIC_AUTHORIZATION_FAILED, None);
+    }
+    // only the coordinators write these, and they must be able to read back every record
+    if is_internal_topic(topic) {
+        return PartitionProduceResponse::error(
+            index,
+            error_code::INVALID_TOPIC_EXCEPTION,
+            Some(format!("Cannot append to internal topic {}", topic)),
+        );
+    }
+    let Some(log) = broker.log_manager.get_log(&TopicPartition::new(topic, index)) else {
+        return PartitionProduceResponse::error(index, error_code::UNKNOWN_TOPIC_OR_PARTITION, None);
+    };
+    let Some(records) = &partition.records else {
+        return PartitionProduceResponse::error(index, error_code::CORRUPT_MESSAGE, Some("records are null".to_string()));
+    };
+    // there's no transaction coordinator to ever commit or abort them
+    if records.batches().any(|batch| batch.is_ok_and(|batch| batch.is_transactional())) {
+        return PartitionProduceResponse::error(
+            index,
+            error_code::INVALID_TXN_STATE,
+            Some("transactions are not supported".to_string()),
+        );
+    }
+
+    // with a single replica the high watermark moves on append, so acks=-1 is as good as acks=1
+    match log.append_as_leader(records) {
+        Ok(info) => {
+            broker.fetch_purgatory.check_and_complete(log.topic_partition());
+            PartitionProduceResponse {
+                index,
+                base_offset: info.first_offset,
+                log_append_time_ms: info.log_append_time,
+                log_start_offset: info.log_start_offset,
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            println!("{}-{}: failed to append records: {}", topic, index, e);
+            PartitionProduceResponse::error(index, e.error_code(), Some(e.to_string()))
+        }
+    }
+}
+
+/// Append the records to their partition logs. acks=0 gets no response, the
+/// connection is closed instead if any partition failed so the client notices.
+fn handle_produce_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<Option<KafResponse>, StrError> {
+    // unsupported versions aren't decoded, like Kafka we just close the connection
+    let body = request.body.into_produce().map_err(|_| "Bad Request".to_string())?;
+
+    let responses: Vec<TopicProduceResponse> = body
+        .topic_data
+        .iter()
+        .map(|topic| TopicProduceResponse {
+            name: topic.name.clone(),
+            partition_responses: topic
+                .partition_data
+                .iter()
+                .map(|partition| produce_to_partition(broker, session, &topic.name, partition, body.acks))
+                .collect(),
+        })
+        .collect();
+
+    if body.acks == 0 {
+        let failed = responses
+            .iter()
+            .flat_map(|topic| &topic.partition_responses)
+            .any(|partition| partition.error_code != error_code::NONE);
+        return if failed { Err("produce with acks=0 failed".to_string()) } else { Ok(None) };
+    }
+
+    Ok(Some(KafResponse::for_request(
+        request.header,
+        Produce(ProduceResponse {
+            responses,
+            throttle_time_ms: 0,
+        }),
+    )))
+}
+
+/// Read one partition for a Fetch, within what's left of the response's max_bytes
+fn fetch_partition(
+    broker: &Broker,
+    topic: &str,
+    partition: &FetchPartition,
+    isolation: FetchIsolation,
+    magic: i8,
+    remaining_bytes: &mut i64,
+    min_one_message: &mut bool,
+) -> PartitionData {
+    let index = partition.partition;
+    let Some(log) = broker.log_manager.get_log(&TopicPartition::new(topic, index)) else {
+        return PartitionData::error(index, error_code::UNKNOWN_TOPIC_OR_PARTITION);
+    };
+
+    // like Kafka, the first partition with data returns at least one batch so
+    // consumers can make progress past batches bigger than their limits
+    let max_bytes = (partition.partition_max_bytes as i64).min(*remaining_bytes).max(0) as u64;
+    match log.read(partition.fetch_offset, max_bytes, isolation, *min_one_message) {
+        Ok(info) => {
+            if !info.records.is_empty() {
+                *min_one_message = false;
+            }
+            *remaining_bytes -= info.records.size_in_bytes() as i64;
+            // old clients only understand the message format of their Fetch version
+            let records = if magic < CURRENT_MAGIC_VALUE {
+                match down_convert(&info.records, magic, partition.fetch_offset) {
+                    Ok(records) => records,
+                    Err(e) => {
+                        println!("{}-{}: unable to down-convert to magic v{}: {}", topic, index, magic, e);
+                        return PartitionData::error(index, error_code::UNSUPPORTED_FOR_MESSAGE_FORMAT);
+                    }
+                }
+            } else {
+                info.records
+            };
+            PartitionData {
+                partition_index: index,
+                high_watermark: info.high_watermark,
+                last_stable_offset: info.last_stable_offset,
+                log_start_offset: info.log_start_offset,
+                // transactions are never aborted here, there's no transaction coordinator
+                aborted_transactions: (isolation == FetchIsolation::TxnCommitted).then(Vec::new),
+                records: Some(records),
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            println!("{}-{}: fetch at offset {} failed: {}", topic, index, partition.fetch_offset, e);
+            PartitionData::error(index, e.error_code())
+        }
+    }
+}
+
+/// Read the partitions in order until max_bytes is used up
+fn read_fetch_partitions(
+    broker: &Broker,
+    session: &Session,
+    topics: &[FetchTopic],
+    names: &[Option<String>],
+    max_bytes: i32,
+    isolation: FetchIsolation,
+    magic: i8,
+) -> Vec<FetchableTopicResponse> {
+    let mut remaining_bytes = max_bytes as i64;
+    let mut min_one_message = true;
+    topics
+        .iter()
+        .zip(names)
+        .map(|(topic, name)| {
+            let partitions = topic
+                .partitions
+                .iter()
+                .map(|partition| match name {
+                    None => PartitionData::error(partition.partition, error_code::UNKNOWN_TOPIC_ID),
+                    Some(name) if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Topic, name) => {
+                        PartitionData::error(partition.partition, error_code::TOPIC_AUTHORIZATION_FAILED)
+                    }
+                    Some(name) => {
+                        fetch_partition(broker, name, partition, isolation, magic, &mut remaining_bytes, &mut min_one_message)
+                    },
+                })
+                .collect();
+            FetchableTopicResponse {
+                topic: topic.topic.clone(),
+                topic_id: topic.topic_id,
+                partitions,
+            }
+        })
+        .collect()
+}
+
+/// A fetch is answered once it has min_bytes of records, or straight away if
+/// a partition failed
+fn fetch_satisfied(responses: &[FetchableTopicResponse], min_bytes: i32) -> bool {
+    let partitions = || responses.iter().flat_map(|topic| &topic.partitions);
+    if partitions().any(|partition| partition.error_code != error_code::NONE) {
+        return true;
+    }
+    let bytes: usize = partitions()
+        .filter_map(|partition| partition.records.as_ref())
+        .map(|records| records.size_in_bytes())
+        .sum();
+    bytes as i64 >= min_bytes as i64
+}
+
+/// Topics are named before v13 and identified by topic id after. With a fetch
+/// session the partitions to read come from the session. Without min_bytes
+/// available the request waits in the fetch purgatory for up to max_wait_ms,
+/// reading again whenever one of its partitions is appended to.
+fn handle_fetch_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_fetch().map_err(|_| "Bad Request".to_string())?;
+    let isolation = match (body.replica_id, body.isolation_level) {
+        (replica_id, _) if replica_id >= 0 => FetchIsolation::LogEnd,
+        (_, 1) => FetchIsolation::TxnCommitted,
+        _ => FetchIsolation::HighWatermark,
+    };
+    let magic = magic_for_fetch_version(request.header.request_api_version);
+    let context = match broker.fetch_sessions.new_context(&body, request.header.request_api_version) {
+        Ok(context) => context,
+        Err(error_code) => {
+            return Ok(KafResponse::for_request(request.header, Fetch(FetchResponse::error(error_code))));
+        }
+    };
+    let names: Vec<Option<String>> = context
+        .topics
+        .iter()
+        .map(|topic| {
+            if topic.topic_id.is_zero() {
+                Some(topic.topic.clone())
+            } else {
+                broker.log_manager.topic_name(&topic.topic_id)
+            }
+        })
+        .collect();
+
+    let mut responses = vec![];
+    if body.max_wait_ms <= 0 {
+        responses = read_fetch_partitions(broker, session, &context.topics, &names, body.max_bytes, isolation, magic);
+    } else {
+        let keys: Vec<TopicPartition> = context
+            .topics
+            .iter()
+            .zip(&names)
+            .filter_map(|(topic, name)| Some((topic, name.as_ref()?)))
+            .flat_map(|(topic, name)| topic.partitions.iter().map(|p| TopicPartition::new(name, p.partition)))
+            .collect();
+        broker.fetch_purgatory.try_complete_else_watch(
+            &keys,
+            Duration::from_millis(body.max_wait_ms as u64),
+            || {
+                responses = read_fetch_partitions(broker, session, &context.topics, &names, body.max_bytes, isolation, magic);
+                fetch_satisfied(&responses, body.min_bytes)
+            },
+        );
+    }
+    Ok(KafResponse::for_request(
+        request.header,
+        Fetch(broker.fetch_sessions.update_and_generate_response(&context, responses)),
+    ))
+}
+
+/// Look up one partition's offset for a ListOffsets
+fn list_partition_offset(
+    broker: &Broker,
+    image: &MetadataImage,
+    topic: &str,
+    partition: &ListOffsetsPartition,
+    replica_id: i32,
+    isolation_level: i8,
+) -> ListOffsetsPartitionResponse {
+    let index = partition.partition_index;
+    let Some(log) = broker.log_manager.get_log(&TopicPartition::new(topic, index)) else {
+        return ListOffsetsPartitionResponse::error(index, error_code::UNKNOWN_TOPIC_OR_PARTITION);
+    };
+    let leader_epoch = image.topic(topic).and_then(|t| t.partitions.get(&index)).map_or(-1, |p| p.leader_epoch);
+    if partition.current_leader_epoch >= 0 && leader_epoch >= 0 {
+        if partition.current_leader_epoch < leader_epoch {
+            return ListOffsetsPartitionResponse::error(index, error_code::FENCED_LEADER_EPOCH);
+        }
+        if partition.current_leader_epoch > leader_epoch {
+            return ListOffsetsPartitionResponse::error(index, error_code::UNKNOWN_LEADER_EPOCH);
+        }
+    }
+
+    // consumers only see what they could fetch, followers the whole log
+    let last_fetchable_offset = match (replica_id, isolation_level) {
+        (replica_id, _) if replica_id >= 0 => log.log_end_offset(),
+        (_, 1) => log.last_stable_offset(),
+        _ => log.high_watermark(),
+    };
+    let at = |offset| Ok(Some(TimestampOffset { timestamp: NO_TIMESTAMP, offset }));
+    let fetchable = |found: &TimestampOffset| found.offset < last_fetchable_offset;
+    let found = match partition.timestamp {
+        LATEST_TIMESTAMP => at(last_fetchable_offset),
+        // no tiered storage, the whole log is local
+        EARLIEST_TIMESTAMP | EARLIEST_LOCAL_TIMESTAMP => at(log.log_start_offset()),
+        LATEST_TIERED_TIMESTAMP => Ok(None),
+        MAX_TIMESTAMP => log.fetch_offset_of_max_timestamp().map(|found| found.filter(fetchable)),
+        timestamp => log.fetch_offset_by_timestamp(timestamp).map(|found| found.filter(fetchable)),
+    };
+    match found {
+        Ok(Some(found)) => ListOffsetsPartitionResponse {
+            partition_index: index,
+            error_code: error_code::NONE,
+            timestamp: found.timestamp,
+            offset: found.offset,
+            leader_epoch,
+        },
+        Ok(None) => ListOffsetsPartitionResponse {
+            leader_epoch,
+            ..ListOffsetsPartitionResponse::error(index, error_code::NONE)
+        },
+        Err(e) => ListOffsetsPartitionResponse::error(index, e.error_code()),
+    }
+}
+
+fn handle_list_offsets_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_list_offsets().map_err(|_| "Bad Request".to_string())?;
+    let image = broker.metadata.image();
+
+    // a partition asked for twice gets INVALID_REQUEST both times
+    let mut requested: HashMap<(&str, i32), usize> = HashMap::new();
+    for topic in &body.topics {
+        for partition in &topic.partitions {
+            *requested.entry((topic.name.as_str(), partition.partition_index)).or_default() += 1;
+        }
+    }
+
+    let topics = body
+        .topics
+        .iter()
+        .map(|topic| {
+            let authorized = broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Topic, &topic.name);
+            ListOffsetsTopicResponse {
+                name: topic.name.clone(),
+                partitions: topic
+                    .partitions
+                    .iter()
+                    .map(|partition| {
+                        let index = partition.partition_index;
+                        if !authorized {
+                            ListOffsetsPartitionResponse::error(index, error_code::TOPIC_AUTHORIZATION_FAILED)
+                        } else if requested[&(topic.name.as_str(), index)] > 1 {
+                            ListOffsetsPartitionResponse::error(index, error_code::INVALID_REQUEST)
+                        } else {
+                            list_partition_offset(broker, &image, &topic.name, partition, body.replica_id, body.isolation_level)
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        ListOffsets(ListOffsetsResponse {
+            throttle_time_ms: 0,
+            topics,
+        }),
+    ))
+}
+
+/// Move one partition's log start offset up to the requested offset
+fn delete_partition_records(broker: &Broker, topic: &str, partition: &DeleteRecordsPartition) -> DeleteRecordsPartitionResult {
+    let index = partition.partition_index;
+    let result = |low_watermark, error_code| DeleteRecordsPartitionResult {
+        partition_index: index,
+        low_watermark,
+        error_code,
+    };
+    let Some(log) = broker.log_manager.get_log(&TopicPartition::new(topic, index)) else {
+        return result(-1, error_code::UNKNOWN_TOPIC_OR_PARTITION);
+    };
+    // compacted topics only lose records to the cleaner
+    if !log.config().cleanup_policy.delete {
+        return result(-1, error_code::POLICY_VIOLATION);
+    }
+    let offset = match partition.offset {
+        HIGH_WATERMARK => log.high_watermark(),
+        offset => offset,
+    };
+    match log.delete_records_before(offset) {
+        Ok(log_start_offset) => result(log_start_offset, error_code::NONE),
+        Err(e) => result(-1, e.error_code()),
+    }
+}
+
+fn handle_delete_records_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_delete_records().map_err(|_| "Bad Request".to_string())?;
+
+    let topics: Vec<_> = body
+        .topics
+        .iter()
+        .map(|topic| {
+            let error_code = if !broker.authorizer.authorize(session, AclOperation::Delete, ResourceType::Topic, &topic.name) {
+                error_code::TOPIC_AUTHORIZATION_FAILED
+            } else if is_internal_topic(&topic.name) {
+                error_code::INVALID_TOPIC_EXCEPTION
+            } else {
+                error_code::NONE
+            };
+            DeleteRecordsTopicResult {
+                name: topic.name.clone(),
+                partitions: topic
+                    .partitions
+                    .iter()
+                    .map(|partition| match error_code {
+                        error_code::NONE => delete_partition_records(broker, &topic.name, partition),
+                        error_code => DeleteRecordsPartitionResult {
+                            partition_index: partition.partition_index,
+                            low_watermark: -1,
+                            error_code,
+                        },
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let deleted = topics.iter().flat_map(|t| &t.partitions).any(|p| p.error_code == error_code::NONE);
+    if deleted {
+        if let Err(e) = broker.log_manager.checkpoint_log_start_offsets() {
+            println!("error while checkpointing log start offsets: {}", e);
+        }
+    }
+
+    Ok(KafResponse::for_request(
+        request.header,
+        DeleteRecords(DeleteRecordsResponse {
+            throttle_time_ms: 0,
+            topics,
+        }),
+    ))
+}
+
+/// Check the replicas explicitly assigned to one partition
+fn validate_replicas(brokers: &[i32], partition: i32, replicas: &[i32]) -> Result<(), String> {
+    if replicas.is_empty() {
+        return Err(format!("Partition {} has no replicas.", partition));
+    }
+    if (1..replicas.len()).any(|i| replicas[..i].contains(&replicas[i])) {
+        return Err(format!("Partition {} has duplicate replicas.", partition));
+    }
+    if let Some(unknown) = replicas.iter().find(|id| !brokers.contains(id)) {
+        return Err(format!("Partition {} has a replica on unknown broker {}.", partition, unknown));
+    }
+    Ok(())
+}
+
+/// A topic's replica assignments: the ones asked for after checking them, or
+/// num_partitions spread over the brokers
+fn topic_assignments(broker: &Broker, topic: &CreatableTopic) -> Result<Vec<Vec<i32>>, (i16, String)> {
+    let brokers = [broker.config.node_id];
+    if !topic.assignments.is_empty() {
+        if topic.num_partitions != -1 || topic.replication_factor != -1 {
+            return Err((
+                error_code::INVALID_REQUEST,
+                "Both num_partitions or replication_factor and assignments were set.".to_string(),
+            ));
+        }
+        let mut assignments: Vec<_> = topic.assignments.iter().collect();
+        assignments.sort_by_key(|assignment| assignment.partition_index);
+        let mut replicas = vec![];
+        for (expected, assignment) in assignments.into_iter().enumerate() {
+            let partition = assignment.partition_index;
+            let invalid = |message: String| Err((error_code::INVALID_REPLICA_ASSIGNMENT, message));
+            if partition != expected as i32 {
+                return invalid("Partitions should be a consecutive 0-based integer sequence.".to_string());
+            }
+            if let Err(message) = validate_replicas(&brokers, partition, &assignment.broker_ids) {
+                return invalid(message);
+            }
+            replicas.push(assignment.broker_ids.clone());
+        }
+        return Ok(replicas);
+    }
+
+    let num_partitions = match topic.num_partitions {
+        -1 => broker.config.get_i64("num.partitions").unwrap_or(1) as i32,
+        num_partitions => num_partitions,
+    };
+    if num_partitions <= 0 {
+        return Err((error_code::INVALID_PARTITIONS, "Number of partitions must be larger than 0.".to_string()));
+    }
+    let replication_factor = match topic.replication_factor {
+        -1 => broker.config.get_i64("default.replication.factor").unwrap_or(1) as i16,
+        replication_factor => replication_factor,
+    };
+    if replication_factor <= 0 {
+        return Err((error_code::INVALID_REPLICATION_FACTOR, "Replication factor must be larger than 0.".to_string()));
+    }
+    assign_replicas(&brokers, num_partitions, replication_factor, 0).ok_or_else(|| {
+        (
+            error_code::INVALID_REPLICATION_FACTOR,
+            format!("Replication factor {} is larger than the {} available broker(s).", replication_factor, brokers.len()),
+        )
     })
 }
 
-// going to be main logic
-pub fn handle_request(request: KafRequest) -> Result<KafResponse, StrError> {
-    match &request.header.request_api_key {
-        KafApiKey::ApiVersions => handle_api_versions(request),
-        KafApiKey::DescribeTopicPartitions => handle_describe_topic_partitions_request(request),
-        _ => handle_unsupported_request(request),
+/// Check one topic of a CreateTopics and create it unless validate_only,
+/// with the image it was created in if it was
+fn create_topic(
+    broker: &Broker,
+    session: &Session,
+    topic: &CreatableTopic,
+    validate_only: bool,
+) -> (CreatableTopicResult, Option<Arc<MetadataImage>>) {
+    let name = topic.name.as_str();
+    let error = |error_code, message| (CreatableTopicResult::error(name, error_code, message), None);
+    if !broker.authorizer.authorize(session, AclOperation::Create, ResourceType::Cluster, CLUSTER_RESOURCE)
+        && !broker.authorizer.authorize(session, AclOperation::Create, ResourceType::Topic, name)
+    {
+        return error(error_code::TOPIC_AUTHORIZATION_FAILED, "Authorization failed.".to_string());
+    }
+    if let Err(message) = validate_topic_name(name) {
+        return error(error_code::INVALID_TOPIC_EXCEPTION, message);
+    }
+    // their coordinators create them with the partitions and configs they rely on
+    if is_internal_topic(name) {
+        return error(error_code::INVALID_REQUEST, format!("Creation of internal topic {} is prohibited.", name));
+    }
+    let image = broker.metadata.image();
+    if image.topic(name).is_some() {
+        return error(error_code::TOPIC_ALREADY_EXISTS, format!("Topic '{}' already exists.", name));
+    }
+    if let Some(existing) = image.colliding_topic(name) {
+        return error(error_code::INVALID_TOPIC_EXCEPTION, format!("Topic '{}' collides with existing topic: {}", name, existing));
+    }
+    if name.contains(['.', '_']) {
+        println!("topic {} has a '.' or '_' in its name, it can collide with another topic's metric names", name);
+    }
+    let assignments = match topic_assignments(broker, topic) {
+        Ok(assignments) => assignments,
+        Err((error_code, message)) => return error(error_code, message),
+    };
+    let mut configs = HashMap::new();
+    for config in &topic.configs {
+        let Some(value) = &config.value else {
+            return error(error_code::INVALID_CONFIG, format!("Null value not supported for topic config {}", config.name));
+        };
+        if let Err(message) = validate_topic_config(&config.name, value) {
+            return error(error_code::INVALID_CONFIG, message);
+        }
+        configs.insert(config.name.clone(), value.clone());
+    }
+
+    let created = if validate_only {
+        None
+    } else {
+        match broker.create_topic(name, &assignments, &configs) {
+            Ok(image) => Some(image),
+            Err(e) => return error(e.error_code(), e.to_string()),
+        }
+    };
+    let topic_id = created.as_ref().and_then(|image| image.topic(name)).map_or(KafUuid::ZERO, |topic| topic.topic_id);
+    // every config the topic ends up with, as DescribeConfigs would describe it
+    let layers = ConfigLayers::new(&broker.config, &image);
+    let configs = TOPIC_CONFIGS
+        .keys()
+        .map(|key| {
+            let synonyms = layers.topic_synonyms(key.name, &configs);
+            let sensitive = key.is_sensitive();
+            CreatableTopicConfigs {
+                name: key.name.to_string(),
+                value: if sensitive { None } else { ConfigLayers::topic_value(key.name, &synonyms) },
+                read_only: false,
+                config_source: synonyms.first().map_or(config_source::DEFAULT_CONFIG, |synonym| synonym.source),
+                is_sensitive: sensitive,
+            }
+        })
+        .collect();
+    let result = CreatableTopicResult {
+        name: name.to_string(),
+        topic_id,
+        error_code: error_code::NONE,
+        error_message: None,
+        num_partitions: assignments.len() as i32,
+        replication_factor: assignments[0].len() as i16,
+        configs: Some(configs),
+    };
+    (result, created)
+}
+
+fn handle_create_topics_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_create_topics().map_err(|_| "Bad Request".to_string())?;
+
+    let topics = body
+        .topics
+        .iter()
+        .map(|topic| {
+            if body.topics.iter().filter(|t| t.name == topic.name).count() > 1 {
+                return CreatableTopicResult::error(&topic.name, error_code::INVALID_REQUEST, "Duplicate topic name.".to_string());
+            }
+            create_topic(broker, session, topic, body.validate_only).0
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        CreateTopics(CreateTopicsResponse {
+            throttle_time_ms: 0,
+            topics,
+        }),
+    ))
+}
+
+/// Delete one topic of a DeleteTopics, by name or id
+fn delete_topic(broker: &Broker, session: &Session, image: &MetadataImage, topic: &DeleteTopicState) -> DeletableTopicResult {
+    let result = |name: Option<String>, topic_id, error_code, error_message: Option<&str>| DeletableTopicResult {
+        name,
+        topic_id,
+        error_code,
+        error_message: error_message.map(str::to_string),
+    };
+    let found = match &topic.name {
+        Some(_) if !topic.topic_id.is_zero() => {
+            return result(
+                topic.name.clone(),
+                topic.topic_id,
+                error_code::INVALID_REQUEST,
+                Some("Only one of name or topic_id can be set."),
+            );
+        }
+        Some(name) if !broker.authorizer.authorize(session, AclOperation::Delete, ResourceType::Topic, name) => {
+            Err(error_code::TOPIC_AUTHORIZATION_FAILED)
+        }
+        Some(name) => image.topic(name).ok_or(error_code::UNKNOWN_TOPIC_OR_PARTITION),
+        None => match image.topic_by_id(&topic.topic_id) {
+            Some(found) if broker.authorizer.authorize(session, AclOperation::Delete, ResourceType::Topic, &found.name) => Ok(found),
+            Some(_) => Err(error_code::TOPIC_AUTHORIZATION_FAILED),
+            None => Err(error_code::UNKNOWN_TOPIC_ID),
+        },
+    };
+    let found = match found {
+        Ok(found) => found,
+        Err(error_code) => return result(topic.name.clone(), topic.topic_id, error_code, None),
+    };
+    if !broker.config.get_bool("delete.topic.enable").unwrap_or(true) {
+        return result(
+            Some(found.name.clone()),
+            found.topic_id,
+            error_code::TOPIC_DELETION_DISABLED,
+            Some("Topic deletion is disabled."),
+        );
+    }
+    match broker.delete_topic(found.topic_id) {
+        Ok(()) => result(Some(found.name.clone()), found.topic_id, error_code::NONE, None),
+        Err(e) => result(Some(found.name.clone()), found.topic_id, e.error_code(), Some(&e.to_string())),
     }
 }
 
-    // Ok(KafResponse::new(4, request.header.correlation_id))
+fn handle_delete_topics_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_delete_topics().map_err(|_| "Bad Request".to_string())?;
+    let image = broker.metadata.image();
+
+    let responses = body
+        .topics
+        .iter()
+        .map(|topic| {
+            let same = |other: &DeleteTopicState| other.name == topic.name && other.topic_id == topic.topic_id;
+            if body.topics.iter().filter(|other| same(other)).count() > 1 {
+                return DeletableTopicResult {
+                    name: topic.name.clone(),
+                    topic_id: topic.topic_id,
+                    error_code: error_code::INVALID_REQUEST,
+                    error_message: Some("Duplicate topic in request.".to_string()),
+                };
+            }
+            delete_topic(broker, session, &image, topic)
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        DeleteTopics(DeleteTopicsResponse {
+            throttle_time_ms: 0,
+            responses,
+        }),
+    ))
+}
+
+/// Check one topic of a CreatePartitions and grow it unless validate_only
+fn create_partitions(
+    broker: &Broker,
+    session: &Session,
+    image: &MetadataImage,
+    topic: &CreatePartitionsTopic,
+    validate_only: bool,
+) -> CreatePartitionsTopicResult {
+    let result = |error_code, error_message: Option<String>| CreatePartitionsTopicResult {
+        name: topic.name.clone(),
+        error_code,
+        error_message,
+    };
+    if !broker.authorizer.authorize(session, AclOperation::Alter, ResourceType::Topic, &topic.name) {
+        return result(error_code::TOPIC_AUTHORIZATION_FAILED, Some("Authorization failed.".to_string()));
+    }
+    let Some(found) = image.topic(&topic.name) else {
+        return result(
+            error_code::UNKNOWN_TOPIC_OR_PARTITION,
+            Some(format!("The topic '{}' does not exist.", topic.name)),
+        );
+    };
+    let current = found.partitions.len() as i32;
+    if topic.count < current {
+        return result(
+            error_code::INVALID_PARTITIONS,
+            Some(format!("Topic currently has {} partitions, which is higher than the requested {}.", current, topic.count)),
+        );
+    }
+    if topic.count == current {
+        return result(error_code::INVALID_PARTITIONS, Some(format!("Topic already has {} partitions.", current)));
+    }
+
+    let brokers = [broker.config.node_id];
+    let added = topic.count - current;
+    // new partitions get as many replicas as the existing ones
+    let replication_factor = found.partitions.values().next().map_or(1, |partition| partition.replicas.len());
+    let assignments = match &topic.assignments {
+        Some(assignments) => {
+            let invalid = |message| result(error_code::INVALID_REPLICA_ASSIGNMENT, Some(message));
+            if assignments.len() as i32 != added {
+                return invalid(format!(
+                    "Increasing the number of partitions by {} but {} assignments provided.",
+                    added,
+                    assignments.len()
+                ));
+            }
+            for (i, replicas) in assignments.iter().enumerate() {
+                let partition = current + i as i32;
+                if let Err(message) = validate_replicas(&brokers, partition, replicas) {
+                    return invalid(message);
+                }
+                if replicas.len() != replication_factor {
+                    return invalid(format!(
+                        "Partition {} has {} replicas but the topic's replication factor is {}.",
+                        partition,
+                        replicas.len(),
+                        replication_factor
+                    ));
+                }
+            }
+            assignments.clone()
+        }
+        None => match assign_replicas(&brokers, added, replication_factor as i16, current) {
+            Some(assignments) => assignments,
+            None => {
+                return result(
+                    error_code::INVALID_REPLICATION_FACTOR,
+                    Some(format!(
+                        "Replication factor {} is larger than the {} available broker(s).",
+                        replication_factor,
+                        brokers.len()
+                    )),
+                )
+            }
+        },
+    };
+    if validate_only {
+        return result(error_code::NONE, None);
+    }
+    match broker.create_partitions(found.topic_id, current, &assignments) {
+        Ok(_) => result(error_code::NONE, None),
+        Err(e) => result(e.error_code(), Some(e.to_string())),
+    }
+}
+
+fn handle_create_partitions_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_create_partitions().map_err(|_| "Bad Request".to_string())?;
+    let image = broker.metadata.image();
+
+    let results = body
+        .topics
+        .iter()
+        .map(|topic| {
+            if body.topics.iter().filter(|other| other.name == topic.name).count() > 1 {
+                return CreatePartitionsTopicResult {
+                    name: topic.name.clone(),
+                    error_code: error_code::INVALID_REQUEST,
+                    error_message: Some("Duplicate topic in request.".to_string()),
+                };
+            }
+            create_partitions(broker, session, &image, topic, body.validate_only)
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        CreatePartitions(CreatePartitionsResponse {
+            throttle_time_ms: 0,
+            results,
+        }),
+    ))
+}
+
+/// Check that the session may do `operation` on the configs of a resource
+/// and that the resource is one we have: a topic, this broker or the
+/// cluster-wide broker defaults
+fn check_config_resource(
+    broker: &Broker,
+    session: &Session,
+    image: &MetadataImage,
+    resource_type: i8,
+    resource_name: &str,
+    operation: AclOperation,
+) -> Result<(), (i16, String)> {
+    match resource_type {
+        TOPIC_RESOURCE_TYPE => {
+            if !broker.authorizer.authorize(session, operation, ResourceType::Topic, resource_name) {
+                return Err((error_code::TOPIC_AUTHORIZATION_FAILED, "Authorization failed.".to_string()));
+            }
+            if image.topic(resource_name).is_none() {
+                return Err((
+                    error_code::UNKNOWN_TOPIC_OR_PARTITION,
+                    format!("The topic '{}' does not exist.", resource_name),
+                ));
+            }
+        }
+        BROKER_RESOURCE_TYPE => {
+            if !broker.authorizer.authorize(session, operation, ResourceType::Cluster, CLUSTER_RESOURCE) {
+                return Err((error_code::CLUSTER_AUTHORIZATION_FAILED, "Authorization failed.".to_string()));
+            }
+            let node_id = broker.config.node_id.to_string();
+            if resource_name != CLUSTER_DEFAULT_RESOURCE && resource_name != node_id {
+                return Err((
+                    error_code::INVALID_REQUEST,
+                    format!("Unexpected broker id, expected {} or empty string, but received {}", node_id, resource_name),
+                ));
+            }
+        }
+        _ => {
+            return Err((error_code::INVALID_REQUEST, format!("Unsupported resource type {}", resource_type)));
+        }
+    }
+    Ok(())
+}
+
+/// The configs a resource of this type can have, topic or broker
+fn config_def(resource_type: i8) -> &'static ConfigDef {
+    if resource_type == TOPIC_RESOURCE_TYPE {
+        &TOPIC_CONFIGS
+    } else {
+        &BROKER_CONFIGS
+    }
+}
+
+/// Err unless every one of `configs` can be set on a resource of this type
+fn validate_configs(resource_type: i8, configs: &HashMap<String, String>) -> Result<(), MetadataError> {
+    for (name, value) in configs {
+        if resource_type == BROKER_RESOURCE_TYPE && BROKER_CONFIGS.key(name).is_some() && !is_dynamic_broker_config(name) {
+            return Err(MetadataError::InvalidConfig(format!("Cannot update these configs dynamically: {}", name)));
+        }
+        config_def(resource_type).validate(name, value).map_err(MetadataError::InvalidConfig)?;
+    }
+    Ok(())
+}
+
+/// Describe the configs of one resource, every one unless it names some
+fn describe_configs(
+    broker: &Broker,
+    session: &Session,
+    image: &MetadataImage,
+    layers: &ConfigLayers,
+    resource: &DescribeConfigsResource,
+    include_synonyms: bool,
+    include_documentation: bool,
+) -> DescribeConfigsResult {
+    let name = resource.resource_name.as_str();
+    let result = |error_code, error_message, configs| DescribeConfigsResult {
+        error_code,
+        error_message,
+        resource_type: resource.resource_type,
+        resource_name: name.to_string(),
+        configs,
+    };
+    if let Err((error_code, message)) =
+        check_config_resource(broker, session, image, resource.resource_type, name, AclOperation::DescribeConfigs)
+    {
+        return result(error_code, Some(message), vec![]);
+    }
+
+    let is_topic = resource.resource_type == TOPIC_RESOURCE_TYPE;
+    let overrides = image.topic_configs(name);
+    let configs = config_def(resource.resource_type)
+        .keys()
+        .filter(|key| resource.configuration_keys.as_ref().map_or(true, |keys| keys.iter().any(|name| name == key.name)))
+        .filter_map(|key| {
+            let (synonyms, value, read_only) = if is_topic {
+                let synonyms = layers.topic_synonyms(key.name, &overrides);
+                let value = ConfigLayers::topic_value(key.name, &synonyms);
+                (synonyms, value, false)
+            } else {
+                let mut synonyms = layers.broker_synonyms(key.name);
+                if name == CLUSTER_DEFAULT_RESOURCE {
+                    // only what's set for every broker
+                    synonyms.retain(|synonym| synonym.source == config_source::DYNAMIC_DEFAULT_BROKER_CONFIG);
+                    if synonyms.is_empty() {
+                        return None;
+                    }
+                }
+                let value = synonyms.first().and_then(|synonym| synonym.value.clone());
+                (synonyms, value, !is_dynamic_broker_config(key.name))
+            };
+            let sensitive = key.is_sensitive();
+            Some(DescribeConfigsResourceResult {
+                name: key.name.to_string(),
+                value: if sensitive { None } else { value },
+                read_only,
+                config_source: synonyms.first().map_or(config_source::DEFAULT_CONFIG, |synonym| synonym.source),
+                is_sensitive: sensitive,
+                synonyms: if include_synonyms {
+                    synonyms
+                        .into_iter()
+                        .map(|ConfigSynonym { name, value, source }| DescribeConfigsSynonym {
+                            name,
+                            value: if sensitive { None } else { value },
+                            source,
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                },
+                config_type: key.config_type.id(),
+                documentation: include_documentation.then(|| key.documentation.to_string()),
+            })
+        })
+        .collect();
+    result(error_code::NONE, None, configs)
+}
+
+fn handle_describe_configs_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_describe_configs().map_err(|_| "Bad Request".to_string())?;
+    let image = broker.metadata.image();
+    let layers = broker.config_layers(&image);
+
+    let results = body
+        .resources
+        .iter()
+        .map(|resource| {
+            describe_configs(broker, session, &image, &layers, resource, body.include_synonyms, body.include_documentation)
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        DescribeConfigs(DescribeConfigsResponse {
+            throttle_time_ms: 0,
+            results,
+        }),
+    ))
+}
+
+fn alter_configs_response(resource_type: i8, resource_name: &str, result: Result<(), (i16, String)>) -> AlterConfigsResourceResponse {
+    let (error_code, error_message) = match result {
+        Ok(()) => (error_code::NONE, None),
+        Err((error_code, message)) => (error_code, Some(message)),
+    };
+    AlterConfigsResourceResponse {
+        error_code,
+        error_message,
+        resource_type,
+        resource_name: resource_name.to_string(),
+    }
+}
+
+/// Replace every config set on one resource of an AlterConfigs, unless validate_only
+fn alter_configs(
+    broker: &Broker,
+    session: &Session,
+    image: &MetadataImage,
+    resource: &AlterConfigsResource,
+    validate_only: bool,
+) -> Result<(), (i16, String)> {
+    let (resource_type, name) = (resource.resource_type, resource.resource_name.as_str());
+    check_config_resource(broker, session, image, resource_type, name, AclOperation::AlterConfigs)?;
+    let mut configs = HashMap::new();
+    for config in &resource.configs {
+        let Some(value) = &config.value else {
+            return Err((error_code::INVALID_CONFIG, format!("Null value not supported for config {}", config.name)));
+        };
+        configs.insert(config.name.clone(), value.clone());
+    }
+    broker
+        .alter_configs(resource_type, name, validate_only, |_| {
+            validate_configs(resource_type, &configs)?;
+            Ok(configs)
+        })
+        .map_err(|e| (e.error_code(), e.to_string()))
+}
+
+fn handle_alter_configs_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_alter_configs().map_err(|_| "Bad Request".to_string())?;
+    let image = broker.metadata.image();
+
+    let responses = body
+        .resources
+        .iter()
+        .map(|resource| {
+            let same = |other: &AlterConfigsResource| {
+                other.resource_type == resource.resource_type && other.resource_name == resource.resource_name
+            };
+            let result = if body.resources.iter().filter(|other| same(other)).count() > 1 {
+                Err((error_code::INVALID_REQUEST, "Duplicate resource in request.".to_string()))
+            } else {
+                alter_configs(broker, session, &image, resource, body.validate_only)
+            };
+            alter_configs_response(resource.resource_type, &resource.resource_name, result)
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        AlterConfigs(AlterConfigsResponse {
+            throttle_time_ms: 0,
+            responses,
+        }),
+    ))
+}
+
+/// Apply one operation of an IncrementalAlterConfigs to the configs set on
+/// a resource. APPEND and SUBTRACT start from the config's default when
+/// it isn't set.
+fn apply_config_op(def: &ConfigDef, configs: &mut HashMap<String, String>, op: &AlterableConfigOp) -> Result<(), MetadataError> {
+    let invalid = |message| Err(MetadataError::InvalidConfig(message));
+    let Some(key) = def.key(&op.name) else {
+        return invalid(format!("Unknown config name {}", op.name));
+    };
+    if op.config_operation == config_operation::DELETE {
+        configs.remove(&op.name);
+        return Ok(());
+    }
+    let Some(value) = &op.value else {
+        return invalid(format!("Null value not supported for config {}", op.name));
+    };
+    match op.config_operation {
+        config_operation::SET => {
+            configs.insert(op.name.clone(), value.clone());
+        }
+        config_operation::APPEND | config_operation::SUBTRACT => {
+            if key.config_type != ConfigType::List {
+                return invalid(format!("Config {} is not a list, it can't be appended to or subtracted from", op.name));
+            }
+            let current = configs.get(&op.name).map(String::as_str).or(key.default).unwrap_or("");
+            let mut items: Vec<&str> = list_items(current);
+            let changes = list_items(value);
+            if op.config_operation == config_operation::APPEND {
+                for item in changes {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            } else {
+                items.retain(|item| !changes.contains(item));
+            }
+            let items = items.join(",");
+            configs.insert(op.name.clone(), items);
+        }
+        other => return invalid(format!("Unknown config operation {}", other)),
+    }
+    Ok(())
+}
+
+/// Apply the operations on one resource of an IncrementalAlterConfigs, unless validate_only
+fn incremental_alter_configs(
+    broker: &Broker,
+    session: &Session,
+    image: &MetadataImage,
+    resource: &IncrementalAlterConfigsResource,
+    validate_only: bool,
+) -> Result<(), (i16, String)> {
+    let (resource_type, name) = (resource.resource_type, resource.resource_name.as_str());
+    check_config_resource(broker, session, image, resource_type, name, AclOperation::AlterConfigs)?;
+    for (i, op) in resource.configs.iter().enumerate() {
+        if resource.configs[..i].iter().any(|other| other.name == op.name) {
+            return Err((error_code::INVALID_REQUEST, format!("Duplicate config key {}", op.name)));
+        }
+    }
+    broker
+        .alter_configs(resource_type, name, validate_only, |current| {
+            let mut altered = current.clone();
+            for op in &resource.configs {
+                apply_config_op(config_def(resource_type), &mut altered, op)?;
+            }
+            validate_configs(resource_type, &altered)?;
+            Ok(altered)
+        })
+        .map_err(|e| (e.error_code(), e.to_string()))
+}
+
+fn handle_incremental_alter_configs_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_incremental_alter_configs().map_err(|_| "Bad Request".to_string())?;
+    let image = broker.metadata.image();
+
+    let responses = body
+        .resources
+        .iter()
+        .map(|resource| {
+            let same = |other: &IncrementalAlterConfigsResource| {
+                other.resource_type == resource.resource_type && other.resource_name == resource.resource_name
+            };
+            let result = if body.resources.iter().filter(|other| same(other)).count() > 1 {
+                Err((error_code::INVALID_REQUEST, "Duplicate resource in request.".to_string()))
+            } else {
+                incremental_alter_configs(broker, session, &image, resource, body.validate_only)
+            };
+            alter_configs_response(resource.resource_type, &resource.resource_name, result)
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        IncrementalAlterConfigs(IncrementalAlterConfigsResponse {
+            throttle_time_ms: 0,
+            responses,
+        }),
+    ))
+}
+
+fn metadata_topic(broker: &Broker, session: &Session, topic: &TopicImage, include_authorized_operations: bool) -> MetadataResponseTopic {
+    let partitions = topic
+        .partitions
+        .iter()
+        .map(|(index, partition)| {
+            // we're the only broker, any other leader isn't one we know of
+            let leader_available = partition.leader == broker.config.node_id;
+            MetadataResponsePartition {
+                error_code: if leader_available { error_code::NONE } else { error_code::LEADER_NOT_AVAILABLE },
+                partition_index: *index,
+                leader_id: if leader_available { partition.leader } else { -1 },
+                leader_epoch: partition.leader_epoch,
+                replica_nodes: partition.replicas.clone(),
+                isr_nodes: partition.isr.clone(),
+                offline_replicas: vec![],
+            }
+        })
+        .collect();
+    MetadataResponseTopic {
+        error_code: error_code::NONE,
+        name: Some(topic.name.clone()),
+        topic_id: topic.topic_id,
+        is_internal: is_internal_topic(&topic.name),
+        partitions,
+        topic_authorized_operations: broker.authorizer.authorized_operations_if_requested(
+            include_authorized_operations,
+            session,
+            ResourceType::Topic,
+            &topic.name,
+        ),
+    }
+}
+
+/// Create a topic a Metadata request asked for, with the broker's defaults
+fn auto_create_topic(broker: &Broker, session: &Session, name: &str) -> Result<Arc<MetadataImage>, i16> {
+    if name == CONSUMER_OFFSETS_TOPIC {
+        return match broker.ensure_offsets_topic() {
+            Ok(()) => Ok(broker.metadata.image()),
+            Err(e) => {
+                println!("unable to create {}: {}", CONSUMER_OFFSETS_TOPIC, e);
+                Err(error_code::COORDINATOR_NOT_AVAILABLE)
+            }
+        };
+    }
+    let topic = CreatableTopic {
+        name: name.to_string(),
+        num_partitions: -1,
+        replication_factor: -1,
+        assignments: vec![],
+        configs: vec![],
+    };
+    match create_topic(broker, session, &topic, false) {
+        (_, Some(image)) => Ok(image),
+        // created by someone else in the meantime
+        (result, None) if result.error_code == error_code::TOPIC_ALREADY_EXISTS => Ok(broker.metadata.image()),
+        (result, None) => Err(result.error_code),
+    }
+}
+
+fn handle_metadata_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_metadata().map_err(|_| "Bad Request".to_string())?;
+    let include_topic_operations = body.include_topic_authorized_operations;
+    let auto_create = body.allow_auto_topic_creation && broker.config.get_bool("auto.create.topics.enable").unwrap_or(true);
+
+    let image = broker.metadata.image();
+    let topics = match &body.topics {
+        // every topic the session may see
+        None => image
+            .topics()
+            .filter(|topic| broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Topic, &topic.name))
+            .map(|topic| metadata_topic(broker, session, topic, include_topic_operations))
+            .collect(),
+        Some(requested) => requested
+            .iter()
+            .map(|requested| {
+                let Some(name) = &requested.name else {
+                    return match image.topic_by_id(&requested.topic_id) {
+                        Some(topic) if broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Topic, &topic.name) => {
+                            metadata_topic(broker, session, topic, include_topic_operations)
+                        }
+                        Some(_) => MetadataResponseTopic::error(error_code::TOPIC_AUTHORIZATION_FAILED, None, requested.topic_id),
+                        None => MetadataResponseTopic::error(error_code::UNKNOWN_TOPIC_ID, None, requested.topic_id),
+                    };
+                };
+                if !broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Topic, name) {
+                    return MetadataResponseTopic::error(error_code::TOPIC_AUTHORIZATION_FAILED, Some(name.clone()), KafUuid::ZERO);
+                }
+                if let Some(topic) = image.topic(name) {
+                    return metadata_topic(broker, session, topic, include_topic_operations);
+                }
+                if !auto_create {
+                    return MetadataResponseTopic::error(error_code::UNKNOWN_TOPIC_OR_PARTITION, Some(name.clone()), KafUuid::ZERO);
+                }
+                let image = match auto_create_topic(broker, session, name) {
+                    Ok(image) => image,
+                    Err(error_code) => return MetadataResponseTopic::error(error_code, Some(name.clone()), KafUuid::ZERO),
+                };
+                match image.topic(name) {
+                    Some(topic) => metadata_topic(broker, session, topic, include_topic_operations),
+                    // deleted again before we could read it
+                    None => MetadataResponseTopic::error(error_code::UNKNOWN_TOPIC_OR_PARTITION, Some(name.clone()), KafUuid::ZERO),
+                }
+            })
+            .collect(),
+    };
+
+    let (host, port) = broker.config.advertised_listener();
+    Ok(KafResponse::for_request(
+        request.header,
+        Metadata(MetadataResponse {
+            throttle_time_ms: 0,
+            brokers: vec![MetadataResponseBroker {
+                node_id: broker.config.node_id,
+                host,
+                port,
+                rack: broker.config.rack(),
+            }],
+            cluster_id: broker.cluster_id.clone(),
+            // combined mode, we're our own controller
+            controller_id: broker.config.node_id,
+            topics,
+            cluster_authorized_operations: broker.authorizer.authorized_operations_if_requested(
+                body.include_cluster_authorized_operations,
+                session,
+                ResourceType::Cluster,
+                CLUSTER_RESOURCE,
+            ),
+        }),
+    ))
+}
+
+fn handle_find_coordinator_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_find_coordinator().map_err(|_| "Bad Request".to_string())?;
+
+    // combined single node mode, we coordinate every group
+    let (host, port) = broker.config.advertised_listener();
+    let coordinators = body
+        .coordinator_keys
+        .iter()
+        .map(|key| match body.key_type {
+            coordinator_type::GROUP if !broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Group, key) => {
+                Coordinator::error(key, error_code::GROUP_AUTHORIZATION_FAILED, None)
+            }
+            coordinator_type::GROUP if key.is_empty() => Coordinator::error(key, error_code::INVALID_GROUP_ID, None),
+            // groups can't commit offsets before it exists
+            coordinator_type::GROUP if broker.ensure_offsets_topic().is_err() => {
+                Coordinator::error(key, error_code::COORDINATOR_NOT_AVAILABLE, None)
+            }
+            coordinator_type::GROUP => Coordinator {
+                key: key.clone(),
+                node_id: broker.config.node_id,
+                host: host.clone(),
+                port,
+                error_code: error_code::NONE,
+                error_message: None,
+            },
+            coordinator_type::TRANSACTION => Coordinator::error(
+                key,
+                error_code::COORDINATOR_NOT_AVAILABLE,
+                Some("Transactions are not supported".to_string()),
+            ),
+            key_type => Coordinator::error(key, error_code::INVALID_REQUEST, Some(format!("Invalid coordinator type {}", key_type))),
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        FindCoordinator(FindCoordinatorResponse {
+            throttle_time_ms: 0,
+            coordinators,
+        }),
+    ))
+}
+
+fn handle_join_group_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_join_group().map_err(|_| "Bad Request".to_string())?;
+
+    let response = if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Group, &body.group_id) {
+        JoinGroupResponse {
+            error_code: error_code::GROUP_AUTHORIZATION_FAILED,
+            generation_id: -1,
+            member_id: body.member_id.clone(),
+            ..Default::default()
+        }
+    } else {
+        let context = JoinContext {
+            client_id: request.header.client_id.as_deref().unwrap_or_default(),
+            client_host: &session.client_host,
+            require_known_member_id: request.header.request_api_version >= 4,
+        };
+        broker.group_coordinator.join_group(&body, context)
+    };
+
+    Ok(KafResponse::for_request(request.header, JoinGroup(response)))
+}
+
+fn handle_sync_group_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_sync_group().map_err(|_| "Bad Request".to_string())?;
+
+    let response = if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Group, &body.group_id) {
+        SyncGroupResponse {
+            error_code: error_code::GROUP_AUTHORIZATION_FAILED,
+            ..Default::default()
+        }
+    } else {
+        broker.group_coordinator.sync_group(&body)
+    };
+
+    Ok(KafResponse::for_request(request.header, SyncGroup(response)))
+}
+
+fn handle_heartbeat_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_heartbeat().map_err(|_| "Bad Request".to_string())?;
+
+    let error_code = if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Group, &body.group_id) {
+        error_code::GROUP_AUTHORIZATION_FAILED
+    } else {
+        broker.group_coordinator.heartbeat(&body)
+    };
+
+    Ok(KafResponse::for_request(
+        request.header,
+        Heartbeat(HeartbeatResponse {
+            throttle_time_ms: 0,
+            error_code,
+        }),
+    ))
+}
+
+fn handle_leave_group_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_leave_group().map_err(|_| "Bad Request".to_string())?;
+
+    let mut response = if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Group, &body.group_id) {
+        LeaveGroupResponse {
+            error_code: error_code::GROUP_AUTHORIZATION_FAILED,
+            ..Default::default()
+        }
+    } else {
+        broker.group_coordinator.leave_group(&body.group_id, &body.members)
+    };
+    // before v3 the one member's error is the request's
+    if request.header.request_api_version < 3 && response.error_code == error_code::NONE {
+        response.error_code = response.members.first().map_or(error_code::NONE, |member| member.error_code);
+    }
+
+    Ok(KafResponse::for_request(request.header, LeaveGroup(response)))
+}
+
+fn handle_offset_commit_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_offset_commit().map_err(|_| "Bad Request".to_string())?;
+
+    let group_error = if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Group, &body.group_id) {
+        Some(error_code::GROUP_AUTHORIZATION_FAILED)
+    } else if let Err(e) = broker.ensure_offsets_topic() {
+        println!("unable to create {}: {}", CONSUMER_OFFSETS_TOPIC, e);
+        Some(error_code::COORDINATOR_NOT_AVAILABLE)
+    } else {
+        None
+    };
+
+    // errors known before committing, the rest is up to the coordinator
+    let image = broker.metadata.image();
+    let mut errors: Vec<Vec<Option<i16>>> = vec![];
+    let mut commits = vec![];
+    for topic in &body.topics {
+        let topic_error = if group_error.is_some() {
+            group_error
+        } else if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Topic, &topic.name) {
+            Some(error_code::TOPIC_AUTHORIZATION_FAILED)
+        } else {
+            None
+        };
+        let known = image.topic(&topic.name);
+        errors.push(
+            topic
+                .partitions
+                .iter()
+                .map(|partition| match topic_error {
+                    Some(error_code) => Some(error_code),
+                    None if !known.is_some_and(|known| known.partitions.contains_key(&partition.partition_index)) => {
+                        Some(error_code::UNKNOWN_TOPIC_OR_PARTITION)
+                    }
+                    None => {
+                        commits.push((TopicPartition::new(&topic.name, partition.partition_index), partition));
+                        None
+                    }
+                })
+                .collect(),
+        );
+    }
+    let mut committed = broker.group_coordinator.commit_offsets(&body, &commits).into_iter();
+
+    let topics = body
+        .topics
+        .iter()
+        .zip(errors)
+        .map(|(topic, errors)| OffsetCommitResponseTopic {
+            name: topic.name.clone(),
+            partitions: topic
+                .partitions
+                .iter()
+                .zip(errors)
+                .map(|(partition, error)| OffsetCommitResponsePartition {
+                    partition_index: partition.partition_index,
+                    error_code: error.or_else(|| committed.next()).unwrap_or(error_code::UNKNOWN_SERVER_ERROR),
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        OffsetCommit(OffsetCommitResponse {
+            throttle_time_ms: 0,
+            topics,
+        }),
+    ))
+}
+
+fn handle_offset_fetch_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_offset_fetch().map_err(|_| "Bad Request".to_string())?;
+
+    let groups = body.groups.iter().map(|group| fetch_group_offsets(broker, session, group)).collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        OffsetFetch(OffsetFetchResponse {
+            throttle_time_ms: 0,
+            groups,
+        }),
+    ))
+}
+
+fn fetch_group_offsets(broker: &Broker, session: &Session, group: &OffsetFetchRequestGroup) -> OffsetFetchResponseGroup {
+    let result = |topics, error_code| OffsetFetchResponseGroup {
+        group_id: group.group_id.clone(),
+        topics,
+        error_code,
+    };
+    if !broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Group, &group.group_id) {
+        return result(vec![], error_code::GROUP_AUTHORIZATION_FAILED);
+    }
+    let can_describe = |topic: &str| broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Topic, topic);
+
+    let mut topics: Vec<OffsetFetchResponseTopic> = vec![];
+    let mut unauthorized = vec![];
+    let requested: Option<Vec<TopicPartition>> = group.topics.as_ref().map(|requested| {
+        requested
+            .iter()
+            .filter(|topic| {
+                let authorized = can_describe(&topic.name);
+                if !authorized {
+                    unauthorized.push(*topic);
+                }
+                authorized
+            })
+            .flat_map(|topic| topic.partition_indexes.iter().map(|partition| TopicPartition::new(&topic.name, *partition)))
+            .collect()
+    });
+    let offsets = match broker.group_coordinator.fetch_offsets(&group.group_id, requested.as_deref()) {
+        Ok(offsets) => offsets,
+        Err(error_code) => return result(vec![], error_code),
+    };
+    // all of the group's offsets leave out the topics the client can't see
+    for (tp, committed) in offsets.into_iter().filter(|(tp, _)| requested.is_some() || can_describe(&tp.topic)) {
+        let partition = match committed {
+            Some(committed) => OffsetFetchResponsePartition {
+                partition_index: tp.partition,
+                committed_offset: committed.offset,
+                committed_leader_epoch: committed.leader_epoch,
+                metadata: Some(committed.metadata),
+                error_code: error_code::NONE,
+            },
+            None => OffsetFetchResponsePartition {
+                partition_index: tp.partition,
+                committed_offset: -1,
+                committed_leader_epoch: NO_LEADER_EPOCH,
+                metadata: Some(String::new()),
+                error_code: error_code::NONE,
+            },
+        };
+        match topics.last_mut() {
+            Some(topic) if topic.name == tp.topic => topic.partitions.push(partition),
+            _ => topics.push(OffsetFetchResponseTopic {
+                name: tp.topic,
+                partitions: vec![partition],
+            }),
+        }
+    }
+    topics.extend(unauthorized.into_iter().map(|topic| OffsetFetchResponseTopic {
+        name: topic.name.clone(),
+        partitions: topic
+            .partition_indexes
+            .iter()
+            .map(|partition| OffsetFetchResponsePartition {
+                partition_index: *partition,
+                committed_offset: -1,
+                committed_leader_epoch: NO_LEADER_EPOCH,
+                metadata: None,
+                error_code: error_code::TOPIC_AUTHORIZATION_FAILED,
+            })
+            .collect(),
+    }));
+    result(topics, error_code::NONE)
+}
+
+fn handle_describe_groups_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_describe_groups().map_err(|_| "Bad Request".to_string())?;
+
+    let groups = body
+        .groups
+        .iter()
+        .map(|group_id| {
+            if !broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Group, group_id) {
+                return DescribedGroup::error(group_id, error_code::GROUP_AUTHORIZATION_FAILED);
+            }
+            DescribedGroup {
+                authorized_operations: broker.authorizer.authorized_operations_if_requested(
+                    body.include_authorized_operations,
+                    session,
+                    ResourceType::Group,
+                    group_id,
+                ),
+                ..broker.group_coordinator.describe_group(group_id)
+            }
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        DescribeGroups(DescribeGroupsResponse {
+            throttle_time_ms: 0,
+            groups,
+        }),
+    ))
+}
+
+fn handle_list_groups_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_list_groups().map_err(|_| "Bad Request".to_string())?;
+
+    let mut response = broker.group_coordinator.list_groups(&body.states_filter, &body.types_filter);
+    // Describe on the cluster shows every group, otherwise only those the client may describe
+    if !broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Cluster, CLUSTER_RESOURCE) {
+        response
+            .groups
+            .retain(|group| broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Group, &group.group_id));
+    }
+
+    Ok(KafResponse::for_request(request.header, ListGroups(response)))
+}
+
+fn handle_delete_groups_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_delete_groups().map_err(|_| "Bad Request".to_string())?;
+
+    let results = body
+        .groups_names
+        .iter()
+        .map(|group_id| DeletableGroupResult {
+            group_id: group_id.clone(),
+            error_code: if !broker.authorizer.authorize(session, AclOperation::Delete, ResourceType::Group, group_id) {
+                error_code::GROUP_AUTHORIZATION_FAILED
+            } else {
+                broker.group_coordinator.delete_group(group_id)
+            },
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        DeleteGroups(DeleteGroupsResponse {
+            throttle_time_ms: 0,
+            results,
+        }),
+    ))
+}
+
+fn handle_offset_delete_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_offset_delete().map_err(|_| "Bad Request".to_string())?;
+
+    let response = |error_code, topics| {
+        Ok(KafResponse::for_request(
+            request.header.clone(),
+            OffsetDelete(OffsetDeleteResponse {
+                error_code,
+                throttle_time_ms: 0,
+                topics,
+            }),
+        ))
+    };
+    if !broker.authorizer.authorize(session, AclOperation::Delete, ResourceType::Group, &body.group_id) {
+        return response(error_code::GROUP_AUTHORIZATION_FAILED, vec![]);
+    }
+
+    // errors known before deleting, the rest is up to the coordinator
+    let image = broker.metadata.image();
+    let partition_error = |topic: &OffsetDeleteRequestTopic, partition: &i32| {
+        if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Topic, &topic.name) {
+            Some(error_code::TOPIC_AUTHORIZATION_FAILED)
+        } else if !image.topic(&topic.name).is_some_and(|known| known.partitions.contains_key(partition)) {
+            Some(error_code::UNKNOWN_TOPIC_OR_PARTITION)
+        } else {
+            None
+        }
+    };
+    let deletable: Vec<TopicPartition> = body
+        .topics
+        .iter()
+        .flat_map(|topic| topic.partitions.iter().map(move |partition| (topic, partition)))
+        .filter(|(topic, partition)| partition_error(topic, partition).is_none())
+        .map(|(topic, partition)| TopicPartition::new(&topic.name, *partition))
+        .collect();
+    let mut deleted = match broker.group_coordinator.delete_offsets(&body.group_id, &deletable) {
+        Ok(errors) => errors.into_iter(),
+        Err(error_code) => return response(error_code, vec![]),
+    };
+
+    let topics = body
+        .topics
+        .iter()
+        .map(|topic| OffsetDeleteResponseTopic {
+            name: topic.name.clone(),
+            partitions: topic
+                .partitions
+                .iter()
+                .map(|partition| OffsetDeleteResponsePartition {
+                    partition_index: *partition,
+                    error_code: partition_error(topic, partition)
+                        .or_else(|| deleted.next())
+                        .unwrap_or(error_code::UNKNOWN_SERVER_ERROR),
+                })
+                .collect(),
+        })
+        .collect();
+    response(error_code::NONE, topics)
+}
+
+/// The topics of the image as consumer group subscriptions resolve them
+fn consumer_group_topics(image: &MetadataImage) -> BTreeMap<String, TopicMetadata> {
+    image
+        .topics()
+        .map(|topic| {
+            let metadata = TopicMetadata {
+                topic_id: topic.topic_id,
+                name: topic.name.clone(),
+                num_partitions: topic.partitions.len() as i32,
+            };
+            (topic.name.clone(), metadata)
+        })
+        .collect()
+}
+
+fn handle_consumer_group_heartbeat_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_consumer_group_heartbeat().map_err(|_| "Bad Request".to_string())?;
+
+    let response = if !broker.authorizer.authorize(session, AclOperation::Read, ResourceType::Group, &body.group_id) {
+        ConsumerGroupHeartbeatResponse::error(error_code::GROUP_AUTHORIZATION_FAILED, None)
+    } else if request.header.request_api_version >= 1 && body.member_id.is_empty() {
+        // v1+ clients generate their member id
+        ConsumerGroupHeartbeatResponse::error(error_code::INVALID_REQUEST, Some("MemberId can't be empty.".to_string()))
+    } else {
+        let topics = consumer_group_topics(&broker.metadata.image());
+        let context = ConsumerContext {
+            client_id: request.header.client_id.as_deref().unwrap_or_default(),
+            client_host: &session.client_host,
+            topics: &topics,
+        };
+        broker.group_coordinator.consumer_group_heartbeat(&body, context)
+    };
+
+    Ok(KafResponse::for_request(request.header, ConsumerGroupHeartbeat(response)))
+}
+
+fn handle_consumer_group_describe_request(
+    broker: &Broker,
+    session: &Session,
+    request: KafRequest,
+) -> Result<KafResponse, StrError> {
+    let body = request.body.into_consumer_group_describe().map_err(|_| "Bad Request".to_string())?;
+
+    let topics = consumer_group_topics(&broker.metadata.image());
+    let groups = body
+        .group_ids
+        .iter()
+        .map(|group_id| {
+            if !broker.authorizer.authorize(session, AclOperation::Describe, ResourceType::Group, group_id) {
+                return DescribedConsumerGroup::error(group_id, error_code::GROUP_AUTHORIZATION_FAILED, None);
+            }
+            DescribedConsumerGroup {
+                authorized_operations: broker.authorizer.authorized_operations_if_requested(
+                    body.include_authorized_operations,
+                    session,
+                    ResourceType::Group,
+                    group_id,
+                ),
+                ..broker.group_coordinator.consumer_group_describe(group_id, &topics)
+            }
+        })
+        .collect();
+
+    Ok(KafResponse::for_request(
+        request.header,
+        ConsumerGroupDescribe(ConsumerGroupDescribeResponse {
+            throttle_time_ms: 0,
+            groups,
+        }),
+    ))
+}
+
+fn handle_unsupported_request(request: KafRequest) -> Result<KafResponse, StrError> {
+    Ok(KafResponse::new(
+        KafResponseHeader::v0(request.header),
+        KafResponseBody::default()
+    ))
+}
+
+// going to be main logic
+// None when the request doesn't get a response (Produce with acks=0)
+pub fn handle_request(broker: &Broker, session: &Session, request: KafRequest) -> Result<Option<KafResponse>, StrError> {
+    match &request.header.request_api_key {
+        KafApiKey::ApiVersions => handle_api_versions(request).map(Some),
+        KafApiKey::DescribeTopicPartitions => handle_describe_topic_partitions_request(broker, session, request).map(Some),
+        KafApiKey::Produce => handle_produce_request(broker, session, request),
+        KafApiKey::Fetch => handle_fetch_request(broker, session, request).map(Some),
+        KafApiKey::ListOffsets => handle_list_offsets_request(broker, session, request).map(Some),
+        KafApiKey::Metadata => handle_metadata_request(broker, session, request).map(Some),
+        KafApiKey::OffsetCommit => handle_offset_commit_request(broker, session, request).map(Some),
+        KafApiKey::OffsetFetch => handle_offset_fetch_request(broker, session, request).map(Some),
+        KafApiKey::FindCoordinator => handle_find_coordinator_request(broker, session, request).map(Some),
+        KafApiKey::JoinGroup => handle_join_group_request(broker, session, request).map(Some),
+        KafApiKey::Heartbeat => handle_heartbeat_request(broker, session, request).map(Some),
+        KafApiKey::LeaveGroup => handle_leave_group_request(broker, session, request).map(Some),
+        KafApiKey::SyncGroup => handle_sync_group_request(broker, session, request).map(Some),
+        KafApiKey::DescribeGroups => handle_describe_groups_request(broker, session, request).map(Some),
+        KafApiKey::ListGroups => handle_list_groups_request(broker, session, request).map(Some),
+        KafApiKey::DeleteGroups => handle_delete_groups_request(broker, session, request).map(Some),
+        KafApiKey::OffsetDelete => handle_offset_delete_request(broker, session, request).map(Some),
+        KafApiKey::ConsumerGroupHeartbeat => handle_consumer_group_heartbeat_request(broker, session, request).map(Some),
+        KafApiKey::ConsumerGroupDescribe => handle_consumer_group_describe_request(broker, session, request).map(Some),
+        KafApiKey::CreateTopics => handle_create_topics_request(broker, session, request).map(Some),
+        KafApiKey::DeleteTopics => handle_delete_topics_request(broker, session, request).map(Some),
+        KafApiKey::DeleteRecords => handle_delete_records_request(broker, session, request).map(Some),
+        KafApiKey::CreatePartitions => handle_create_partitions_request(broker, session, request).map(Some),
+        KafApiKey::DescribeConfigs => handle_describe_configs_request(broker, session, request).map(Some),
+        KafApiKey::AlterConfigs => handle_alter_configs_request(broker, session, request).map(Some),
+        KafApiKey::IncrementalAlterConfigs => handle_incremental_alter_configs_request(broker, session, request).map(Some),
+        _ => handle_unsupported_request(request).map(Some),
+    }
+}
+
+    // Ok(KafResponse::new(4, request.header.correlation_id))
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc, thread, time::Instant};
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{
+        common::{
+            acl::{AccessControlEntry, AclBinding, AclPermissionType, KafPrincipal, PatternType, ResourcePattern},
+            codec::write_string,
+            config::BrokerConfig,
+            request::{
+                alter_configs::{AlterConfigsBody, AlterableConfig},
+                consumer_group_describe::ConsumerGroupDescribeBody,
+                consumer_group_heartbeat::ConsumerGroupHeartbeatBody,
+                create_partitions::CreatePartitionsBody,
+                delete_groups::DeleteGroupsBody,
+                delete_records::{DeleteRecordsBody, DeleteRecordsTopic},
+                describe_configs::DescribeConfigsBody,
+                describe_groups::DescribeGroupsBody,
+                find_coordinator::FindCoordinatorBody,
+                heartbeat::HeartbeatBody,
+                incremental_alter_configs::IncrementalAlterConfigsBody,
+                join_group::{JoinGroupBody, JoinGroupRequestProtocol, UNKNOWN_MEMBER_ID},
+                leave_group::{LeaveGroupBody, MemberIdentity},
+                list_groups::ListGroupsBody,
+                offset_commit::{OffsetCommitBody, OffsetCommitRequestPartition, OffsetCommitRequestTopic, NO_GENERATION_ID},
+                offset_delete::OffsetDeleteBody,
+                offset_fetch::{OffsetFetchBody, OffsetFetchRequestTopic},
+                sync_group::{SyncGroupBody, SyncGroupRequestAssignment},
+                create_topics::{CreatableReplicaAssignment, CreatableTopicConfig, CreateTopicsBody},
+                delete_topics::DeleteTopicsBody,
+                fetch::FetchBody,
+                list_offsets::{ListOffsetsBody, ListOffsetsTopic},
+                metadata::{MetadataBody, MetadataRequestTopic},
+                produce::{ProduceBody, TopicProduceData},
+                request::KafRequestBody,
+            },
+            uuid::KafUuid,
+        },
+        coordinator::group::GroupState,
+        log::LogManager,
+        records::{MemoryRecords, Record, RecordBatchBuilder, RecordHeader, MAGIC_VALUE_V0, MAGIC_VALUE_V1},
+        utils::clock::SystemClock,
+    };
+
+    fn broker(dir: &std::path::Path) -> Broker {
+        broker_with(dir, &[])
+    }
+
+    fn broker_with(dir: &std::path::Path, props: &[(&str, &str)]) -> Broker {
+        let mut props: HashMap<_, _> = props.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        props.insert("log.dirs".to_string(), dir.to_str().unwrap().to_string());
+        Broker::load(BrokerConfig::from_props(props), Arc::new(SystemClock)).unwrap()
+    }
+
+    fn records(count: usize, value_size: usize) -> MemoryRecords {
+        let mut builder = RecordBatchBuilder::new(0);
+        for _ in 0..count {
+            builder.append(&Record::new(1_000, None, Some(Bytes::from(vec![b'v'; value_size]))));
+        }
+        MemoryRecords::from_batches(&[builder.build().unwrap()])
+    }
+
+    fn produce(broker: &Broker, acks: i16, partitions: Vec<(i32, Option<MemoryRecords>)>) -> Result<Option<KafResponse>, StrError> {
+        produce_to(broker, "orders", acks, partitions)
+    }
+
+    fn produce_to(
+        broker: &Broker,
+        topic: &str,
+        acks: i16,
+        partitions: Vec<(i32, Option<MemoryRecords>)>,
+    ) -> Result<Option<KafResponse>, StrError> {
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::Produce,
+                request_api_version: 9,
+                correlation_id: 7,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::Produce(ProduceBody {
+                transactional_id: None,
+                acks,
+                timeout_ms: 1_000,
+                topic_data: vec![TopicProduceData {
+                    name: topic.to_string(),
+                    partition_data: partitions
+                        .into_iter()
+                        .map(|(index, records)| PartitionProduceData { index, records })
+                        .collect(),
+                }],
+            }),
+        };
+        handle_request(broker, &Session::default(), request)
+    }
+
+    fn partition_responses(response: Option<KafResponse>) -> Vec<PartitionProduceResponse> {
+        let response = response.unwrap();
+        assert_eq!(response.api_version, 9);
+        let body = response.body.into_produce().unwrap();
+        body.responses.into_iter().flat_map(|t| t.partition_responses).collect()
+    }
+
+    #[test]
+    fn produce_appends_and_reports_per_partition_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.log_manager.get_or_create_log(&TopicPartition::new("orders", 0), &HashMap::new()).unwrap();
+        broker
+            .log_manager
+            .get_or_create_log(
+                &TopicPartition::new("orders", 1),
+                &HashMap::from([("max.message.bytes".to_string(), "1000".to_string())]),
+            )
+            .unwrap();
+
+        let first = partition_responses(produce(&broker, 1, vec![(0, Some(records(3, 10)))]).unwrap());
+        assert_eq!((first[0].error_code, first[0].base_offset, first[0].log_start_offset), (error_code::NONE, 0, 0));
+        assert_eq!(first[0].log_append_time_ms, -1);
+
+        let mut corrupt = records(1, 10).into_bytes().to_vec();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0xff;
+        let responses = partition_responses(
+            produce(
+                &broker,
+                -1,
+                vec![
+                    (0, Some(records(2, 10))),
+                    (0, Some(MemoryRecords::new(Bytes::from(corrupt)))),
+                    (1, Some(records(1, 2000))),
+                    (5, Some(records(1, 10))),
+                ],
+            )
+            .unwrap(),
+        );
+        let codes: Vec<_> = responses.iter().map(|p| p.error_code).collect();
+        assert_eq!(
+            codes,
+            vec![error_code::NONE, error_code::CORRUPT_MESSAGE, error_code::MESSAGE_TOO_LARGE, error_code::UNKNOWN_TOPIC_OR_PARTITION]
+        );
+        assert_eq!(responses[0].base_offset, 3);
+        assert!(responses[1].error_message.is_some());
+
+        let invalid = partition_responses(produce(&broker, 2, vec![(0, Some(records(1, 10)))]).unwrap());
+        assert_eq!(invalid[0].error_code, error_code::INVALID_REQUIRED_ACKS);
+        let mut builder = RecordBatchBuilder::new(0).producer(7, 0, 0).transactional(true);
+        builder.append(&Record::new(1_000, None, None));
+        let transactional = MemoryRecords::from_batches(&[builder.build().unwrap()]);
+        let rejected = partition_responses(produce(&broker, 1, vec![(0, Some(transactional))]).unwrap());
+        assert_eq!(rejected[0].error_code, error_code::INVALID_TXN_STATE);
+        assert_eq!(broker.log_manager.get_log(&TopicPartition::new("orders", 0)).unwrap().log_end_offset(), 5);
+    }
+
+    #[test]
+    fn acks_zero_gets_no_response_and_closes_on_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.log_manager.get_or_create_log(&TopicPartition::new("orders", 0), &HashMap::new()).unwrap();
+
+        assert!(produce(&broker, 0, vec![(0, Some(records(1, 10)))]).unwrap().is_none());
+        assert_eq!(broker.log_manager.get_log(&TopicPartition::new("orders", 0)).unwrap().log_end_offset(), 1);
+        assert!(produce(&broker, 0, vec![(3, Some(records(1, 10)))]).is_err());
+    }
+
+    #[test]
+    fn internal_topics_cannot_be_produced_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.ensure_offsets_topic().unwrap();
+
+        let responses = partition_responses(produce_to(&broker, CONSUMER_OFFSETS_TOPIC, 1, vec![(0, Some(records(1, 10)))]).unwrap());
+        assert_eq!(responses[0].error_code, error_code::INVALID_TOPIC_EXCEPTION);
+        let log = broker.log_manager.get_log(&TopicPartition::new(CONSUMER_OFFSETS_TOPIC, 0)).unwrap();
+        assert_eq!(log.log_end_offset(), 0);
+    }
+
+    fn fetch_body(max_bytes: i32, partitions: Vec<(KafUuid, i32, i64, i32)>) -> FetchBody {
+        FetchBody {
+            replica_id: -1,
+            max_wait_ms: 0,
+            min_bytes: 0,
+            max_bytes,
+            isolation_level: 0,
+            session_id: 0,
+            session_epoch: -1,
+            topics: partitions
+                .into_iter()
+                .map(|(topic_id, partition, fetch_offset, partition_max_bytes)| FetchTopic {
+                    topic: if topic_id.is_zero() { "orders".to_string() } else { String::new() },
+                    topic_id,
+                    partitions: vec![FetchPartition {
+                        partition,
+                        current_leader_epoch: -1,
+                        fetch_offset,
+                        last_fetched_epoch: -1,
+                        log_start_offset: -1,
+                        partition_max_bytes,
+                    }],
+                })
+                .collect(),
+            forgotten_topics_data: vec![],
+            rack_id: String::new(),
+        }
+    }
+
+    fn fetch_with(broker: &Broker, version: i16, body: FetchBody) -> FetchResponse {
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::Fetch,
+                request_api_version: version,
+                correlation_id: 8,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::Fetch(body),
+        };
+        handle_request(broker, &Session::default(), request).unwrap().unwrap().body.into_fetch().unwrap()
+    }
+
+    fn fetch(broker: &Broker, version: i16, max_bytes: i32, partitions: Vec<(KafUuid, i32, i64, i32)>) -> FetchResponse {
+        fetch_with(broker, version, fetch_body(max_bytes, partitions))
+    }
+
+    #[test]
+    fn fetch_reads_by_name_or_topic_id_within_the_byte_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        let topic_id = KafUuid([9; 16]);
+        for partition in 0..2 {
+            let log = broker.log_manager.get_or_create_log(&TopicPartition::new("orders", partition), &HashMap::new()).unwrap();
+            log.assign_topic_id(topic_id).unwrap();
+        }
+        for _ in 0..3 {
+            produce(&broker, 1, vec![(0, Some(records(1, 100))), (1, Some(records(1, 100)))]).unwrap();
+        }
+        let batch_size = records(1, 100).size_in_bytes() as i64;
+
+        let by_name = fetch(&broker, 12, 1 << 20, vec![(KafUuid::ZERO, 0, 1, 1 << 20)]);
+        let data = &by_name.responses[0].partitions[0];
+        assert_eq!((data.error_code, data.high_watermark, data.last_stable_offset, data.log_start_offset), (0, 3, 3, 0));
+        assert_eq!(data.records.as_ref().unwrap().size_in_bytes() as i64, 2 * batch_size);
+        assert_eq!(data.aborted_transactions, None);
+
+        // partition_max_bytes, but always at least one batch for the first partition
+        let limited = fetch(&broker, 13, 1 << 20, vec![(topic_id, 0, 0, 10), (topic_id, 1, 0, batch_size as i32 * 2)]);
+        assert_eq!(limited.responses[0].topic_id, topic_id);
+        let sizes: Vec<_> = limited
+            .responses
+            .iter()
+            .map(|t| t.partitions[0].records.as_ref().unwrap().size_in_bytes() as i64)
+            .collect();
+        assert_eq!(sizes, vec![batch_size, 2 * batch_size]);
+
+        // max_bytes is shared by the whole response
+        let shared = fetch(&broker, 13, batch_size as i32 * 2, vec![(topic_id, 0, 0, 1 << 20), (topic_id, 1, 0, 1 << 20)]);
+        let sizes: Vec<_> = shared
+            .responses
+            .iter()
+            .map(|t| t.partitions[0].records.as_ref().unwrap().size_in_bytes() as i64)
+            .collect();
+        assert_eq!(sizes, vec![2 * batch_size, 0]);
+
+        let errors = fetch(
+            &broker,
+            13,
+            1 << 20,
+            vec![(topic_id, 0, 4, 1 << 20), (KafUuid([1; 16]), 0, 0, 1 << 20), (topic_id, 7, 0, 1 << 20)],
+        );
+        let codes: Vec<_> = errors.responses.iter().map(|t| t.partitions[0].error_code).collect();
+        assert_eq!(
+            codes,
+            vec![error_code::OFFSET_OUT_OF_RANGE, error_code::UNKNOWN_TOPIC_ID, error_code::UNKNOWN_TOPIC_OR_PARTITION]
+        );
+    }
+
+    #[test]
+    fn old_fetch_versions_get_down_converted_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        for partition in 0..2 {
+            broker.log_manager.get_or_create_log(&TopicPartition::new("orders", partition), &HashMap::new()).unwrap();
+        }
+        let mut builder = RecordBatchBuilder::new(0);
+        builder.append(&Record::new(1_000, None, None).with_headers(vec![RecordHeader { key: "h".to_string(), value: None }]));
+        let with_headers = MemoryRecords::from_batches(&[builder.build().unwrap()]);
+        produce(&broker, 1, vec![(0, Some(records(2, 10))), (1, Some(with_headers))]).unwrap();
+
+        for (version, magic) in [(3, MAGIC_VALUE_V1), (0, MAGIC_VALUE_V0)] {
+            let fetched = fetch(&broker, version, 1 << 20, vec![(KafUuid::ZERO, 0, 1, 1 << 20), (KafUuid::ZERO, 1, 0, 1 << 20)]);
+            let partitions: Vec<_> = fetched.responses.iter().map(|t| &t.partitions[0]).collect();
+            let messages: Vec<_> = partitions[0].records.as_ref().unwrap().legacy_records().map(|m| m.unwrap()).collect();
+            assert_eq!(messages.iter().map(|m| (m.magic(), m.offset())).collect::<Vec<_>>(), vec![(magic, 1)]);
+            // v0/v1 messages have nowhere to put headers
+            assert_eq!(partitions[1].error_code, error_code::UNSUPPORTED_FOR_MESSAGE_FORMAT);
+        }
+    }
+
+    #[test]
+    fn fetch_waits_for_min_bytes_until_an_append_or_max_wait_ms() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.log_manager.get_or_create_log(&TopicPartition::new("orders", 0), &HashMap::new()).unwrap();
+        let waiting = |max_wait_ms| FetchBody {
+            max_wait_ms,
+            min_bytes: 1,
+            ..fetch_body(1 << 20, vec![(KafUuid::ZERO, 0, 0, 1 << 20)])
+        };
+
+        let start = Instant::now();
+        let expired = fetch_with(&broker, 12, waiting(100));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(expired.responses[0].partitions[0].records.as_ref().unwrap().size_in_bytes(), 0);
+
+        thread::scope(|s| {
+            let start = Instant::now();
+            let parked = s.spawn(|| fetch_with(&broker, 12, waiting(30_000)));
+            while broker.fetch_purgatory.delayed() == 0 {
+                thread::yield_now();
+            }
+            produce(&broker, -1, vec![(0, Some(records(2, 10)))]).unwrap();
+            let woken = parked.join().unwrap();
+            assert!(start.elapsed() < Duration::from_secs(30));
+            let data = &woken.responses[0].partitions[0];
+            assert_eq!((data.error_code, data.high_watermark), (0, 2));
+            assert_eq!(data.records.as_ref().unwrap().size_in_bytes(), records(2, 10).size_in_bytes());
+        });
+
+        // errors don't wait
+        let start = Instant::now();
+        let unknown = fetch_with(&broker, 12, FetchBody { topics: fetch_body(0, vec![(KafUuid::ZERO, 5, 0, 1)]).topics, ..waiting(30_000) });
+        assert_eq!(unknown.responses[0].partitions[0].error_code, error_code::UNKNOWN_TOPIC_OR_PARTITION);
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn fetch_sessions_only_return_partitions_that_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        for partition in 0..2 {
+            broker.log_manager.get_or_create_log(&TopicPartition::new("orders", partition), &HashMap::new()).unwrap();
+        }
+        let partitions = vec![(KafUuid::ZERO, 0, 0, 1 << 20), (KafUuid::ZERO, 1, 0, 1 << 20)];
+        let full = fetch_with(&broker, 12, FetchBody { session_epoch: 0, ..fetch_body(1 << 20, partitions) });
+        assert_eq!(full.responses.len(), 2);
+        assert_ne!(full.session_id, 0);
+
+        produce(&broker, 1, vec![(1, Some(records(1, 10)))]).unwrap();
+        let incremental = FetchBody {
+            session_id: full.session_id,
+            session_epoch: 1,
+            ..fetch_body(1 << 20, vec![])
+        };
+        let response = fetch_with(&broker, 12, incremental.clone());
+        assert_eq!(response.session_id, full.session_id);
+        let changed: Vec<_> = response.responses.iter().map(|t| (t.partitions[0].partition_index, t.partitions[0].high_watermark)).collect();
+        assert_eq!(changed, vec![(1, 1)]);
+
+        // the same epoch again
+        let replayed = fetch_with(&broker, 12, incremental);
+        assert_eq!((replayed.error_code, replayed.responses.len()), (error_code::INVALID_FETCH_SESSION_EPOCH, 0));
+    }
+
+    fn metadata(broker: &Broker, version: i16, topics: Option<Vec<(KafUuid, Option<&str>)>>, allow_auto_topic_creation: bool) -> MetadataResponse {
+        metadata_as(broker, &Session::default(), version, topics, allow_auto_topic_creation)
+    }
+
+    fn metadata_as(
+        broker: &Broker,
+        session: &Session,
+        version: i16,
+        topics: Option<Vec<(KafUuid, Option<&str>)>>,
+        allow_auto_topic_creation: bool,
+    ) -> MetadataResponse {
+        let topics = topics.map(|topics| {
+            topics
+                .into_iter()
+                .map(|(topic_id, name)| MetadataRequestTopic { topic_id, name: name.map(str::to_string) })
+                .collect()
+        });
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::Metadata,
+                request_api_version: version,
+                correlation_id: 9,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::Metadata(MetadataBody {
+                topics,
+                allow_auto_topic_creation,
+                include_cluster_authorized_operations: false,
+                include_topic_authorized_operations: true,
+            }),
+        };
+        handle_request(broker, session, request).unwrap().unwrap().body.into_metadata().unwrap()
+    }
+
+    #[test]
+    fn configured_acls_decide_the_authorized_operations_of_topics() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut broker = broker_with(dir.path(), &[("super.users", "User:admin"), ("allow.everyone.if.no.acl.found", "false")]);
+        broker.create_topic("orders", &[vec![1]], &HashMap::new()).unwrap();
+        broker.create_topic("payments", &[vec![1]], &HashMap::new()).unwrap();
+        broker.authorizer.add_acl(AclBinding {
+            pattern: ResourcePattern {
+                resource_type: ResourceType::Topic,
+                name: "orders".to_string(),
+                pattern_type: PatternType::Literal,
+            },
+            entry: AccessControlEntry {
+                principal: KafPrincipal::anonymous().to_string(),
+                host: "*".to_string(),
+                operation: AclOperation::Read,
+                permission_type: AclPermissionType::Allow,
+            },
+        });
+
+        // anonymous may only read orders, which implies describing it
+        let topics = metadata(&broker, 12, None, false).topics;
+        let operations: Vec<_> = topics.iter().map(|t| (t.name.as_deref(), t.topic_authorized_operations)).collect();
+        assert_eq!(operations, vec![(Some("orders"), (1 << 3) | (1 << 8))]);
+
+        let admin = Session {
+            principal: KafPrincipal::user("admin"),
+            ..Session::default()
+        };
+        let topics = metadata_as(&broker, &admin, 12, None, false).topics;
+        let operations: Vec<_> = topics.iter().map(|t| (t.name.as_deref(), t.topic_authorized_operations)).collect();
+        assert_eq!(operations, vec![(Some("orders"), 0xdf8), (Some("payments"), 0xdf8)]);
+    }
+
+    #[test]
+    fn metadata_describes_the_image_and_auto_creates_topics() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("meta.properties"), "version=1\ncluster.id=MkU3OEVBNTcwNTJENDM2Qk\nnode.id=1\n").unwrap();
+        let broker = broker(dir.path());
+        let image = broker.create_topic("orders", &[vec![1], vec![2]], &HashMap::new()).unwrap();
+        let orders_id = image.topic("orders").unwrap().topic_id;
+        assert!(broker.log_manager.get_log(&TopicPartition::new("orders", 0)).is_some());
+        // partition 1 is another broker's
+        assert!(broker.log_manager.get_log(&TopicPartition::new("orders", 1)).is_none());
+
+        let all = metadata(&broker, 12, None, true);
+        assert_eq!(all.cluster_id.as_deref(), Some("MkU3OEVBNTcwNTJENDM2Qk"));
+        assert_eq!((all.controller_id, all.brokers[0].host.as_str(), all.brokers[0].port), (1, "localhost", 9092));
+        assert_eq!(all.topics.len(), 1);
+        let orders = &all.topics[0];
+        assert_eq!((orders.topic_id, orders.is_internal), (orders_id, false));
+        assert_ne!(orders.topic_authorized_operations, AUTHORIZED_OPERATIONS_OMITTED);
+        let leaders: Vec<_> = orders.partitions.iter().map(|p| (p.error_code, p.leader_id)).collect();
+        assert_eq!(leaders, vec![(error_code::NONE, 1), (error_code::LEADER_NOT_AVAILABLE, -1)]);
+
+        let requested = metadata(
+            &broker,
+            12,
+            Some(vec![(orders_id, None), (KafUuid([5; 16]), None), (KafUuid::ZERO, Some("payments")), (KafUuid::ZERO, Some("bad/name"))]),
+            false,
+        );
+        let errors: Vec<_> = requested.topics.iter().map(|t| (t.error_code, t.name.as_deref())).collect();
+        assert_eq!(
+            errors,
+            vec![
+                (error_code::NONE, Some("orders")),
+                (error_code::UNKNOWN_TOPIC_ID, None),
+                (error_code::UNKNOWN_TOPIC_OR_PARTITION, Some("payments")),
+                (error_code::UNKNOWN_TOPIC_OR_PARTITION, Some("bad/name")),
+            ]
+        );
+
+        // before v4 topics are always auto created
+        let created = metadata(&broker, 1, Some(vec![(KafUuid::ZERO, Some("payments")), (KafUuid::ZERO, Some("bad/name"))]), true);
+        let errors: Vec<_> = created.topics.iter().map(|t| t.error_code).collect();
+        assert_eq!(errors, vec![error_code::NONE, error_code::INVALID_TOPIC_EXCEPTION]);
+        let payments = broker.metadata.image().topic("payments").unwrap().clone();
+        assert_eq!(created.topics[0].topic_id, payments.topic_id);
+        assert_eq!(payments.partitions.len(), 1);
+        let log = broker.log_manager.get_log(&TopicPartition::new("payments", 0)).unwrap();
+        assert_eq!(log.topic_id(), Some(payments.topic_id));
+
+        // internal topics are only ever created the way their coordinator wants them
+        let internal = metadata(&broker, 1, Some(vec![(KafUuid::ZERO, Some(CONSUMER_OFFSETS_TOPIC))]), true);
+        assert_eq!((internal.topics[0].error_code, internal.topics[0].is_internal), (error_code::NONE, true));
+        let offsets = broker.metadata.image().topic(CONSUMER_OFFSETS_TOPIC).unwrap().partitions.len();
+        assert_eq!(offsets as i32, broker.group_coordinator.offsets().config().num_partitions);
+    }
+
+    fn list_offsets(broker: &Broker, partitions: Vec<(i32, i32, i64)>) -> Vec<ListOffsetsPartitionResponse> {
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::ListOffsets,
+                request_api_version: 9,
+                correlation_id: 10,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::ListOffsets(ListOffsetsBody {
+                replica_id: -1,
+                isolation_level: 0,
+                topics: vec![ListOffsetsTopic {
+                    name: "orders".to_string(),
+                    partitions: partitions
+                        .into_iter()
+                        .map(|(partition_index, current_leader_epoch, timestamp)| ListOffsetsPartition {
+                            partition_index,
+                            current_leader_epoch,
+                            timestamp,
+                        })
+                        .collect(),
+                }],
+                timeout_ms: 0,
+            }),
+        };
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        response.body.into_list_offsets().unwrap().topics.remove(0).partitions
+    }
+
+    #[test]
+    fn list_offsets_by_special_value_and_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.create_topic("orders", &[vec![1], vec![1]], &HashMap::new()).unwrap();
+        let log = broker.log_manager.get_log(&TopicPartition::new("orders", 0)).unwrap();
+        for timestamps in [[1_000, 1_001], [5_000, 1_500], [2_000, 2_001]] {
+            let mut builder = RecordBatchBuilder::new(0);
+            for timestamp in timestamps {
+                builder.append(&Record::new(timestamp, None, Some(Bytes::from_static(b"v"))));
+            }
+            log.append_as_leader(&MemoryRecords::from_batches(&[builder.build().unwrap()])).unwrap();
+        }
+
+        let offsets = |timestamp| {
+            let response = &list_offsets(&broker, vec![(0, 0, timestamp)])[0];
+            (response.error_code, response.timestamp, response.offset)
+        };
+        assert_eq!(offsets(EARLIEST_TIMESTAMP), (0, -1, 0));
+        assert_eq!(offsets(EARLIEST_LOCAL_TIMESTAMP), (0, -1, 0));
+        assert_eq!(offsets(LATEST_TIMESTAMP), (0, -1, 6));
+        assert_eq!(offsets(MAX_TIMESTAMP), (0, 5_000, 2));
+        assert_eq!(offsets(LATEST_TIERED_TIMESTAMP), (0, -1, -1));
+        assert_eq!(offsets(1_001), (0, 1_001, 1));
+        assert_eq!(offsets(1_200), (0, 5_000, 2));
+        assert_eq!(offsets(9_000), (0, -1, -1));
+
+        // an empty partition has no max timestamp
+        let empty = &list_offsets(&broker, vec![(1, -1, MAX_TIMESTAMP)])[0];
+        assert_eq!((empty.error_code, empty.offset, empty.leader_epoch), (0, -1, 0));
+
+        let errors: Vec<_> = list_offsets(&broker, vec![(0, 1, -1), (7, -1, -1), (1, -1, -2), (1, -1, -1)])
+            .iter()
+            .map(|p| p.error_code)
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                error_code::UNKNOWN_LEADER_EPOCH,
+                error_code::UNKNOWN_TOPIC_OR_PARTITION,
+                error_code::INVALID_REQUEST,
+                error_code::INVALID_REQUEST,
+            ]
+        );
+    }
+
+    fn create_topics(broker: &Broker, topics: Vec<CreatableTopic>, validate_only: bool) -> Vec<CreatableTopicResult> {
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::CreateTopics,
+                request_api_version: 7,
+                correlation_id: 11,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::CreateTopics(CreateTopicsBody {
+                topics,
+                timeout_ms: 30_000,
+                validate_only,
+            }),
+        };
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        response.body.into_create_topics().unwrap().topics
+    }
+
+    fn creatable(name: &str, num_partitions: i32, replication_factor: i16, configs: &[(&str, Option<&str>)]) -> CreatableTopic {
+        CreatableTopic {
+            name: name.to_string(),
+            num_partitions,
+            replication_factor,
+            assignments: vec![],
+            configs: configs
+                .iter()
+                .map(|(name, value)| CreatableTopicConfig {
+                    name: name.to_string(),
+                    value: value.map(str::to_string),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn create_topics_validates_and_writes_to_the_metadata_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+
+        let checked = create_topics(&broker, vec![creatable("orders", 3, 1, &[("cleanup.policy", Some("compact"))])], true);
+        assert_eq!((checked[0].error_code, checked[0].num_partitions, checked[0].topic_id), (0, 3, KafUuid::ZERO));
+        assert!(broker.metadata.image().topic("orders").is_none());
+
+        let created = create_topics(
+            &broker,
+            vec![creatable("orders", 3, -1, &[("cleanup.policy", Some("compact"))]), creatable("events.eu", -1, -1, &[])],
+            false,
+        );
+        assert_eq!(created.iter().map(|t| t.error_code).collect::<Vec<_>>(), vec![0, 0]);
+        let orders = broker.metadata.image().topic("orders").unwrap().clone();
+        assert_eq!((created[0].topic_id, created[0].replication_factor), (orders.topic_id, 1));
+        // the whole config of the topic, defaults next to what it set
+        let configs = created[0].configs.as_ref().unwrap();
+        assert_eq!(configs.len(), TOPIC_CONFIGS.keys().count());
+        let config = |name: &str| {
+            let config = configs.iter().find(|config| config.name == name).unwrap();
+            (config.value.as_deref(), config.config_source, config.read_only, config.is_sensitive)
+        };
+        assert_eq!(config("cleanup.policy"), (Some("compact"), config_source::DYNAMIC_TOPIC_CONFIG, false, false));
+        assert_eq!(config("retention.ms"), (Some("604800000"), config_source::DEFAULT_CONFIG, false, false));
+        assert_eq!(orders.partitions.len(), 3);
+        let log = broker.log_manager.get_log(&TopicPartition::new("orders", 2)).unwrap();
+        assert_eq!(log.topic_id(), Some(orders.topic_id));
+        assert!(log.config().cleanup_policy.compact);
+
+        let mut assigned = creatable("payments", -1, -1, &[]);
+        assigned.assignments = vec![
+            CreatableReplicaAssignment { partition_index: 1, broker_ids: vec![1] },
+            CreatableReplicaAssignment { partition_index: 0, broker_ids: vec![1] },
+        ];
+        let mut gap = creatable("refunds", -1, -1, &[]);
+        gap.assignments = vec![CreatableReplicaAssignment { partition_index: 1, broker_ids: vec![1] }];
+        let mut elsewhere = creatable("returns", -1, -1, &[]);
+        elsewhere.assignments = vec![CreatableReplicaAssignment { partition_index: 0, broker_ids: vec![2] }];
+        let results = create_topics(
+            &broker,
+            vec![
+                assigned,
+                gap,
+                elsewhere,
+                creatable("orders", 1, 1, &[]),
+                creatable("events_eu", 1, 1, &[]),
+                creatable("bad/name", 1, 1, &[]),
+                creatable(&"a".repeat(250), 1, 1, &[]),
+                creatable("zero", 0, 1, &[]),
+                creatable("wide", 1, 3, &[]),
+                creatable("tuned", 1, 1, &[("retention.ms", Some("soon"))]),
+                creatable("unknown", 1, 1, &[("no.such.config", Some("1"))]),
+                creatable(CONSUMER_OFFSETS_TOPIC, 1, 1, &[("cleanup.policy", Some("delete"))]),
+                creatable("twice", 1, 1, &[]),
+                creatable("twice", 1, 1, &[]),
+            ],
+            false,
+        );
+        let errors: Vec<_> = results.iter().map(|t| t.error_code).collect();
+        assert_eq!(
+            errors,
+            vec![
+                error_code::NONE,
+                error_code::INVALID_REPLICA_ASSIGNMENT,
+                error_code::INVALID_REPLICA_ASSIGNMENT,
+                error_code::TOPIC_ALREADY_EXISTS,
+                error_code::INVALID_TOPIC_EXCEPTION,
+                error_code::INVALID_TOPIC_EXCEPTION,
+                error_code::INVALID_TOPIC_EXCEPTION,
+                error_code::INVALID_PARTITIONS,
+                error_code::INVALID_REPLICATION_FACTOR,
+                error_code::INVALID_CONFIG,
+                error_code::INVALID_CONFIG,
+                error_code::INVALID_REQUEST,
+                error_code::INVALID_REQUEST,
+                error_code::INVALID_REQUEST,
+            ]
+        );
+        assert_eq!(results[0].num_partitions, 2);
+        assert_eq!(results[3].num_partitions, -1);
+        let names: Vec<_> = broker.metadata.image().topics().map(|t| t.name.clone()).collect();
+        assert_eq!(names, vec!["events.eu", "orders", "payments"]);
+    }
+
+    fn delete_topics(broker: &Broker, version: i16, topics: Vec<(Option<&str>, KafUuid)>) -> Vec<DeletableTopicResult> {
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::DeleteTopics,
+                request_api_version: version,
+                correlation_id: 12,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::DeleteTopics(DeleteTopicsBody {
+                topics: topics
+                    .into_iter()
+                    .map(|(name, topic_id)| DeleteTopicState { name: name.map(str::to_string), topic_id })
+                    .collect(),
+                timeout_ms: 30_000,
+            }),
+        };
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        response.body.into_delete_topics().unwrap().responses
+    }
+
+    #[test]
+    fn deleted_topics_are_gone_for_produce_and_fetch_and_their_dirs_renamed() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.create_topic("orders", &[vec![1], vec![1]], &HashMap::new()).unwrap();
+        let image = broker.create_topic("payments", &[vec![1]], &HashMap::new()).unwrap();
+        let payments_id = image.topic("payments").unwrap().topic_id;
+        produce(&broker, 1, vec![(0, Some(records(1, 10)))]).unwrap();
+        // e.g. a produce that looked the log up just before the delete
+        let held = broker.log_manager.get_log(&TopicPartition::new("orders", 0)).unwrap();
+
+        let deleted = delete_topics(&broker, 5, vec![(Some("orders"), KafUuid::ZERO), (Some("refunds"), KafUuid::ZERO)]);
+        let errors: Vec<_> = deleted.iter().map(|t| t.error_code).collect();
+        assert_eq!(errors, vec![error_code::NONE, error_code::UNKNOWN_TOPIC_OR_PARTITION]);
+        let deleted = delete_topics(
+            &broker,
+            6,
+            vec![(None, payments_id), (None, KafUuid([5; 16])), (Some("orders"), payments_id), (None, payments_id)],
+        );
+        let errors: Vec<_> = deleted.iter().map(|t| (t.error_code, t.name.as_deref())).collect();
+        assert_eq!(
+            errors,
+            vec![
+                (error_code::INVALID_REQUEST, None),
+                (error_code::UNKNOWN_TOPIC_ID, None),
+                (error_code::INVALID_REQUEST, Some("orders")),
+                (error_code::INVALID_REQUEST, None),
+            ]
+        );
+        let deleted = delete_topics(&broker, 6, vec![(None, payments_id)]);
+        assert_eq!((deleted[0].error_code, deleted[0].name.as_deref()), (error_code::NONE, Some("payments")));
+        assert_eq!(broker.metadata.image().topics().count(), 0);
+
+        let produced = partition_responses(produce(&broker, 1, vec![(0, Some(records(1, 10)))]).unwrap());
+        assert_eq!(produced[0].error_code, error_code::UNKNOWN_TOPIC_OR_PARTITION);
+        let fetched = fetch(&broker, 12, 1 << 20, vec![(KafUuid::ZERO, 0, 0, 1 << 20)]);
+        assert_eq!(fetched.responses[0].partitions[0].error_code, error_code::UNKNOWN_TOPIC_OR_PARTITION);
+        let append = held.append_as_leader(&records(1, 10)).unwrap_err();
+        assert_eq!(append.error_code(), error_code::UNKNOWN_TOPIC_OR_PARTITION);
+        assert_eq!(held.read(0, 1 << 20, FetchIsolation::HighWatermark, true).unwrap_err().error_code(), error_code::UNKNOWN_TOPIC_OR_PARTITION);
+
+        let mut dirs: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| !name.starts_with("__cluster_metadata") && !name.ends_with("checkpoint"))
+            .collect();
+        dirs.sort();
+        assert_eq!(dirs.len(), 3);
+        assert!(dirs.iter().all(|name| name.ends_with("-delete")));
+        assert!(dirs[0].starts_with("orders-0.") && dirs[2].starts_with("payments-0."));
+
+        // the name can be used again, by a new topic
+        let recreated = broker.create_topic("orders", &[vec![1]], &HashMap::new()).unwrap().topic("orders").unwrap().topic_id;
+        let log = broker.log_manager.get_log(&TopicPartition::new("orders", 0)).unwrap();
+        assert_eq!((log.topic_id(), log.log_end_offset()), (Some(recreated), 0));
+    }
+
+    #[test]
+    fn logs_of_topics_missing_from_the_metadata_are_deleted_on_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.create_topic("orders", &[vec![1]], &HashMap::new()).unwrap();
+        // e.g. a topic deleted while this broker was down
+        broker.log_manager.get_or_create_log(&TopicPartition::new("stale", 0), &HashMap::new()).unwrap();
+
+        drop(broker);
+        let broker = self::broker(dir.path());
+        assert!(broker.log_manager.get_log(&TopicPartition::new("stale", 0)).is_none());
+        assert!(broker.log_manager.get_log(&TopicPartition::new("orders", 0)).is_some());
+        let dirs: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with("stale-0"))
+            .collect();
+        assert_eq!(dirs.len(), 1);
+        assert!(dirs[0].ends_with("-delete"));
+    }
+
+    fn grow(name: &str, count: i32, assignments: Option<Vec<Vec<i32>>>) -> CreatePartitionsTopic {
+        CreatePartitionsTopic { name: name.to_string(), count, assignments }
+    }
+
+    fn create_partitions(broker: &Broker, topics: Vec<CreatePartitionsTopic>, validate_only: bool) -> Vec<i16> {
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::CreatePartitions,
+                request_api_version: 3,
+                correlation_id: 13,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::CreatePartitions(CreatePartitionsBody {
+                topics,
+                timeout_ms: 30_000,
+                validate_only,
+            }),
+        };
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        let results = response.body.into_create_partitions().unwrap().results;
+        results.iter().map(|result| result.error_code).collect()
+    }
+
+    #[test]
+    fn create_partitions_only_grows_topics_and_publishes_partitions_with_their_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        let orders_id = broker.create_topic("orders", &[vec![1]], &HashMap::new()).unwrap().topic("orders").unwrap().topic_id;
+        broker.create_topic("payments", &[vec![1], vec![1]], &HashMap::new()).unwrap();
+        let partition_count = |name| broker.metadata.image().topic(name).unwrap().partitions.len();
+
+        assert_eq!(create_partitions(&broker, vec![grow("orders", 3, None)], true), vec![error_code::NONE]);
+        assert_eq!(partition_count("orders"), 1);
+
+        let errors = create_partitions(
+            &broker,
+            vec![
+                grow("payments", 1, None),
+                grow("refunds", 2, None),
+                grow("orders", 3, Some(vec![vec![1]])),
+                grow("orders", 3, None),
+            ],
+            false,
+        );
+        assert_eq!(
+            errors,
+            vec![
+                error_code::INVALID_PARTITIONS,
+                error_code::UNKNOWN_TOPIC_OR_PARTITION,
+                error_code::INVALID_REQUEST,
+                error_code::INVALID_REQUEST
+            ]
+        );
+        let errors = create_partitions(
+            &broker,
+            vec![grow("payments", 2, None), grow("orders", 2, Some(vec![vec![2]])), grow("refunds", 2, Some(vec![vec![1, 1]]))],
+            false,
+        );
+        assert_eq!(
+            errors,
+            vec![error_code::INVALID_PARTITIONS, error_code::INVALID_REPLICA_ASSIGNMENT, error_code::UNKNOWN_TOPIC_OR_PARTITION]
+        );
+        assert_eq!(partition_count("orders"), 1);
+
+        let created = create_partitions(&broker, vec![grow("orders", 2, Some(vec![vec![1]])), grow("payments", 4, None)], false);
+        assert_eq!(created, vec![error_code::NONE; 2]);
+        let image = broker.metadata.image();
+        assert_eq!((partition_count("orders"), partition_count("payments")), (2, 4));
+        // every partition in the image already has its log
+        for tp in image.topics().flat_map(|topic| topic.topic_partitions()) {
+            assert!(broker.log_manager.get_log(&tp).is_some(), "{}", tp);
+        }
+        let log = broker.log_manager.get_log(&TopicPartition::new("orders", 1)).unwrap();
+        assert_eq!(log.topic_id(), Some(orders_id));
+        assert_eq!(image.topic("payments").unwrap().partitions[&3].leader, 1);
+
+        // and they're there after a restart
+        drop(broker);
+        let broker = self::broker(dir.path());
+        assert_eq!(broker.metadata.image().topic("payments").unwrap().partitions.len(), 4);
+    }
+
+    fn delete_records(broker: &Broker, topic: &str, partitions: Vec<(i32, i64)>) -> Vec<(i64, i16)> {
+        let request = KafRequest {
+            header: KafRequestHeader {
+                request_api_key: KafApiKey::DeleteRecords,
+                request_api_version: 2,
+                correlation_id: 14,
+                client_id: None,
+                tags: None,
+            },
+            body: KafRequestBody::DeleteRecords(DeleteRecordsBody {
+                topics: vec![DeleteRecordsTopic {
+                    name: topic.to_string(),
+                    partitions: partitions
+                        .into_iter()
+                        .map(|(partition_index, offset)| DeleteRecordsPartition { partition_index, offset })
+                        .collect(),
+                }],
+                timeout_ms: 30_000,
+            }),
+        };
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        let topics = response.body.into_delete_records().unwrap().topics;
+        topics[0].partitions.iter().map(|p| (p.low_watermark, p.error_code)).collect()
+    }
+
+    #[test]
+    fn delete_records_moves_the_log_start_offset_up_to_the_high_watermark() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        broker.create_topic("orders", &[vec![1], vec![1]], &HashMap::new()).unwrap();
+        let compacted = HashMap::from([("cleanup.policy".to_string(), "compact".to_string())]);
+        broker.create_topic("users", &[vec![1]], &compacted).unwrap();
+        for _ in 0..3 {
+            produce(&broker, 1, vec![(0, Some(records(2, 10)))]).unwrap();
+        }
+        broker.log_manager.get_log(&TopicPartition::new("orders", 0)).unwrap().update_high_watermark(4);
+
+        let deleted = delete_records(&broker, "orders", vec![(0, 3), (1, 5), (2, 0)]);
+        assert_eq!(
+            deleted,
+            vec![(3, error_code::NONE), (-1, error_code::OFFSET_OUT_OF_RANGE), (-1, error_code::UNKNOWN_TOPIC_OR_PARTITION)]
+        );
+        assert_eq!(delete_records(&broker, "orders", vec![(0, 5)]), vec![(-1, error_code::OFFSET_OUT_OF_RANGE)]);
+        assert_eq!(delete_records(&broker, "orders", vec![(0, HIGH_WATERMARK)]), vec![(4, error_code::NONE)]);
+        assert_eq!(delete_records(&broker, "users", vec![(0, 0)]), vec![(-1, error_code::POLICY_VIOLATION)]);
+
+        let fetched = fetch(&broker, 12, 1 << 20, vec![(KafUuid::ZERO, 0, 2, 1 << 20)]);
+        assert_eq!(fetched.responses[0].partitions[0].error_code, error_code::OFFSET_OUT_OF_RANGE);
+        let earliest = &list_offsets(&broker, vec![(0, -1, EARLIEST_TIMESTAMP)])[0];
+        assert_eq!(earliest.offset, 4);
+
+        // the new log start offset is checkpointed right away
+        drop(broker);
+        let broker = self::broker(dir.path());
+        assert_eq!(broker.log_manager.get_log(&TopicPartition::new("orders", 0)).unwrap().log_start_offset(), 4);
+    }
+
+    fn config_request(api_key: KafApiKey, version: i16, body: KafRequestBody) -> KafRequest {
+        KafRequest {
+            header: KafRequestHeader {
+                request_api_key: api_key,
+                request_api_version: version,
+                correlation_id: 15,
+                client_id: None,
+                tags: None,
+            },
+            body,
+        }
+    }
+
+    fn describe_configs(broker: &Broker, resource_type: i8, name: &str, keys: Option<&[&str]>) -> DescribeConfigsResult {
+        let body = DescribeConfigsBody {
+            resources: vec![DescribeConfigsResource {
+                resource_type,
+                resource_name: name.to_string(),
+                configuration_keys: keys.map(|keys| keys.iter().map(|key| key.to_string()).collect()),
+            }],
+            include_synonyms: true,
+            include_documentation: true,
+        };
+        let request = config_request(KafApiKey::DescribeConfigs, 4, KafRequestBody::DescribeConfigs(body));
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        response.body.into_describe_configs().unwrap().results.remove(0)
+    }
+
+    fn alter_configs(broker: &Broker, resource_type: i8, name: &str, configs: &[(&str, &str)]) -> i16 {
+        let body = AlterConfigsBody {
+            resources: vec![AlterConfigsResource {
+                resource_type,
+                resource_name: name.to_string(),
+                configs: configs
+                    .iter()
+                    .map(|(name, value)| AlterableConfig { name: name.to_string(), value: Some(value.to_string()) })
+                    .collect(),
+            }],
+            validate_only: false,
+        };
+        let request = config_request(KafApiKey::AlterConfigs, 2, KafRequestBody::AlterConfigs(body));
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        response.body.into_alter_configs().unwrap().responses[0].error_code
+    }
+
+    fn incremental_alter_configs(broker: &Broker, resource_type: i8, name: &str, ops: &[(&str, i8, Option<&str>)], validate_only: bool) -> i16 {
+        let body = IncrementalAlterConfigsBody {
+            resources: vec![IncrementalAlterConfigsResource {
+                resource_type,
+                resource_name: name.to_string(),
+                configs: ops
+                    .iter()
+                    .map(|(name, config_operation, value)| AlterableConfigOp {
+                        name: name.to_string(),
+                        config_operation: *config_operation,
+                        value: value.map(str::to_string),
+                    })
+                    .collect(),
+            }],
+            validate_only,
+        };
+        let request = config_request(KafApiKey::IncrementalAlterConfigs, 1, KafRequestBody::IncrementalAlterConfigs(body));
+        let response = handle_request(broker, &Session::default(), request).unwrap().unwrap();
+        response.body.into_incremental_alter_configs().unwrap().responses[0].error_code
+    }
+
+    #[test]
+    fn describe_configs_reports_values_with_their_synonyms_and_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        create_topics(&broker, vec![creatable("orders", 1, 1, &[("cleanup.policy", Some("compact"))])], false);
+
+        let orders = describe_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", Some(&["cleanup.policy", "retention.ms"]));
+        assert_eq!(orders.error_code, error_code::NONE);
+        let [cleanup_policy, retention] = &orders.configs[..] else {
+            panic!("expected two configs, got {:?}", orders.configs);
+        };
+        assert_eq!((cleanup_policy.value.as_deref(), cleanup_policy.config_source), (Some("compact"), config_source::DYNAMIC_TOPIC_CONFIG));
+        let synonyms: Vec<_> = cleanup_policy.synonyms.iter().map(|s| (s.name.as_str(), s.value.as_deref(), s.source)).collect();
+        assert_eq!(
+            synonyms,
+            vec![
+                ("cleanup.policy", Some("compact"), config_source::DYNAMIC_TOPIC_CONFIG),
+                ("log.cleanup.policy", Some("delete"), config_source::DEFAULT_CONFIG),
+            ]
+        );
+        assert_eq!(cleanup_policy.config_type, ConfigType::List.id());
+        assert!(cleanup_policy.documentation.is_some() && !cleanup_policy.read_only);
+        // 168 hours
+        assert_eq!((retention.value.as_deref(), retention.config_source), (Some("604800000"), config_source::DEFAULT_CONFIG));
+        assert_eq!(retention.synonyms[0].name, "log.retention.hours");
+        assert_eq!(describe_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", None).configs.len(), TOPIC_CONFIGS.keys().count());
+
+        let node = describe_configs(&broker, BROKER_RESOURCE_TYPE, "1", Some(&["log.dirs", "log.retention.ms"]));
+        let read_only: Vec<_> = node.configs.iter().map(|c| (c.name.as_str(), c.read_only, c.config_source)).collect();
+        assert_eq!(
+            read_only,
+            vec![
+                ("log.dirs", true, config_source::STATIC_BROKER_CONFIG),
+                ("log.retention.ms", false, config_source::DEFAULT_CONFIG),
+            ]
+        );
+        // nothing is set for every broker yet
+        assert!(describe_configs(&broker, BROKER_RESOURCE_TYPE, CLUSTER_DEFAULT_RESOURCE, None).configs.is_empty());
+
+        assert_eq!(describe_configs(&broker, TOPIC_RESOURCE_TYPE, "missing", None).error_code, error_code::UNKNOWN_TOPIC_OR_PARTITION);
+        assert_eq!(describe_configs(&broker, BROKER_RESOURCE_TYPE, "7", None).error_code, error_code::INVALID_REQUEST);
+        assert_eq!(describe_configs(&broker, 3, "orders", None).error_code, error_code::INVALID_REQUEST);
+    }
+
+    #[test]
+    fn config_changes_reach_the_logs_and_survive_a_restart() {
+        use config_operation::{APPEND, DELETE, SET, SUBTRACT};
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        create_topics(&broker, vec![creatable("orders", 1, 1, &[]), creatable("events", 1, 1, &[])], false);
+        let tp = TopicPartition::new("orders", 0);
+        let log_config = |broker: &Broker, topic: &str| broker.log_manager.get_log(&TopicPartition::new(topic, 0)).unwrap().config();
+        let overrides = |broker: &Broker| {
+            let mut configs: Vec<_> = broker.metadata.image().topic_configs("orders").into_iter().collect();
+            configs.sort();
+            configs
+        };
+
+        assert_eq!(alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &[("segment.bytes", "1")]), error_code::INVALID_CONFIG);
+        assert_eq!(alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &[("unknown", "1")]), error_code::INVALID_CONFIG);
+        assert_eq!(alter_configs(&broker, TOPIC_RESOURCE_TYPE, "missing", &[]), error_code::UNKNOWN_TOPIC_OR_PARTITION);
+        let altered = alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &[("retention.ms", "1000"), ("cleanup.policy", "compact")]);
+        assert_eq!(altered, error_code::NONE);
+        assert_eq!(broker.log_manager.get_log(&tp).unwrap().config().retention_ms, 1000);
+        // AlterConfigs replaces everything set on the topic
+        assert_eq!(alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &[("retention.ms", "2000")]), error_code::NONE);
+        assert_eq!(overrides(&broker), vec![("retention.ms".to_string(), "2000".to_string())]);
+        assert!(!log_config(&broker, "orders").cleanup_policy.compact);
+
+        // appending starts from the default
+        let append = [("cleanup.policy", APPEND, Some("compact"))];
+        assert_eq!(incremental_alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &append, true), error_code::NONE);
+        assert_eq!(overrides(&broker).len(), 1);
+        assert_eq!(incremental_alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &append, false), error_code::NONE);
+        assert_eq!(broker.metadata.image().topic_configs("orders")["cleanup.policy"], "delete,compact");
+        let subtract = [("cleanup.policy", SUBTRACT, Some("delete")), ("retention.ms", DELETE, None)];
+        assert_eq!(incremental_alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &subtract, false), error_code::NONE);
+        assert_eq!(overrides(&broker), vec![("cleanup.policy".to_string(), "compact".to_string())]);
+        let config = log_config(&broker, "orders");
+        assert_eq!((config.cleanup_policy.compact, config.cleanup_policy.delete, config.retention_ms), (true, false, 604_800_000));
+        for invalid in [[("cleanup.policy", SUBTRACT, Some("compact"))], [("retention.ms", APPEND, Some("1"))], [("retention.ms", SET, None)]] {
+            assert_eq!(incremental_alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &invalid, false), error_code::INVALID_CONFIG);
+        }
+        let duplicate = [("retention.ms", SET, Some("1")), ("retention.ms", DELETE, None)];
+        assert_eq!(incremental_alter_configs(&broker, TOPIC_RESOURCE_TYPE, "orders", &duplicate, false), error_code::INVALID_REQUEST);
+
+        // dynamic broker configs are the defaults of topics without overrides
+        let set = [("log.retention.ms", SET, Some("5000"))];
+        assert_eq!(incremental_alter_configs(&broker, BROKER_RESOURCE_TYPE, CLUSTER_DEFAULT_RESOURCE, &set, false), error_code::NONE);
+        assert_eq!(log_config(&broker, "events").retention_ms, 5000);
+        let static_only = [("log.dirs", SET, Some("/tmp"))];
+        assert_eq!(incremental_alter_configs(&broker, BROKER_RESOURCE_TYPE, "1", &static_only, false), error_code::INVALID_CONFIG);
+        let defaults = describe_configs(&broker, BROKER_RESOURCE_TYPE, CLUSTER_DEFAULT_RESOURCE, None);
+        assert_eq!(defaults.configs.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["log.retention.ms"]);
+        let retention = &describe_configs(&broker, TOPIC_RESOURCE_TYPE, "events", Some(&["retention.ms"])).configs[0];
+        assert_eq!((retention.value.as_deref(), retention.config_source), (Some("5000"), config_source::DYNAMIC_DEFAULT_BROKER_CONFIG));
+
+        drop(broker);
+        let broker = self::broker(dir.path());
+        assert_eq!(overrides(&broker), vec![("cleanup.policy".to_string(), "compact".to_string())]);
+        assert!(log_config(&broker, "orders").cleanup_policy.compact);
+        assert_eq!(log_config(&broker, "events").retention_ms, 5000);
+    }
+
+    fn group_request(api_key: KafApiKey, version: i16, body: KafRequestBody) -> KafRequest {
+        let mut request = config_request(api_key, version, body);
+        request.header.client_id = Some("consumer".to_string());
+        request
+    }
+
+    #[test]
+    fn a_consumer_finds_its_coordinator_joins_syncs_and_leaves() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker_with(dir.path(), &[("group.initial.rebalance.delay.ms", "0")]);
+        let session = Session::default();
+        let call = |request| handle_request(&broker, &session, request).unwrap().unwrap().body;
+
+        let find = |version, key_type, keys: &[&str]| {
+            let body = FindCoordinatorBody {
+                key_type,
+                coordinator_keys: keys.iter().map(|key| key.to_string()).collect(),
+            };
+            call(group_request(KafApiKey::FindCoordinator, version, KafRequestBody::FindCoordinator(body)))
+                .into_find_coordinator()
+                .unwrap()
+                .coordinators
+        };
+        let coordinators = find(4, coordinator_type::GROUP, &["group", ""]);
+        let (host, port) = broker.config.advertised_listener();
+        assert_eq!((coordinators[0].node_id, &coordinators[0].host, coordinators[0].port), (1, &host, port));
+        assert_eq!(coordinators[1].error_code, error_code::INVALID_GROUP_ID);
+        assert_eq!(find(3, coordinator_type::TRANSACTION, &["tx"])[0].error_code, error_code::COORDINATOR_NOT_AVAILABLE);
+
+        let join = |version, member_id: &str| {
+            let body = JoinGroupBody {
+                group_id: "group".to_string(),
+                session_timeout_ms: 10_000,
+                rebalance_timeout_ms: 10_000,
+                member_id: member_id.to_string(),
+                group_instance_id: None,
+                protocol_type: "consumer".to_string(),
+                protocols: vec![JoinGroupRequestProtocol {
+                    name: "range".to_string(),
+                    metadata: Bytes::from_static(b"subscription"),
+                }],
+                reason: None,
+            };
+            call(group_request(KafApiKey::JoinGroup, version, KafRequestBody::JoinGroup(body))).into_join_group().unwrap()
+        };
+        let response = join(5, UNKNOWN_MEMBER_ID);
+        assert_eq!(response.error_code, error_code::MEMBER_ID_REQUIRED);
+        let response = join(5, &response.member_id);
+        assert_eq!((response.error_code, response.generation_id), (error_code::NONE, 1));
+        assert!(response.member_id.starts_with("consumer-"));
+        assert_eq!(response.leader, response.member_id);
+        assert_eq!(&response.members[0].metadata[..], b"subscription");
+        let member_id = response.member_id;
+        // joining again without changes is answered with the same generation
+        let response = join(5, &member_id);
+        assert_eq!((response.error_code, response.generation_id), (error_code::NONE, 1));
+
+        let body = SyncGroupBody {
+            group_id: "group".to_string(),
+            generation_id: 1,
+            member_id: member_id.clone(),
+            group_instance_id: None,
+            protocol_type: Some("consumer".to_string()),
+            protocol_name: Some("range".to_string()),
+            assignments: response
+                .members
+                .iter()
+                .map(|member| SyncGroupRequestAssignment {
+                    member_id: member.member_id.clone(),
+                    assignment: Bytes::from_static(b"orders-0"),
+                })
+                .collect(),
+        };
+        let response = call(group_request(KafApiKey::SyncGroup, 5, KafRequestBody::SyncGroup(body))).into_sync_group().unwrap();
+        assert_eq!((response.error_code, &response.assignment[..]), (error_code::NONE, &b"orders-0"[..]));
+
+        let heartbeat = |generation_id| {
+            let body = HeartbeatBody {
+                group_id: "group".to_string(),
+                generation_id,
+                member_id: member_id.clone(),
+                group_instance_id: None,
+            };
+            call(group_request(KafApiKey::Heartbeat, 4, KafRequestBody::Heartbeat(body))).into_heartbeat().unwrap().error_code
+        };
+        assert_eq!(heartbeat(1), error_code::NONE);
+        assert_eq!(heartbeat(0), error_code::ILLEGAL_GENERATION);
+
+        let leave = |member_id: &str| {
+            let body = LeaveGroupBody {
+                group_id: "group".to_string(),
+                members: vec![MemberIdentity {
+                    member_id: member_id.to_string(),
+                    group_instance_id: None,
+                    reason: None,
+                }],
+            };
+            call(group_request(KafApiKey::LeaveGroup, 1, KafRequestBody::LeaveGroup(body))).into_leave_group().unwrap().error_code
+        };
+        assert_eq!(leave(&member_id), error_code::NONE);
+        assert_eq!(leave(&member_id), error_code::UNKNOWN_MEMBER_ID);
+        assert_eq!(heartbeat(1), error_code::UNKNOWN_MEMBER_ID);
+    }
+
+    fn commit_offsets(broker: &Broker, partitions: &[(&str, i32, i64, &str)]) -> Vec<(String, i32, i16)> {
+        let body = OffsetCommitBody {
+            group_id: "app".to_string(),
+            generation_id_or_member_epoch: NO_GENERATION_ID,
+            member_id: String::new(),
+            group_instance_id: None,
+            retention_time_ms: -1,
+            topics: partitions
+                .iter()
+                .map(|(topic, partition, offset, metadata)| OffsetCommitRequestTopic {
+                    name: topic.to_string(),
+                    partitions: vec![OffsetCommitRequestPartition {
+                        partition_index: *partition,
+                        committed_offset: *offset,
+                        committed_leader_epoch: 3,
+                        committed_metadata: Some(metadata.to_string()),
+                    }],
+                })
+                .collect(),
+        };
+        let response = handle_request(broker, &Session::default(), group_request(KafApiKey::OffsetCommit, 8, KafRequestBody::OffsetCommit(body)))
+            .unwrap()
+            .unwrap()
+            .body
+            .into_offset_commit()
+            .unwrap();
+        response
+            .topics
+            .into_iter()
+            .flat_map(|topic| topic.partitions.into_iter().map(move |p| (topic.name.clone(), p.partition_index, p.error_code)))
+            .collect()
+    }
+
+    /// the topics and partitions asked for, None for all
+    type Requested<'a> = Option<Vec<(&'a str, Vec<i32>)>>;
+
+    fn fetch_offsets(broker: &Broker, version: i16, groups: &[(&str, Requested)]) -> Vec<OffsetFetchResponseGroup> {
+        let body = OffsetFetchBody {
+            groups: groups
+                .iter()
+                .map(|(group_id, topics)| OffsetFetchRequestGroup {
+                    group_id: group_id.to_string(),
+                    member_id: None,
+                    member_epoch: -1,
+                    topics: topics.as_ref().map(|topics| {
+                        topics
+                            .iter()
+                            .map(|(name, partition_indexes)| OffsetFetchRequestTopic {
+                                name: name.to_string(),
+                                partition_indexes: partition_indexes.clone(),
+                            })
+                            .collect()
+                    }),
+                })
+                .collect(),
+            require_stable: false,
+        };
+        handle_request(broker, &Session::default(), group_request(KafApiKey::OffsetFetch, version, KafRequestBody::OffsetFetch(body)))
+            .unwrap()
+            .unwrap()
+            .body
+            .into_offset_fetch()
+            .unwrap()
+            .groups
+    }
+
+    #[test]
+    fn committed_offsets_are_fetched_back_and_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let props = [("offsets.topic.num.partitions", "3"), ("offset.metadata.max.bytes", "8")];
+        let broker = broker_with(dir.path(), &props);
+        broker.create_topic("orders", &[vec![1], vec![1]], &HashMap::new()).unwrap();
+
+        let errors = commit_offsets(
+            &broker,
+            &[("orders", 0, 10, "m"), ("orders", 1, 20, "too much metadata"), ("orders", 7, 0, ""), ("missing", 0, 0, "")],
+        );
+        assert_eq!(
+            errors,
+            vec![
+                ("orders".to_string(), 0, error_code::NONE),
+                ("orders".to_string(), 1, error_code::OFFSET_METADATA_TOO_LARGE),
+                ("orders".to_string(), 7, error_code::UNKNOWN_TOPIC_OR_PARTITION),
+                ("missing".to_string(), 0, error_code::UNKNOWN_TOPIC_OR_PARTITION),
+            ]
+        );
+        assert!(broker.metadata.image().topic(CONSUMER_OFFSETS_TOPIC).is_some());
+
+        let groups = fetch_offsets(&broker, 7, &[("app", Some(vec![("orders", vec![0, 1])]))]);
+        let partitions = &groups[0].topics[0].partitions;
+        assert_eq!(groups[0].error_code, error_code::NONE);
+        assert_eq!((partitions[0].committed_offset, partitions[0].committed_leader_epoch), (10, 3));
+        assert_eq!(partitions[0].metadata.as_deref(), Some("m"));
+        assert_eq!((partitions[1].committed_offset, partitions[1].error_code), (-1, error_code::NONE));
+
+        // v8 asks for several groups, null topics meaning all of them
+        let groups = fetch_offsets(&broker, 8, &[("app", None), ("other", None)]);
+        assert_eq!((groups[0].topics.len(), groups[0].topics[0].partitions.len()), (1, 1));
+        assert_eq!((groups[1].group_id.as_str(), groups[1].topics.len()), ("other", 0));
+        drop(broker);
+
+        let broker = broker_with(dir.path(), &props);
+        let groups = fetch_offsets(&broker, 8, &[("app", None)]);
+        assert_eq!(groups[0].error_code, error_code::COORDINATOR_LOAD_IN_PROGRESS);
+        broker.group_coordinator.load_offsets();
+        let groups = fetch_offsets(&broker, 8, &[("app", None)]);
+        assert_eq!(groups[0].topics[0].partitions[0].committed_offset, 10);
+        assert_eq!(broker.group_coordinator.group_state("app"), Some(GroupState::Empty));
+    }
+
+    #[test]
+    fn groups_use_the_partitions_the_offsets_topic_has() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker_with(dir.path(), &[("offsets.topic.num.partitions", "3")]);
+        broker.create_topic("orders", &[vec![1]], &HashMap::new()).unwrap();
+        assert_eq!(commit_offsets(&broker, &[("orders", 0, 10, "")])[0].2, error_code::NONE);
+        drop(broker);
+
+        // the config changed, the topic didn't
+        let broker = self::broker(dir.path());
+        broker.group_coordinator.load_offsets();
+        assert!((0..20).all(|i| broker.group_coordinator.offsets().partition_for(&format!("group-{}", i)) < 3));
+        assert_eq!(commit_offsets(&broker, &[("orders", 0, 20, "")])[0].2, error_code::NONE);
+        let groups = fetch_offsets(&broker, 8, &[("app", None)]);
+        assert_eq!(groups[0].topics[0].partitions[0].committed_offset, 20);
+    }
+
+    /// Join "app" as its only member and sync, subscribed to `topic`
+    fn join_and_sync(broker: &Broker, topic: &str) -> String {
+        let call = |request| handle_request(broker, &Session::default(), request).unwrap().unwrap().body;
+        // ConsumerProtocolSubscription v0: version, topics, null user_data
+        let mut subscription = vec![0, 0, 0, 0, 0, 1];
+        write_string(&mut subscription, topic, false);
+        subscription.extend((-1i32).to_be_bytes());
+        let join = |member_id: &str| {
+            let body = JoinGroupBody {
+                group_id: "app".to_string(),
+                session_timeout_ms: 10_000,
+                rebalance_timeout_ms: 10_000,
+                member_id: member_id.to_string(),
+                group_instance_id: None,
+                protocol_type: "consumer".to_string(),
+                protocols: vec![JoinGroupRequestProtocol {
+                    name: "range".to_string(),
+                    metadata: Bytes::from(subscription.clone()),
+                }],
+                reason: None,
+            };
+            call(group_request(KafApiKey::JoinGroup, 3, KafRequestBody::JoinGroup(body))).into_join_group().unwrap()
+        };
+        let response = join(UNKNOWN_MEMBER_ID);
+        assert_eq!(response.error_code, error_code::NONE);
+        let body = SyncGroupBody {
+            group_id: "app".to_string(),
+            generation_id: response.generation_id,
+            member_id: response.member_id.clone(),
+            group_instance_id: None,
+            protocol_type: None,
+            protocol_name: None,
+            assignments: vec![SyncGroupRequestAssignment {
+                member_id: response.member_id.clone(),
+                assignment: Bytes::from_static(b"assigned"),
+            }],
+        };
+        let synced = call(group_request(KafApiKey::SyncGroup, 3, KafRequestBody::SyncGroup(body))).into_sync_group().unwrap();
+        assert_eq!(synced.error_code, error_code::NONE);
+        response.member_id
+    }
+
+    #[test]
+    fn groups_are_listed_described_and_deleted_once_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker_with(dir.path(), &[("offsets.topic.num.partitions", "3"), ("group.initial.rebalance.delay.ms", "0")]);
+        let session = Session::default();
+        let call = |request| handle_request(&broker, &session, request).unwrap().unwrap().body;
+        broker.create_topic("orders", &[vec![1]], &HashMap::new()).unwrap();
+        broker.create_topic("payments", &[vec![1]], &HashMap::new()).unwrap();
+        let errors = commit_offsets(&broker, &[("orders", 0, 5, ""), ("payments", 0, 7, "")]);
+        assert!(errors.iter().all(|(_, _, error)| *error == error_code::NONE));
+
+        let list = |states: &[&str]| {
+            let body = ListGroupsBody {
+                states_filter: states.iter().map(|state| state.to_string()).collect(),
+                types_filter: vec![],
+            };
+            let response = call(group_request(KafApiKey::ListGroups, 4, KafRequestBody::ListGroups(body))).into_list_groups().unwrap();
+            assert_eq!(response.error_code, error_code::NONE);
+            response.groups.into_iter().map(|group| (group.group_id, group.group_state)).collect::<Vec<_>>()
+        };
+        assert_eq!(list(&[]), vec![("app".to_string(), "Empty".to_string())]);
+        let member_id = join_and_sync(&broker, "orders");
+        assert_eq!(list(&["stable"]), vec![("app".to_string(), "Stable".to_string())]);
+        assert!(list(&["Empty"]).is_empty());
+
+        let body = DescribeGroupsBody {
+            groups: vec!["app".to_string(), "unknown".to_string()],
+            include_authorized_operations: true,
+        };
+        let groups = call(group_request(KafApiKey::DescribeGroups, 5, KafRequestBody::DescribeGroups(body))).into_describe_groups().unwrap().groups;
+        assert_eq!((groups[0].group_state.as_str(), groups[0].protocol_data.as_str()), ("Stable", "range"));
+        assert_eq!(groups[0].members[0].member_id, member_id);
+        assert_eq!(&groups[0].members[0].member_assignment[..], b"assigned");
+        assert_ne!(groups[0].authorized_operations, AUTHORIZED_OPERATIONS_OMITTED);
+        assert_eq!((groups[1].error_code, groups[1].group_state.as_str()), (error_code::NONE, "Dead"));
+
+        // the member still consumes orders
+        let body = OffsetDeleteBody {
+            group_id: "app".to_string(),
+            topics: vec![
+                OffsetDeleteRequestTopic { name: "orders".to_string(), partitions: vec![0] },
+                OffsetDeleteRequestTopic { name: "payments".to_string(), partitions: vec![0, 1] },
+            ],
+        };
+        let response = call(group_request(KafApiKey::OffsetDelete, 0, KafRequestBody::OffsetDelete(body))).into_offset_delete().unwrap();
+        let errors: Vec<i16> = response.topics.iter().flat_map(|topic| topic.partitions.iter().map(|p| p.error_code)).collect();
+        assert_eq!(
+            errors,
+            vec![error_code::GROUP_SUBSCRIBED_TO_TOPIC, error_code::NONE, error_code::UNKNOWN_TOPIC_OR_PARTITION]
+        );
+        let offsets = fetch_offsets(&broker, 8, &[("app", None)]);
+        assert_eq!(offsets[0].topics.iter().map(|topic| topic.name.as_str()).collect::<Vec<_>>(), vec!["orders"]);
+
+        let delete = |groups: &[&str]| {
+            let body = DeleteGroupsBody {
+                groups_names: groups.iter().map(|group| group.to_string()).collect(),
+            };
+            let response = call(group_request(KafApiKey::DeleteGroups, 2, KafRequestBody::DeleteGroups(body))).into_delete_groups().unwrap();
+            response.results.into_iter().map(|result| result.error_code).collect::<Vec<_>>()
+        };
+        assert_eq!(delete(&["app", "unknown"]), vec![error_code::NON_EMPTY_GROUP, error_code::GROUP_ID_NOT_FOUND]);
+        let members = [MemberIdentity {
+            member_id,
+            group_instance_id: None,
+            reason: None,
+        }];
+        assert_eq!(broker.group_coordinator.leave_group("app", &members).error_code, error_code::NONE);
+        assert_eq!(delete(&["app"]), vec![error_code::NONE]);
+        assert!(list(&[]).is_empty());
+        assert!(fetch_offsets(&broker, 8, &[("app", None)])[0].topics.is_empty());
+    }
+
+    #[test]
+    fn consumer_groups_subscribe_by_regex_and_are_described() {
+        let dir = tempfile::tempdir().unwrap();
+        let broker = broker(dir.path());
+        let session = Session::default();
+        let call = |request| handle_request(&broker, &session, request).unwrap().unwrap().body;
+        broker.create_topic("orders-eu", &[vec![1]], &HashMap::new()).unwrap();
+        broker.create_topic("orders-us", &[vec![1]], &HashMap::new()).unwrap();
+        broker.create_topic("payments", &[vec![1]], &HashMap::new()).unwrap();
+
+        let heartbeat = |member_epoch: i32, regex: Option<&str>, assignor: Option<&str>| {
+            let body = ConsumerGroupHeartbeatBody {
+                group_id: "app".to_string(),
+                member_id: "member".to_string(),
+                member_epoch,
+                rebalance_timeout_ms: 30_000,
+                subscribed_topic_regex: regex.map(str::to_string),
+                server_assignor: assignor.map(str::to_string),
+                ..Default::default()
+            };
+            let request = group_request(KafApiKey::ConsumerGroupHeartbeat, 1, KafRequestBody::ConsumerGroupHeartbeat(body));
+            call(request).into_consumer_group_heartbeat().unwrap()
+        };
+        assert_eq!(heartbeat(0, Some("orders-("), None).error_code, error_code::INVALID_REGULAR_EXPRESSION);
+        assert_eq!(heartbeat(0, Some("orders-.*"), Some("sticky")).error_code, error_code::UNSUPPORTED_ASSIGNOR);
+        assert_eq!(heartbeat(0, None, None).error_code, error_code::INVALID_REQUEST);
+        let response = heartbeat(0, Some("orders-.*"), Some("range"));
+        assert_eq!((response.error_code, response.member_epoch), (error_code::NONE, 1));
+        assert_eq!(response.assignment.map(|topics| topics.len()), Some(2));
+
+        // topics created later that match join the subscription
+        broker.create_topic("orders-apac", &[vec![1]], &HashMap::new()).unwrap();
+        let response = heartbeat(1, None, None);
+        assert_eq!(response.member_epoch, 2);
+        assert_eq!(response.assignment.map(|topics| topics.len()), Some(3));
+
+        let body = ConsumerGroupDescribeBody {
+            group_ids: vec!["app".to_string(), "unknown".to_string()],
+            include_authorized_operations: false,
+        };
+        let groups = call(group_request(KafApiKey::ConsumerGroupDescribe, 0, KafRequestBody::ConsumerGroupDescribe(body)))
+            .into_consumer_group_describe()
+            .unwrap()
+            .groups;
+        assert_eq!((groups[0].group_state.as_str(), groups[0].group_epoch, groups[0].assignor_name.as_str()), ("Stable", 2, "range"));
+        let member = &groups[0].members[0];
+        assert_eq!((member.member_id.as_str(), member.subscribed_topic_regex.as_deref()), ("member", Some("orders-.*")));
+        let mut topics: Vec<&str> = member.assignment.iter().map(|topic| topic.topic_name.as_str()).collect();
+        topics.sort();
+        assert_eq!(topics, vec!["orders-apac", "orders-eu", "orders-us"]);
+        assert_eq!(member.assignment, member.target_assignment);
+        assert_eq!(groups[0].authorized_operations, AUTHORIZED_OPERATIONS_OMITTED);
+        assert_eq!(groups[1].error_code, error_code::GROUP_ID_NOT_FOUND);
+
+        // it's a consumer group, not a classic one
+        let body = DescribeGroupsBody {
+            groups: vec!["app".to_string()],
+            include_authorized_operations: false,
+        };
+        let groups = call(group_request(KafApiKey::DescribeGroups, 5, KafRequestBody::DescribeGroups(body))).into_describe_groups().unwrap().groups;
+        assert_eq!(groups[0].error_code, error_code::GROUP_ID_NOT_FOUND);
+    }
+}
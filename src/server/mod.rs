@@ -1,7 +1,12 @@
+pub mod authorizer;
+pub mod broker;
+pub mod dynamic_config;
+pub mod fetch_session;
 mod handlers;
+pub mod purgatory;
 
 use std::{
-    io::{Read, Write},
+    io::{ErrorKind, Read, Write},
     net::TcpStream,
 };
 
@@ -10,34 +15,48 @@ use crate::{
         request::{self, KafRequest},
         response::KafResponse,
         DecodeFromBytes, EncodeToBytes,
-    }, server::handlers::handle_request, StrError
+    }, server::{authorizer::Session, broker::Broker, handlers::handle_request}, StrError
 };
 
-pub fn handle_stream(mut stream: TcpStream) -> Result<(), std::io::Error> {
-    // Read the 4-byte message length prefix
-    let mut len_buf = [0u8; 4];
-    stream.read_exact(&mut len_buf)?;
-    let message_len = i32::from_be_bytes(len_buf) as usize;
-
-    println!("Message length: {}", message_len);
-
-    // Read exactly message_len bytes
-    let mut buf = vec![0u8; message_len];
-    stream.read_exact(&mut buf)?;
-
-    println!("buf: {:x?}{:x?}", message_len.to_be_bytes(), buf);
-
-    let mut offset = 0;
-    let request = KafRequest::read_from_u8(&buf, &mut offset).expect("failed to read request");
-    println!("received request: {:#?}", request);
-
-    // CALL: handle_request
-    let response = handle_request(request).expect("failed to get a response");
-    println!("sending response: {:#?}", response);
-    let response_bytes = response.encode_to_bytes();
-
-    println!("writing response: ${:x?}", response_bytes);
-    stream.write_all(&response_bytes)?;
-
-    Ok(())
+/// Serve requests from one client until it disconnects. A request we can't
+/// decode or handle closes the connection, like Kafka does.
+pub fn handle_stream(mut stream: TcpStream, broker: &Broker) -> Result<(), std::io::Error> {
+    let session = Session {
+        client_host: stream.peer_addr()?.ip().to_string(),
+        ..Default::default()
+    };
+
+    loop {
+        // Read the 4-byte message length prefix
+        let mut len_buf = [0u8; 4];
+        match stream.read_exact(&mut len_buf) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let message_len = i32::from_be_bytes(len_buf) as usize;
+
+        println!("Message length: {}", message_len);
+
+        // Read exactly message_len bytes
+        let mut buf = vec![0u8; message_len];
+        stream.read_exact(&mut buf)?;
+
+        let mut offset = 0;
+        let request = KafRequest::read_from_u8(&buf, &mut offset)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, format!("failed to read request: {}", e)))?;
+        // not the body, produce requests carry whole record batches
+        println!("received request: {:?}", request.header);
+
+        // CALL: handle_request
+        let response = handle_request(broker, &session, request)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, format!("failed to handle request: {}", e)))?;
+        let Some(response) = response else {
+            continue;
+        };
+        println!("sending response: {:?}", response.header);
+        let response_bytes = response.encode_to_bytes();
+
+        stream.write_all(&response_bytes)?;
+    }
 }
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// One parked request, woken whenever one of the keys it watches changes
+#[derive(Debug, Default)]
+struct Waiter {
+    woken: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Waiter {
+    fn wake(&self) {
+        *self.woken.lock().unwrap() = true;
+        self.cond.notify_one();
+    }
+
+    /// Block until woken or the deadline passes. A wake up that came in
+    /// while the caller was checking isn't lost, it returns straight away.
+    fn wait_until(&self, deadline: Instant) {
+        let mut woken = self.woken.lock().unwrap();
+        while !*woken {
+            let now = Instant::now();
+            if now >= deadline {
+                return;
+            }
+            woken = self.cond.wait_timeout(woken, deadline - now).unwrap().0;
+        }
+        *woken = false;
+    }
+}
+
+/// Where requests that can't be answered yet wait, like Kafka's delayed
+/// operation purgatory: a Fetch until min_bytes are available, a Produce with
+/// acks=-1 until the high watermark catches up, a JoinGroup until the other
+/// members rejoin. The operation watches some keys (partitions, group ids...)
+/// and whoever changes one of them calls `check_and_complete` with it.
+///
+/// Each connection is served by its own thread, so the request's thread parks
+/// here itself, there is no separate timer to expire operations.
+pub struct DelayedOperationPurgatory<K> {
+    watchers: Mutex<HashMap<K, Vec<Arc<Waiter>>>>,
+    delayed: AtomicUsize,
+}
+
+impl<K> Default for DelayedOperationPurgatory<K> {
+    fn default() -> Self {
+        DelayedOperationPurgatory {
+            watchers: Mutex::new(HashMap::new()),
+            delayed: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<K> fmt::Debug for DelayedOperationPurgatory<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelayedOperationPurgatory").field("delayed", &self.delayed.load(Ordering::Relaxed)).finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone> DelayedOperationPurgatory<K> {
+    /// Complete the operation now if `try_complete` says it can, otherwise
+    /// watch `keys` and check again each time one of them changes, until it
+    /// completes or `timeout` expires. `try_complete` is called one last time
+    /// on expiry. Returns whether the operation completed before expiring.
+    pub fn try_complete_else_watch(&self, keys: &[K], timeout: Duration, mut try_complete: impl FnMut() -> bool) -> bool {
+        if try_complete() {
+            return true;
+        }
+        let deadline = Instant::now() + timeout;
+        let waiter = Arc::new(Waiter::default());
+        let _watch = Watch::new(self, keys, waiter.clone());
+        loop {
+            // checked after watching, so a change in between isn't missed
+            if try_complete() {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            waiter.wait_until(deadline);
+        }
+    }
+
+    /// Wake the operations watching `key` so they check whether they can complete
+    pub fn check_and_complete(&self, key: &K) {
+        if let Some(waiters) = self.watchers.lock().unwrap().get(key) {
+            waiters.iter().for_each(|waiter| waiter.wake());
+        }
+    }
+
+    /// Number of operations currently waiting
+    pub fn delayed(&self) -> usize {
+        self.delayed.load(Ordering::Relaxed)
+    }
+}
+
+/// Registration of a waiter under its keys, removed when the operation
+/// completes or expires
+struct Watch<'a, K: Hash + Eq + Clone> {
+    purgatory: &'a DelayedOperationPurgatory<K>,
+    keys: &'a [K],
+    waiter: Arc<Waiter>,
+}
+
+impl<'a, K: Hash + Eq + Clone> Watch<'a, K> {
+    fn new(purgatory: &'a DelayedOperationPurgatory<K>, keys: &'a [K], waiter: Arc<Waiter>) -> Self {
+        let mut watchers = purgatory.watchers.lock().unwrap();
+        for key in keys {
+            watchers.entry(key.clone()).or_default().push(waiter.clone());
+        }
+        purgatory.delayed.fetch_add(1, Ordering::Relaxed);
+        Watch { purgatory, keys, waiter }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Drop for Watch<'_, K> {
+    fn drop(&mut self) {
+        let mut watchers = self.purgatory.watchers.lock().unwrap();
+        for key in self.keys {
+            if let Some(waiters) = watchers.get_mut(key) {
+                waiters.retain(|waiter| !Arc::ptr_eq(waiter, &self.waiter));
+                if waiters.is_empty() {
+                    watchers.remove(key);
+                }
+            }
+        }
+        self.purgatory.delayed.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::AtomicBool, thread};
+
+    use super::*;
+
+    #[test]
+    fn completes_when_a_watched_key_changes_or_expires() {
+        let purgatory = DelayedOperationPurgatory::<&str>::default();
+        let ready = AtomicBool::new(false);
+
+        thread::scope(|s| {
+            let waiting = s.spawn(|| {
+                let start = Instant::now();
+                let completed = purgatory.try_complete_else_watch(&["a", "b"], Duration::from_secs(30), || ready.load(Ordering::SeqCst));
+                (completed, start.elapsed())
+            });
+            while purgatory.delayed() == 0 {
+                thread::yield_now();
+            }
+            // a change nobody waits on doesn't complete anything
+            purgatory.check_and_complete(&"c");
+            ready.store(true, Ordering::SeqCst);
+            purgatory.check_and_complete(&"b");
+            let (completed, elapsed) = waiting.join().unwrap();
+            assert!(completed);
+            assert!(elapsed < Duration::from_secs(30));
+        });
+        assert_eq!(purgatory.delayed(), 0);
+        assert!(purgatory.watchers.lock().unwrap().is_empty());
+
+        let mut checks = 0;
+        let start = Instant::now();
+        let completed = purgatory.try_complete_else_watch(&["a"], Duration::from_millis(50), || {
+            checks += 1;
+            false
+        });
+        assert!(!completed);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        // before watching, after watching and on expiry
+        assert_eq!(checks, 3);
+        assert_eq!(purgatory.delayed(), 0);
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicI64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Source of wall clock time, so time based behaviour (segment rolling,
+/// retention, ...) can be tested without sleeping.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Milliseconds since the unix epoch
+    fn now_ms(&self) -> i64;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// A clock that only moves when told to
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now_ms: AtomicI64,
+}
+
+impl MockClock {
+    pub fn new(now_ms: i64) -> Self {
+        MockClock {
+            now_ms: AtomicI64::new(now_ms),
+        }
+    }
+
+    pub fn set(&self, now_ms: i64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, ms: i64) {
+        self.now_ms.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> i64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
@@ -17,6 +17,11 @@ pub fn read_u8_be(input: &[u8], off: &mut usize) -> Result<u8, EncodingError> {
     Ok(u8::from_be_bytes([b[0]]))
 }
 
+pub fn read_i8_be(input: &[u8], off: &mut usize) -> Result<i8, EncodingError> {
+    let b = read_exact(input, off, 1)?;
+    Ok(i8::from_be_bytes([b[0]]))
+}
+
 pub fn read_i16_be(input: &[u8], off: &mut usize) -> Result<i16, EncodingError> {
     let b = read_exact(input, off, 2)?;
     Ok(i16::from_be_bytes([b[0], b[1]]))
@@ -27,6 +32,16 @@ pub fn read_i32_be(input: &[u8], off: &mut usize) -> Result<i32, EncodingError>
     Ok(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
 }
 
+pub fn read_u32_be(input: &[u8], off: &mut usize) -> Result<u32, EncodingError> {
+    let b = read_exact(input, off, 4)?;
+    Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+pub fn read_i64_be(input: &[u8], off: &mut usize) -> Result<i64, EncodingError> {
+    let b = read_exact(input, off, 8)?;
+    Ok(i64::from_be_bytes(b.try_into().unwrap()))
+}
+
 /// Kafka UNSIGNED_VARINT (LEB128-like, 7 bits per byte, MSB=continue)
 pub fn read_unsigned_varint(input: &[u8], off: &mut usize) -> Result<u32, EncodingError> {
     let mut x: u64 = 0;
@@ -54,6 +69,37 @@ pub fn read_unsigned_varint(input: &[u8], off: &mut usize) -> Result<u32, Encodi
     Err(EncodingError::VarIntTooLong)
 }
 
+/// Unsigned varint of up to 10 bytes, used as the base for VARLONG
+pub fn read_unsigned_varlong(input: &[u8], off: &mut usize) -> Result<u64, EncodingError> {
+    let mut x: u64 = 0;
+
+    for i in 0..10 {
+        let b = *read_exact(input, off, 1)?.first().unwrap();
+        let val = (b & 0x7F) as u64;
+
+        if i == 9 && val > 1 {
+            return Err(EncodingError::VarIntOverflow);
+        }
+        x |= val << (7 * i);
+        if (b & 0x80) == 0 {
+            return Ok(x);
+        }
+    }
+    Err(EncodingError::VarIntTooLong)
+}
+
+/// Kafka VARINT: zigzag encoded i32 on top of UNSIGNED_VARINT
+pub fn read_varint(input: &[u8], off: &mut usize) -> Result<i32, EncodingError> {
+    let x = read_unsigned_varint(input, off)?;
+    Ok(((x >> 1) as i32) ^ -((x & 1) as i32))
+}
+
+/// Kafka VARLONG: zigzag encoded i64
+pub fn read_varlong(input: &[u8], off: &mut usize) -> Result<i64, EncodingError> {
+    let x = read_unsigned_varlong(input, off)?;
+    Ok(((x >> 1) as i64) ^ -((x & 1) as i64))
+}
+
 pub fn read_string_exact(input: &[u8], off: &mut usize, length: u32) -> Result<String, EncodingError> {
     let bytes = read_exact(input, off, length as usize)?;
     str::from_utf8(bytes)
@@ -121,6 +167,24 @@ pub fn encode_unsigned_varint(mut input: u32) -> Vec<u8> {
     out
 }
 
+pub fn encode_unsigned_varlong(mut input: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(10);
+    while input >= 0x80 {
+        out.push(((input as u8) & 0x7F) | 0x80);
+        input >>= 7;
+    }
+    out.push(input as u8);
+    out
+}
+
+pub fn encode_varint(input: i32) -> Vec<u8> {
+    encode_unsigned_varint(((input << 1) ^ (input >> 31)) as u32)
+}
+
+pub fn encode_varlong(input: i64) -> Vec<u8> {
+    encode_unsigned_varlong(((input << 1) ^ (input >> 63)) as u64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,4 +206,25 @@ mod tests {
         // 300 -> [0xAC, 0x02]
         assert_eq!(encode_unsigned_varint(300), vec![0xAC, 0x02]);
     }
+
+    #[test]
+    fn zigzag_round_trip() {
+        // -1 -> 1, 1 -> 2, -64 -> 127
+        assert_eq!(encode_varint(-1), vec![0x01]);
+        assert_eq!(encode_varint(1), vec![0x02]);
+        assert_eq!(encode_varint(-64), vec![0x7F]);
+
+        for v in [0i64, 1, -1, 300, -300, i32::MAX as i64, i64::MIN, i64::MAX] {
+            let bytes = encode_varlong(v);
+            let mut off = 0;
+            assert_eq!(read_varlong(&bytes, &mut off).unwrap(), v);
+            assert_eq!(off, bytes.len());
+        }
+
+        for v in [0i32, 63, -64, i32::MIN, i32::MAX] {
+            let bytes = encode_varint(v);
+            let mut off = 0;
+            assert_eq!(read_varint(&bytes, &mut off).unwrap(), v);
+        }
+    }
 }
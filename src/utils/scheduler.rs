@@ -0,0 +1,15 @@
+use std::{
+    io,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Run `task` every `period` on a thread of its own, for the broker's
+/// housekeeping jobs (retention, flushing, checkpoints...). The first run
+/// is one period after startup.
+pub fn schedule(name: &str, period: Duration, mut task: impl FnMut() + Send + 'static) -> io::Result<JoinHandle<()>> {
+    thread::Builder::new().name(name.to_string()).spawn(move || loop {
+        thread::sleep(period);
+        task();
+    })
+}